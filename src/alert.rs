@@ -0,0 +1,83 @@
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::models::NormalizedTx;
+
+/// Upper bound on one webhook delivery, so a stalled endpoint cannot hold up
+/// later alerts for long.
+pub const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A pending transaction that touched a watched address; also the JSON body
+/// POSTed to `watch-address --webhook`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Alert {
+    pub hash: String,
+    pub from: String,
+    pub to: Option<String>,
+    pub value_wei: String,
+}
+
+impl From<&NormalizedTx> for Alert {
+    fn from(tx: &NormalizedTx) -> Self {
+        Self {
+            hash: tx.hash.clone(),
+            from: tx.from.clone(),
+            to: tx.to.clone(),
+            value_wei: tx.value_wei.clone(),
+        }
+    }
+}
+
+/// `key=value` alert line; contract creations show `to=-`.
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "alert hash={} from={} to={} value_wei={}",
+            self.hash,
+            self.from,
+            self.to.as_deref().unwrap_or("-"),
+            self.value_wei
+        )
+    }
+}
+
+/// POSTs `alert` as JSON to `url`; non-2xx responses are errors.
+pub async fn post_webhook(client: &reqwest::Client, url: &str, alert: &Alert) -> Result<()> {
+    client
+        .post(url)
+        .timeout(WEBHOOK_TIMEOUT)
+        .json(alert)
+        .send()
+        .await
+        .with_context(|| format!("failed to deliver alert {} to {}", alert.hash, url))?
+        .error_for_status()
+        .with_context(|| format!("webhook {} rejected alert {}", url, alert.hash))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alert_line_and_json_carry_tx_fields() {
+        let alert = Alert {
+            hash: "0xabc".to_string(),
+            from: "0x01".to_string(),
+            to: None,
+            value_wei: "1000".to_string(),
+        };
+        assert_eq!(
+            alert.to_string(),
+            "alert hash=0xabc from=0x01 to=- value_wei=1000"
+        );
+        assert_eq!(
+            serde_json::to_value(&alert).unwrap(),
+            serde_json::json!({"hash": "0xabc", "from": "0x01", "to": null, "value_wei": "1000"})
+        );
+    }
+}
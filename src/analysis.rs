@@ -0,0 +1,381 @@
+use std::collections::{HashMap, HashSet};
+
+use ethers_core::types::U256;
+use serde::Serialize;
+
+use crate::models::{NonceGap, NormalizedTx};
+
+/// Well-known Ethereum mainnet DEX routers (lowercase).
+pub const DEFAULT_ROUTERS: &[&str] = &[
+    // Uniswap V2 Router02
+    "0x7a250d5630b4cf539739df2c5dacb4c659f2488d",
+    // Uniswap V3 SwapRouter
+    "0xe592427a0aece92de3edee1f18e0157c05861564",
+    // Uniswap V3 SwapRouter02
+    "0x68b3465833fb72a70ecdf485e0e4c7bd8665fc45",
+    // Uniswap Universal Router
+    "0x3fc91a3afd70395cd496c647d5a8b9c0e6f6d4ad",
+    // SushiSwap Router
+    "0xd9e1ce17f2641f24ae83637ab66a2cca9c378b9f",
+];
+
+/// [`DEFAULT_ROUTERS`] as a set, used wherever `MEV_ROUTERS` is unset.
+pub fn default_routers() -> HashSet<String> {
+    DEFAULT_ROUTERS.iter().map(|r| r.to_string()).collect()
+}
+
+/// 1 ETH in wei.
+pub const DEFAULT_MEV_MIN_VALUE_WEI: &str = "1000000000000000000";
+
+/// V2-style swap selectors and the argument index holding `amountOutMin`.
+const AMOUNT_OUT_MIN_ARG: &[(&str, usize)] = &[
+    // swapExactETHForTokens(uint256 amountOutMin, address[], address, uint256)
+    ("0x7ff36ab5", 0),
+    // swapExactTokensForTokens(uint256 amountIn, uint256 amountOutMin, ...)
+    ("0x38ed1739", 1),
+    // swapExactTokensForETH(uint256 amountIn, uint256 amountOutMin, ...)
+    ("0x18cbafe5", 1),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MevReason {
+    /// Swap value at or above the configured threshold.
+    HighValue,
+    /// Swap decoded with `amountOutMin == 0`.
+    NoSlippageProtection,
+}
+
+/// Best-effort sandwich-target heuristic: a pending transaction sent to a
+/// known router that is either large or carries no slippage bound.
+#[derive(Debug, Clone)]
+pub struct MevHeuristic {
+    routers: HashSet<String>,
+    min_value_wei: U256,
+}
+
+impl Default for MevHeuristic {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+impl MevHeuristic {
+    pub fn new(routers: Option<HashSet<String>>, min_value_wei: Option<U256>) -> Self {
+        Self {
+            routers: routers.unwrap_or_else(default_routers),
+            min_value_wei: min_value_wei
+                .unwrap_or_else(|| U256::from_dec_str(DEFAULT_MEV_MIN_VALUE_WEI).unwrap()),
+        }
+    }
+
+    pub fn routers(&self) -> impl Iterator<Item = &String> {
+        self.routers.iter()
+    }
+
+    /// The routers as a set, for [`TxFlags::from_tx`](crate::models::TxFlags::from_tx).
+    pub fn router_set(&self) -> &HashSet<String> {
+        &self.routers
+    }
+
+    /// Returns why `tx` looks like a sandwich target, or an empty list if it
+    /// does not.
+    pub fn classify(&self, tx: &NormalizedTx) -> Vec<MevReason> {
+        let mut reasons = Vec::new();
+        let to_router = tx
+            .to
+            .as_ref()
+            .map(|to| self.routers.contains(to))
+            .unwrap_or(false);
+        if tx.block_number.is_some() || !to_router {
+            return reasons;
+        }
+
+        let value = U256::from_dec_str(&tx.value_wei).unwrap_or_default();
+        if value >= self.min_value_wei {
+            reasons.push(MevReason::HighValue);
+        }
+        if tx
+            .input_data
+            .as_deref()
+            .and_then(amount_out_min)
+            .map(|min| min.is_zero())
+            .unwrap_or(false)
+        {
+            reasons.push(MevReason::NoSlippageProtection);
+        }
+        reasons
+    }
+}
+
+/// Rough next-block inclusion outlook for a pending transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InclusionLabel {
+    Likely,
+    Borderline,
+    Unlikely,
+}
+
+/// Prices within this many percent of the reference median are borderline.
+const INCLUSION_BAND_PCT: u64 = 10;
+
+/// Heuristic only: compares a pending price with the median recently included
+/// price and ignores base-fee movement, priority tips and block fullness.
+pub fn classify_inclusion(price: U256, median: U256) -> InclusionLabel {
+    let upper = median * (100 + INCLUSION_BAND_PCT) / 100;
+    let lower = median * (100 - INCLUSION_BAND_PCT) / 100;
+    if price >= upper {
+        InclusionLabel::Likely
+    } else if price >= lower {
+        InclusionLabel::Borderline
+    } else {
+        InclusionLabel::Unlikely
+    }
+}
+
+/// The price a transaction bids: `gasPrice` for legacy transactions, else
+/// `maxFeePerGas` as an upper bound.
+pub fn effective_gas_price(tx: &NormalizedTx) -> Option<U256> {
+    tx.gas_price_wei
+        .as_deref()
+        .or(tx.max_fee_per_gas_wei.as_deref())
+        .and_then(|raw| U256::from_dec_str(raw).ok())
+}
+
+/// What a mined transaction actually paid per gas: `min(maxFeePerGas,
+/// baseFee + maxPriorityFeePerGas)` for EIP-1559 transactions whose block
+/// base fee is known, otherwise [`effective_gas_price`].
+pub fn effective_gas_price_at(tx: &NormalizedTx, base_fee: Option<U256>) -> Option<U256> {
+    let parse = |raw: Option<&str>| raw.and_then(|raw| U256::from_dec_str(raw).ok());
+    let max_fee = parse(tx.max_fee_per_gas_wei.as_deref());
+    let priority = parse(tx.max_priority_fee_per_gas_wei.as_deref());
+    match (max_fee, priority, base_fee) {
+        (Some(max_fee), Some(priority), Some(base_fee)) => {
+            Some(max_fee.min(base_fee.saturating_add(priority)))
+        }
+        _ => effective_gas_price(tx),
+    }
+}
+
+/// One attempt in a replacement series for a single `(from, nonce)`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RbfBump {
+    pub hash: String,
+    pub chain_id: i64,
+    pub effective_gas_price_wei: Option<String>,
+    /// Percent change over the previous priced attempt on the same chain as a
+    /// decimal string with two places (e.g. `"12.50"`, `"-3.00"`); `None` for the first attempt or
+    /// when either price is missing or zero.
+    pub bump_pct: Option<String>,
+}
+
+/// Percent change in [`effective_gas_price`] between consecutive attempts,
+/// computed in integer basis points so large wei values stay exact. The same
+/// `(from, nonce)` on another chain is a different transaction, so each chain
+/// is compared only with itself.
+pub fn rbf_bumps(attempts: &[NormalizedTx]) -> Vec<RbfBump> {
+    let mut previous: HashMap<i64, U256> = HashMap::new();
+    attempts
+        .iter()
+        .map(|tx| {
+            let price = effective_gas_price(tx);
+            let bump_pct = previous
+                .get(&tx.chain_id)
+                .copied()
+                .zip(price)
+                .filter(|(prev, _)| !prev.is_zero())
+                .map(|(prev, price)| percent_change(prev, price));
+            if let Some(price) = price {
+                previous.insert(tx.chain_id, price);
+            }
+            RbfBump {
+                hash: tx.hash.clone(),
+                chain_id: tx.chain_id,
+                effective_gas_price_wei: price.map(|p| p.to_string()),
+                bump_pct,
+            }
+        })
+        .collect()
+}
+
+/// `(to - from) / from` as a percentage truncated to two decimal places.
+fn percent_change(from: U256, to: U256) -> String {
+    let (sign, delta) = if to >= from {
+        ("", to - from)
+    } else {
+        ("-", from - to)
+    };
+    let basis_points = delta.saturating_mul(U256::from(10_000u64)) / from;
+    let hundred = U256::from(100u64);
+    format!(
+        "{}{}.{:0>2}",
+        sign,
+        basis_points / hundred,
+        (basis_points % hundred).to_string()
+    )
+}
+
+/// Ranges missing between consecutive entries of `nonces` (ascending,
+/// distinct). Nothing below the lowest nonce is reported: ingest rarely
+/// starts at an account's first transaction.
+pub fn nonce_gaps(nonces: &[i64]) -> Vec<NonceGap> {
+    nonces
+        .windows(2)
+        .filter(|pair| pair[1] - pair[0] > 1)
+        .map(|pair| NonceGap {
+            start: pair[0] + 1,
+            end: pair[1] - 1,
+        })
+        .collect()
+}
+
+/// Lower median of `values` (sorted in place).
+pub fn median(values: &mut [U256]) -> Option<U256> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    Some(values[(values.len() - 1) / 2])
+}
+
+/// Decodes `amountOutMin` from V2-style swap calldata, if the selector is known.
+fn amount_out_min(input_data: &str) -> Option<U256> {
+    let selector = crate::fourbyte::selector_of(input_data)?.to_ascii_lowercase();
+    let (_, index) = AMOUNT_OUT_MIN_ARG.iter().find(|(s, _)| *s == selector)?;
+    let start = 10 + index * 64;
+    let word = input_data.get(start..start + 64)?;
+    U256::from_str_radix(word, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending_swap(value_wei: &str, input_data: &str) -> NormalizedTx {
+        NormalizedTx {
+            hash: "0x01".to_string(),
+            from: "0xabc".to_string(),
+            to: Some(DEFAULT_ROUTERS[0].to_string()),
+            value_wei: value_wei.to_string(),
+            gas: 200_000,
+            gas_price_wei: None,
+            max_fee_per_gas_wei: None,
+            max_priority_fee_per_gas_wei: None,
+            nonce: 0,
+            block_number: None,
+            timestamp: None,
+            status: None,
+            input_data: Some(input_data.to_string()),
+            tx_index: None,
+            chain_id: 1,
+            tx_type: None,
+            input_size: None,
+        }
+    }
+
+    #[test]
+    fn flags_large_and_unprotected_router_swaps() {
+        let heuristic = MevHeuristic::default();
+        let zero_min = format!("0x7ff36ab5{}", "0".repeat(64));
+        let some_min = format!("0x7ff36ab5{:0>64}", "1");
+
+        assert_eq!(
+            heuristic.classify(&pending_swap("2000000000000000000", &some_min)),
+            vec![MevReason::HighValue]
+        );
+        assert_eq!(
+            heuristic.classify(&pending_swap("1000", &zero_min)),
+            vec![MevReason::NoSlippageProtection]
+        );
+        assert!(heuristic
+            .classify(&pending_swap("1000", &some_min))
+            .is_empty());
+
+        let mut mined = pending_swap("2000000000000000000", &zero_min);
+        mined.block_number = Some(1);
+        assert!(heuristic.classify(&mined).is_empty());
+    }
+
+    #[test]
+    fn inclusion_labels_use_band_around_median() {
+        let mut prices: Vec<U256> = [30u64, 10, 20].into_iter().map(U256::from).collect();
+        let median = median(&mut prices).unwrap();
+        assert_eq!(median, U256::from(20));
+        assert_eq!(
+            classify_inclusion(U256::from(22), median),
+            InclusionLabel::Likely
+        );
+        assert_eq!(
+            classify_inclusion(U256::from(19), median),
+            InclusionLabel::Borderline
+        );
+        assert_eq!(
+            classify_inclusion(U256::from(17), median),
+            InclusionLabel::Unlikely
+        );
+    }
+
+    #[test]
+    fn rbf_bumps_measure_step_percentages_exactly() {
+        let attempt = |hash: &str, price: Option<&str>| NormalizedTx {
+            hash: hash.to_string(),
+            gas_price_wei: price.map(str::to_string),
+            ..pending_swap("0", "0x")
+        };
+        let huge = "100000000000000000000000000000000000000";
+        let bumps = rbf_bumps(&[
+            attempt("0x1", Some("1000")),
+            attempt("0x2", Some("1125")),
+            attempt("0x3", None),
+            attempt("0x4", Some("1091")),
+            attempt("0x5", Some(huge)),
+        ]);
+        let pcts: Vec<Option<&str>> = bumps.iter().map(|b| b.bump_pct.as_deref()).collect();
+        assert_eq!(pcts[..4], [None, Some("12.50"), None, Some("-3.02")]);
+        assert_eq!(pcts[4], Some("9165902841429880843263061411549037480.20"));
+    }
+
+    #[test]
+    fn nonce_gaps_cover_missing_ranges_only() {
+        assert!(nonce_gaps(&[]).is_empty());
+        assert!(nonce_gaps(&[7]).is_empty());
+        assert!(nonce_gaps(&[3, 4, 5]).is_empty());
+        assert_eq!(
+            nonce_gaps(&[3, 5, 6, 10]),
+            vec![NonceGap { start: 4, end: 4 }, NonceGap { start: 7, end: 9 }]
+        );
+    }
+
+    #[test]
+    fn effective_price_caps_tip_at_max_fee() {
+        let dynamic = |max_fee: &str, priority: &str| NormalizedTx {
+            max_fee_per_gas_wei: Some(max_fee.to_string()),
+            max_priority_fee_per_gas_wei: Some(priority.to_string()),
+            ..pending_swap("0", "0x")
+        };
+        let base_fee = Some(U256::from(1200));
+
+        assert_eq!(
+            effective_gas_price_at(&dynamic("5000", "100"), base_fee),
+            Some(U256::from(1300))
+        );
+        assert_eq!(
+            effective_gas_price_at(&dynamic("1250", "100"), base_fee),
+            Some(U256::from(1250))
+        );
+        // Without a base fee only the max fee bound is known.
+        assert_eq!(
+            effective_gas_price_at(&dynamic("5000", "100"), None),
+            Some(U256::from(5000))
+        );
+        let legacy = NormalizedTx {
+            gas_price_wei: Some("900".to_string()),
+            ..pending_swap("0", "0x")
+        };
+        assert_eq!(
+            effective_gas_price_at(&legacy, base_fee),
+            Some(U256::from(900))
+        );
+    }
+}
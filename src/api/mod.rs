@@ -1,147 +1,1897 @@
 use anyhow::Result;
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    routing::get,
+    body::Body,
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, MatchedPath, Path, Request, State,
+    },
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
     Json, Router,
 };
+use ethers_core::types::U256;
+use futures_util::TryStreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tower_http::compression::predicate::{Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+use utoipa::{IntoParams, ToSchema};
 
+use self::extract::{JsonBody, Query};
+use crate::analysis::{self, InclusionLabel, MevHeuristic, MevReason, RbfBump};
+use crate::chain::ChainUnits;
+use crate::chain_head::ChainHead;
+use crate::eth::{unix_now_secs, EthClient};
+use crate::fourbyte::SignatureDb;
+use crate::ingest::{self, IngestOptions, IngestSummary};
 use crate::ingest_stats::INGEST_STATS;
-use crate::models::{GasStats, NormalizedTx, TopSender};
-use crate::storage::{self, DbPool};
+use crate::metrics::{self, HTTP_LATENCY};
+use crate::models::{
+    AddressSummary, ArrivalRate, BaseFeeStats, BlockSummary, FlowSummary, GasHistogramBucket,
+    GasStats, LogEntry, NonceGaps, NormalizedTx, SimulationResult, StoredTx, ThroughputBucket,
+    TimelineEntry, TokenTransfer, TopSender, TxFlags, WindowSummary,
+};
+use crate::rate_limit::RateLimiter;
+use crate::recent_cache::RecentTxCache;
+use crate::storage::{self, RecentCursor, RecentOrder, RecentTxFilter, Storage};
+use crate::tx_feed::TxFeed;
+
+mod extract;
+mod openapi;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub pool: DbPool,
+    /// SQLite or Postgres, as picked by `DATABASE_URL`.
+    pub store: Arc<dyn Storage>,
+    /// RPC client for endpoints that need live chain access; `None` disables them.
+    pub eth: Option<EthClient>,
+    /// Cached chain head used to derive confirmation counts.
+    pub chain_head: Arc<ChainHead>,
+    /// Selector lookup used to label transactions with `method_signature`.
+    pub signatures: Arc<SignatureDb>,
+    /// Heuristic behind `/analysis/mev-targets`.
+    pub mev: Arc<MevHeuristic>,
+    /// In-memory ring of freshly ingested transactions, when enabled.
+    pub recent_cache: Option<Arc<RecentTxCache>>,
+    /// Display units used for formatted gas prices.
+    pub units: Arc<ChainUnits>,
+    /// Settings used by `POST /ingest`.
+    pub ingest: Arc<IngestOptions>,
+    /// Required `x-api-key` value for mutating endpoints; `None` disables them.
+    pub api_key: Option<Arc<str>>,
+    /// Bearer token required on every route but `/health`; `None` leaves the
+    /// API open.
+    pub api_token: Option<Arc<str>>,
+    /// Ordering for `/tx/recent` when the request does not pick one.
+    pub recent_order: RecentOrder,
+    /// Per-client request budget; `None` leaves the API unthrottled.
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Newly stored transactions pushed to `/ws/txs` subscribers.
+    pub tx_feed: TxFeed,
 }
 
-#[derive(Serialize)]
+impl AppState {
+    pub fn new(store: Arc<dyn Storage>) -> Self {
+        Self {
+            store: store.clone(),
+            eth: None,
+            chain_head: Arc::new(ChainHead::new()),
+            signatures: Arc::new(SignatureDb::builtin()),
+            mev: Arc::new(MevHeuristic::default()),
+            recent_cache: None,
+            units: Arc::new(ChainUnits::default()),
+            ingest: Arc::new(IngestOptions::default()),
+            api_key: None,
+            api_token: None,
+            recent_order: RecentOrder::default(),
+            rate_limiter: None,
+            tx_feed: TxFeed::new(store),
+        }
+    }
+
+    pub fn with_recent_order(mut self, order: RecentOrder) -> Self {
+        self.recent_order = order;
+        self
+    }
+
+    pub fn with_ingest(mut self, options: IngestOptions, api_key: Option<String>) -> Self {
+        self.ingest = Arc::new(options);
+        self.api_key = api_key.map(Arc::from);
+        self
+    }
+
+    pub fn with_api_token(mut self, token: Option<String>) -> Self {
+        self.api_token = token.map(Arc::from);
+        self
+    }
+
+    /// Throttles each client IP to `rps` requests per second with bursts of
+    /// up to `burst`.
+    pub fn with_rate_limit(mut self, rps: f64, burst: u32) -> Self {
+        self.rate_limiter = Some(Arc::new(RateLimiter::new(rps, burst)));
+        self
+    }
+
+    pub fn with_units(mut self, units: ChainUnits) -> Self {
+        self.units = Arc::new(units);
+        self
+    }
+
+    /// Enables the recent-transaction ring buffer; a capacity of zero leaves it off.
+    pub fn with_recent_cache(mut self, capacity: usize) -> Self {
+        self.recent_cache = (capacity > 0).then(|| Arc::new(RecentTxCache::new(capacity)));
+        self
+    }
+
+    pub fn with_mev(mut self, mev: MevHeuristic) -> Self {
+        self.mev = Arc::new(mev);
+        self
+    }
+
+    pub fn with_signatures(mut self, signatures: SignatureDb) -> Self {
+        self.signatures = Arc::new(signatures);
+        self
+    }
+
+    pub fn with_eth(mut self, eth: EthClient) -> Self {
+        self.eth = Some(eth);
+        self
+    }
+}
+
+#[derive(Serialize, ToSchema)]
 struct HealthResponse {
+    #[schema(value_type = String, example = "ok")]
     status: &'static str,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct TopSendersResponse {
     top_senders: Vec<TopSender>,
 }
 
 #[derive(Serialize)]
+struct TopReceiversResponse {
+    top_receivers: Vec<TopSender>,
+}
+
+#[derive(Serialize, ToSchema)]
 struct GasStatsResponse {
     min: Option<i64>,
     max: Option<i64>,
     avg: Option<f64>,
+    p25: Option<i64>,
+    p50: Option<i64>,
+    p75: Option<i64>,
+    p90: Option<i64>,
+    /// Display unit for the `*_formatted` fields, per the configured chain.
+    unit: String,
+    min_formatted: Option<String>,
+    max_formatted: Option<String>,
+    avg_formatted: Option<String>,
+    p25_formatted: Option<String>,
+    p50_formatted: Option<String>,
+    p75_formatted: Option<String>,
+    p90_formatted: Option<String>,
+}
+
+impl GasStatsResponse {
+    fn new(stats: Option<GasStats>, units: &ChainUnits) -> Self {
+        let fmt = |value: i64| units.format_gas_price(&value.to_string());
+        match stats {
+            Some(stats) => GasStatsResponse {
+                min: Some(stats.min),
+                max: Some(stats.max),
+                avg: Some(stats.avg),
+                p25: Some(stats.p25),
+                p50: Some(stats.p50),
+                p75: Some(stats.p75),
+                p90: Some(stats.p90),
+                unit: units.gas_price_unit.clone(),
+                min_formatted: fmt(stats.min),
+                max_formatted: fmt(stats.max),
+                avg_formatted: units.format_gas_price(&format!("{:.0}", stats.avg)),
+                p25_formatted: fmt(stats.p25),
+                p50_formatted: fmt(stats.p50),
+                p75_formatted: fmt(stats.p75),
+                p90_formatted: fmt(stats.p90),
+            },
+            None => GasStatsResponse {
+                min: None,
+                max: None,
+                avg: None,
+                p25: None,
+                p50: None,
+                p75: None,
+                p90: None,
+                unit: units.gas_price_unit.clone(),
+                min_formatted: None,
+                max_formatted: None,
+                avg_formatted: None,
+                p25_formatted: None,
+                p50_formatted: None,
+                p75_formatted: None,
+                p90_formatted: None,
+            },
+        }
+    }
 }
 
 #[derive(Serialize)]
+struct BaseFeeStatsResponse {
+    min: Option<i64>,
+    max: Option<i64>,
+    avg: Option<f64>,
+    /// Blocks in the window that carried a base fee.
+    blocks: i64,
+    unit: String,
+    min_formatted: Option<String>,
+    max_formatted: Option<String>,
+    avg_formatted: Option<String>,
+}
+
+impl BaseFeeStatsResponse {
+    fn new(stats: Option<BaseFeeStats>, units: &ChainUnits) -> Self {
+        let fmt = |value: i64| units.format_gas_price(&value.to_string());
+        match stats {
+            Some(stats) => BaseFeeStatsResponse {
+                min: Some(stats.min),
+                max: Some(stats.max),
+                avg: Some(stats.avg),
+                blocks: stats.blocks,
+                unit: units.gas_price_unit.clone(),
+                min_formatted: fmt(stats.min),
+                max_formatted: fmt(stats.max),
+                avg_formatted: units.format_gas_price(&format!("{:.0}", stats.avg)),
+            },
+            None => BaseFeeStatsResponse {
+                min: None,
+                max: None,
+                avg: None,
+                blocks: 0,
+                unit: units.gas_price_unit.clone(),
+                min_formatted: None,
+                max_formatted: None,
+                avg_formatted: None,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
 struct IngestStatsResponse {
     blocks: u64,
     transactions: u64,
     pending_transactions: u64,
+    dropped_due_to_backpressure: u64,
+    skipped_blocks: u64,
+    rpc_errors: u64,
+    insert_errors: u64,
+    /// Seconds the latest ingest run in this process took.
+    last_run_secs: Option<f64>,
+    /// Transactions stored per second across this process's ingest runs.
+    avg_tx_per_sec: Option<f64>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct RecentTxsResponse {
-    transactions: Vec<NormalizedTx>,
+    transactions: Vec<TxView>,
+    /// Pass as `before` for the next page; empty once the data runs out, and
+    /// absent where paging is unsupported (`order=block`, `/tx/by-address`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TxBatchResponse {
+    /// Found transactions, in request order.
+    transactions: Vec<TxView>,
+    /// Requested hashes with no stored row.
+    missing: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct RecentLogsResponse {
+    logs: Vec<LogEntry>,
+}
+
+#[derive(Serialize)]
+struct RecentBlocksResponse {
+    blocks: Vec<BlockSummary>,
+}
+
+#[derive(Serialize)]
+struct TokenTransfersResponse {
+    transfers: Vec<TokenTransfer>,
+}
+
+/// A stored transaction plus its stored derived columns and fields looked up
+/// at read time.
+#[derive(Serialize, ToSchema)]
+struct TxView {
+    #[serde(flatten)]
+    tx: NormalizedTx,
+    confirmations: Option<u64>,
+    /// Lowercase 4-byte method selector of the calldata.
+    input_selector: Option<String>,
+    method_signature: Option<String>,
+    /// Names of the set [`TxFlags`].
+    #[schema(value_type = Vec<String>)]
+    flags: Vec<&'static str>,
+}
+
+impl TxView {
+    fn new(stored: StoredTx, state: &AppState) -> Self {
+        let StoredTx {
+            tx,
+            flags,
+            input_selector,
+        } = stored;
+        let confirmations = state.chain_head.confirmations(tx.block_number);
+        let method_signature = tx
+            .input_data
+            .as_deref()
+            .and_then(|input| state.signatures.lookup_input(input))
+            .map(str::to_string);
+        Self {
+            tx,
+            confirmations,
+            input_selector,
+            method_signature,
+            flags: flags.names(),
+        }
+    }
 }
 
-pub async fn run_http_server(addr: &str, pool: DbPool) -> Result<()> {
-    let state = AppState { pool };
-    let app = app_router(state);
+#[derive(Serialize)]
+struct LargestTxResponse {
+    transaction: Option<NormalizedTx>,
+}
+
+#[derive(Serialize)]
+struct TimelineResponse {
+    address: String,
+    timeline: Vec<TimelineEntry>,
+}
+
+#[derive(Serialize)]
+struct MevTargetsResponse {
+    targets: Vec<MevTarget>,
+}
+
+#[derive(Serialize)]
+struct MevTarget {
+    #[serde(flatten)]
+    tx: NormalizedTx,
+    reasons: Vec<MevReason>,
+}
+
+#[derive(Serialize)]
+struct SimulateResponse {
+    hash: String,
+    #[serde(flatten)]
+    result: SimulationResult,
+}
+
+pub async fn run_http_server(addr: &str, state: AppState, prefix: &str) -> Result<()> {
+    let store = state.store.clone();
+    let app = app_router_with_prefix(state, prefix);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
     let actual = listener.local_addr()?;
     tracing::info!("HTTP server listening on http://{}", actual);
 
-    axum::serve(listener, app).await?;
+    // Connection info feeds the per-IP rate limiter.
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await?;
+    // In-flight handlers have finished, so the pool can close cleanly.
+    store.close().await;
     Ok(())
 }
 
+/// Resolves on Ctrl-C or, on Unix, SIGTERM (container stop).
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::warn!("failed to listen for Ctrl-C: {}", err);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                tracing::warn!("failed to listen for SIGTERM: {}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    tracing::info!("shutting down");
+}
+
+/// Mounts every route under `prefix` (e.g. `/mempool`) for reverse proxies
+/// serving a sub-path, keeping `/health` at the root for probes. An empty
+/// prefix is the same as [`app_router`].
+pub fn app_router_with_prefix(state: AppState, prefix: &str) -> Router {
+    if prefix.is_empty() {
+        return app_router(state);
+    }
+    Router::new()
+        .route("/health", get(health))
+        .with_state(state.clone())
+        .nest(prefix, app_router(state))
+}
+
 pub fn app_router(state: AppState) -> Router {
     Router::new()
         .route("/health", get(health))
         .route("/stats/top-senders", get(stats_top_senders))
+        .route("/stats/top-receivers", get(stats_top_receivers))
         .route("/stats/gas", get(stats_gas))
+        .route("/stats/gas-at", get(stats_gas_at))
+        .route("/stats/gas-histogram", get(stats_gas_histogram))
+        .route("/stats/base-fee", get(stats_base_fee))
         .route("/stats/ingest", get(stats_ingest))
+        .route("/stats/ingest/stream", get(stats_ingest_stream))
+        .route("/metrics", get(prometheus_metrics))
+        .route("/openapi.json", get(openapi::openapi_json))
+        .route("/stats/lag", get(stats_lag))
+        .route("/stats/largest-tx", get(stats_largest_tx))
+        .route("/stats/arrival-rate", get(stats_arrival_rate))
+        .route("/stats/throughput", get(stats_throughput))
+        .route("/ingest", post(trigger_ingest))
+        .route("/blocks/recent", get(recent_blocks))
         .route("/tx/recent", get(recent_txs))
+        .route("/tx/batch", post(tx_batch))
+        .route("/tx/by-address", get(txs_by_address))
+        .route("/tx/:hash", get(get_tx))
+        .route("/tx/:hash/simulate", get(simulate_tx))
+        .route("/ws/txs", get(ws_txs))
+        .route("/logs/recent", get(recent_logs))
+        .route("/token-transfers", get(token_transfers))
+        .route("/address/:addr/summary", get(address_summary))
+        .route("/address/:addr/nonce-gaps", get(address_nonce_gaps))
+        .route("/analysis/timeline", get(address_timeline))
+        .route("/analysis/flow", get(address_flow))
+        .route("/analysis/compare", get(compare_windows))
+        .route("/analysis/mev-targets", get(mev_targets))
+        .route("/analysis/inclusion-estimate", get(inclusion_estimate))
+        .route("/analysis/rbf-bumps", get(rbf_bumps))
+        .layer(middleware::from_fn(pretty_json))
+        .layer(
+            CompressionLayer::new()
+                .compress_when(SizeAbove::new(COMPRESSION_MIN_BYTES).and(is_json_response)),
+        )
+        .layer(middleware::from_fn(record_latency))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_bearer_token,
+        ))
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit))
         .with_state(state)
 }
 
-async fn health() -> Json<HealthResponse> {
-    Json(HealthResponse { status: "ok" })
+/// JSON bodies smaller than this are sent uncompressed; the encoding overhead
+/// outweighs the savings.
+const COMPRESSION_MIN_BYTES: u16 = 1024;
+
+/// Compression predicate: only JSON responses are gzip/brotli encoded.
+fn is_json_response(
+    _: StatusCode,
+    _: axum::http::Version,
+    headers: &HeaderMap,
+    _: &axum::http::Extensions,
+) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/json"))
 }
 
-#[derive(Debug, Deserialize)]
+/// Rejects requests over the client's budget with 429 and `Retry-After`.
+/// Clients are keyed by peer IP; routers served without connect info (or
+/// behind a proxy) share one budget. `/health` is never throttled.
+async fn rate_limit(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let Some(limiter) = state.rate_limiter.as_ref() else {
+        return next.run(req).await;
+    };
+    if req.uri().path() == "/health" {
+        return next.run(req).await;
+    }
+    let ip = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    match limiter.check(ip) {
+        Ok(()) => next.run(req).await,
+        Err(wait) => {
+            let mut response =
+                ApiError::new(StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded").into_response();
+            let secs = wait.as_secs_f64().ceil().max(1.0) as u64;
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+            response
+        }
+    }
+}
+
+/// Rejects requests without `Authorization: Bearer <API_TOKEN>` with 401.
+/// `/health` stays open for probes, as does everything when no token is set.
+/// Runs inside the rate limiter so guessing attempts are throttled too.
+async fn require_bearer_token(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let Some(expected) = state.api_token.as_deref() else {
+        return next.run(req).await;
+    };
+    if req.uri().path() == "/health" {
+        return next.run(req).await;
+    }
+    let given = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    match given {
+        Some(given) if constant_time_eq(given.as_bytes(), expected.as_bytes()) => {
+            next.run(req).await
+        }
+        _ => {
+            let mut response =
+                ApiError::new(StatusCode::UNAUTHORIZED, "missing or invalid bearer token")
+                    .into_response();
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            response
+        }
+    }
+}
+
+/// Compares secrets without exiting at the first differing byte, so response
+/// timing does not reveal how much of a guess was right. Only the length can
+/// leak.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Times every routed request into [`HTTP_LATENCY`], keyed by its route
+/// template (e.g. `/tx/:hash/simulate`) so series stay bounded.
+async fn record_latency(req: Request, next: Next) -> Response {
+    let Some(route) = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_owned())
+    else {
+        return next.run(req).await;
+    };
+    let started = Instant::now();
+    let response = next.run(req).await;
+    HTTP_LATENCY.observe(&route, started.elapsed());
+    response
+}
+
+/// JSON response serialized with indentation, for human-readable output.
+struct PrettyJson<T>(T);
+
+impl<T: Serialize> IntoResponse for PrettyJson<T> {
+    fn into_response(self) -> Response {
+        match serde_json::to_string_pretty(&self.0) {
+            Ok(body) => (
+                [(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("application/json"),
+                )],
+                body,
+            )
+                .into_response(),
+            Err(err) => internal_error(err).into_response(),
+        }
+    }
+}
+
+/// Re-serializes JSON responses through [`PrettyJson`] when the request
+/// carries `pretty=true`; everything else passes through untouched.
+async fn pretty_json(req: Request, next: Next) -> Response {
+    let wants_pretty = req
+        .uri()
+        .query()
+        .map(|q| q.split('&').any(|pair| pair == "pretty=true"))
+        .unwrap_or(false);
+
+    let response = next.run(req).await;
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .map(|v| v.as_bytes().starts_with(b"application/json"))
+        .unwrap_or(false);
+    if !wants_pretty || !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let value = match axum::body::to_bytes(body, usize::MAX)
+        .await
+        .map_err(internal_error)
+        .and_then(|bytes| {
+            serde_json::from_slice::<serde_json::Value>(&bytes).map_err(internal_error)
+        }) {
+        Ok(value) => value,
+        Err(err) => return err.into_response(),
+    };
+
+    let pretty = PrettyJson(value).into_response();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::new(pretty.into_body()))
+}
+
+/// Upper bound on the database check, so a saturated pool reports degraded
+/// instead of stalling the probe for the whole acquire timeout.
+const HEALTH_DB_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[utoipa::path(
+    get,
+    path = "/health",
+    responses(
+        (status = 200, body = HealthResponse),
+        (status = 503, body = HealthResponse, description = "Database unreachable"),
+    )
+)]
+async fn health(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    match tokio::time::timeout(HEALTH_DB_TIMEOUT, state.store.ping()).await {
+        Ok(Ok(())) => (StatusCode::OK, Json(HealthResponse { status: "ok" })),
+        Ok(Err(err)) => {
+            tracing::warn!("health check: database ping failed: {:#}", err);
+            degraded()
+        }
+        Err(_) => {
+            tracing::warn!("health check: database ping timed out");
+            degraded()
+        }
+    }
+}
+
+fn degraded() -> (StatusCode, Json<HealthResponse>) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(HealthResponse { status: "degraded" }),
+    )
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
 struct TopSendersParams {
+    #[param(default = 10)]
     limit: Option<u64>,
+    /// Only transactions stored for this chain id.
+    chain_id: Option<i64>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/stats/top-senders",
+    params(TopSendersParams),
+    responses(
+        (status = 200, body = TopSendersResponse),
+        (status = 500, body = ErrorBody),
+    )
+)]
 async fn stats_top_senders(
     State(state): State<AppState>,
     Query(params): Query<TopSendersParams>,
-) -> Result<Json<TopSendersResponse>, (StatusCode, String)> {
+) -> Result<Json<TopSendersResponse>, ApiError> {
     let limit = params.limit.unwrap_or(10) as i64;
-    let rows = storage::get_top_senders(&state.pool, limit)
+    let rows = state
+        .store
+        .get_top_senders(limit, params.chain_id)
         .await
         .map_err(internal_error)?;
     Ok(Json(TopSendersResponse { top_senders: rows }))
 }
 
-#[derive(Debug, Deserialize)]
+/// Same parameters as `/stats/top-senders`, grouped by receiver.
+async fn stats_top_receivers(
+    State(state): State<AppState>,
+    Query(params): Query<TopSendersParams>,
+) -> Result<Json<TopReceiversResponse>, ApiError> {
+    let limit = params.limit.unwrap_or(10) as i64;
+    let rows = state
+        .store
+        .get_top_receivers(limit, params.chain_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(TopReceiversResponse {
+        top_receivers: rows,
+    }))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
 struct GasStatsParams {
+    /// Newest stored blocks to aggregate over.
+    #[param(default = 50)]
     blocks: Option<u64>,
+    /// Only blocks stored for this chain id.
+    chain_id: Option<i64>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/stats/gas",
+    params(GasStatsParams),
+    responses(
+        (status = 200, body = GasStatsResponse),
+        (status = 500, body = ErrorBody),
+    )
+)]
 async fn stats_gas(
     State(state): State<AppState>,
     Query(params): Query<GasStatsParams>,
-) -> Result<Json<GasStatsResponse>, (StatusCode, String)> {
+) -> Result<Json<GasStatsResponse>, ApiError> {
     let blocks = params.blocks.unwrap_or(50) as i64;
-    let stats = storage::get_gas_stats(&state.pool, blocks)
+    let stats = state
+        .store
+        .get_gas_stats(blocks, params.chain_id)
         .await
         .map_err(internal_error)?;
 
-    let response = match stats {
-        Some(GasStats { min, max, avg }) => GasStatsResponse {
-            min: Some(min),
-            max: Some(max),
-            avg: Some(avg),
-        },
-        None => GasStatsResponse {
-            min: None,
-            max: None,
-            avg: None,
-        },
-    };
+    Ok(Json(GasStatsResponse::new(stats, &state.units)))
+}
+
+/// Widest accepted histogram bucket; keeps the wei divisor well inside i64.
+const MAX_HISTOGRAM_BUCKET_GWEI: u64 = 1_000_000;
+
+#[derive(Debug, Deserialize)]
+struct GasHistogramParams {
+    blocks: Option<u64>,
+    /// Bucket width in gwei.
+    bucket: Option<u64>,
+    chain_id: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct GasHistogramResponse {
+    blocks: i64,
+    bucket_width_gwei: i64,
+    buckets: Vec<GasHistogramBucket>,
+}
+
+async fn stats_gas_histogram(
+    State(state): State<AppState>,
+    Query(params): Query<GasHistogramParams>,
+) -> Result<Json<GasHistogramResponse>, ApiError> {
+    let blocks = params.blocks.unwrap_or(50) as i64;
+    let bucket = params.bucket.unwrap_or(5);
+    if bucket == 0 || bucket > MAX_HISTOGRAM_BUCKET_GWEI {
+        return Err(bad_request(format!(
+            "bucket must be between 1 and {} gwei",
+            MAX_HISTOGRAM_BUCKET_GWEI
+        )));
+    }
+    let buckets = state
+        .store
+        .get_gas_histogram(blocks, bucket as i64, params.chain_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(GasHistogramResponse {
+        blocks,
+        bucket_width_gwei: bucket as i64,
+        buckets,
+    }))
+}
 
-    Ok(Json(response))
+async fn stats_base_fee(
+    State(state): State<AppState>,
+    Query(params): Query<GasStatsParams>,
+) -> Result<Json<BaseFeeStatsResponse>, ApiError> {
+    let blocks = params.blocks.unwrap_or(50) as i64;
+    let stats = state
+        .store
+        .get_base_fee_stats(blocks, params.chain_id)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(BaseFeeStatsResponse::new(stats, &state.units)))
+}
+
+#[derive(Debug, Deserialize)]
+struct GasAtParams {
+    timestamp: Option<i64>,
+    window_secs: Option<i64>,
+    chain_id: Option<i64>,
+}
+
+async fn stats_gas_at(
+    State(state): State<AppState>,
+    Query(params): Query<GasAtParams>,
+) -> Result<Json<GasStatsResponse>, ApiError> {
+    let timestamp = params
+        .timestamp
+        .ok_or_else(|| bad_request("missing timestamp parameter".to_string()))?;
+    let window_secs = params.window_secs.unwrap_or(600);
+    if window_secs <= 0 {
+        return Err(bad_request("window_secs must be positive".to_string()));
+    }
+    let stats = state
+        .store
+        .get_gas_stats_at(timestamp, window_secs, params.chain_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(GasStatsResponse::new(stats, &state.units)))
+}
+
+#[derive(Debug, Deserialize)]
+struct LargestTxParams {
+    blocks: Option<u64>,
+    chain_id: Option<i64>,
+}
+
+async fn stats_largest_tx(
+    State(state): State<AppState>,
+    Query(params): Query<LargestTxParams>,
+) -> Result<Json<LargestTxResponse>, ApiError> {
+    let blocks = params.blocks.unwrap_or(50) as i64;
+    let transaction = state
+        .store
+        .get_max_value_transaction(blocks, params.chain_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(LargestTxResponse { transaction }))
 }
 
+#[derive(Debug, Deserialize)]
+struct ArrivalRateParams {
+    sample_id: Option<i64>,
+    bucket_secs: Option<i64>,
+}
+
+async fn stats_arrival_rate(
+    State(state): State<AppState>,
+    Query(params): Query<ArrivalRateParams>,
+) -> Result<Json<ArrivalRate>, ApiError> {
+    let bucket_secs = params.bucket_secs.unwrap_or(1);
+    state
+        .store
+        .get_arrival_rate(params.sample_id, bucket_secs)
+        .await
+        .map_err(internal_error)?
+        .map(Json)
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, "no mempool sample found"))
+}
+
+/// Longest throughput window served (30 days); larger requests are clamped.
+const MAX_THROUGHPUT_WINDOW_SECS: i64 = 30 * 24 * 3600;
+
+#[derive(Debug, Deserialize)]
+struct ThroughputParams {
+    /// Bucket width in seconds.
+    bucket: Option<i64>,
+    /// How far back from now to look, in seconds.
+    window: Option<i64>,
+    chain_id: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct ThroughputResponse {
+    bucket_secs: i64,
+    window_secs: i64,
+    buckets: Vec<ThroughputBucket>,
+}
+
+async fn stats_throughput(
+    State(state): State<AppState>,
+    Query(params): Query<ThroughputParams>,
+) -> Result<Json<ThroughputResponse>, ApiError> {
+    let bucket_secs = params.bucket.unwrap_or(60);
+    let window_secs = params.window.unwrap_or(3600);
+    if bucket_secs <= 0 || window_secs <= 0 {
+        return Err(bad_request(
+            "bucket and window must be positive".to_string(),
+        ));
+    }
+    // A bucket wider than the window would only ever hold one partial bucket.
+    let window_secs = window_secs.min(MAX_THROUGHPUT_WINDOW_SECS);
+    let bucket_secs = bucket_secs.min(window_secs);
+    let buckets = state
+        .store
+        .get_throughput(bucket_secs, window_secs, unix_now_secs(), params.chain_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(ThroughputResponse {
+        bucket_secs,
+        window_secs,
+        buckets,
+    }))
+}
+
+/// `INGEST_STATS` counters and API latency histograms for Prometheus scrapes.
+async fn prometheus_metrics() -> impl IntoResponse {
+    let mut body = metrics::render(&INGEST_STATS.snapshot());
+    body.push_str(&HTTP_LATENCY.render());
+    ([(header::CONTENT_TYPE, metrics::CONTENT_TYPE)], body)
+}
+
+#[utoipa::path(get, path = "/stats/ingest", responses((status = 200, body = IngestStatsResponse)))]
 async fn stats_ingest() -> Json<IngestStatsResponse> {
     let snap = INGEST_STATS.snapshot();
     Json(IngestStatsResponse {
         blocks: snap.blocks,
         transactions: snap.transactions,
         pending_transactions: snap.pending_transactions,
+        dropped_due_to_backpressure: snap.dropped_due_to_backpressure,
+        skipped_blocks: snap.skipped_blocks,
+        rpc_errors: snap.rpc_errors,
+        insert_errors: snap.insert_errors,
+        last_run_secs: snap.last_run_secs,
+        avg_tx_per_sec: snap.avg_tx_per_sec,
     })
 }
 
+/// Default and lower bound, in seconds, of the `/stats/ingest/stream` period.
+const INGEST_STREAM_DEFAULT_SECS: u64 = 5;
+const INGEST_STREAM_MIN_SECS: u64 = 1;
+
+#[derive(Debug, Deserialize)]
+struct IngestStreamParams {
+    interval_secs: Option<u64>,
+}
+
+/// Server-sent `ingest` events carrying an `IngestSnapshot` every
+/// `interval_secs`, starting immediately. Axum drops the stream, and with it
+/// the timer, once the client disconnects.
+async fn stats_ingest_stream(
+    Query(params): Query<IngestStreamParams>,
+) -> Sse<impl futures_util::Stream<Item = Result<Event, axum::Error>>> {
+    let secs = params
+        .interval_secs
+        .unwrap_or(INGEST_STREAM_DEFAULT_SECS)
+        .max(INGEST_STREAM_MIN_SECS);
+    let mut ticks = tokio::time::interval(Duration::from_secs(secs));
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let events = futures_util::stream::unfold(ticks, |mut ticks| async move {
+        ticks.tick().await;
+        let event = Event::default()
+            .event("ingest")
+            .json_data(INGEST_STATS.snapshot());
+        Some((event, ticks))
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+#[derive(Serialize)]
+struct LagResponse {
+    /// Cached RPC head; `None` until `serve` has fetched it.
+    head_block: Option<u64>,
+    head_age_secs: Option<u64>,
+    latest_stored_block: Option<i64>,
+    /// Blocks the database trails the head by.
+    lag_blocks: Option<u64>,
+}
+
 #[derive(Debug, Deserialize)]
+struct LagParams {
+    /// Defaults to the chain of the RPC endpoint the head is polled from.
+    chain_id: Option<i64>,
+}
+
+async fn stats_lag(
+    State(state): State<AppState>,
+    Query(params): Query<LagParams>,
+) -> Result<Json<LagResponse>, ApiError> {
+    let head_block = state.chain_head.get();
+    let chain_id = match (params.chain_id, &state.eth) {
+        (Some(chain_id), _) => Some(chain_id),
+        (None, Some(eth)) => eth.chain_id().await.ok(),
+        (None, None) => None,
+    };
+    let latest_stored_block = state
+        .store
+        .get_latest_block_number(chain_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(LagResponse {
+        head_block,
+        head_age_secs: state.chain_head.age_secs(),
+        latest_stored_block,
+        lag_blocks: state.chain_head.confirmations(latest_stored_block),
+    }))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
 struct RecentTxParams {
+    #[param(default = 20)]
     limit: Option<u64>,
+    /// Sender or receiver, case-insensitive.
+    address: Option<String>,
+    /// `true` keeps transactions with calldata, `false` plain transfers.
+    has_input: Option<bool>,
+    /// Comma-separated flag names; rows with any of them match.
+    flags: Option<String>,
+    /// 4-byte method selector, e.g. `0xa9059cbb`.
+    selector: Option<String>,
+    /// Only transactions stored for this chain id.
+    chain_id: Option<i64>,
+    /// EIP-2718 transaction type, e.g. `2` for EIP-1559.
+    tx_type: Option<i64>,
+    /// `timestamp` or `block`; defaults to `RECENT_ORDER`.
+    order: Option<String>,
+    /// Hides transactions below this many wei.
+    min_value_wei: Option<String>,
+    /// `next_cursor` from the previous page.
+    before: Option<String>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/tx/recent",
+    params(RecentTxParams),
+    responses(
+        (status = 200, body = RecentTxsResponse),
+        (status = 400, body = ErrorBody),
+        (status = 500, body = ErrorBody),
+    )
+)]
 async fn recent_txs(
     State(state): State<AppState>,
     Query(params): Query<RecentTxParams>,
-) -> Result<Json<RecentTxsResponse>, (StatusCode, String)> {
+) -> Result<Json<RecentTxsResponse>, ApiError> {
+    let limit = params.limit.unwrap_or(20) as i64;
+    let filter = RecentTxFilter {
+        address: params
+            .address
+            .as_deref()
+            .map(parse_address_param)
+            .transpose()?,
+        has_input: params.has_input,
+        flags_any: params.flags.as_deref().map(parse_flags_param).transpose()?,
+        selector: params
+            .selector
+            .as_deref()
+            .map(parse_selector_param)
+            .transpose()?,
+        chain_id: params.chain_id,
+        tx_type: params.tx_type,
+        min_value_wei: params
+            .min_value_wei
+            .as_deref()
+            .map(parse_wei_param)
+            .transpose()?,
+        order: match params.order.as_deref() {
+            Some(raw) => raw.parse().map_err(bad_request)?,
+            None => state.recent_order,
+        },
+        before: params
+            .before
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+            .map_err(bad_request)?,
+        ..Default::default()
+    };
+    if filter.before.is_some() && filter.order != RecentOrder::Timestamp {
+        return Err(bad_request(
+            "before is only supported with order=timestamp".to_string(),
+        ));
+    }
+    let cached = match (&state.recent_cache, filter.is_unfiltered()) {
+        (Some(cache), true) => cache.recent(limit as usize),
+        _ => None,
+    };
+    let txs = match cached {
+        Some(txs) => txs
+            .into_iter()
+            .map(|tx| StoredTx::derive(tx, state.mev.router_set()))
+            .collect(),
+        None => state
+            .store
+            .get_recent_stored_transactions(limit, &filter)
+            .await
+            .map_err(internal_error)?,
+    };
+    // Cursors only exist in timestamp order; there, a short page means there
+    // is nothing older left to fetch.
+    let next_cursor = match (filter.order, txs.last()) {
+        (RecentOrder::Block, _) => None,
+        (RecentOrder::Timestamp, Some(last)) if txs.len() as i64 == limit => {
+            Some(RecentCursor::after(&last.tx).encode())
+        }
+        (RecentOrder::Timestamp, _) => Some(String::new()),
+    };
+    let transactions = txs.into_iter().map(|tx| TxView::new(tx, &state)).collect();
+    Ok(Json(RecentTxsResponse {
+        transactions,
+        next_cursor,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct ByAddressParams {
+    address: Option<String>,
+    limit: Option<u64>,
+    chain_id: Option<i64>,
+}
+
+async fn txs_by_address(
+    State(state): State<AppState>,
+    Query(params): Query<ByAddressParams>,
+) -> Result<Json<RecentTxsResponse>, ApiError> {
+    let address = required_address(params.address.as_deref(), "address")?;
     let limit = params.limit.unwrap_or(20) as i64;
-    let txs = storage::get_recent_transactions(&state.pool, limit)
+    let txs = state
+        .store
+        .get_transactions_by_address(&address, limit, params.chain_id)
+        .await
+        .map_err(internal_error)?;
+    let transactions = txs.into_iter().map(|tx| TxView::new(tx, &state)).collect();
+    Ok(Json(RecentTxsResponse {
+        transactions,
+        next_cursor: None,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct WsTxsParams {
+    address: Option<String>,
+}
+
+/// Pushes each newly stored transaction as a JSON text frame shaped like a
+/// `/tx/recent` row, optionally only those from or to `address`.
+async fn ws_txs(
+    State(state): State<AppState>,
+    Query(params): Query<WsTxsParams>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    let address = params
+        .address
+        .as_deref()
+        .map(parse_address_param)
+        .transpose()?;
+    // Subscribe before the handshake so nothing stored meanwhile is missed.
+    let feed = state.tx_feed.subscribe().await.map_err(internal_error)?;
+    Ok(ws.on_upgrade(move |socket| stream_txs(socket, feed, address, state)))
+}
+
+async fn stream_txs(
+    mut socket: WebSocket,
+    mut feed: broadcast::Receiver<StoredTx>,
+    address: Option<String>,
+    state: AppState,
+) {
+    loop {
+        tokio::select! {
+            received = feed.recv() => match received {
+                Ok(stored) => {
+                    if let Some(addr) = address.as_deref() {
+                        let tx = &stored.tx;
+                        let matches = tx.from.eq_ignore_ascii_case(addr)
+                            || tx.to.as_deref().is_some_and(|to| to.eq_ignore_ascii_case(addr));
+                        if !matches {
+                            continue;
+                        }
+                    }
+                    let text = match serde_json::to_string(&TxView::new(stored, &state)) {
+                        Ok(text) => text,
+                        Err(err) => {
+                            tracing::warn!("failed to serialize tx for /ws/txs: {}", err);
+                            continue;
+                        }
+                    };
+                    if socket.send(Message::Text(text)).await.is_err() {
+                        return;
+                    }
+                }
+                // Dropping a slow client keeps the feed from ever waiting on it.
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!("closing /ws/txs client that fell {} txs behind", missed);
+                    let close = CloseFrame {
+                        code: close_code::AGAIN,
+                        reason: "client too slow".into(),
+                    };
+                    let _ = socket.send(Message::Close(Some(close))).await;
+                    return;
+                }
+                Err(RecvError::Closed) => return,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// Upper bound on hashes per `POST /tx/batch` request.
+const MAX_TX_BATCH: usize = 1000;
+
+async fn tx_batch(
+    State(state): State<AppState>,
+    Query(params): Query<TxLookupParams>,
+    JsonBody(hashes): JsonBody<Vec<String>>,
+) -> Result<Json<TxBatchResponse>, ApiError> {
+    if hashes.len() > MAX_TX_BATCH {
+        return Err(bad_request(format!(
+            "batch of {} hashes exceeds the limit of {}",
+            hashes.len(),
+            MAX_TX_BATCH
+        )));
+    }
+    let mut requested = Vec::with_capacity(hashes.len());
+    for hash in &hashes {
+        let hash = parse_hash_param(hash)?;
+        if !requested.contains(&hash) {
+            requested.push(hash);
+        }
+    }
+    let mut found: HashMap<String, StoredTx> = HashMap::new();
+    let rows = state
+        .store
+        .get_transactions_by_hashes(&requested, params.chain_id)
+        .await
+        .map_err(internal_error)?;
+    // Rows come by chain id, so a hash stored on several chains resolves to
+    // the lowest one, as for `GET /tx/:hash`.
+    for stored in rows {
+        found.entry(stored.tx.hash.clone()).or_insert(stored);
+    }
+    let mut transactions = Vec::with_capacity(found.len());
+    let mut missing = Vec::new();
+    for hash in requested {
+        match found.remove(&hash) {
+            Some(tx) => transactions.push(TxView::new(tx, &state)),
+            None => missing.push(hash),
+        }
+    }
+    Ok(Json(TxBatchResponse {
+        transactions,
+        missing,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct RecentLogsParams {
+    limit: Option<u64>,
+    address: Option<String>,
+    chain_id: Option<i64>,
+}
+
+async fn recent_logs(
+    State(state): State<AppState>,
+    Query(params): Query<RecentLogsParams>,
+) -> Result<Json<RecentLogsResponse>, ApiError> {
+    let limit = params.limit.unwrap_or(20) as i64;
+    let address = params
+        .address
+        .as_deref()
+        .map(parse_address_param)
+        .transpose()?;
+    let logs = state
+        .store
+        .get_recent_logs(limit, address.as_deref(), params.chain_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(RecentLogsResponse { logs }))
+}
+
+#[derive(Debug, Deserialize)]
+struct RecentBlocksParams {
+    limit: Option<u64>,
+    chain_id: Option<i64>,
+}
+
+async fn recent_blocks(
+    State(state): State<AppState>,
+    Query(params): Query<RecentBlocksParams>,
+) -> Result<Json<RecentBlocksResponse>, ApiError> {
+    let limit = params.limit.unwrap_or(20) as i64;
+    let blocks = state
+        .store
+        .get_recent_blocks(limit, params.chain_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(RecentBlocksResponse { blocks }))
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenTransfersParams {
+    limit: Option<u64>,
+    token: Option<String>,
+    chain_id: Option<i64>,
+}
+
+async fn token_transfers(
+    State(state): State<AppState>,
+    Query(params): Query<TokenTransfersParams>,
+) -> Result<Json<TokenTransfersResponse>, ApiError> {
+    let limit = params.limit.unwrap_or(20) as i64;
+    let token = params
+        .token
+        .as_deref()
+        .map(parse_address_param)
+        .transpose()?;
+    let transfers = state
+        .store
+        .get_token_transfers(limit, token.as_deref(), params.chain_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(TokenTransfersResponse { transfers }))
+}
+
+#[derive(Debug, Deserialize)]
+struct IngestParams {
+    blocks: Option<u64>,
+}
+
+async fn trigger_ingest(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<IngestParams>,
+) -> Result<Json<IngestSummary>, ApiError> {
+    require_api_key(&state, &headers)?;
+    let blocks = params.blocks.unwrap_or(1);
+    if blocks > state.ingest.blocks_soft_cap {
+        return Err(bad_request(format!(
+            "blocks={} exceeds INGEST_BLOCKS_SOFT_CAP ({}); use ingest-once --yes instead",
+            blocks, state.ingest.blocks_soft_cap
+        )));
+    }
+    let eth = state.eth.as_ref().ok_or_else(|| {
+        ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "ingestion requires an RPC client",
+        )
+    })?;
+    let started = Instant::now();
+    let summary = ingest::ingest_recent(
+        eth,
+        Some(state.store.as_ref()),
+        blocks,
+        &state.ingest,
+        state.recent_cache.as_deref(),
+    )
+    .await
+    .map_err(internal_error)?;
+    INGEST_STATS.record_run(started.elapsed(), summary.transactions as u64);
+    tracing::info!(
+        "on-demand ingest stored {} blocks, {} transactions",
+        summary.blocks,
+        summary.transactions
+    );
+    Ok(Json(summary))
+}
+
+#[derive(Debug, Deserialize)]
+struct TimelineParams {
+    address: Option<String>,
+    limit: Option<u64>,
+    before: Option<i64>,
+    chain_id: Option<i64>,
+}
+
+async fn address_timeline(
+    State(state): State<AppState>,
+    Query(params): Query<TimelineParams>,
+) -> Result<Json<TimelineResponse>, ApiError> {
+    let address = required_address(params.address.as_deref(), "address")?;
+    let limit = params.limit.unwrap_or(100) as i64;
+    let timeline = state
+        .store
+        .get_address_timeline(&address, limit, params.before, params.chain_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(TimelineResponse { address, timeline }))
+}
+
+#[derive(Debug, Deserialize)]
+struct AddressSummaryParams {
+    chain_id: Option<i64>,
+}
+
+async fn address_summary(
+    State(state): State<AppState>,
+    Path(addr): Path<String>,
+    Query(params): Query<AddressSummaryParams>,
+) -> Result<Json<AddressSummary>, ApiError> {
+    let address = parse_address_param(&addr)?;
+    let summary = state
+        .store
+        .get_address_summary(&address, params.chain_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(summary))
+}
+
+#[derive(Debug, Deserialize)]
+struct NonceGapsParams {
+    chain_id: Option<i64>,
+}
+
+async fn address_nonce_gaps(
+    State(state): State<AppState>,
+    Path(addr): Path<String>,
+    Query(params): Query<NonceGapsParams>,
+) -> Result<Json<NonceGaps>, ApiError> {
+    let address = parse_address_param(&addr)?;
+    let gaps = state
+        .store
+        .get_nonce_gaps(&address, params.chain_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(gaps))
+}
+
+#[derive(Debug, Deserialize)]
+struct FlowParams {
+    from: Option<String>,
+    to: Option<String>,
+    chain_id: Option<i64>,
+}
+
+async fn address_flow(
+    State(state): State<AppState>,
+    Query(params): Query<FlowParams>,
+) -> Result<Json<FlowSummary>, ApiError> {
+    let from = required_address(params.from.as_deref(), "from")?;
+    let to = required_address(params.to.as_deref(), "to")?;
+    let flow = state
+        .store
+        .get_flow_between(&from, &to, params.chain_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(flow))
+}
+
+#[derive(Debug, Deserialize)]
+struct CompareParams {
+    window_a: Option<String>,
+    window_b: Option<String>,
+    chain_id: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct WindowDelta {
+    tx_count: i64,
+    avg_gas_price_wei: Option<f64>,
+    /// Signed decimal wei difference (`b - a`).
+    avg_value_wei: Option<String>,
+    unique_addresses: i64,
+}
+
+#[derive(Serialize)]
+struct CompareResponse {
+    window_a: WindowSummary,
+    window_b: WindowSummary,
+    /// `window_b` minus `window_a`.
+    delta: WindowDelta,
+}
+
+async fn compare_windows(
+    State(state): State<AppState>,
+    Query(params): Query<CompareParams>,
+) -> Result<Json<CompareResponse>, ApiError> {
+    let (a_from, a_to) = parse_block_window(params.window_a.as_deref(), "window_a")?;
+    let (b_from, b_to) = parse_block_window(params.window_b.as_deref(), "window_b")?;
+    let window_a = state
+        .store
+        .get_window_summary(a_from, a_to, params.chain_id)
+        .await
+        .map_err(internal_error)?;
+    let window_b = state
+        .store
+        .get_window_summary(b_from, b_to, params.chain_id)
+        .await
+        .map_err(internal_error)?;
+
+    let delta = WindowDelta {
+        tx_count: window_b.tx_count - window_a.tx_count,
+        avg_gas_price_wei: window_a
+            .avg_gas_price_wei
+            .zip(window_b.avg_gas_price_wei)
+            .map(|(a, b)| b - a),
+        avg_value_wei: window_a
+            .avg_value_wei
+            .as_deref()
+            .zip(window_b.avg_value_wei.as_deref())
+            .and_then(|(a, b)| signed_wei_delta(a, b)),
+        unique_addresses: window_b.unique_addresses - window_a.unique_addresses,
+    };
+    Ok(Json(CompareResponse {
+        window_a,
+        window_b,
+        delta,
+    }))
+}
+
+fn parse_flags_param(raw: &str) -> Result<i64, ApiError> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .try_fold(0, |mask, name| {
+            TxFlags::bit_named(name)
+                .map(|bit| mask | bit)
+                .ok_or_else(|| bad_request(format!("unknown flag {:?}", name)))
+        })
+}
+
+/// Parses an inclusive `start-end` block range.
+fn parse_block_window(raw: Option<&str>, name: &str) -> Result<(i64, i64), ApiError> {
+    let raw = raw.ok_or_else(|| bad_request(format!("missing {} parameter", name)))?;
+    let parsed = raw.split_once('-').and_then(|(from, to)| {
+        Some((
+            from.trim().parse::<i64>().ok()?,
+            to.trim().parse::<i64>().ok()?,
+        ))
+    });
+    match parsed {
+        Some((from, to)) if 0 <= from && from <= to => Ok((from, to)),
+        _ => Err(bad_request(format!(
+            "invalid {} {:?}; expected start-end block numbers",
+            name, raw
+        ))),
+    }
+}
+
+/// `b - a` for decimal wei strings, with a leading `-` when negative.
+fn signed_wei_delta(a: &str, b: &str) -> Option<String> {
+    let a = U256::from_dec_str(a).ok()?;
+    let b = U256::from_dec_str(b).ok()?;
+    Some(if b >= a {
+        (b - a).to_string()
+    } else {
+        format!("-{}", a - b)
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct MevTargetsParams {
+    limit: Option<u64>,
+    chain_id: Option<i64>,
+}
+
+async fn mev_targets(
+    State(state): State<AppState>,
+    Query(params): Query<MevTargetsParams>,
+) -> Result<Json<MevTargetsResponse>, ApiError> {
+    let limit = params.limit.unwrap_or(500) as i64;
+    let filter = RecentTxFilter {
+        pending_only: true,
+        to_any: Some(state.mev.routers().cloned().collect()),
+        chain_id: params.chain_id,
+        ..Default::default()
+    };
+    let txs = state
+        .store
+        .get_recent_transactions(limit, &filter)
         .await
         .map_err(internal_error)?;
-    Ok(Json(RecentTxsResponse { transactions: txs }))
+
+    let targets = txs
+        .into_iter()
+        .filter_map(|tx| {
+            let reasons = state.mev.classify(&tx);
+            (!reasons.is_empty()).then_some(MevTarget { tx, reasons })
+        })
+        .collect();
+    Ok(Json(MevTargetsResponse { targets }))
+}
+
+#[derive(Debug, Deserialize)]
+struct RbfBumpsParams {
+    address: Option<String>,
+    nonce: Option<i64>,
+    chain_id: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct RbfBumpsResponse {
+    address: String,
+    nonce: i64,
+    attempts: Vec<RbfBump>,
+}
+
+/// Fee increases across stored attempts for one `(from, nonce)`.
+async fn rbf_bumps(
+    State(state): State<AppState>,
+    Query(params): Query<RbfBumpsParams>,
+) -> Result<Json<RbfBumpsResponse>, ApiError> {
+    let address = required_address(params.address.as_deref(), "address")?;
+    let nonce = params
+        .nonce
+        .ok_or_else(|| bad_request("missing nonce parameter".to_string()))?;
+    let attempts = state
+        .store
+        .get_nonce_attempts(&address, nonce, params.chain_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(RbfBumpsResponse {
+        address,
+        nonce,
+        attempts: analysis::rbf_bumps(&attempts),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct InclusionParams {
+    blocks: Option<u64>,
+    limit: Option<u64>,
+    chain_id: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct InclusionEstimate {
+    hash: String,
+    effective_gas_price_wei: Option<String>,
+    /// `None` when the tx carries no price or there is no reference median.
+    label: Option<InclusionLabel>,
+}
+
+#[derive(Serialize)]
+struct InclusionResponse {
+    blocks: u64,
+    median_gas_price_wei: Option<String>,
+    estimates: Vec<InclusionEstimate>,
+}
+
+/// Heuristic: labels pending transactions by their bid relative to the median
+/// gas price included over the last `blocks` stored blocks.
+async fn inclusion_estimate(
+    State(state): State<AppState>,
+    Query(params): Query<InclusionParams>,
+) -> Result<Json<InclusionResponse>, ApiError> {
+    let blocks = params.blocks.unwrap_or(20);
+    let limit = params.limit.unwrap_or(50) as i64;
+    let mut included = state
+        .store
+        .stream_block_transactions(blocks as i64, params.chain_id)
+        .try_fold(Vec::new(), |mut acc, tx| async move {
+            acc.extend(analysis::effective_gas_price(&tx));
+            Ok(acc)
+        })
+        .await
+        .map_err(internal_error)?;
+    let median = analysis::median(&mut included);
+
+    let filter = RecentTxFilter {
+        pending_only: true,
+        chain_id: params.chain_id,
+        ..Default::default()
+    };
+    let pending = state
+        .store
+        .get_recent_transactions(limit, &filter)
+        .await
+        .map_err(internal_error)?;
+    let estimates = pending
+        .into_iter()
+        .map(|tx| {
+            let price = analysis::effective_gas_price(&tx);
+            InclusionEstimate {
+                hash: tx.hash,
+                effective_gas_price_wei: price.map(|p| p.to_string()),
+                label: price
+                    .zip(median)
+                    .map(|(price, median)| analysis::classify_inclusion(price, median)),
+            }
+        })
+        .collect();
+
+    Ok(Json(InclusionResponse {
+        blocks,
+        median_gas_price_wei: median.map(|m| m.to_string()),
+        estimates,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct TxLookupParams {
+    /// Without it, a hash stored on several chains resolves to the lowest
+    /// chain id.
+    chain_id: Option<i64>,
+}
+
+async fn get_tx(
+    State(state): State<AppState>,
+    Path(hash): Path<String>,
+    Query(params): Query<TxLookupParams>,
+) -> Result<Json<TxView>, ApiError> {
+    let hash = parse_hash_param(&hash)?;
+    let tx = state
+        .store
+        .get_transaction_by_hash(&hash, params.chain_id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                format!("transaction {} not found", hash),
+            )
+        })?;
+    Ok(Json(TxView::new(tx, &state)))
+}
+
+async fn simulate_tx(
+    State(state): State<AppState>,
+    Path(hash): Path<String>,
+    Query(params): Query<TxLookupParams>,
+) -> Result<Json<SimulateResponse>, ApiError> {
+    let hash = parse_hash_param(&hash)?;
+    let tx = state
+        .store
+        .get_transaction_by_hash(&hash, params.chain_id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                format!("transaction {} not found", hash),
+            )
+        })?
+        .tx;
+    if tx.block_number.is_some() {
+        return Err(bad_request(format!(
+            "transaction {} is already mined; only pending transactions can be simulated",
+            hash
+        )));
+    }
+    let eth = state.eth.as_ref().ok_or_else(|| {
+        ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "simulation requires an RPC client",
+        )
+    })?;
+
+    let result = eth
+        .simulate(&tx)
+        .await
+        .map_err(|e| ApiError::new(StatusCode::BAD_GATEWAY, format!("{:#}", e)))?;
+    Ok(Json(SimulateResponse { hash, result }))
+}
+
+/// Normalizes a transaction hash path/query parameter to `0x` + 64 lowercase
+/// hex chars, rejecting anything else with 400.
+fn parse_hash_param(raw: &str) -> Result<String, ApiError> {
+    let trimmed = raw.trim();
+    let hex = trimmed
+        .strip_prefix("0x")
+        .or_else(|| trimmed.strip_prefix("0X"))
+        .ok_or_else(|| bad_request(format!("invalid transaction hash: {:?}", raw)))?;
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(bad_request(format!("invalid transaction hash: {:?}", raw)));
+    }
+    Ok(format!("0x{}", hex.to_ascii_lowercase()))
+}
+
+/// Normalizes a method selector (`0x` + 8 hex chars) to lowercase.
+fn parse_selector_param(raw: &str) -> Result<String, ApiError> {
+    let trimmed = raw.trim();
+    match trimmed
+        .strip_prefix("0x")
+        .or_else(|| trimmed.strip_prefix("0X"))
+    {
+        Some(hex) if hex.len() == 8 && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+            Ok(format!("0x{}", hex.to_ascii_lowercase()))
+        }
+        _ => Err(bad_request(format!("invalid selector: {:?}", raw))),
+    }
+}
+
+/// Validates a non-negative decimal wei amount and strips leading zeros so it
+/// matches the stored canonical form.
+fn parse_wei_param(raw: &str) -> Result<String, ApiError> {
+    storage::canonical_wei(raw).ok_or_else(|| bad_request(format!("invalid wei amount: {:?}", raw)))
+}
+
+/// Normalizes an address query parameter to the stored form (`0x` + 40
+/// lowercase hex chars). Surrounding whitespace and a missing `0x` prefix are
+/// tolerated; anything else is rejected with 400.
+fn parse_address_param(raw: &str) -> Result<String, ApiError> {
+    let trimmed = raw.trim();
+    let hex = trimmed
+        .strip_prefix("0x")
+        .or_else(|| trimmed.strip_prefix("0X"))
+        .unwrap_or(trimmed);
+    if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(bad_request(format!("invalid address: {:?}", raw)));
+    }
+    Ok(format!("0x{}", hex.to_ascii_lowercase()))
+}
+
+fn required_address(raw: Option<&str>, name: &str) -> Result<String, ApiError> {
+    let raw = raw.ok_or_else(|| bad_request(format!("missing {} parameter", name)))?;
+    parse_address_param(raw)
+}
+
+/// Checks the `x-api-key` header against `API_KEY`. Without a configured key
+/// the guarded endpoints stay disabled.
+fn require_api_key(state: &AppState, headers: &HeaderMap) -> Result<(), ApiError> {
+    let Some(expected) = state.api_key.as_deref() else {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "endpoint disabled; set API_KEY to enable it",
+        ));
+    };
+    match headers.get("x-api-key").and_then(|v| v.to_str().ok()) {
+        Some(given) if constant_time_eq(given.as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => Err(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "missing or invalid x-api-key",
+        )),
+    }
+}
+
+/// Error returned by every handler, rendered as
+/// `{"error": {"code": "bad_request", "message": "..."}}` with the matching
+/// status. `code` is the snake_case reason phrase of the status.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// JSON shape of an [`ApiError`].
+#[derive(Serialize, ToSchema)]
+struct ErrorBody {
+    error: ErrorDetail,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ErrorDetail {
+    #[schema(example = "bad_request")]
+    code: String,
+    message: String,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let code = self
+            .status
+            .canonical_reason()
+            .unwrap_or("error")
+            .to_ascii_lowercase()
+            .replace(' ', "_");
+        let body = ErrorBody {
+            error: ErrorDetail {
+                code,
+                message: self.message,
+            },
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+fn bad_request(message: String) -> ApiError {
+    ApiError::new(StatusCode::BAD_REQUEST, message)
 }
 
-fn internal_error<E: std::fmt::Display>(err: E) -> (StatusCode, String) {
-    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+fn internal_error<E: std::fmt::Display>(err: E) -> ApiError {
+    ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
 }
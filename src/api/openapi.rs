@@ -0,0 +1,39 @@
+use axum::Json;
+use utoipa::OpenApi;
+
+use super::{
+    ErrorBody, ErrorDetail, GasStatsResponse, HealthResponse, IngestStatsResponse,
+    RecentTxsResponse, TopSendersResponse, TxView,
+};
+use crate::models::{NormalizedTxJson, TopSender};
+
+/// OpenAPI 3.0 description of the annotated routes. Paths are relative to
+/// `API_PREFIX` when one is set.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "mempool-sentinel API"),
+    paths(
+        super::health,
+        super::stats_top_senders,
+        super::stats_gas,
+        super::stats_ingest,
+        super::recent_txs,
+    ),
+    components(schemas(
+        HealthResponse,
+        TopSendersResponse,
+        TopSender,
+        GasStatsResponse,
+        IngestStatsResponse,
+        RecentTxsResponse,
+        TxView,
+        NormalizedTxJson,
+        ErrorBody,
+        ErrorDetail,
+    ))
+)]
+pub(super) struct ApiDoc;
+
+pub(super) async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
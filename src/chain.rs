@@ -0,0 +1,128 @@
+use std::sync::OnceLock;
+
+/// Units loaded from the configuration, installed once at startup.
+static CONFIGURED: OnceLock<ChainUnits> = OnceLock::new();
+
+/// Display units for a chain's native token and gas price, so formatted
+/// values stay meaningful on networks that do not follow Ethereum's scale.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainUnits {
+    pub chain_id: u64,
+    /// Ticker of the native token (`ETH` on mainnet).
+    pub symbol: String,
+    /// Decimals between the base unit and one whole native token.
+    pub native_decimals: u32,
+    /// Decimals between the base unit and the unit gas prices are quoted in.
+    pub gas_price_decimals: u32,
+    /// Name of the gas price display unit (`gwei` on mainnet).
+    pub gas_price_unit: String,
+}
+
+impl Default for ChainUnits {
+    fn default() -> Self {
+        Self::for_chain_id(1)
+    }
+}
+
+impl ChainUnits {
+    /// Built-in presets for a few well-known chains; anything else gets
+    /// Ethereum's 18/9 decimals under a generic symbol.
+    pub fn for_chain_id(chain_id: u64) -> Self {
+        let symbol = match chain_id {
+            1 | 10 | 8453 | 42161 | 11155111 => "ETH",
+            56 => "BNB",
+            137 => "POL",
+            100 => "xDAI",
+            43114 => "AVAX",
+            _ => "NATIVE",
+        };
+        Self {
+            chain_id,
+            symbol: symbol.to_string(),
+            native_decimals: 18,
+            gas_price_decimals: 9,
+            gas_price_unit: "gwei".to_string(),
+        }
+    }
+
+    /// Makes these the units reported by [`ChainUnits::native_decimals_for`]
+    /// for their chain; only the first call takes effect.
+    pub fn install(self) {
+        let _ = CONFIGURED.set(self);
+    }
+
+    /// Native decimals for transactions observed on `chain_id`: the installed
+    /// units (so `NATIVE_DECIMALS` applies) on the configured chain, the
+    /// built-in preset on any other.
+    pub fn native_decimals_for(chain_id: i64) -> u32 {
+        match CONFIGURED.get() {
+            Some(units) if units.chain_id as i64 == chain_id => units.native_decimals,
+            _ => Self::for_chain_id(chain_id as u64).native_decimals,
+        }
+    }
+
+    /// Formats a decimal base-unit amount in whole native tokens.
+    pub fn format_value(&self, base_units: &str) -> Option<String> {
+        format_units(base_units, self.native_decimals, true)
+    }
+
+    /// Formats a decimal base-unit gas price in the chain's gas price unit.
+    pub fn format_gas_price(&self, base_units: &str) -> Option<String> {
+        format_units(base_units, self.gas_price_decimals, true)
+    }
+}
+
+/// Shifts a non-negative decimal integer string right by `decimals` places,
+/// trimming trailing zeros when `trim` is set and keeping all `decimals`
+/// digits otherwise. Works on strings so amounts wider than 64 bits keep full
+/// precision.
+pub fn format_units(raw: &str, decimals: u32, trim: bool) -> Option<String> {
+    if raw.is_empty() || !raw.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let digits = raw.trim_start_matches('0');
+    let decimals = decimals as usize;
+    let padded = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, frac) = padded.split_at(padded.len() - decimals);
+    let frac = if trim {
+        frac.trim_end_matches('0')
+    } else {
+        frac
+    };
+    Some(if frac.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, frac)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_units_handles_scale_and_width() {
+        assert_eq!(format_units("1500000000", 9, true).as_deref(), Some("1.5"));
+        assert_eq!(format_units("42", 9, true).as_deref(), Some("0.000000042"));
+        assert_eq!(format_units("0", 18, true).as_deref(), Some("0"));
+        assert_eq!(format_units("7", 0, true).as_deref(), Some("7"));
+        assert_eq!(
+            format_units("123456789012345678901234567890", 18, true).as_deref(),
+            Some("123456789012.34567890123456789")
+        );
+        assert_eq!(format_units("-1", 9, true), None);
+        assert_eq!(
+            format_units("1500000000", 9, false).as_deref(),
+            Some("1.500000000")
+        );
+        assert_eq!(format_units("7", 0, false).as_deref(), Some("7"));
+    }
+
+    #[test]
+    fn unknown_chain_keeps_ethereum_scale() {
+        let units = ChainUnits::for_chain_id(999_999);
+        assert_eq!(units.native_decimals, 18);
+        assert_eq!(units.gas_price_unit, "gwei");
+        assert_eq!(ChainUnits::for_chain_id(137).symbol, "POL");
+    }
+}
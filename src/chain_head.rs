@@ -0,0 +1,82 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::eth::{unix_now_secs, EthClient};
+
+/// Latest known chain head, shared between a background refresher and the
+/// request handlers that read it. Zero means "not fetched yet".
+#[derive(Debug, Default)]
+pub struct ChainHead {
+    number: AtomicU64,
+    updated_at: AtomicU64,
+}
+
+impl ChainHead {
+    pub const fn new() -> Self {
+        Self {
+            number: AtomicU64::new(0),
+            updated_at: AtomicU64::new(0),
+        }
+    }
+
+    pub fn set(&self, number: u64) {
+        self.number.store(number, Ordering::Relaxed);
+        self.updated_at.store(unix_now(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> Option<u64> {
+        match self.number.load(Ordering::Relaxed) {
+            0 => None,
+            n => Some(n),
+        }
+    }
+
+    /// Seconds since the head was last refreshed, or `None` before the first fetch.
+    pub fn age_secs(&self) -> Option<u64> {
+        self.get()?;
+        Some(unix_now().saturating_sub(self.updated_at.load(Ordering::Relaxed)))
+    }
+
+    /// Confirmations for a transaction mined in `block_number`, or `None` when
+    /// the transaction is pending or the head is unknown.
+    pub fn confirmations(&self, block_number: Option<i64>) -> Option<u64> {
+        let head = self.get()?;
+        let block = u64::try_from(block_number?).ok()?;
+        Some(head.saturating_sub(block))
+    }
+}
+
+/// Polls the RPC head every `interval` and publishes it into `head`.
+pub async fn run_refresher(head: std::sync::Arc<ChainHead>, eth: EthClient, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        match eth.latest_block_number().await {
+            Ok(number) => head.set(number),
+            Err(err) => tracing::debug!("chain head refresh failed: {:#}", err),
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    unix_now_secs().max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confirmations_need_head_and_block() {
+        let head = ChainHead::new();
+        assert_eq!(head.confirmations(Some(5)), None);
+
+        assert_eq!(head.age_secs(), None);
+
+        head.set(12);
+        assert!(head.age_secs().unwrap() <= 1);
+        assert_eq!(head.confirmations(Some(5)), Some(7));
+        assert_eq!(head.confirmations(Some(20)), Some(0));
+        assert_eq!(head.confirmations(None), None);
+    }
+}
@@ -1,4 +1,6 @@
-use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -9,27 +11,97 @@ use clap::{Parser, Subcommand};
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+    /// Output of the query commands (top-senders, balances, recent-txs, gas-stats)
+    /// and of watch-address alerts
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+    /// TOML file with settings; environment variables override its values
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Fetch and filter without writing to the database (ingest-once,
+    /// mempool-sample); logs what would have been inserted
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON document on stdout
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// Header row plus one row per transaction, text columns quoted
+    Csv,
+    /// One JSON array of transactions
+    Json,
 }
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
-    /// Fetch last N blocks and store transactions
+    /// Fetch last N blocks, or a fixed block range, and store transactions
     IngestOnce {
         #[arg(long, default_value_t = 5)]
         blocks: u64,
+        /// First block of an inclusive range to ingest instead of the last N
+        #[arg(long, requires = "to_block", conflicts_with = "blocks")]
+        from_block: Option<u64>,
+        /// Last block of the `--from-block` range
+        #[arg(long, requires = "from_block", conflicts_with = "blocks")]
+        to_block: Option<u64>,
+        /// Allow `--blocks` (or a range) above the INGEST_BLOCKS_SOFT_CAP safety limit
+        #[arg(long)]
+        yes: bool,
+        /// Fetch receipts to record success/failed status (about doubles RPC calls)
+        #[arg(long)]
+        with_receipts: bool,
+    },
+    /// Keep ingesting new blocks as the chain head advances
+    Follow {
+        /// Seconds between head polls
+        #[arg(long, default_value_t = 12)]
+        poll_secs: u64,
     },
     /// Sample pending txs for a duration
     MempoolSample {
         #[arg(long, default_value_t = 15)]
         duration_secs: u64,
+        /// Stop after observing this many pending hashes, counted before
+        /// filtering, so fewer may be inserted
         #[arg(long, default_value_t = 500)]
         max: u64,
+        /// Number of recent pending hashes remembered to skip duplicates (0 disables)
+        #[arg(long, default_value_t = 10_000)]
+        dedup_window: u64,
+        /// Write buffered transactions once this many have collected
+        #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u64).range(1..))]
+        flush_size: u64,
+        /// Also write the buffer at least this often, even below `--flush-size`
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        flush_interval_secs: Option<u64>,
+    },
+    /// Alert on pending transactions from or to the given addresses
+    WatchAddress {
+        /// Address to watch; repeat or comma-separate for several
+        #[arg(long = "address", required = true, value_delimiter = ',', value_parser = parse_address)]
+        addresses: Vec<String>,
+        /// POST each alert as JSON to this URL as well
+        #[arg(long, value_name = "URL")]
+        webhook: Option<String>,
     },
     /// Print top senders by tx count
     TopSenders {
         #[arg(long, default_value_t = 10)]
         limit: u64,
     },
+    /// Print current ETH balances of the top senders
+    Balances {
+        #[arg(long, default_value_t = 10)]
+        limit: u64,
+    },
     /// Print recent transactions
     RecentTxs {
         #[arg(long, default_value_t = 20)]
@@ -40,10 +112,279 @@ pub enum Commands {
         #[arg(long, default_value_t = 10)]
         blocks: u64,
     },
+    /// Delete mined transactions, and then their emptied blocks, older than N days
+    Prune {
+        #[arg(long)]
+        older_than_days: u64,
+        /// Run VACUUM afterwards to return the freed space to the filesystem
+        #[arg(long)]
+        vacuum: bool,
+    },
+    /// Write stored transactions, oldest block first, to a CSV or JSON file
+    Export {
+        /// File layout (the global `--format` only applies to the query commands)
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        export_format: ExportFormat,
+        #[arg(long, value_name = "PATH")]
+        out: PathBuf,
+        /// Stop after this many transactions
+        #[arg(long)]
+        limit: Option<u64>,
+        /// First block to include; pending transactions are skipped when a bound is set
+        #[arg(long)]
+        from_block: Option<u64>,
+        /// Last block to include
+        #[arg(long)]
+        to_block: Option<u64>,
+    },
     /// Run the HTTP API server
     Serve {
         /// Override bind address, e.g. 0.0.0.0:8080
         #[arg(long)]
         addr: Option<String>,
+        /// Also follow the chain head in the background, polling every N seconds
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        follow_secs: Option<u64>,
     },
 }
+
+/// Lowercases `raw` so it compares equal to normalized transaction addresses.
+fn parse_address(raw: &str) -> Result<String, String> {
+    let addr = raw.trim().to_ascii_lowercase();
+    if crate::config::is_hex_address(&addr) {
+        Ok(addr)
+    } else {
+        Err("expected a 0x-prefixed 20-byte hex address".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serve_follows_only_when_asked() {
+        let cli = Cli::try_parse_from(["rust-eth-mempool-lab", "serve"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Serve {
+                follow_secs: None,
+                ..
+            }
+        ));
+        let cli =
+            Cli::try_parse_from(["rust-eth-mempool-lab", "serve", "--follow-secs", "6"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Serve {
+                follow_secs: Some(6),
+                ..
+            }
+        ));
+        assert!(
+            Cli::try_parse_from(["rust-eth-mempool-lab", "serve", "--follow-secs", "0"]).is_err()
+        );
+    }
+
+    #[test]
+    fn mempool_sample_parses_duration_and_max() {
+        let cli = Cli::try_parse_from([
+            "rust-eth-mempool-lab",
+            "mempool-sample",
+            "--duration-secs",
+            "5",
+            "--max",
+            "50",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::MempoolSample {
+                duration_secs,
+                max,
+                flush_size,
+                flush_interval_secs,
+                ..
+            } => {
+                assert_eq!(duration_secs, 5);
+                assert_eq!(max, 50);
+                assert_eq!(flush_size, 100);
+                assert_eq!(flush_interval_secs, None);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn mempool_sample_takes_flush_size_and_interval() {
+        let cli = Cli::try_parse_from([
+            "rust-eth-mempool-lab",
+            "mempool-sample",
+            "--flush-size",
+            "25",
+            "--flush-interval-secs",
+            "2",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::MempoolSample {
+                flush_size: 25,
+                flush_interval_secs: Some(2),
+                ..
+            }
+        ));
+        for zero in ["--flush-size", "--flush-interval-secs"] {
+            assert!(
+                Cli::try_parse_from(["rust-eth-mempool-lab", "mempool-sample", zero, "0"]).is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn format_defaults_to_text_and_is_global() {
+        let cli = Cli::try_parse_from(["rust-eth-mempool-lab", "gas-stats"]).unwrap();
+        assert_eq!(cli.format, OutputFormat::Text);
+        let cli = Cli::try_parse_from(["rust-eth-mempool-lab", "top-senders", "--format", "json"])
+            .unwrap();
+        assert_eq!(cli.format, OutputFormat::Json);
+        assert!(
+            Cli::try_parse_from(["rust-eth-mempool-lab", "--format", "xml", "gas-stats"]).is_err()
+        );
+    }
+
+    #[test]
+    fn config_path_is_global() {
+        let cli =
+            Cli::try_parse_from(["rust-eth-mempool-lab", "serve", "--config", "app.toml"]).unwrap();
+        assert_eq!(cli.config, Some(PathBuf::from("app.toml")));
+        let cli = Cli::try_parse_from(["rust-eth-mempool-lab", "gas-stats"]).unwrap();
+        assert_eq!(cli.config, None);
+    }
+
+    #[test]
+    fn dry_run_is_global_and_off_by_default() {
+        let cli = Cli::try_parse_from(["rust-eth-mempool-lab", "ingest-once"]).unwrap();
+        assert!(!cli.dry_run);
+        let cli =
+            Cli::try_parse_from(["rust-eth-mempool-lab", "mempool-sample", "--dry-run"]).unwrap();
+        assert!(cli.dry_run);
+        let cli =
+            Cli::try_parse_from(["rust-eth-mempool-lab", "--dry-run", "ingest-once"]).unwrap();
+        assert!(cli.dry_run);
+    }
+
+    #[test]
+    fn prune_requires_age_and_takes_vacuum_flag() {
+        let cli = Cli::try_parse_from([
+            "rust-eth-mempool-lab",
+            "prune",
+            "--older-than-days",
+            "30",
+            "--vacuum",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Prune {
+                older_than_days: 30,
+                vacuum: true
+            }
+        ));
+        assert!(Cli::try_parse_from(["rust-eth-mempool-lab", "prune"]).is_err());
+    }
+
+    #[test]
+    fn export_defaults_to_csv_and_takes_block_range() {
+        let cli = Cli::try_parse_from([
+            "rust-eth-mempool-lab",
+            "export",
+            "--out",
+            "txs.csv",
+            "--from-block",
+            "100",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Export {
+                export_format,
+                out,
+                limit,
+                from_block,
+                to_block,
+            } => {
+                assert_eq!(export_format, ExportFormat::Csv);
+                assert_eq!(out, PathBuf::from("txs.csv"));
+                assert_eq!((limit, from_block, to_block), (None, Some(100), None));
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn watch_address_collects_lowercased_addresses() {
+        let a = format!("0x{}", "AB".repeat(20));
+        let b = format!("0x{}", "cd".repeat(20));
+        let c = format!("0x{}", "ef".repeat(20));
+        let joined = format!("{},{}", b, c);
+        let cli = Cli::try_parse_from([
+            "rust-eth-mempool-lab",
+            "watch-address",
+            "--address",
+            &a,
+            "--address",
+            &joined,
+            "--webhook",
+            "http://localhost:9000/hook",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::WatchAddress { addresses, webhook } => {
+                assert_eq!(addresses, vec![a.to_ascii_lowercase(), b, c]);
+                assert_eq!(webhook.as_deref(), Some("http://localhost:9000/hook"));
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+        assert!(Cli::try_parse_from(["rust-eth-mempool-lab", "watch-address"]).is_err());
+        assert!(Cli::try_parse_from([
+            "rust-eth-mempool-lab",
+            "watch-address",
+            "--address",
+            "0x1234"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn ingest_once_takes_range_or_blocks_but_not_both() {
+        let cli = Cli::try_parse_from([
+            "rust-eth-mempool-lab",
+            "ingest-once",
+            "--from-block",
+            "100",
+            "--to-block",
+            "120",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::IngestOnce {
+                from_block: Some(100),
+                to_block: Some(120),
+                ..
+            }
+        ));
+        let parse = |args: &[&str]| {
+            Cli::try_parse_from(["rust-eth-mempool-lab", "ingest-once"].iter().chain(args))
+        };
+        assert!(parse(&["--from-block", "100"]).is_err());
+        assert!(parse(&["--to-block", "120"]).is_err());
+        assert!(parse(&["--blocks", "3", "--from-block", "1", "--to-block", "2"]).is_err());
+    }
+
+    #[test]
+    fn recent_txs_parses_limit() {
+        let cli =
+            Cli::try_parse_from(["rust-eth-mempool-lab", "recent-txs", "--limit", "3"]).unwrap();
+        assert!(matches!(cli.command, Commands::RecentTxs { limit: 3 }));
+    }
+}
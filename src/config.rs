@@ -1,41 +1,510 @@
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::env;
+use std::path::{Path, PathBuf};
+
+use ethers_core::types::{H256, U256};
+
+use crate::chain::ChainUnits;
+use crate::eth::{
+    MempoolSubMode, DEFAULT_FETCH_CONCURRENCY, DEFAULT_RPC_MAX_RETRIES, DEFAULT_RPC_RETRY_BASE_MS,
+};
+use crate::insert_queue::BackpressurePolicy;
+use crate::storage::{
+    RecentOrder, DEFAULT_DB_MAX_CONNECTIONS, DEFAULT_INSERT_QUEUE_CAPACITY,
+    DEFAULT_SQLITE_BUSY_TIMEOUT_MS,
+};
+
+const DEFAULT_INGEST_BLOCKS_SOFT_CAP: u64 = 1000;
 
 #[derive(Debug, Clone)]
 pub struct Config {
+    /// One or more comma-separated HTTP endpoints, tried in order on failure.
     pub eth_rpc_url: String,
     pub eth_ws_url: Option<String>,
     pub database_url: String,
     pub http_bind_addr: String,
     pub filter_addresses: Option<HashSet<String>>,
+    /// Keep only transactions worth at least this much (decimal wei).
+    pub filter_min_value_wei: Option<String>,
+    /// Fraction of each block's transactions kept during ingest, in `(0, 1]`.
+    pub block_sample_rate: Option<f64>,
+    /// Prometheus Pushgateway base URL; batch commands push final counters here.
+    pub pushgateway_url: Option<String>,
+    /// Force the per-hash hydration path instead of full-block RPC requests.
+    pub eth_disable_full_block: bool,
+    /// Reject block fetches spanning more than this many blocks in one call.
+    pub eth_max_blocks_per_call: Option<u64>,
+    /// RPC requests in flight at once while fetching blocks, e.g. when
+    /// hydrating transactions by hash or fetching receipts (`ETH_FETCH_CONCURRENCY`).
+    pub eth_fetch_concurrency: usize,
+    /// JSON map of 4-byte selector to function signature, merged over the built-ins.
+    pub fourbyte_file: Option<PathBuf>,
+    /// `ingest-once --blocks` above this needs `--yes`.
+    pub ingest_blocks_soft_cap: u64,
+    /// Router addresses considered by the MEV-target heuristic (defaults built in).
+    pub mev_routers: Option<HashSet<String>>,
+    /// Minimum swap value in wei flagged as a likely MEV target.
+    pub mev_min_value_wei: Option<U256>,
+    /// Capacity of the in-memory recent-transaction ring in `serve` (0 disables).
+    pub recent_cache_size: usize,
+    /// Chain id stored rows are keyed by (`CHAIN_ID`); unset asks the RPC
+    /// endpoint (`eth_chainId`).
+    pub chain_id: Option<u64>,
+    /// Chain-specific display units, from `CHAIN_ID` plus decimal overrides.
+    pub chain_units: ChainUnits,
+    /// Built-in normalization hooks to enable, by name (see `tx_metadata`).
+    pub tx_metadata_hooks: Vec<String>,
+    /// Store calldata zstd-compressed (per-row flag, so mixed data is fine).
+    pub compress_input_data: bool,
+    /// Shared secret for mutating endpoints (`x-api-key` header); unset disables them.
+    pub api_key: Option<String>,
+    /// Bearer token every API route except `/health` requires (`API_TOKEN`);
+    /// unset leaves the API open.
+    pub api_token: Option<String>,
+    /// Max batches queued for the pending-sample writer.
+    pub insert_queue_capacity: usize,
+    /// Milliseconds a SQLite connection waits on a lock (`SQLITE_BUSY_TIMEOUT_MS`).
+    pub sqlite_busy_timeout_ms: u64,
+    /// Upper bound on pooled database connections (`DB_MAX_CONNECTIONS`).
+    pub db_max_connections: u32,
+    /// Connections kept open while idle (`DB_MIN_CONNECTIONS`).
+    pub db_min_connections: u32,
+    /// Seconds to wait for a free pooled connection (`DB_ACQUIRE_TIMEOUT_SECS`);
+    /// unset keeps the driver default.
+    pub db_acquire_timeout_secs: Option<u64>,
+    /// `block` (default) stalls the producer when the queue is full;
+    /// `drop_oldest` discards queued batches instead.
+    pub insert_queue_policy: BackpressurePolicy,
+    /// Event signature hashes (topic0) whose logs are stored during block ingest.
+    pub watch_topics: Vec<H256>,
+    /// Path the HTTP routes are nested under, normalized to `/segment` form;
+    /// empty serves them at the root.
+    pub api_prefix: String,
+    /// Default ranking for "recent transaction" reads (`RECENT_ORDER`).
+    pub recent_order: RecentOrder,
+    /// Age limit for stored rows, enforced after each ingest run (`RETENTION_MAX_AGE_SECS`).
+    pub retention_max_age_secs: Option<u64>,
+    /// Row cap for stored transactions, enforced after each ingest run (`RETENTION_MAX_ROWS`).
+    pub retention_max_rows: Option<u64>,
+    /// `hashes` (default) or `full` pending-transaction subscriptions (`MEMPOOL_SUB_MODE`).
+    pub mempool_sub_mode: MempoolSubMode,
+    /// Retries per RPC call after a transport error (`ETH_RPC_MAX_RETRIES`, 0 disables).
+    pub eth_rpc_max_retries: u32,
+    /// First retry delay; doubles per attempt, plus jitter (`ETH_RPC_RETRY_BASE_MS`).
+    pub eth_rpc_retry_base_ms: u64,
+    /// Per-client API requests per second (`HTTP_RATE_LIMIT_RPS`); unset disables limiting.
+    pub http_rate_limit_rps: Option<f64>,
+    /// Requests a client may make at once before the rate applies
+    /// (`HTTP_RATE_LIMIT_BURST`, defaults to the rate rounded up).
+    pub http_rate_limit_burst: Option<u32>,
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum ConfigError {
     #[error("missing ETH_RPC_URL env var")]
     MissingEthRpcUrl,
+    #[error("invalid BLOCK_SAMPLE_RATE {0:?}; expected a number in (0, 1]")]
+    InvalidBlockSampleRate(String),
+    #[error("invalid {name} value {value:?}")]
+    InvalidValue { name: &'static str, value: String },
+    #[error("invalid {name} entry {value:?}; expected a 0x-prefixed 20-byte hex address")]
+    InvalidAddress { name: &'static str, value: String },
+    #[error("failed to read config file {path}: {source}")]
+    ReadFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid config file {path}: {message}")]
+    ParseFile { path: PathBuf, message: String },
 }
 
+/// TOML keys named after a [`Config`] field whose env var is spelled
+/// differently; every other key is its env var name in lowercase.
+const FILE_KEY_ALIASES: &[(&str, &str)] = &[
+    ("http_bind_addr", "HTTP_BIND"),
+    ("recent_cache_size", "RECENT_TX_CACHE_SIZE"),
+];
+
 impl Config {
+    /// Defaults for everything except the RPC endpoint, for embedding the crate
+    /// as a library; adjust the public fields directly afterwards.
+    pub fn new(eth_rpc_url: impl Into<String>) -> Self {
+        Self {
+            eth_rpc_url: eth_rpc_url.into(),
+            eth_ws_url: None,
+            database_url: "sqlite://data/mempool.db".to_string(),
+            http_bind_addr: "127.0.0.1:8080".to_string(),
+            filter_addresses: None,
+            filter_min_value_wei: None,
+            block_sample_rate: None,
+            pushgateway_url: None,
+            eth_disable_full_block: false,
+            eth_max_blocks_per_call: None,
+            eth_fetch_concurrency: DEFAULT_FETCH_CONCURRENCY,
+            fourbyte_file: None,
+            ingest_blocks_soft_cap: DEFAULT_INGEST_BLOCKS_SOFT_CAP,
+            mev_routers: None,
+            mev_min_value_wei: None,
+            recent_cache_size: 0,
+            chain_id: None,
+            chain_units: ChainUnits::default(),
+            tx_metadata_hooks: Vec::new(),
+            compress_input_data: false,
+            api_key: None,
+            api_token: None,
+            insert_queue_capacity: DEFAULT_INSERT_QUEUE_CAPACITY,
+            sqlite_busy_timeout_ms: DEFAULT_SQLITE_BUSY_TIMEOUT_MS,
+            db_max_connections: DEFAULT_DB_MAX_CONNECTIONS,
+            db_min_connections: 0,
+            db_acquire_timeout_secs: None,
+            insert_queue_policy: BackpressurePolicy::default(),
+            watch_topics: Vec::new(),
+            api_prefix: String::new(),
+            recent_order: RecentOrder::default(),
+            retention_max_age_secs: None,
+            retention_max_rows: None,
+            mempool_sub_mode: MempoolSubMode::default(),
+            eth_rpc_max_retries: DEFAULT_RPC_MAX_RETRIES,
+            eth_rpc_retry_base_ms: DEFAULT_RPC_RETRY_BASE_MS,
+            http_rate_limit_rps: None,
+            http_rate_limit_burst: None,
+        }
+    }
+
     pub fn from_env() -> Result<Self, ConfigError> {
-        let eth_rpc_url = env::var("ETH_RPC_URL").map_err(|_| ConfigError::MissingEthRpcUrl)?;
-        let eth_ws_url = env::var("ETH_WS_URL").ok();
-
-        let database_url =
-            env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://data/mempool.db".to_string());
-        let http_bind_addr = env::var("HTTP_BIND").unwrap_or_else(|_| "127.0.0.1:8080".to_string());
-        let filter_addresses = env::var("FILTER_ADDRESSES")
-            .ok()
-            .map(parse_filter_addresses)
-            .and_then(|set| if set.is_empty() { None } else { Some(set) });
-
-        Ok(Self {
-            eth_rpc_url,
-            eth_ws_url,
-            database_url,
-            http_bind_addr,
-            filter_addresses,
+        Self::load(&Vars::new(None, None))
+    }
+
+    /// Loads settings from a TOML file whose keys are the env var names in
+    /// lowercase (`eth_rpc_url`, `filter_addresses`, ...; `http_bind_addr`
+    /// is also accepted for `HTTP_BIND`). Lists such as `filter_addresses`
+    /// may be TOML arrays. Environment variables override file values, unless
+    /// they are blank. Unknown keys are rejected.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let raw = std::fs::read_to_string(path).map_err(|source| ConfigError::ReadFile {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let file = parse_config_file(&raw).map_err(|message| ConfigError::ParseFile {
+            path: path.to_path_buf(),
+            message,
+        })?;
+        let vars = Vars::new(None, Some(&file));
+        let config = Self::load(&vars)?;
+        if let Some(key) = vars.unread_file_key() {
+            return Err(ConfigError::ParseFile {
+                path: path.to_path_buf(),
+                message: format!("unknown setting {}", key),
+            });
+        }
+        Ok(config)
+    }
+
+    /// Like [`Config::from_env`], but entries in `overrides` (keyed by env var
+    /// name) take precedence over the process environment.
+    pub fn from_env_with_overrides(
+        overrides: &HashMap<String, String>,
+    ) -> Result<Self, ConfigError> {
+        Self::load(&Vars::new(Some(overrides), None))
+    }
+
+    fn load(vars: &Vars<'_>) -> Result<Self, ConfigError> {
+        let eth_rpc_url = vars
+            .get("ETH_RPC_URL")
+            .ok_or(ConfigError::MissingEthRpcUrl)?;
+        let mut config = Self::new(eth_rpc_url);
+
+        config.eth_ws_url = vars.get("ETH_WS_URL");
+        let derive_ws = vars.parse_bool("DERIVE_WS_URL")?.unwrap_or(false);
+        if config.eth_ws_url.is_none() && derive_ws {
+            config.eth_ws_url = Some(derive_ws_url(&config.eth_rpc_url).ok_or_else(|| {
+                ConfigError::InvalidValue {
+                    name: "ETH_RPC_URL",
+                    value: config.eth_rpc_url.clone(),
+                }
+            })?);
+        }
+        if let Some(url) = vars.get("DATABASE_URL") {
+            config.database_url = url;
+        }
+        if let Some(addr) = vars.get("HTTP_BIND") {
+            config.http_bind_addr = addr;
+        }
+        config.filter_addresses = vars.parse_addresses("FILTER_ADDRESSES")?;
+        config.filter_min_value_wei = vars.parse_wei("FILTER_MIN_VALUE_WEI")?;
+        config.block_sample_rate = vars
+            .get_nonblank("BLOCK_SAMPLE_RATE")
+            .map(|raw| parse_sample_rate(&raw))
+            .transpose()?;
+        config.pushgateway_url = vars.get_nonblank("PUSHGATEWAY_URL");
+        config.eth_disable_full_block = vars.parse_bool("ETH_DISABLE_FULL_BLOCK")?.unwrap_or(false);
+        config.eth_max_blocks_per_call = vars.parse("ETH_MAX_BLOCKS_PER_CALL")?;
+        if let Some(concurrency) = vars.parse("ETH_FETCH_CONCURRENCY")? {
+            if concurrency == 0 {
+                return Err(ConfigError::InvalidValue {
+                    name: "ETH_FETCH_CONCURRENCY",
+                    value: "0".to_string(),
+                });
+            }
+            config.eth_fetch_concurrency = concurrency;
+        }
+        config.fourbyte_file = vars.parse::<PathBuf>("FOURBYTE_FILE")?;
+        if let Some(cap) = vars.parse("INGEST_BLOCKS_SOFT_CAP")? {
+            config.ingest_blocks_soft_cap = cap;
+        }
+        config.mev_routers = vars.parse_addresses("MEV_ROUTERS")?;
+        config.mev_min_value_wei = vars
+            .parse_wei("MEV_MIN_VALUE_WEI")?
+            .map(|raw| {
+                U256::from_dec_str(&raw).map_err(|_| ConfigError::InvalidValue {
+                    name: "MEV_MIN_VALUE_WEI",
+                    value: raw,
+                })
+            })
+            .transpose()?;
+        config.recent_cache_size = vars.parse("RECENT_TX_CACHE_SIZE")?.unwrap_or(0);
+
+        config.chain_id = vars.parse("CHAIN_ID")?;
+        let units = &mut config.chain_units;
+        *units = ChainUnits::for_chain_id(config.chain_id.unwrap_or(1));
+        if let Some(symbol) = vars.get_nonblank("NATIVE_SYMBOL") {
+            units.symbol = symbol.trim().to_string();
+        }
+        if let Some(decimals) = vars.parse("NATIVE_DECIMALS")? {
+            units.native_decimals = decimals;
+        }
+        if let Some(decimals) = vars.parse("GAS_PRICE_DECIMALS")? {
+            units.gas_price_decimals = decimals;
+        }
+        if let Some(unit) = vars.get_nonblank("GAS_PRICE_UNIT") {
+            units.gas_price_unit = unit.trim().to_string();
+        }
+
+        config.tx_metadata_hooks = vars
+            .get("TX_METADATA_HOOKS")
+            .map(|raw| {
+                raw.split(',')
+                    .map(|name| name.trim().to_ascii_lowercase())
+                    .filter(|name| !name.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        config.compress_input_data = vars.parse_bool("COMPRESS_INPUT_DATA")?.unwrap_or(false);
+        config.api_key = vars
+            .get_nonblank("API_KEY")
+            .map(|key| key.trim().to_string());
+        config.api_token = vars
+            .get_nonblank("API_TOKEN")
+            .map(|token| token.trim().to_string());
+        if let Some(capacity) = vars.parse("INSERT_QUEUE_CAPACITY")? {
+            config.insert_queue_capacity = capacity;
+        }
+        if let Some(timeout) = vars.parse("SQLITE_BUSY_TIMEOUT_MS")? {
+            config.sqlite_busy_timeout_ms = timeout;
+        }
+        if let Some(max) = vars.parse("DB_MAX_CONNECTIONS")? {
+            config.db_max_connections = max;
+        }
+        if let Some(min) = vars.parse("DB_MIN_CONNECTIONS")? {
+            config.db_min_connections = min;
+        }
+        if config.db_max_connections == 0 || config.db_min_connections > config.db_max_connections {
+            return Err(ConfigError::InvalidValue {
+                name: "DB_MAX_CONNECTIONS",
+                value: format!(
+                    "{} (DB_MIN_CONNECTIONS={})",
+                    config.db_max_connections, config.db_min_connections
+                ),
+            });
+        }
+        config.db_acquire_timeout_secs = vars.parse("DB_ACQUIRE_TIMEOUT_SECS")?;
+        if let Some(policy) = vars.parse("INSERT_QUEUE_POLICY")? {
+            config.insert_queue_policy = policy;
+        }
+        if let Some(raw) = vars.get("WATCH_TOPICS") {
+            config.watch_topics = parse_watch_topics(&raw)?;
+        }
+        if let Some(raw) = vars.get("API_PREFIX") {
+            config.api_prefix = normalize_api_prefix(&raw);
+        }
+        if let Some(order) = vars.parse("RECENT_ORDER")? {
+            config.recent_order = order;
+        }
+        config.retention_max_age_secs = vars.parse("RETENTION_MAX_AGE_SECS")?;
+        config.retention_max_rows = vars.parse("RETENTION_MAX_ROWS")?;
+        if let Some(mode) = vars.parse("MEMPOOL_SUB_MODE")? {
+            config.mempool_sub_mode = mode;
+        }
+        if let Some(retries) = vars.parse("ETH_RPC_MAX_RETRIES")? {
+            config.eth_rpc_max_retries = retries;
+        }
+        if let Some(base_ms) = vars.parse("ETH_RPC_RETRY_BASE_MS")? {
+            config.eth_rpc_retry_base_ms = base_ms;
+        }
+        config.http_rate_limit_rps = vars.parse::<f64>("HTTP_RATE_LIMIT_RPS")?;
+        if let Some(rps) = config.http_rate_limit_rps {
+            if !(rps.is_finite() && rps > 0.0) {
+                return Err(ConfigError::InvalidValue {
+                    name: "HTTP_RATE_LIMIT_RPS",
+                    value: rps.to_string(),
+                });
+            }
+        }
+        config.http_rate_limit_burst = vars.parse("HTTP_RATE_LIMIT_BURST")?;
+
+        Ok(config)
+    }
+}
+
+/// Raw setting lookup: the process environment, under optional overrides
+/// and over optional config-file values (both keyed by env var name). Blank
+/// overrides and environment values count as unset.
+struct Vars<'a> {
+    overrides: Option<&'a HashMap<String, String>>,
+    file: Option<&'a HashMap<String, String>>,
+    /// Every name looked up, so unknown config-file keys can be reported.
+    read: RefCell<HashSet<String>>,
+}
+
+impl<'a> Vars<'a> {
+    fn new(
+        overrides: Option<&'a HashMap<String, String>>,
+        file: Option<&'a HashMap<String, String>>,
+    ) -> Self {
+        Self {
+            overrides,
+            file,
+            read: RefCell::default(),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<String> {
+        self.read.borrow_mut().insert(name.to_string());
+        let nonblank = |raw: &String| !raw.trim().is_empty();
+        self.overrides
+            .and_then(|overrides| overrides.get(name).cloned())
+            .filter(nonblank)
+            .or_else(|| env::var(name).ok().filter(nonblank))
+            .or_else(|| self.file.and_then(|file| file.get(name).cloned()))
+    }
+
+    /// A config-file key (lowercased) that no setting looked up, if any.
+    fn unread_file_key(&self) -> Option<String> {
+        let read = self.read.borrow();
+        let mut unread: Vec<_> = self
+            .file?
+            .keys()
+            .filter(|name| !read.contains(*name))
+            .collect();
+        unread.sort();
+        unread.first().map(|name| name.to_ascii_lowercase())
+    }
+
+    fn get_nonblank(&self, name: &str) -> Option<String> {
+        self.get(name).filter(|raw| !raw.trim().is_empty())
+    }
+
+    /// Parses an optional setting; unset or blank means `None`.
+    fn parse<T: std::str::FromStr>(&self, name: &'static str) -> Result<Option<T>, ConfigError> {
+        match self.get_nonblank(name) {
+            Some(raw) => raw
+                .trim()
+                .parse()
+                .map(Some)
+                .map_err(|_| ConfigError::InvalidValue { name, value: raw }),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads an optional non-negative decimal wei amount, kept as a string
+    /// since it may exceed 64 bits.
+    fn parse_wei(&self, name: &'static str) -> Result<Option<String>, ConfigError> {
+        match self.get_nonblank(name) {
+            Some(raw) => {
+                let trimmed = raw.trim();
+                if trimmed.chars().all(|c| c.is_ascii_digit()) {
+                    Ok(Some(trimmed.to_string()))
+                } else {
+                    Err(ConfigError::InvalidValue { name, value: raw })
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Comma-separated addresses, lowercased; empty means `None`.
+    fn parse_addresses(&self, name: &'static str) -> Result<Option<HashSet<String>>, ConfigError> {
+        let Some(raw) = self.get(name) else {
+            return Ok(None);
+        };
+        let set = parse_filter_addresses(raw);
+        if let Some(bad) = set.iter().find(|addr| !is_hex_address(addr)) {
+            return Err(ConfigError::InvalidAddress {
+                name,
+                value: bad.clone(),
+            });
+        }
+        Ok(if set.is_empty() { None } else { Some(set) })
+    }
+
+    fn parse_bool(&self, name: &'static str) -> Result<Option<bool>, ConfigError> {
+        match self.get_nonblank(name) {
+            Some(raw) => match raw.trim().to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => Ok(Some(true)),
+                "0" | "false" | "no" | "off" => Ok(Some(false)),
+                _ => Err(ConfigError::InvalidValue { name, value: raw }),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+/// `0x` followed by exactly 40 hex digits (either case).
+pub fn is_hex_address(addr: &str) -> bool {
+    addr.strip_prefix("0x")
+        .is_some_and(|hex| hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Flattens a TOML config file into env-var-keyed strings, so file values go
+/// through the same parsing as the environment. Arrays become comma lists.
+fn parse_config_file(raw: &str) -> Result<HashMap<String, String>, String> {
+    let table: toml::Table = raw
+        .parse()
+        .map_err(|err: toml::de::Error| err.to_string())?;
+    table
+        .into_iter()
+        .map(|(key, value)| {
+            let name = FILE_KEY_ALIASES
+                .iter()
+                .find(|(alias, _)| *alias == key)
+                .map(|(_, name)| name.to_string())
+                .unwrap_or_else(|| key.to_ascii_uppercase());
+            let value = match value {
+                toml::Value::Array(items) => items
+                    .into_iter()
+                    .map(|item| toml_scalar(&key, item))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .join(","),
+                other => toml_scalar(&key, other)?,
+            };
+            Ok((name, value))
         })
+        .collect()
+}
+
+fn toml_scalar(key: &str, value: toml::Value) -> Result<String, String> {
+    match value {
+        toml::Value::String(s) => Ok(s),
+        toml::Value::Integer(n) => Ok(n.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        toml::Value::Boolean(b) => Ok(b.to_string()),
+        other => Err(format!(
+            "unsupported {} value for {}",
+            other.type_str(),
+            key
+        )),
     }
 }
 
@@ -45,3 +514,251 @@ fn parse_filter_addresses(raw: String) -> HashSet<String> {
         .filter(|s| !s.is_empty())
         .collect()
 }
+
+/// `mempool/`, `/mempool` and ` /mempool/ ` all become `/mempool`; `/` alone
+/// means no prefix.
+fn normalize_api_prefix(raw: &str) -> String {
+    let trimmed = raw.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", trimmed)
+    }
+}
+
+fn parse_watch_topics(raw: &str) -> Result<Vec<H256>, ConfigError> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|topic| !topic.is_empty())
+        .map(|topic| {
+            topic.parse().map_err(|_| ConfigError::InvalidValue {
+                name: "WATCH_TOPICS",
+                value: topic.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn parse_sample_rate(raw: &str) -> Result<f64, ConfigError> {
+    match raw.trim().parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate <= 1.0 => Ok(rate),
+        _ => Err(ConfigError::InvalidBlockSampleRate(raw.to_string())),
+    }
+}
+
+/// Swaps an `http(s)://` RPC URL to the matching `ws(s)://` scheme, keeping
+/// host, path, and query. Only correct for providers that serve both
+/// transports on the same endpoint, hence opt-in via `DERIVE_WS_URL`. Only
+/// the first of several endpoints is used.
+fn derive_ws_url(rpc_url: &str) -> Option<String> {
+    let first = rpc_url.split(',').next()?.trim();
+    let mut url = url::Url::parse(first).ok()?;
+    let scheme = match url.scheme() {
+        "https" => "wss",
+        "http" => "ws",
+        _ => return None,
+    };
+    url.set_scheme(scheme).ok()?;
+    Some(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StorageOptions;
+
+    #[test]
+    fn derive_ws_url_swaps_scheme() {
+        assert_eq!(
+            derive_ws_url("https://eth-mainnet.example.com/v2/key").as_deref(),
+            Some("wss://eth-mainnet.example.com/v2/key")
+        );
+        assert_eq!(
+            derive_ws_url("http://127.0.0.1:8545").as_deref(),
+            Some("ws://127.0.0.1:8545/")
+        );
+        assert_eq!(
+            derive_ws_url("https://a.example/key, https://b.example").as_deref(),
+            Some("wss://a.example/key")
+        );
+        assert_eq!(derive_ws_url("ipc:///tmp/geth.ipc"), None);
+        assert_eq!(derive_ws_url("not a url"), None);
+    }
+
+    #[test]
+    fn overrides_take_precedence_and_fill_defaults() {
+        let overrides: HashMap<String, String> = [
+            ("ETH_RPC_URL", "http://override.invalid:8545"),
+            ("DATABASE_URL", "sqlite::memory:"),
+            ("CHAIN_ID", "137"),
+            ("INSERT_QUEUE_POLICY", "drop_oldest"),
+            ("MEMPOOL_SUB_MODE", "Full"),
+            ("ETH_RPC_MAX_RETRIES", "0"),
+            ("FILTER_MIN_VALUE_WEI", "1000000000000000000"),
+            ("HTTP_RATE_LIMIT_RPS", "2.5"),
+            ("SQLITE_BUSY_TIMEOUT_MS", "250"),
+            ("DB_MAX_CONNECTIONS", "2"),
+            ("DB_MIN_CONNECTIONS", "1"),
+            ("API_TOKEN", " s3cret "),
+            ("ETH_FETCH_CONCURRENCY", "16"),
+            ("MEV_MIN_VALUE_WEI", "5000000000000000000"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let config = Config::from_env_with_overrides(&overrides).unwrap();
+        assert_eq!(config.eth_rpc_url, "http://override.invalid:8545");
+        assert_eq!(config.database_url, "sqlite::memory:");
+        assert_eq!(config.chain_id, Some(137));
+        assert_eq!(config.chain_units.symbol, "POL");
+        assert_eq!(StorageOptions::from_config(&config).legacy_chain_id, 137);
+        assert_eq!(config.insert_queue_policy, BackpressurePolicy::DropOldest);
+        assert_eq!(config.mempool_sub_mode, MempoolSubMode::Full);
+        assert_eq!(config.eth_rpc_max_retries, 0);
+        assert_eq!(config.eth_rpc_retry_base_ms, DEFAULT_RPC_RETRY_BASE_MS);
+        assert_eq!(
+            config.filter_min_value_wei.as_deref(),
+            Some("1000000000000000000")
+        );
+        assert_eq!(config.http_rate_limit_rps, Some(2.5));
+        assert_eq!(config.http_rate_limit_burst, None);
+        assert_eq!(config.sqlite_busy_timeout_ms, 250);
+        assert_eq!(config.db_max_connections, 2);
+        assert_eq!(config.db_min_connections, 1);
+        assert_eq!(config.db_acquire_timeout_secs, None);
+        assert_eq!(config.api_token.as_deref(), Some("s3cret"));
+        assert_eq!(config.api_key, None);
+        assert_eq!(config.eth_fetch_concurrency, 16);
+        assert_eq!(config.mev_min_value_wei, Some(U256::exp10(18) * 5));
+        assert_eq!(normalize_api_prefix(" mempool/ "), "/mempool");
+        assert_eq!(normalize_api_prefix("/"), "");
+
+        let bad: HashMap<String, String> = [
+            ("ETH_RPC_URL", "http://x"),
+            ("API_KEY", " "),
+            ("COMPRESS_INPUT_DATA", "maybe"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        assert!(matches!(
+            Config::from_env_with_overrides(&bad),
+            Err(ConfigError::InvalidValue {
+                name: "COMPRESS_INPUT_DATA",
+                ..
+            })
+        ));
+        // One past U256::MAX.
+        let overflow: HashMap<String, String> = [
+            ("ETH_RPC_URL", "http://x"),
+            (
+                "MEV_MIN_VALUE_WEI",
+                "115792089237316195423570985008687907853269984665640564039457584007913129639936",
+            ),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        assert!(matches!(
+            Config::from_env_with_overrides(&overflow),
+            Err(ConfigError::InvalidValue {
+                name: "MEV_MIN_VALUE_WEI",
+                ..
+            })
+        ));
+        let inverted: HashMap<String, String> = [
+            ("ETH_RPC_URL", "http://x"),
+            ("DB_MAX_CONNECTIONS", "2"),
+            ("DB_MIN_CONNECTIONS", "3"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        assert!(matches!(
+            Config::from_env_with_overrides(&inverted),
+            Err(ConfigError::InvalidValue {
+                name: "DB_MAX_CONNECTIONS",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn config_file_values_sit_under_the_environment() {
+        let file = parse_config_file(
+            r#"
+            eth_rpc_url = "http://file.invalid:8545"
+            http_bind_addr = "0.0.0.0:9000"
+            filter_addresses = [
+                "0x00000000000000000000000000000000000000AA",
+                "0x00000000000000000000000000000000000000bb",
+            ]
+            block_sample_rate = 0.5
+            eth_disable_full_block = true
+            "#,
+        )
+        .unwrap();
+        let overrides: HashMap<String, String> = [
+            (
+                "ETH_RPC_URL".to_string(),
+                "http://env.invalid:8545".to_string(),
+            ),
+            ("HTTP_BIND".to_string(), " ".to_string()),
+        ]
+        .into();
+        let vars = Vars::new(Some(&overrides), Some(&file));
+        let config = Config::load(&vars).unwrap();
+        assert_eq!(vars.unread_file_key(), None);
+        assert_eq!(config.eth_rpc_url, "http://env.invalid:8545");
+        // A blank override does not hide the file value.
+        assert_eq!(config.http_bind_addr, "0.0.0.0:9000");
+        assert_eq!(config.block_sample_rate, Some(0.5));
+        assert!(config.eth_disable_full_block);
+        let filter = config.filter_addresses.unwrap();
+        assert!(filter.contains("0x00000000000000000000000000000000000000aa"));
+        assert_eq!(filter.len(), 2);
+
+        assert!(parse_config_file("watch_topics = { a = 1 }").is_err());
+        let path = std::env::temp_dir().join(format!("config_unknown_{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "eth_rpc_url = \"http://x\"\nfilter_adresses = \"0x\"\n",
+        )
+        .unwrap();
+        let err = Config::from_file(&path).unwrap_err();
+        let _ = std::fs::remove_file(&path);
+        assert!(
+            err.to_string().contains("unknown setting filter_adresses"),
+            "{}",
+            err
+        );
+        assert!(matches!(
+            Config::from_file("/nonexistent/mempool.toml"),
+            Err(ConfigError::ReadFile { .. })
+        ));
+    }
+
+    #[test]
+    fn malformed_filter_addresses_are_rejected() {
+        for bad in [
+            "0x1234",
+            "00000000000000000000000000000000000000aa00",
+            "0xzz00000000000000000000000000000000000000",
+        ] {
+            let overrides: HashMap<String, String> = [
+                ("ETH_RPC_URL".to_string(), "http://x".to_string()),
+                ("FILTER_ADDRESSES".to_string(), bad.to_string()),
+            ]
+            .into();
+            let err = Config::from_env_with_overrides(&overrides).unwrap_err();
+            assert!(matches!(
+                err,
+                ConfigError::InvalidAddress {
+                    name: "FILTER_ADDRESSES",
+                    ..
+                }
+            ));
+            assert!(err.to_string().contains("0x-prefixed 20-byte hex address"));
+        }
+    }
+}
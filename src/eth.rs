@@ -1,21 +1,199 @@
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::str::FromStr;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use anyhow::{Context, Result};
-use ethers_core::types::{Block, BlockId, Transaction, H160, H256, U256};
-use ethers_providers::{Http, Middleware, Provider, Ws};
-use futures_util::StreamExt;
+use anyhow::{anyhow, Context, Result};
+use ethers_core::abi::{self, ParamType, Token};
+use ethers_core::types::{
+    Block, BlockId, BlockNumber, Bytes, Filter, Log, Transaction, TransactionRequest, H160, H256,
+    U256,
+};
+use ethers_core::utils::keccak256;
+use ethers_providers::{Middleware, Provider, ProviderError, RpcError, Ws};
+use futures_util::stream::BoxStream;
+use futures_util::{StreamExt, TryStreamExt};
 use std::collections::HashSet;
-use url::Url;
+use tokio::sync::{mpsc, OnceCell};
 
 use crate::{
+    config::Config,
+    failover::{FailoverHttp, RATE_LIMIT_CODES},
     ingest_stats::INGEST_STATS,
-    models::{BlockInfo, NormalizedTx},
-    storage::{self, DbPool},
+    insert_queue::InsertQueue,
+    models::{BlockInfo, LogEntry, NormalizedTx, SimulationResult, TokenTransfer, TxMetadata},
+    storage::{self, Storage, StorageOptions},
+    tx_metadata::MetadataHooks,
 };
 
+/// One block as returned by [`EthClient::fetch_block`]: the block, its
+/// normalized transactions and the metadata hooks' output for them.
+#[derive(Debug, Clone)]
+pub struct FetchedBlock {
+    pub info: BlockInfo,
+    pub txs: Vec<NormalizedTx>,
+    pub metadata: Vec<TxMetadata>,
+}
+
 #[derive(Clone)]
 pub struct EthClient {
-    provider: Provider<Http>,
+    provider: Provider<FailoverHttp>,
+    /// Skip `eth_getBlockByNumber(.., true)` and hydrate transactions by hash.
+    disable_full_block: bool,
+    /// Upper bound on blocks fetched by a single call, if any.
+    max_blocks_per_call: Option<u64>,
+    /// RPC requests in flight at once while fetching a block.
+    fetch_concurrency: usize,
+    /// Normalization hooks run on each fetched transaction.
+    metadata_hooks: MetadataHooks,
+    /// Write options for rows stored by `sample_pending`.
+    storage_options: StorageOptions,
+    /// Pending subscription flavour used by `sample_pending`.
+    sub_mode: MempoolSubMode,
+    /// Backoff applied to transient failures of individual RPC calls.
+    retry: RetryPolicy,
+    /// Chain id stamped on fetched blocks and transactions; see `chain_id`.
+    chain_id: Arc<OnceCell<i64>>,
+    /// Whether failures and drops count towards `INGEST_STATS`; off for dry
+    /// runs, which must leave the process counters alone.
+    record_stats: bool,
+}
+
+/// Retries for one RPC call: up to `max_retries` more attempts after the
+/// first, waiting `base_delay * 2^n` plus up to 50% jitter between them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+}
+
+/// Retries per RPC call unless `ETH_RPC_MAX_RETRIES` says otherwise.
+pub const DEFAULT_RPC_MAX_RETRIES: u32 = 3;
+
+/// First retry delay in milliseconds unless `ETH_RPC_RETRY_BASE_MS` says
+/// otherwise.
+pub const DEFAULT_RPC_RETRY_BASE_MS: u64 = 250;
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_RPC_MAX_RETRIES,
+            base_delay: Duration::from_millis(DEFAULT_RPC_RETRY_BASE_MS),
+        }
+    }
+}
+
+/// Whether a failed call is worth retrying: transport failures, unparsable
+/// replies and rate limits are, while the node's own answer to the request
+/// (a revert, an invalid parameter) and local decoding errors are not.
+fn is_transient(err: &ProviderError) -> bool {
+    match err {
+        ProviderError::JsonRpcClientError(err) => match err.as_error_response() {
+            Some(response) => RATE_LIMIT_CODES.contains(&response.code),
+            None => true,
+        },
+        ProviderError::HTTPError(_) => true,
+        _ => false,
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (1-based), jitter excluded.
+    fn backoff(&self, retry: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(1u32 << retry.saturating_sub(1).min(16))
+    }
+
+    fn jittered(&self, retry: u32) -> Duration {
+        let backoff = self.backoff(retry);
+        // Cheap randomness without a `rand` dependency: std's hasher keys are
+        // randomly seeded.
+        let random = RandomState::new().build_hasher().finish();
+        let jitter_ms = match backoff.as_millis() as u64 / 2 {
+            0 => 0,
+            half => random % half,
+        };
+        backoff + Duration::from_millis(jitter_ms)
+    }
+
+    /// Runs `call` until it succeeds, fails with an error that is not
+    /// [transient](is_transient), or the retries are used up, logging a
+    /// warning before each retry. `what` names the call in the log.
+    async fn run<T, F, Fut>(&self, what: &str, mut call: F) -> Result<T, ProviderError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        let mut retry = 0;
+        loop {
+            match call().await {
+                Ok(value) => return Ok(value),
+                Err(err) if retry < self.max_retries && is_transient(&err) => {
+                    retry += 1;
+                    let delay = self.jittered(retry);
+                    tracing::warn!(
+                        "{} failed (retry {}/{} in {:?}): {}",
+                        what,
+                        retry,
+                        self.max_retries,
+                        delay,
+                        err
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// Reconnect attempts per `sample_pending` run after the WebSocket drops.
+pub const MAX_WS_RECONNECTS: usize = 5;
+
+/// Recent hashes `watch_pending` remembers to avoid alerting twice.
+const WATCH_DEDUP_WINDOW: usize = 10_000;
+
+/// Transaction and receipt requests in flight at once unless
+/// `ETH_FETCH_CONCURRENCY` says otherwise.
+pub const DEFAULT_FETCH_CONCURRENCY: usize = 8;
+
+fn receipt_status_label(status: u64) -> &'static str {
+    if status == 1 {
+        "success"
+    } else {
+        "failed"
+    }
+}
+
+/// How `sample_pending` learns about pending transactions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MempoolSubMode {
+    /// `newPendingTransactions` hashes, each hydrated with `eth_getTransactionByHash`.
+    #[default]
+    Hashes,
+    /// `alchemy_pendingTransactions` full bodies, with no per-hash fetch.
+    Full,
+}
+
+impl FromStr for MempoolSubMode {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "hashes" => Ok(Self::Hashes),
+            "full" => Ok(Self::Full),
+            other => Err(format!("unknown mempool subscription mode {:?}", other)),
+        }
+    }
+}
+
+/// One subscription item: a bare hash or an already-complete body.
+enum PendingItem {
+    Hash(H256),
+    Tx(Box<Transaction>),
 }
 
 #[derive(Debug, Default)]
@@ -24,144 +202,873 @@ pub struct PendingSampleStats {
     pub fetched: usize,
     pub inserted: usize,
     pub insert_errors: usize,
+    pub duplicates_skipped: usize,
+    /// Transactions discarded because the insert queue was full
+    /// (`INSERT_QUEUE_POLICY=drop_oldest` only).
+    pub dropped_due_to_backpressure: usize,
+    /// Row id of this run in `mempool_samples`, keying its arrival offsets.
+    pub sample_id: i64,
+    /// WebSocket reconnect attempts after the subscription dropped early.
+    pub reconnects: usize,
+}
+
+/// Limits and write batching for one `sample_pending` run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SampleOptions {
+    pub duration: Duration,
+    /// Stop after observing this many pending hashes, counted before filtering.
+    pub max: usize,
+    /// Recent hashes remembered to skip duplicates (0 disables).
+    pub dedup_window: usize,
+    /// Buffered transactions that trigger a write.
+    pub flush_size: usize,
+    /// Also write a non-empty buffer at least this often; `None` flushes on
+    /// size (and at the end) only.
+    pub flush_interval: Option<Duration>,
+}
+
+impl SampleOptions {
+    /// Whether a buffer of `len` transactions, last written `since_flush`
+    /// ago, is due to be written.
+    fn flush_due(&self, len: usize, since_flush: Duration) -> bool {
+        len > 0
+            && (len >= self.flush_size
+                || self
+                    .flush_interval
+                    .is_some_and(|interval| since_flush >= interval))
+    }
+}
+
+/// Pending transactions awaiting insert, with their arrival offsets (ms since
+/// the sample started).
+#[derive(Default)]
+struct PendingBatch {
+    txs: Vec<NormalizedTx>,
+    arrivals: Vec<(String, i64)>,
+    metadata: Vec<TxMetadata>,
+}
+
+impl PendingBatch {
+    fn push(&mut self, tx: NormalizedTx, offset_ms: i64) {
+        self.arrivals.push((tx.hash.clone(), offset_ms));
+        self.txs.push(tx);
+    }
+
+    fn len(&self) -> usize {
+        self.txs.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.txs.is_empty()
+    }
+}
+
+/// Hands a full batch to the writer task, accounting for anything the
+/// backpressure policy dropped; `record_stats` is off for dry runs.
+async fn enqueue_batch(
+    queue: &InsertQueue<PendingBatch>,
+    batch: PendingBatch,
+    stats: &mut PendingSampleStats,
+    record_stats: bool,
+) {
+    if let Some(dropped) = queue.push(batch).await {
+        stats.dropped_due_to_backpressure += dropped.len();
+        if record_stats {
+            INGEST_STATS.inc_dropped_due_to_backpressure(dropped.len() as u64);
+        }
+        tracing::warn!(
+            "insert queue full; dropped {} pending transactions",
+            dropped.len()
+        );
+    }
+}
+
+async fn flush_pending(
+    store: &dyn Storage,
+    options: &StorageOptions,
+    sample_id: i64,
+    chain_id: i64,
+    batch: &mut PendingBatch,
+    stats: &mut PendingSampleStats,
+) {
+    match store.insert_transactions(&batch.txs, options).await {
+        Ok(_) => {
+            stats.inserted += batch.len();
+            INGEST_STATS.inc_pending_transactions(batch.len() as u64);
+        }
+        Err(e) => {
+            stats.insert_errors += 1;
+            INGEST_STATS.inc_insert_errors(1);
+            tracing::warn!("failed inserting pending tx batch: {}", e);
+        }
+    }
+    if let Err(e) = store
+        .insert_arrivals(sample_id, chain_id, &batch.arrivals)
+        .await
+    {
+        stats.insert_errors += 1;
+        INGEST_STATS.inc_insert_errors(1);
+        tracing::warn!("failed recording pending tx arrivals: {}", e);
+    }
+    if !batch.metadata.is_empty() {
+        if let Err(e) = store.insert_tx_metadata(&batch.metadata).await {
+            stats.insert_errors += 1;
+            INGEST_STATS.inc_insert_errors(1);
+            tracing::warn!("failed recording pending tx metadata: {}", e);
+        }
+    }
+    batch.txs.clear();
+    batch.arrivals.clear();
+    batch.metadata.clear();
+}
+
+/// Hashes listed when a dry run logs what it skipped writing.
+const DRY_RUN_SAMPLE_HASHES: usize = 5;
+
+/// Logs the transactions a dry run would have inserted for `source`: their
+/// count and the first few hashes.
+pub(crate) fn log_dry_run(source: &str, txs: &[NormalizedTx]) {
+    let sample: Vec<&str> = txs
+        .iter()
+        .take(DRY_RUN_SAMPLE_HASHES)
+        .map(|tx| tx.hash.as_str())
+        .collect();
+    tracing::info!(
+        "dry run: {}: would insert {} transactions [{}]",
+        source,
+        txs.len(),
+        sample.join(", ")
+    );
+}
+
+/// Current unix time in whole seconds.
+pub fn unix_now_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Bounded set of recently seen pending hashes; the oldest entry is evicted
+/// once `capacity` is reached. A capacity of zero disables deduplication.
+struct RecentHashes {
+    capacity: usize,
+    seen: HashSet<H256>,
+    order: VecDeque<H256>,
+}
+
+impl RecentHashes {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            seen: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Records `hash` and returns `true` if it was not already in the window.
+    fn insert(&mut self, hash: H256) -> bool {
+        if self.capacity == 0 {
+            return true;
+        }
+        if !self.seen.insert(hash) {
+            return false;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.order.push_back(hash);
+        true
+    }
 }
 
 impl EthClient {
+    /// `rpc_url` may list several comma-separated endpoints; see
+    /// [`FailoverHttp`].
     pub fn new(rpc_url: &str) -> Result<Self> {
         let client = reqwest::Client::builder()
             .no_proxy()
             .build()
             .context("failed to build reqwest client")?;
-        let url = Url::parse(rpc_url).context("invalid ETH_RPC_URL")?;
-        let transport = Http::new_with_client(url, client);
-        let provider = Provider::new(transport);
-        Ok(Self { provider })
+        let provider = Provider::new(FailoverHttp::new(rpc_url, client)?);
+        Ok(Self {
+            provider,
+            disable_full_block: false,
+            max_blocks_per_call: None,
+            fetch_concurrency: DEFAULT_FETCH_CONCURRENCY,
+            metadata_hooks: MetadataHooks::default(),
+            storage_options: StorageOptions::default(),
+            sub_mode: MempoolSubMode::default(),
+            retry: RetryPolicy::default(),
+            chain_id: Arc::new(OnceCell::new()),
+            record_stats: true,
+        })
     }
 
-    pub async fn fetch_recent_blocks(
-        &self,
-        count: u64,
-    ) -> Result<Vec<(BlockInfo, Vec<NormalizedTx>)>> {
-        if count == 0 {
-            return Ok(Vec::new());
+    /// Builds a client from `ETH_RPC_URL` plus the RPC cost guardrails in `config`.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut client = Self::new(&config.eth_rpc_url)?;
+        client.disable_full_block = config.eth_disable_full_block;
+        client.max_blocks_per_call = config.eth_max_blocks_per_call;
+        client.fetch_concurrency = config.eth_fetch_concurrency;
+        client.storage_options = StorageOptions::from_config(config);
+        client.sub_mode = config.mempool_sub_mode;
+        if let Some(chain_id) = config.chain_id {
+            client.chain_id = Arc::new(OnceCell::new_with(Some(chain_id as i64)));
         }
+        client.retry = RetryPolicy {
+            max_retries: config.eth_rpc_max_retries,
+            base_delay: Duration::from_millis(config.eth_rpc_retry_base_ms),
+        };
+        for name in &config.tx_metadata_hooks {
+            if !client.metadata_hooks.register_builtin(name) {
+                return Err(anyhow!("unknown TX_METADATA_HOOKS entry {:?}", name));
+            }
+        }
+        Ok(client)
+    }
+
+    /// Adds a custom normalization hook; its output is stored in `tx_metadata`.
+    pub fn with_metadata_hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Transaction) -> Vec<(String, String)> + Send + Sync + 'static,
+    {
+        self.metadata_hooks.register(hook);
+        self
+    }
+
+    /// Marks the client as serving a dry run, so RPC errors, skipped blocks
+    /// and backpressure drops are not added to `INGEST_STATS`.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.record_stats = !dry_run;
+        self
+    }
+
+    /// Runs the metadata hooks over `tx`.
+    fn tx_metadata(&self, chain_id: i64, tx: &Transaction) -> Vec<TxMetadata> {
+        if self.metadata_hooks.is_empty() {
+            return Vec::new();
+        }
+        self.metadata_hooks
+            .collect(chain_id, &format!("0x{:x}", tx.hash), tx)
+    }
 
-        let latest = self
-            .provider
-            .get_block_number()
+    /// Probes the RPC endpoint and returns the current head block number.
+    pub async fn check_connectivity(&self) -> Result<u64> {
+        self.latest_block_number()
+            .await
+            .context("RPC endpoint unreachable")
+    }
+
+    /// Chain id of the endpoint (`eth_chainId`), asked once and shared by
+    /// clones; `CHAIN_ID` in the config skips the call.
+    pub async fn chain_id(&self) -> Result<i64> {
+        self.chain_id
+            .get_or_try_init(|| async {
+                let id = self
+                    .retry
+                    .run("eth_chainId", || self.provider.get_chainid())
+                    .await
+                    .context("failed to fetch chain id")?;
+                i64::try_from(id).map_err(|_| anyhow!("chain id {} out of range", id))
+            })
+            .await
+            .copied()
+    }
+
+    pub async fn latest_block_number(&self) -> Result<u64> {
+        let head = self
+            .retry
+            .run("eth_blockNumber", || self.provider.get_block_number())
             .await
             .context("failed to fetch latest block number")?;
+        Ok(head.as_u64())
+    }
+
+    pub async fn fetch_recent_blocks(&self, count: u64) -> Result<Vec<FetchedBlock>> {
+        match self.recent_block_range(count).await? {
+            Some((start, end)) => self.fetch_block_range(start, end).await,
+            None => Ok(Vec::new()),
+        }
+    }
 
-        let start = latest.saturating_sub((count - 1).into());
+    /// Fetches the inclusive range `from..=to`, skipping blocks the provider
+    /// has nothing for.
+    pub async fn fetch_block_range(&self, from: u64, to: u64) -> Result<Vec<FetchedBlock>> {
+        self.check_block_range(from, to)?;
         let mut out = Vec::new();
+        for num in from..=to {
+            if let Some(block) = self.fetch_block(num).await? {
+                out.push(block);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Rejects reversed ranges and ranges wider than `ETH_MAX_BLOCKS_PER_CALL`.
+    pub fn check_block_range(&self, from: u64, to: u64) -> Result<()> {
+        if from > to {
+            return Err(anyhow!(
+                "invalid block range {}..={}: from is after to",
+                from,
+                to
+            ));
+        }
+        self.ensure_within_call_limit(to - from + 1)
+    }
 
-        for num in start.as_u64()..=latest.as_u64() {
-            let block_id = BlockId::Number(num.into());
+    /// Inclusive range covering the last `count` blocks up to the current
+    /// head, or `None` when `count` is zero.
+    pub async fn recent_block_range(&self, count: u64) -> Result<Option<(u64, u64)>> {
+        if count == 0 {
+            return Ok(None);
+        }
+        self.ensure_within_call_limit(count)?;
+        let latest = self.latest_block_number().await?;
+        Ok(Some((latest.saturating_sub(count - 1), latest)))
+    }
+
+    fn ensure_within_call_limit(&self, count: u64) -> Result<()> {
+        match self.max_blocks_per_call {
+            Some(max) if count > max => Err(anyhow!(
+                "refusing to fetch {} blocks in one call (ETH_MAX_BLOCKS_PER_CALL={})",
+                count,
+                max
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fetches one block with its transactions, preferring a single full-block
+    /// request and falling back to hydrating transactions by hash. Failures
+    /// (after the per-call retries) count towards `INGEST_STATS.rpc_errors`
+    /// unless this is a dry run.
+    pub async fn fetch_block(&self, num: u64) -> Result<Option<FetchedBlock>> {
+        let fetched = self.fetch_block_once(num).await;
+        if fetched.is_err() && self.record_stats {
+            INGEST_STATS.inc_rpc_errors(1);
+        }
+        fetched
+    }
+
+    async fn fetch_block_once(&self, num: u64) -> Result<Option<FetchedBlock>> {
+        let chain_id = self.chain_id().await?;
+        let block_id = BlockId::Number(num.into());
+        if !self.disable_full_block {
             let maybe_block = self
-                .provider
-                .get_block_with_txs(block_id)
+                .retry
+                .run("eth_getBlockByNumber", || {
+                    self.provider.get_block_with_txs(block_id)
+                })
                 .await
                 .with_context(|| format!("failed to fetch block {}", num))?;
 
             if let Some(block) = maybe_block {
-                if let Some(normalized) = normalize_block(block) {
-                    out.push(normalized);
-                    continue;
+                let metadata = block
+                    .transactions
+                    .iter()
+                    .flat_map(|tx| self.tx_metadata(chain_id, tx))
+                    .collect();
+                if let Some((info, txs)) = normalize_block(block, chain_id) {
+                    return Ok(Some(FetchedBlock {
+                        info,
+                        txs,
+                        metadata,
+                    }));
+                }
+            }
+        }
+
+        // Fallback: fetch block hashes and hydrate transactions individually,
+        // up to `fetch_concurrency` at once.
+        let maybe_hash_block = self
+            .retry
+            .run("eth_getBlockByNumber", || self.provider.get_block(block_id))
+            .await
+            .with_context(|| format!("failed to fetch block {} (hash fallback)", num))?;
+        if let Some(hash_block) = maybe_hash_block {
+            if let (Some(number), Some(hash)) = (hash_block.number, hash_block.hash) {
+                let timestamp = block_timestamp(number.as_u64(), hash_block.timestamp);
+                let mut fetched: Vec<(usize, Option<Transaction>)> =
+                    futures_util::stream::iter(hash_block.transactions.into_iter().enumerate())
+                        .map(|(index, tx_hash)| async move {
+                            let tx = self
+                                .retry
+                                .run("eth_getTransactionByHash", || {
+                                    self.provider.get_transaction(tx_hash)
+                                })
+                                .await?;
+                            Ok::<_, ProviderError>((index, tx))
+                        })
+                        .buffer_unordered(self.fetch_concurrency)
+                        .try_collect()
+                        .await?;
+                // Responses arrive in completion order; restore block order.
+                fetched.sort_unstable_by_key(|(index, _)| *index);
+                let mut txs = Vec::new();
+                let mut metadata = Vec::new();
+                for (index, full_tx) in fetched {
+                    if let Some(full_tx) = full_tx {
+                        metadata.extend(self.tx_metadata(chain_id, &full_tx));
+                        txs.push(normalize_tx(
+                            full_tx,
+                            chain_id,
+                            number.as_u64() as i64,
+                            timestamp,
+                            index as i64,
+                        ));
+                    }
                 }
+                let block_info = BlockInfo {
+                    number: number.as_u64() as i64,
+                    hash: format!("0x{:x}", hash),
+                    timestamp,
+                    base_fee_wei: hash_block.base_fee_per_gas.map(|fee| fee.to_string()),
+                    chain_id,
+                };
+                return Ok(Some(FetchedBlock {
+                    info: block_info,
+                    txs,
+                    metadata,
+                }));
             }
+        }
 
-            // Fallback: fetch block hashes and hydrate transactions individually.
-            let maybe_hash_block = self
-                .provider
-                .get_block(block_id)
+        tracing::warn!(
+            "block {} skipped: no usable block from either fetch path",
+            num
+        );
+        if self.record_stats {
+            INGEST_STATS.inc_skipped_blocks(1);
+        }
+        Ok(None)
+    }
+
+    /// Sets `status` on mined transactions from their receipts (`"success"` or
+    /// `"failed"`), fetching up to `fetch_concurrency` receipts at once,
+    /// and returns the ERC-20 transfers decoded from those receipts' logs.
+    /// Pending transactions, pre-Byzantium receipts and unparsable hashes keep `None`.
+    pub async fn apply_receipts(&self, txs: &mut [NormalizedTx]) -> Result<Vec<TokenTransfer>> {
+        let hashes: Vec<(usize, H256)> = txs
+            .iter()
+            .enumerate()
+            .filter(|(_, tx)| tx.block_number.is_some())
+            .filter_map(|(i, tx)| Some((i, tx.hash.parse().ok()?)))
+            .collect();
+        let mut receipts = futures_util::stream::iter(hashes)
+            .map(|(i, hash)| async move {
+                let receipt = self
+                    .retry
+                    .run("eth_getTransactionReceipt", || {
+                        self.provider.get_transaction_receipt(hash)
+                    })
+                    .await
+                    .with_context(|| format!("failed to fetch receipt for 0x{:x}", hash))?;
+                Ok::<_, anyhow::Error>((i, receipt))
+            })
+            .buffer_unordered(self.fetch_concurrency);
+        let mut transfers = Vec::new();
+        while let Some(result) = receipts.next().await {
+            let (i, receipt) = result?;
+            let Some(receipt) = receipt else {
+                txs[i].status = None;
+                continue;
+            };
+            txs[i].status = receipt
+                .status
+                .map(|status| receipt_status_label(status.as_u64()).to_string());
+            let chain_id = txs[i].chain_id;
+            transfers.extend(
+                receipt
+                    .logs
+                    .iter()
+                    .filter_map(|log| decode_erc20_transfer(log, chain_id)),
+            );
+        }
+        transfers.sort_by_key(|t| (t.block_number, t.log_index));
+        Ok(transfers)
+    }
+
+    /// Returns logs in `from_block..=to_block` whose topic0 is any of `topics`
+    /// (`eth_getLogs`). An empty topic list matches nothing.
+    pub async fn fetch_logs(
+        &self,
+        from_block: u64,
+        to_block: u64,
+        topics: &[H256],
+    ) -> Result<Vec<LogEntry>> {
+        if topics.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.ensure_within_call_limit(to_block.saturating_sub(from_block) + 1)?;
+        let chain_id = self.chain_id().await?;
+        let filter = Filter::new()
+            .from_block(from_block)
+            .to_block(to_block)
+            .topic0(topics.to_vec());
+        let logs = self.provider.get_logs(&filter).await.with_context(|| {
+            format!(
+                "failed to fetch logs for blocks {}..={}",
+                from_block, to_block
+            )
+        })?;
+        Ok(logs
+            .into_iter()
+            .filter_map(|log| normalize_log(log, chain_id))
+            .collect())
+    }
+
+    pub async fn get_balance(&self, address: &str) -> Result<U256> {
+        let addr: H160 = address
+            .parse()
+            .with_context(|| format!("invalid address {}", address))?;
+        self.provider
+            .get_balance(addr, None)
+            .await
+            .with_context(|| format!("failed to fetch balance for {}", address))
+    }
+
+    /// Replays a stored transaction with `eth_call` against the latest block.
+    /// The outcome reflects current chain state, not the state the sender saw.
+    pub async fn simulate(&self, tx: &NormalizedTx) -> Result<SimulationResult> {
+        let from: H160 = tx.from.parse().context("invalid from address")?;
+        let value = U256::from_dec_str(&tx.value_wei).context("invalid value_wei")?;
+        let data: Bytes = tx
+            .input_data
+            .as_deref()
+            .unwrap_or("0x")
+            .parse()
+            .context("invalid input_data")?;
+
+        let mut request = TransactionRequest::new()
+            .from(from)
+            .value(value)
+            .data(data)
+            .gas(tx.gas);
+        if let Some(to) = &tx.to {
+            let to: H160 = to.parse().context("invalid to address")?;
+            request = request.to(to);
+        }
+
+        let block = Some(BlockId::Number(BlockNumber::Latest));
+        match self.provider.call(&request.into(), block).await {
+            Ok(_) => Ok(SimulationResult {
+                success: true,
+                revert_reason: None,
+            }),
+            Err(err) => match err.as_error_response().and_then(|e| e.as_revert_data()) {
+                Some(data) => Ok(SimulationResult {
+                    success: false,
+                    revert_reason: decode_revert_reason(&data),
+                }),
+                None => Err(err).context("eth_call failed"),
+            },
+        }
+    }
+
+    /// Opens the pending-transaction subscription for [`Self::sub_mode`],
+    /// falling back from full bodies to hashes if the provider refuses.
+    async fn subscribe_pending<'a>(
+        &self,
+        ws_provider: &'a Provider<Ws>,
+    ) -> Result<BoxStream<'a, PendingItem>> {
+        let full = match self.sub_mode {
+            MempoolSubMode::Full => match ws_provider
+                .subscribe::<_, Transaction>(["alchemy_pendingTransactions"])
+                .await
+            {
+                Ok(stream) => Some(stream.map(|tx| PendingItem::Tx(Box::new(tx))).boxed()),
+                Err(err) => {
+                    tracing::warn!(
+                        "full pending subscription unsupported ({}); falling back to hashes",
+                        err
+                    );
+                    None
+                }
+            },
+            MempoolSubMode::Hashes => None,
+        };
+        Ok(match full {
+            Some(stream) => stream,
+            None => ws_provider
+                .subscribe_pending_txs()
                 .await
-                .with_context(|| format!("failed to fetch block {} (hash fallback)", num))?;
-            if let Some(hash_block) = maybe_hash_block {
-                if let (Some(number), Some(hash)) = (hash_block.number, hash_block.hash) {
-                    let timestamp = hash_block.timestamp.as_u64() as i64;
-                    let mut txs = Vec::new();
-                    for tx_hash in hash_block.transactions {
-                        if let Some(full_tx) = self.provider.get_transaction(tx_hash).await? {
-                            txs.push(normalize_tx(full_tx, number.as_u64() as i64, timestamp));
+                .context("failed to subscribe to pending txs")?
+                .map(PendingItem::Hash)
+                .boxed(),
+        })
+    }
+
+    /// Opens a fresh WebSocket after the pending subscription dropped, waiting
+    /// with the RPC retry backoff between attempts. Returns `None` once
+    /// [`MAX_WS_RECONNECTS`] is used up or the sample deadline has passed.
+    async fn reconnect_ws(
+        &self,
+        ws_url: &str,
+        deadline: Option<Instant>,
+        stats: &mut PendingSampleStats,
+    ) -> Option<Provider<Ws>> {
+        while stats.reconnects < MAX_WS_RECONNECTS {
+            let delay = self.retry.jittered(stats.reconnects as u32 + 1);
+            if deadline.is_some_and(|deadline| Instant::now() + delay >= deadline) {
+                return None;
+            }
+            stats.reconnects += 1;
+            tracing::warn!(
+                "pending subscription dropped; reconnecting in {:?} ({}/{})",
+                delay,
+                stats.reconnects,
+                MAX_WS_RECONNECTS
+            );
+            tokio::time::sleep(delay).await;
+            match Provider::<Ws>::connect(ws_url).await {
+                Ok(provider) => return Some(provider),
+                Err(err) => tracing::warn!("reconnect to ETH_WS_URL failed: {}", err),
+            }
+        }
+        tracing::warn!(
+            "giving up on the pending subscription after {} reconnects",
+            MAX_WS_RECONNECTS
+        );
+        None
+    }
+
+    /// The full transaction behind a subscription item, fetched by hash in
+    /// `hashes` mode. `None` means it already left the mempool.
+    async fn pending_body(&self, item: PendingItem) -> Result<Option<Transaction>, ProviderError> {
+        match item {
+            PendingItem::Tx(tx) => Ok(Some(*tx)),
+            PendingItem::Hash(hash) => {
+                self.retry
+                    .run("eth_getTransactionByHash", || {
+                        self.provider.get_transaction(hash)
+                    })
+                    .await
+            }
+        }
+    }
+
+    /// Sends every pending transaction matching `filter` to `matches` until the
+    /// subscription is lost for good or the receiver is dropped. Unlike
+    /// `sample_pending` nothing is stored, and the reconnect budget is
+    /// restored after each successful resubscribe.
+    pub async fn watch_pending(
+        &self,
+        ws_url: &str,
+        filter: &TxFilter,
+        matches: mpsc::Sender<NormalizedTx>,
+    ) -> Result<()> {
+        let chain_id = self.chain_id().await?;
+        let mut ws_provider = Provider::<Ws>::connect(ws_url)
+            .await
+            .context("failed to connect to ETH_WS_URL")?;
+        let mut sub = self.subscribe_pending(&ws_provider).await?;
+        let mut recent = RecentHashes::new(WATCH_DEDUP_WINDOW);
+        let mut reconnects = PendingSampleStats::default();
+
+        'watch: loop {
+            let Some(item) = sub.next().await else {
+                drop(sub);
+                loop {
+                    let Some(provider) = self.reconnect_ws(ws_url, None, &mut reconnects).await
+                    else {
+                        return Err(anyhow!("pending subscription lost"));
+                    };
+                    ws_provider = provider;
+                    match self.subscribe_pending(&ws_provider).await {
+                        Ok(stream) => {
+                            sub = stream;
+                            reconnects.reconnects = 0;
+                            continue 'watch;
                         }
+                        Err(err) => tracing::warn!("resubscribe failed: {:#}", err),
+                    }
+                }
+            };
+            let hash = match &item {
+                PendingItem::Hash(hash) => *hash,
+                PendingItem::Tx(tx) => tx.hash,
+            };
+            if !recent.insert(hash) {
+                continue;
+            }
+            match self.pending_body(item).await {
+                Ok(Some(tx)) => {
+                    let normalized = normalize_pending_tx(tx, chain_id);
+                    if filter.matches(&normalized) && matches.send(normalized).await.is_err() {
+                        return Ok(());
                     }
-                    let block_info = BlockInfo {
-                        number: number.as_u64() as i64,
-                        hash: format!("0x{:x}", hash),
-                        timestamp,
-                    };
-                    out.push((block_info, txs));
                 }
+                Ok(None) => {}
+                Err(err) => tracing::warn!("failed to fetch pending tx {}: {}", hash, err),
             }
         }
-
-        Ok(out)
     }
 
+    /// Without a `store` this is a dry run: batches are logged instead of
+    /// written and no sample row is created.
     pub async fn sample_pending(
         &self,
         ws_url: &str,
-        duration: Duration,
-        max: usize,
-        pool: &DbPool,
-        filters: Option<HashSet<String>>,
+        options: &SampleOptions,
+        store: Option<Arc<dyn Storage>>,
+        filter: TxFilter,
     ) -> Result<PendingSampleStats> {
-        let ws_provider = Provider::<Ws>::connect(ws_url)
+        let chain_id = self.chain_id().await?;
+        let mut ws_provider = Provider::<Ws>::connect(ws_url)
             .await
             .context("failed to connect to ETH_WS_URL")?;
-        let mut sub = ws_provider
-            .subscribe_pending_txs()
-            .await
-            .context("failed to subscribe to pending txs")?;
+        let mut sub = self.subscribe_pending(&ws_provider).await?;
 
         let mut stats = PendingSampleStats::default();
-        let mut buffer: Vec<NormalizedTx> = Vec::new();
+        let mut batch = PendingBatch::default();
+        let mut recent = RecentHashes::new(options.dedup_window);
 
-        let flush_every = 100usize;
-        let deadline = Instant::now() + duration;
+        let max = options.max;
+        let started = Instant::now();
+        let deadline = started + options.duration;
+        let mut last_flush = started;
+        let sample_id = match &store {
+            Some(store) => store.create_sample(unix_now_secs()).await?,
+            None => 0,
+        };
+        stats.sample_id = sample_id;
+
+        // Writes run on their own task so slow inserts do not stall the
+        // subscription; the queue bound caps how far they may fall behind.
+        let queue = InsertQueue::new(
+            self.storage_options.insert_queue_capacity,
+            self.storage_options.backpressure,
+        );
+        let writer = {
+            let queue = queue.clone();
+            let options = self.storage_options.clone();
+            tokio::spawn(async move {
+                let mut written = PendingSampleStats::default();
+                while let Some(mut batch) = queue.pop().await {
+                    match &store {
+                        Some(store) => {
+                            flush_pending(
+                                store.as_ref(),
+                                &options,
+                                sample_id,
+                                chain_id,
+                                &mut batch,
+                                &mut written,
+                            )
+                            .await
+                        }
+                        None => log_dry_run("pending batch", &batch.txs),
+                    }
+                }
+                written
+            })
+        };
 
-        while stats.received < max {
-            let remaining = deadline.saturating_duration_since(Instant::now());
-            if remaining.is_zero() {
+        'sample: while stats.received < max {
+            let now = Instant::now();
+            if now >= deadline {
                 break;
             }
+            // Wake for the flush timer as well as for the deadline.
+            let wake = match options.flush_interval {
+                Some(interval) => deadline.min(last_flush + interval),
+                None => deadline,
+            };
 
-            let next = tokio::time::timeout(remaining, sub.next()).await;
-            let Some(hash) = (match next {
-                Ok(item) => item,
-                Err(_) => break,
-            }) else {
-                break;
+            let wait = wake.saturating_duration_since(now);
+
+            let item = match tokio::time::timeout(wait, sub.next()).await {
+                Ok(Some(item)) => item,
+                Ok(None) => {
+                    // The connection dropped before the deadline: keep what was
+                    // gathered, then resubscribe on a fresh connection.
+                    drop(sub);
+                    if !batch.is_empty() {
+                        enqueue_batch(
+                            &queue,
+                            std::mem::take(&mut batch),
+                            &mut stats,
+                            self.record_stats,
+                        )
+                        .await;
+                        last_flush = Instant::now();
+                    }
+                    loop {
+                        let Some(provider) =
+                            self.reconnect_ws(ws_url, Some(deadline), &mut stats).await
+                        else {
+                            break 'sample;
+                        };
+                        ws_provider = provider;
+                        match self.subscribe_pending(&ws_provider).await {
+                            Ok(stream) => {
+                                sub = stream;
+                                continue 'sample;
+                            }
+                            Err(err) => tracing::warn!("resubscribe failed: {:#}", err),
+                        }
+                    }
+                }
+                Err(_) => {
+                    // Deadline (checked above) or flush timer; an empty buffer
+                    // just restarts the timer.
+                    if options.flush_due(batch.len(), last_flush.elapsed()) {
+                        enqueue_batch(
+                            &queue,
+                            std::mem::take(&mut batch),
+                            &mut stats,
+                            self.record_stats,
+                        )
+                        .await;
+                    }
+                    last_flush = Instant::now();
+                    continue;
+                }
             };
+            let hash = match &item {
+                PendingItem::Hash(hash) => *hash,
+                PendingItem::Tx(tx) => tx.hash,
+            };
+
+            if !recent.insert(hash) {
+                stats.duplicates_skipped += 1;
+                continue;
+            }
 
             stats.received += 1;
+            let offset_ms = started.elapsed().as_millis() as i64;
 
-            match self.provider.get_transaction(hash).await {
+            match self.pending_body(item).await {
                 Ok(Some(tx)) => {
                     stats.fetched += 1;
-                    let normalized = normalize_pending_tx(tx);
-                    if include_tx(&normalized, filters.as_ref()) {
-                        buffer.push(normalized);
+                    let metadata = self.tx_metadata(chain_id, &tx);
+                    let normalized = normalize_pending_tx(tx, chain_id);
+                    if filter.matches(&normalized) {
+                        batch.push(normalized, offset_ms);
+                        batch.metadata.extend(metadata);
                     }
                 }
                 Ok(None) => {}
                 Err(err) => {
+                    if self.record_stats {
+                        INGEST_STATS.inc_rpc_errors(1);
+                    }
                     tracing::warn!("failed to fetch pending tx {}: {}", hash, err);
                 }
             }
 
-            if buffer.len() >= flush_every {
-                match storage::insert_transactions(pool, &buffer).await {
-                    Ok(_) => {
-                        stats.inserted += buffer.len();
-                        INGEST_STATS.inc_pending_transactions(buffer.len() as u64);
-                    }
-                    Err(e) => {
-                        stats.insert_errors += 1;
-                        tracing::warn!("failed inserting pending tx batch: {}", e);
-                    }
-                }
-                buffer.clear();
+            if options.flush_due(batch.len(), last_flush.elapsed()) {
+                enqueue_batch(
+                    &queue,
+                    std::mem::take(&mut batch),
+                    &mut stats,
+                    self.record_stats,
+                )
+                .await;
+                last_flush = Instant::now();
             }
 
             if stats.received >= max {
@@ -169,44 +1076,97 @@ impl EthClient {
             }
         }
 
-        if !buffer.is_empty() {
-            match storage::insert_transactions(pool, &buffer).await {
-                Ok(_) => {
-                    stats.inserted += buffer.len();
-                    INGEST_STATS.inc_pending_transactions(buffer.len() as u64);
-                }
-                Err(e) => {
-                    stats.insert_errors += 1;
-                    tracing::warn!("failed inserting final pending tx batch: {}", e);
-                }
-            }
+        if !batch.is_empty() {
+            enqueue_batch(&queue, batch, &mut stats, self.record_stats).await;
         }
+        queue.close();
+        let written = writer.await.context("insert writer task failed")?;
+        stats.inserted = written.inserted;
+        stats.insert_errors = written.insert_errors;
 
         Ok(stats)
     }
 }
 
-fn normalize_block(block: Block<Transaction>) -> Option<(BlockInfo, Vec<NormalizedTx>)> {
+fn normalize_block(
+    block: Block<Transaction>,
+    chain_id: i64,
+) -> Option<(BlockInfo, Vec<NormalizedTx>)> {
     let number: i64 = block.number?.as_u64() as i64;
     let hash: H256 = block.hash?;
-    let timestamp = block.timestamp.as_u64() as i64;
+    let timestamp = block_timestamp(number as u64, block.timestamp);
 
     let block_info = BlockInfo {
         number,
         hash: format!("0x{:x}", hash),
         timestamp,
+        base_fee_wei: block.base_fee_per_gas.map(|fee| fee.to_string()),
+        chain_id,
     };
 
     let txs = block
         .transactions
         .into_iter()
-        .map(|tx| normalize_tx(tx, number, timestamp))
+        .enumerate()
+        .map(|(index, tx)| normalize_tx(tx, chain_id, number, timestamp, index as i64))
         .collect();
 
     Some((block_info, txs))
 }
 
-fn normalize_tx(tx: Transaction, block_number: i64, timestamp: i64) -> NormalizedTx {
+/// Event signature of ERC-20 (and ERC-721) transfers.
+const ERC20_TRANSFER_SIGNATURE: &str = "Transfer(address,address,uint256)";
+
+/// topic0 of [`ERC20_TRANSFER_SIGNATURE`].
+pub fn erc20_transfer_topic() -> H256 {
+    H256(keccak256(ERC20_TRANSFER_SIGNATURE))
+}
+
+/// Decodes an ERC-20 `Transfer` log. Other events, removed logs and ERC-721
+/// transfers (whose token id is a fourth, indexed topic) yield `None`.
+pub fn decode_erc20_transfer(log: &Log, chain_id: i64) -> Option<TokenTransfer> {
+    if log.removed == Some(true)
+        || log.topics.len() != 3
+        || log.topics[0] != erc20_transfer_topic()
+        || log.data.len() != 32
+    {
+        return None;
+    }
+    Some(TokenTransfer {
+        chain_id,
+        tx_hash: format!("0x{:x}", log.transaction_hash?),
+        log_index: log.log_index?.as_u64() as i64,
+        block_number: log.block_number.map(|n| n.as_u64() as i64),
+        token: address_to_lower_hex(log.address),
+        from: address_to_lower_hex(H160::from(log.topics[1])),
+        to: address_to_lower_hex(H160::from(log.topics[2])),
+        amount: U256::from_big_endian(&log.data).to_string(),
+    })
+}
+
+/// Drops logs without block position (pending or removed by a reorg).
+fn normalize_log(log: Log, chain_id: i64) -> Option<LogEntry> {
+    if log.removed == Some(true) {
+        return None;
+    }
+    Some(LogEntry {
+        chain_id,
+        block_number: log.block_number?.as_u64() as i64,
+        tx_hash: format!("0x{:x}", log.transaction_hash?),
+        log_index: log.log_index?.as_u64() as i64,
+        address: address_to_lower_hex(log.address),
+        topics: log.topics.iter().map(|t| format!("0x{:x}", t)).collect(),
+        data: log.data.to_string(),
+    })
+}
+
+fn normalize_tx(
+    tx: Transaction,
+    chain_id: i64,
+    block_number: i64,
+    timestamp: i64,
+    tx_index: i64,
+) -> NormalizedTx {
     NormalizedTx {
         hash: format!("0x{:x}", tx.hash),
         from: address_to_lower_hex(tx.from),
@@ -215,14 +1175,20 @@ fn normalize_tx(tx: Transaction, block_number: i64, timestamp: i64) -> Normalize
         gas: u256_to_i64_lossy(tx.gas),
         gas_price_wei: tx.gas_price.map(|v| v.to_string()),
         max_fee_per_gas_wei: tx.max_fee_per_gas.map(|v| v.to_string()),
+        max_priority_fee_per_gas_wei: tx.max_priority_fee_per_gas.map(|v| v.to_string()),
         nonce: u256_to_i64_lossy(tx.nonce),
         block_number: Some(block_number),
         timestamp: Some(timestamp),
         status: None,
+        input_data: Some(tx.input.to_string()),
+        tx_index: Some(tx_index),
+        chain_id,
+        tx_type: Some(tx_type_of(&tx)),
+        input_size: Some(tx.input.len() as i64),
     }
 }
 
-fn normalize_pending_tx(tx: Transaction) -> NormalizedTx {
+fn normalize_pending_tx(tx: Transaction, chain_id: i64) -> NormalizedTx {
     NormalizedTx {
         hash: format!("0x{:x}", tx.hash),
         from: address_to_lower_hex(tx.from),
@@ -231,24 +1197,84 @@ fn normalize_pending_tx(tx: Transaction) -> NormalizedTx {
         gas: u256_to_i64_lossy(tx.gas),
         gas_price_wei: tx.gas_price.map(|v| v.to_string()),
         max_fee_per_gas_wei: tx.max_fee_per_gas.map(|v| v.to_string()),
+        max_priority_fee_per_gas_wei: tx.max_priority_fee_per_gas.map(|v| v.to_string()),
         nonce: u256_to_i64_lossy(tx.nonce),
         block_number: None,
         timestamp: None,
         status: None,
+        input_data: Some(tx.input.to_string()),
+        tx_index: None,
+        chain_id,
+        tx_type: Some(tx_type_of(&tx)),
+        input_size: Some(tx.input.len() as i64),
     }
 }
 
-fn include_tx(tx: &NormalizedTx, filters: Option<&HashSet<String>>) -> bool {
-    if let Some(filter) = filters {
-        let from_match = filter.contains(&tx.from);
-        let to_match = tx
-            .to
-            .as_ref()
-            .map(|addr| filter.contains(addr))
-            .unwrap_or(false);
-        from_match || to_match
-    } else {
-        true
+/// EIP-2718 type of `tx`; pre-typed transactions carry none and count as
+/// legacy (0).
+fn tx_type_of(tx: &Transaction) -> i64 {
+    tx.transaction_type.map_or(0, |t| t.as_u64() as i64)
+}
+
+/// Decodes a Solidity `Error(string)` revert payload; custom errors and empty
+/// reverts yield `None`.
+fn decode_revert_reason(data: &[u8]) -> Option<String> {
+    const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+    let payload = data.strip_prefix(&ERROR_SELECTOR)?;
+    match abi::decode(&[ParamType::String], payload).ok()?.pop()? {
+        Token::String(reason) => Some(reason),
+        _ => None,
+    }
+}
+
+/// Which transactions block ingest and mempool sampling keep
+/// (`FILTER_ADDRESSES`, `FILTER_MIN_VALUE_WEI`). When both are set a
+/// transaction must match an address *and* meet the value threshold.
+#[derive(Debug, Clone, Default)]
+pub struct TxFilter {
+    pub addresses: Option<HashSet<String>>,
+    /// Decimal wei; compared as a big number, so any `U256` value works.
+    pub min_value_wei: Option<String>,
+}
+
+impl TxFilter {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            addresses: config.filter_addresses.clone(),
+            min_value_wei: config.filter_min_value_wei.clone(),
+        }
+    }
+
+    pub fn matches(&self, tx: &NormalizedTx) -> bool {
+        self.matches_address(tx) && self.meets_min_value(tx)
+    }
+
+    fn matches_address(&self, tx: &NormalizedTx) -> bool {
+        match &self.addresses {
+            Some(filter) => {
+                filter.contains(&tx.from)
+                    || tx
+                        .to
+                        .as_ref()
+                        .map(|addr| filter.contains(addr))
+                        .unwrap_or(false)
+            }
+            None => true,
+        }
+    }
+
+    fn meets_min_value(&self, tx: &NormalizedTx) -> bool {
+        let Some(min) = self.min_value_wei.as_deref() else {
+            return true;
+        };
+        // Canonical decimals order by (length, text); non-numeric never passes.
+        match (
+            storage::canonical_wei(&tx.value_wei),
+            storage::canonical_wei(min),
+        ) {
+            (Some(value), Some(min)) => (value.len(), &value) >= (min.len(), &min),
+            _ => false,
+        }
     }
 }
 
@@ -265,41 +1291,131 @@ fn u256_to_i64_lossy(value: U256) -> i64 {
     u256_to_i64_opt(value).unwrap_or(i64::MAX)
 }
 
+/// Clamps a header timestamp that does not fit `i64` to `i64::MAX`, so a bogus
+/// far-future value sorts last instead of wrapping negative.
+fn block_timestamp(number: u64, timestamp: U256) -> i64 {
+    u256_to_i64_opt(timestamp).unwrap_or_else(|| {
+        tracing::warn!(
+            "block {} has out-of-range timestamp {}; clamping to i64::MAX",
+            number,
+            timestamp
+        );
+        i64::MAX
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use ethers_core::types::U256;
+    use ethers_providers::{HttpClientError, JsonRpcError};
+
+    fn transfer(from: &str, value_wei: &str) -> NormalizedTx {
+        NormalizedTx {
+            hash: "0x01".to_string(),
+            from: from.to_string(),
+            to: Some("0xbbb".to_string()),
+            value_wei: value_wei.to_string(),
+            gas: 21_000,
+            gas_price_wei: None,
+            max_fee_per_gas_wei: None,
+            max_priority_fee_per_gas_wei: None,
+            nonce: 0,
+            block_number: None,
+            timestamp: None,
+            status: None,
+            input_data: None,
+            tx_index: None,
+            chain_id: 1,
+            tx_type: None,
+            input_size: None,
+        }
+    }
+
+    #[test]
+    fn tx_filter_applies_value_threshold() {
+        let filter = TxFilter {
+            addresses: None,
+            min_value_wei: Some("1000000000000000000".to_string()),
+        };
+        assert!(filter.matches(&transfer("0xaaa", "1000000000000000000")));
+        assert!(filter.matches(&transfer("0xaaa", "2000000000000000000")));
+        assert!(!filter.matches(&transfer("0xaaa", "999999999999999999")));
+        assert!(!filter.matches(&transfer("0xaaa", "0")));
+        // Beyond u128, where a naive numeric parse would overflow.
+        let huge = format!("1{}", "0".repeat(45));
+        assert!(filter.matches(&transfer("0xaaa", &huge)));
+        assert!(TxFilter::default().matches(&transfer("0xaaa", "0")));
+    }
+
+    #[test]
+    fn tx_filter_requires_address_and_value() {
+        let filter = TxFilter {
+            addresses: Some(HashSet::from(["0xaaa".to_string()])),
+            min_value_wei: Some("100".to_string()),
+        };
+        assert!(filter.matches(&transfer("0xaaa", "100")));
+        assert!(!filter.matches(&transfer("0xaaa", "99")));
+        assert!(!filter.matches(&transfer("0xccc", "500")));
+        assert!(filter.matches(&NormalizedTx {
+            to: Some("0xaaa".to_string()),
+            ..transfer("0xccc", "500")
+        }));
+    }
 
     #[test]
     fn normalize_legacy_tx_sets_gas_price() {
-        let mut tx = Transaction::default();
-        tx.hash = H256::from_low_u64_be(1);
-        tx.from = H160::from_low_u64_be(2);
-        tx.to = Some(H160::from_low_u64_be(3));
-        tx.value = U256::from(42u64);
-        tx.gas = U256::from(21_000u64);
-        tx.nonce = U256::from(7u64);
-        tx.gas_price = Some(U256::from(1000u64));
-        tx.max_fee_per_gas = None;
-
-        let normalized = normalize_tx(tx, 10, 1234);
+        let tx = Transaction {
+            hash: H256::from_low_u64_be(1),
+            from: H160::from_low_u64_be(2),
+            to: Some(H160::from_low_u64_be(3)),
+            value: U256::from(42u64),
+            gas: U256::from(21_000u64),
+            nonce: U256::from(7u64),
+            gas_price: Some(U256::from(1000u64)),
+            max_fee_per_gas: None,
+            ..Default::default()
+        };
+
+        let normalized = normalize_tx(tx, 1, 10, 1234, 0);
         assert_eq!(normalized.gas_price_wei, Some("1000".to_string()));
         assert_eq!(normalized.max_fee_per_gas_wei, None);
+        // Pre-typed transactions carry no type and are stored as legacy.
+        assert_eq!(normalized.tx_type, Some(0));
+        assert_eq!(normalized.input_size, Some(0));
+    }
+
+    #[test]
+    fn normalize_typed_tx_records_type_and_input_size() {
+        let tx = Transaction {
+            hash: H256::from_low_u64_be(12),
+            from: H160::from_low_u64_be(13),
+            max_fee_per_gas: Some(U256::from(3_000_000_000u64)),
+            transaction_type: Some(2u64.into()),
+            input: vec![0xa9, 0x05, 0x9c, 0xbb, 0x00].into(),
+            ..Default::default()
+        };
+
+        let normalized = normalize_pending_tx(tx, 1);
+        assert_eq!(normalized.tx_type, Some(2));
+        assert_eq!(normalized.input_size, Some(5));
     }
 
     #[test]
     fn normalize_eip1559_tx_sets_max_fee() {
-        let mut tx = Transaction::default();
-        tx.hash = H256::from_low_u64_be(5);
-        tx.from = H160::from_low_u64_be(6);
-        tx.to = Some(H160::from_low_u64_be(7));
-        tx.value = U256::from(99u64);
-        tx.gas = U256::from(30_000u64);
-        tx.nonce = U256::from(8u64);
-        tx.gas_price = None;
-        tx.max_fee_per_gas = Some(U256::from(2_000_000_000u64));
-
-        let normalized = normalize_tx(tx, 11, 4567);
+        let tx = Transaction {
+            hash: H256::from_low_u64_be(5),
+            from: H160::from_low_u64_be(6),
+            to: Some(H160::from_low_u64_be(7)),
+            value: U256::from(99u64),
+            gas: U256::from(30_000u64),
+            nonce: U256::from(8u64),
+            gas_price: None,
+            max_fee_per_gas: Some(U256::from(2_000_000_000u64)),
+            ..Default::default()
+        };
+
+        let normalized = normalize_tx(tx, 1, 11, 4567, 3);
         assert_eq!(
             normalized.max_fee_per_gas_wei,
             Some("2000000000".to_string())
@@ -309,17 +1425,329 @@ mod tests {
 
     #[test]
     fn normalize_pending_tx_sets_block_fields_none() {
-        let mut tx = Transaction::default();
-        tx.hash = H256::from_low_u64_be(9);
-        tx.from = H160::from_low_u64_be(10);
-        tx.value = U256::from(123u64);
-        tx.gas = U256::from(50_000u64);
-        tx.nonce = U256::from(3u64);
-        tx.gas_price = Some(U256::from(5000u64));
-
-        let normalized = normalize_pending_tx(tx);
+        let tx = Transaction {
+            hash: H256::from_low_u64_be(9),
+            from: H160::from_low_u64_be(10),
+            value: U256::from(123u64),
+            gas: U256::from(50_000u64),
+            nonce: U256::from(3u64),
+            gas_price: Some(U256::from(5000u64)),
+            ..Default::default()
+        };
+
+        let normalized = normalize_pending_tx(tx, 10);
+        assert_eq!(normalized.chain_id, 10);
         assert_eq!(normalized.block_number, None);
         assert_eq!(normalized.timestamp, None);
         assert_eq!(normalized.gas_price_wei, Some("5000".to_string()));
     }
+
+    #[test]
+    fn normalize_block_clamps_huge_timestamp() {
+        let tx = Transaction {
+            hash: H256::from_low_u64_be(1),
+            ..Default::default()
+        };
+        let block = Block {
+            number: Some(20u64.into()),
+            hash: Some(H256::from_low_u64_be(20)),
+            timestamp: U256::from(u64::MAX),
+            transactions: vec![tx],
+            ..Default::default()
+        };
+
+        let (info, txs) = normalize_block(block, 8453).unwrap();
+        assert_eq!((info.chain_id, txs[0].chain_id), (8453, 8453));
+        assert_eq!(info.timestamp, i64::MAX);
+        assert_eq!(txs[0].timestamp, Some(i64::MAX));
+        assert_eq!(txs[0].tx_index, Some(0));
+        assert_eq!(block_timestamp(20, U256::MAX), i64::MAX);
+        assert_eq!(
+            block_timestamp(20, U256::from(1_700_000_000u64)),
+            1_700_000_000
+        );
+    }
+
+    #[test]
+    fn retry_backoff_doubles_with_bounded_jitter() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            base_delay: Duration::from_millis(100),
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        for _ in 0..20 {
+            let delay = policy.jittered(2);
+            assert!(delay >= Duration::from_millis(200) && delay < Duration::from_millis(300));
+        }
+    }
+
+    fn rpc_error(code: i64) -> ProviderError {
+        ProviderError::JsonRpcClientError(Box::new(HttpClientError::JsonRpcError(JsonRpcError {
+            code,
+            message: String::new(),
+            data: None,
+        })))
+    }
+
+    #[tokio::test]
+    async fn retry_gives_up_after_max_retries() {
+        let policy = RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+        };
+        let calls = std::sync::atomic::AtomicU32::new(0);
+        let result: Result<u64, _> = policy
+            .run("test", || async {
+                calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Err(rpc_error(429))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        let calls = std::sync::atomic::AtomicU32::new(0);
+        let result = policy
+            .run("test", || async {
+                match calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) {
+                    0 => Err(rpc_error(-32005)),
+                    _ => Ok(7u64),
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn only_transient_errors_are_retried() {
+        let policy = RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+        };
+        for err in [
+            || rpc_error(3),
+            || rpc_error(-32602),
+            || ProviderError::CustomError("bad input".to_string()),
+        ] {
+            let calls = std::sync::atomic::AtomicU32::new(0);
+            let result: Result<u64, _> = policy
+                .run("test", || async {
+                    calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    Err(err())
+                })
+                .await;
+            assert!(result.is_err());
+            assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn hash_fallback_keeps_block_order_when_hydrated_concurrently() {
+        use ethers_core::types::Block;
+
+        let hashes: Vec<H256> = (1..=4).map(H256::from_low_u64_be).collect();
+        let block = serde_json::to_value(Block::<H256> {
+            number: Some(7u64.into()),
+            hash: Some(H256::from_low_u64_be(0xb7)),
+            timestamp: U256::from(1_700_000_000u64),
+            transactions: hashes.clone(),
+            ..Default::default()
+        })
+        .unwrap();
+        let rpc = move |axum::Json(req): axum::Json<serde_json::Value>| {
+            let block = block.clone();
+            async move {
+                let result = match req["method"].as_str().unwrap() {
+                    "eth_getBlockByNumber" => block,
+                    "eth_getTransactionByHash" => {
+                        let hash: H256 = serde_json::from_value(req["params"][0].clone()).unwrap();
+                        // Earlier transactions answer last.
+                        let delay = 50 - 10 * hash.to_low_u64_be();
+                        tokio::time::sleep(Duration::from_millis(delay)).await;
+                        serde_json::to_value(Transaction {
+                            hash,
+                            ..Default::default()
+                        })
+                        .unwrap()
+                    }
+                    other => panic!("unexpected method {}", other),
+                };
+                axum::Json(serde_json::json!({"jsonrpc": "2.0", "id": req["id"], "result": result}))
+            }
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = axum::Router::new().route("/", axum::routing::post(rpc));
+        let server = tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut client = EthClient::new(&format!("http://{}", addr)).unwrap();
+        client.disable_full_block = true;
+        client.chain_id = Arc::new(OnceCell::new_with(Some(1)));
+        let block = client.fetch_block(7).await.unwrap().unwrap();
+        assert_eq!(block.info.number, 7);
+        let order: Vec<_> = block
+            .txs
+            .iter()
+            .map(|tx| (tx.hash.clone(), tx.tx_index))
+            .collect();
+        let expected: Vec<_> = hashes
+            .iter()
+            .enumerate()
+            .map(|(index, hash)| (format!("0x{:x}", hash), Some(index as i64)))
+            .collect();
+        assert_eq!(order, expected);
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn hash_fallback_after_unusable_full_block_runs_hooks_once() {
+        let hashes: Vec<H256> = (1..=2).map(H256::from_low_u64_be).collect();
+        let txs: Vec<Transaction> = hashes
+            .iter()
+            .map(|hash| Transaction {
+                hash: *hash,
+                ..Default::default()
+            })
+            .collect();
+        // No hash, so the full block cannot be normalized.
+        let full_block = serde_json::to_value(Block::<Transaction> {
+            number: Some(7u64.into()),
+            transactions: txs.clone(),
+            ..Default::default()
+        })
+        .unwrap();
+        let hash_block = serde_json::to_value(Block::<H256> {
+            number: Some(7u64.into()),
+            hash: Some(H256::from_low_u64_be(0xb7)),
+            transactions: hashes.clone(),
+            ..Default::default()
+        })
+        .unwrap();
+        let rpc = move |axum::Json(req): axum::Json<serde_json::Value>| {
+            let (full_block, hash_block, txs) =
+                (full_block.clone(), hash_block.clone(), txs.clone());
+            async move {
+                let result = match req["method"].as_str().unwrap() {
+                    "eth_getBlockByNumber" if req["params"][1] == true => full_block,
+                    "eth_getBlockByNumber" => hash_block,
+                    "eth_getTransactionByHash" => {
+                        let hash: H256 = serde_json::from_value(req["params"][0].clone()).unwrap();
+                        serde_json::to_value(txs.iter().find(|tx| tx.hash == hash)).unwrap()
+                    }
+                    other => panic!("unexpected method {}", other),
+                };
+                axum::Json(serde_json::json!({"jsonrpc": "2.0", "id": req["id"], "result": result}))
+            }
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = axum::Router::new().route("/", axum::routing::post(rpc));
+        let server = tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut client = EthClient::new(&format!("http://{}", addr))
+            .unwrap()
+            .with_metadata_hook(|_| vec![("seen".to_string(), "1".to_string())]);
+        client.chain_id = Arc::new(OnceCell::new_with(Some(1)));
+        let block = client.fetch_block(7).await.unwrap().unwrap();
+        let tagged: Vec<_> = block.metadata.iter().map(|row| row.hash.clone()).collect();
+        let expected: Vec<_> = hashes.iter().map(|hash| format!("0x{:x}", hash)).collect();
+        assert_eq!(tagged, expected);
+        server.abort();
+    }
+
+    #[test]
+    fn sample_flush_is_due_on_size_or_interval() {
+        let mut options = SampleOptions {
+            duration: Duration::from_secs(60),
+            max: 500,
+            dedup_window: 0,
+            flush_size: 3,
+            flush_interval: None,
+        };
+        let long_ago = Duration::from_secs(3600);
+        assert!(!options.flush_due(2, long_ago));
+        assert!(options.flush_due(3, Duration::ZERO));
+
+        options.flush_interval = Some(Duration::from_secs(5));
+        assert!(!options.flush_due(1, Duration::from_secs(4)));
+        assert!(options.flush_due(1, Duration::from_secs(5)));
+        // Nothing buffered, nothing to write.
+        assert!(!options.flush_due(0, long_ago));
+    }
+
+    #[test]
+    fn receipt_status_maps_to_labels() {
+        assert_eq!(receipt_status_label(1), "success");
+        assert_eq!(receipt_status_label(0), "failed");
+    }
+
+    #[test]
+    fn decode_erc20_transfer_reads_topics_and_amount() {
+        let mut amount = [0u8; 32];
+        U256::from(1_500_000u64).to_big_endian(&mut amount);
+        let log = Log {
+            address: H160::from_low_u64_be(0xdac),
+            topics: vec![
+                erc20_transfer_topic(),
+                H256::from(H160::from_low_u64_be(0xa1)),
+                H256::from(H160::from_low_u64_be(0xb2)),
+            ],
+            data: Bytes::from(amount.to_vec()),
+            block_number: Some(7u64.into()),
+            transaction_hash: Some(H256::from_low_u64_be(9)),
+            log_index: Some(3u64.into()),
+            ..Default::default()
+        };
+
+        let transfer = decode_erc20_transfer(&log, 8453).unwrap();
+        assert_eq!(
+            erc20_transfer_topic(),
+            "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+                .parse::<H256>()
+                .unwrap()
+        );
+        assert_eq!(transfer.token, format!("0x{:040x}", 0xdac));
+        assert_eq!(transfer.from, format!("0x{:040x}", 0xa1));
+        assert_eq!(transfer.to, format!("0x{:040x}", 0xb2));
+        assert_eq!(transfer.amount, "1500000");
+        assert_eq!(transfer.log_index, 3);
+        assert_eq!((transfer.chain_id, transfer.block_number), (8453, Some(7)));
+
+        // ERC-721 indexes the token id as a fourth topic.
+        let mut nft = log.clone();
+        nft.topics.push(H256::from_low_u64_be(1));
+        nft.data = Bytes::default();
+        assert!(decode_erc20_transfer(&nft, 1).is_none());
+        let mut other = log;
+        other.topics[0] = H256::from_low_u64_be(1);
+        assert!(decode_erc20_transfer(&other, 1).is_none());
+    }
+
+    #[test]
+    fn decode_revert_reason_reads_error_string() {
+        let mut data = vec![0x08, 0xc3, 0x79, 0xa0];
+        data.extend(abi::encode(&[Token::String("insufficient balance".into())]));
+        assert_eq!(
+            decode_revert_reason(&data),
+            Some("insufficient balance".to_string())
+        );
+        assert_eq!(decode_revert_reason(&[]), None);
+        assert_eq!(decode_revert_reason(&[0xde, 0xad, 0xbe, 0xef]), None);
+    }
+
+    #[test]
+    fn recent_hashes_skips_duplicates_within_window() {
+        let mut recent = RecentHashes::new(2);
+        let a = H256::from_low_u64_be(1);
+        let b = H256::from_low_u64_be(2);
+        let c = H256::from_low_u64_be(3);
+
+        assert!(recent.insert(a));
+        assert!(!recent.insert(a));
+        assert!(recent.insert(b));
+        assert!(recent.insert(c));
+        // `a` was evicted when `c` arrived, so it is treated as new again.
+        assert!(recent.insert(a));
+        assert!(!recent.insert(c));
+    }
 }
@@ -0,0 +1,292 @@
+use std::io::{self, BufWriter, Write};
+
+use futures_util::TryStreamExt;
+use sqlx::SqlitePool;
+
+use crate::cli::ExportFormat;
+use crate::models::NormalizedTx;
+use crate::storage::{self, BlockRange};
+
+/// CSV header, one column per [`NormalizedTx`] field in declaration order.
+pub const CSV_HEADER: &str = "hash,from,to,value_wei,gas,gas_price_wei,max_fee_per_gas_wei,\
+max_priority_fee_per_gas_wei,nonce,block_number,timestamp,status,input_data,tx_index,chain_id,tx_type,input_size\n";
+
+/// Buffered writer for row-oriented exports that flushes every
+/// `flush_every` rows and logs progress every `progress_every` rows, so an
+/// interrupted export still leaves a usable prefix on disk.
+pub struct ProgressWriter<W: Write> {
+    inner: BufWriter<W>,
+    rows: u64,
+    flush_every: u64,
+    progress_every: u64,
+}
+
+impl<W: Write> ProgressWriter<W> {
+    /// A zero interval disables periodic flushing or progress logging.
+    pub fn new(inner: W, flush_every: u64, progress_every: u64) -> Self {
+        Self {
+            inner: BufWriter::new(inner),
+            rows: 0,
+            flush_every,
+            progress_every,
+        }
+    }
+
+    /// Writes raw bytes that are not a data row, such as a header or framing.
+    pub fn write_raw(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.inner.write_all(bytes)
+    }
+
+    /// Writes one complete row and applies the flush/progress cadence.
+    pub fn write_row(&mut self, row: &[u8]) -> io::Result<()> {
+        self.inner.write_all(row)?;
+        self.rows += 1;
+        if self.flush_every > 0 && self.rows % self.flush_every == 0 {
+            self.inner.flush()?;
+        }
+        if self.progress_every > 0 && self.rows % self.progress_every == 0 {
+            tracing::info!("export progress: {} rows written", self.rows);
+        }
+        Ok(())
+    }
+
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Flushes remaining buffered bytes and returns the number of rows written.
+    pub fn finish(mut self) -> io::Result<u64> {
+        self.inner.flush()?;
+        Ok(self.rows)
+    }
+}
+
+/// Streams the stored transactions in `range` into `writer` as CSV or a JSON
+/// array, without holding more than one row in memory, and returns how many
+/// rows were written.
+pub async fn export_transactions<W: Write>(
+    pool: &SqlitePool,
+    format: ExportFormat,
+    range: BlockRange,
+    limit: Option<u64>,
+    mut writer: ProgressWriter<W>,
+) -> anyhow::Result<u64> {
+    let mut rows = storage::stream_transactions(pool, range, limit);
+    match format {
+        ExportFormat::Csv => {
+            writer.write_raw(CSV_HEADER.as_bytes())?;
+            while let Some(tx) = rows.try_next().await? {
+                writer.write_row(csv_row(&tx).as_bytes())?;
+            }
+        }
+        ExportFormat::Json => {
+            writer.write_raw(b"[")?;
+            while let Some(tx) = rows.try_next().await? {
+                let mut row = if writer.rows() == 0 {
+                    b"\n  ".to_vec()
+                } else {
+                    b",\n  ".to_vec()
+                };
+                serde_json::to_writer(&mut row, &tx)?;
+                writer.write_row(&row)?;
+            }
+            writer.write_raw(b"\n]\n")?;
+        }
+    }
+    Ok(writer.finish()?)
+}
+
+/// One CSV line matching [`CSV_HEADER`]. String columns are always quoted;
+/// numbers are bare and missing values are empty.
+pub fn csv_row(tx: &NormalizedTx) -> String {
+    let text = |value: Option<&str>| value.map(quote).unwrap_or_default();
+    let number = |value: Option<i64>| value.map(|n| n.to_string()).unwrap_or_default();
+    [
+        quote(&tx.hash),
+        quote(&tx.from),
+        text(tx.to.as_deref()),
+        quote(&tx.value_wei),
+        tx.gas.to_string(),
+        text(tx.gas_price_wei.as_deref()),
+        text(tx.max_fee_per_gas_wei.as_deref()),
+        text(tx.max_priority_fee_per_gas_wei.as_deref()),
+        tx.nonce.to_string(),
+        number(tx.block_number),
+        number(tx.timestamp),
+        text(tx.status.as_deref()),
+        text(tx.input_data.as_deref()),
+        number(tx.tx_index),
+        tx.chain_id.to_string(),
+        number(tx.tx_type),
+        number(tx.input_size),
+    ]
+    .join(",")
+        + "\n"
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Records what actually reached the underlying sink.
+    #[derive(Clone, Default)]
+    struct Sink(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn flushes_every_n_rows() {
+        let sink = Sink::default();
+        let mut writer = ProgressWriter::new(sink.clone(), 2, 0);
+
+        writer.write_row(b"a\n").unwrap();
+        assert!(sink.0.borrow().is_empty());
+        writer.write_row(b"b\n").unwrap();
+        assert_eq!(sink.0.borrow().as_slice(), b"a\nb\n");
+
+        writer.write_row(b"c\n").unwrap();
+        assert_eq!(writer.finish().unwrap(), 3);
+        assert_eq!(sink.0.borrow().as_slice(), b"a\nb\nc\n");
+    }
+
+    #[tokio::test]
+    async fn export_streams_rows_in_chain_order() {
+        use crate::models::BlockInfo;
+
+        let path = std::env::temp_dir().join(format!("export_{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let pool = storage::init_pool(&format!("sqlite://{}?mode=rwc", path.display()))
+            .await
+            .unwrap();
+        for number in [1, 2] {
+            let block = BlockInfo {
+                number,
+                hash: format!("0xb{}", number),
+                timestamp: 1_700_000_000 + number,
+                base_fee_wei: None,
+                chain_id: 1,
+            };
+            storage::insert_block(&pool, &block).await.unwrap();
+        }
+        let tx = |hash: &str, block_number: Option<i64>, tx_index: Option<i64>| NormalizedTx {
+            hash: hash.to_string(),
+            from: "0xa".to_string(),
+            to: None,
+            value_wei: "1".to_string(),
+            gas: 21_000,
+            gas_price_wei: None,
+            max_fee_per_gas_wei: None,
+            max_priority_fee_per_gas_wei: None,
+            nonce: 0,
+            block_number,
+            timestamp: None,
+            status: None,
+            input_data: None,
+            tx_index,
+            chain_id: 1,
+            tx_type: None,
+            input_size: None,
+        };
+        // Stored out of order; the export follows block, then position.
+        storage::insert_transactions(
+            &pool,
+            &[
+                tx("0xpending", None, None),
+                tx("0x2a", Some(2), Some(0)),
+                tx("0x1b", Some(1), Some(1)),
+                tx("0x1a", Some(1), Some(0)),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let export = |range: BlockRange| {
+            let pool = &pool;
+            async move {
+                let sink = Sink::default();
+                let writer = ProgressWriter::new(sink.clone(), 0, 0);
+                let rows = export_transactions(pool, ExportFormat::Csv, range, None, writer)
+                    .await
+                    .unwrap();
+                let text = String::from_utf8(sink.0.take()).unwrap();
+                let hashes: Vec<String> = text
+                    .lines()
+                    .skip(1)
+                    .map(|line| {
+                        line.split(',')
+                            .next()
+                            .unwrap()
+                            .trim_matches('"')
+                            .to_string()
+                    })
+                    .collect();
+                (rows, hashes)
+            }
+        };
+        assert_eq!(
+            export(BlockRange::default()).await,
+            (
+                4,
+                vec![
+                    "0x1a".into(),
+                    "0x1b".into(),
+                    "0x2a".into(),
+                    "0xpending".into()
+                ]
+            )
+        );
+        let bounded = BlockRange {
+            from: Some(2),
+            to: None,
+        };
+        assert_eq!(export(bounded).await, (1, vec!["0x2a".to_string()]));
+
+        pool.close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn csv_rows_quote_text_and_match_header() {
+        let tx = NormalizedTx {
+            hash: "0x01".to_string(),
+            from: "0xa\"b".to_string(),
+            to: None,
+            value_wei: "1000000000000000000000000000000".to_string(),
+            gas: 21_000,
+            gas_price_wei: Some("1000".to_string()),
+            max_fee_per_gas_wei: None,
+            max_priority_fee_per_gas_wei: None,
+            nonce: 7,
+            block_number: Some(12),
+            timestamp: None,
+            status: Some("success".to_string()),
+            input_data: Some("0x".to_string()),
+            tx_index: Some(3),
+            chain_id: 1,
+            tx_type: Some(0),
+            input_size: Some(0),
+        };
+        let row = csv_row(&tx);
+        assert_eq!(
+            row,
+            "\"0x01\",\"0xa\"\"b\",,\"1000000000000000000000000000000\",21000,\"1000\",,,7,12,,\
+             \"success\",\"0x\",3,1,0,0\n"
+        );
+        assert_eq!(row.matches(',').count(), CSV_HEADER.matches(',').count());
+    }
+}
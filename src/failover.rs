@@ -0,0 +1,272 @@
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use ethers_providers::{Http, HttpClientError, JsonRpcClient, JsonRpcError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use url::Url;
+
+/// Consecutive failures after which an endpoint is skipped for a while.
+pub const FAILURE_THRESHOLD: u32 = 3;
+
+/// How long a repeatedly failing endpoint is skipped.
+pub const COOLDOWN: Duration = Duration::from_secs(30);
+
+/// JSON-RPC error codes providers use for rate limiting. Any other error
+/// response is the node's answer to the request and is returned as-is.
+pub(crate) const RATE_LIMIT_CODES: [i64; 2] = [429, -32005];
+
+#[derive(Debug)]
+struct Endpoint {
+    transport: Http,
+    /// Host only, so API keys embedded in paths stay out of the logs.
+    label: String,
+    consecutive_failures: AtomicU32,
+    failures: AtomicU64,
+    /// Milliseconds since `FailoverHttp::started` before which the endpoint
+    /// is skipped; zero when it is healthy.
+    skip_until_ms: AtomicU64,
+}
+
+/// HTTP transport over one or more `ETH_RPC_URL` endpoints. Requests go to
+/// the endpoint that last answered; transport errors, unparsable replies and
+/// rate-limit errors move on to the next one. An endpoint failing
+/// [`FAILURE_THRESHOLD`] times in a row is skipped for [`COOLDOWN`] unless
+/// every endpoint is cooling down. With a single URL this behaves like a
+/// plain [`Http`] transport. Clones share endpoint state.
+#[derive(Debug, Clone)]
+pub struct FailoverHttp {
+    endpoints: Arc<[Endpoint]>,
+    /// Index of the endpoint tried first.
+    current: Arc<AtomicUsize>,
+    started: Instant,
+}
+
+impl FailoverHttp {
+    /// `urls` is a comma-separated list; blank entries are ignored.
+    pub fn new(urls: &str, client: reqwest::Client) -> Result<Self> {
+        let endpoints = urls
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .enumerate()
+            .map(|(index, raw)| {
+                let url = Url::parse(raw)
+                    .with_context(|| format!("invalid ETH_RPC_URL entry #{}", index + 1))?;
+                Ok(Endpoint {
+                    label: format!("#{} ({})", index + 1, url.host_str().unwrap_or("?")),
+                    transport: Http::new_with_client(url, client.clone()),
+                    consecutive_failures: AtomicU32::new(0),
+                    failures: AtomicU64::new(0),
+                    skip_until_ms: AtomicU64::new(0),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if endpoints.is_empty() {
+            return Err(anyhow!("ETH_RPC_URL lists no endpoints"));
+        }
+        Ok(Self {
+            endpoints: endpoints.into(),
+            current: Arc::new(AtomicUsize::new(0)),
+            started: Instant::now(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    /// Total failures per endpoint, in `ETH_RPC_URL` order.
+    pub fn failure_counts(&self) -> Vec<u64> {
+        self.endpoints
+            .iter()
+            .map(|endpoint| endpoint.failures.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    fn now_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    /// Endpoint indexes in the order to try them: healthy ones starting at
+    /// `current`, then cooling-down ones as a last resort.
+    fn attempt_order(&self) -> Vec<usize> {
+        let now = self.now_ms();
+        let first = self.current.load(Ordering::Relaxed);
+        let (mut healthy, cooling): (Vec<usize>, Vec<usize>) = (0..self.len())
+            .map(|offset| (first + offset) % self.len())
+            .partition(|&index| self.endpoints[index].skip_until_ms.load(Ordering::Relaxed) <= now);
+        healthy.extend(cooling);
+        healthy
+    }
+
+    fn record_failure(&self, index: usize, err: &HttpClientError) {
+        let endpoint = &self.endpoints[index];
+        endpoint.failures.fetch_add(1, Ordering::Relaxed);
+        let streak = endpoint
+            .consecutive_failures
+            .fetch_add(1, Ordering::Relaxed)
+            + 1;
+        if self.len() == 1 {
+            return;
+        }
+        if streak >= FAILURE_THRESHOLD {
+            endpoint.consecutive_failures.store(0, Ordering::Relaxed);
+            endpoint.skip_until_ms.store(
+                self.now_ms() + COOLDOWN.as_millis() as u64,
+                Ordering::Relaxed,
+            );
+            tracing::warn!(
+                "RPC endpoint {} failed {} times in a row ({}); skipping it for {:?}",
+                endpoint.label,
+                streak,
+                err,
+                COOLDOWN
+            );
+        } else {
+            tracing::warn!(
+                "RPC endpoint {} failed ({}); trying the next one",
+                endpoint.label,
+                err
+            );
+        }
+    }
+
+    fn record_success(&self, index: usize) {
+        let endpoint = &self.endpoints[index];
+        endpoint.consecutive_failures.store(0, Ordering::Relaxed);
+        endpoint.skip_until_ms.store(0, Ordering::Relaxed);
+        self.current.store(index, Ordering::Relaxed);
+    }
+}
+
+/// Whether `err` says something about the endpoint rather than the request.
+fn should_fail_over(err: &HttpClientError) -> bool {
+    match err {
+        HttpClientError::JsonRpcError(JsonRpcError { code, .. }) => RATE_LIMIT_CODES.contains(code),
+        _ => true,
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for FailoverHttp {
+    type Error = HttpClientError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        // Serialized once so every attempt sends the same parameters.
+        let params = serde_json::to_value(params).map_err(|err| HttpClientError::SerdeJson {
+            err,
+            text: String::new(),
+        })?;
+        let mut last_err = None;
+        for index in self.attempt_order() {
+            match self.endpoints[index]
+                .transport
+                .request(method, &params)
+                .await
+            {
+                Ok(value) => {
+                    self.record_success(index);
+                    return Ok(value);
+                }
+                Err(err) if should_fail_over(&err) => {
+                    self.record_failure(index, &err);
+                    last_err = Some(err);
+                }
+                Err(err) => {
+                    self.record_success(index);
+                    return Err(err);
+                }
+            }
+        }
+        Err(last_err.expect("at least one endpoint"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transport(urls: &str) -> FailoverHttp {
+        FailoverHttp::new(urls, reqwest::Client::new()).unwrap()
+    }
+
+    #[test]
+    fn parses_comma_separated_urls() {
+        assert_eq!(transport("http://a:8545").len(), 1);
+        let failover = transport(" http://a:8545 , https://b/v2/key,");
+        assert_eq!(failover.len(), 2);
+        assert_eq!(failover.endpoints[1].label, "#2 (b)");
+        assert!(FailoverHttp::new(" , ", reqwest::Client::new()).is_err());
+        assert!(FailoverHttp::new("http://a,not a url", reqwest::Client::new()).is_err());
+    }
+
+    #[test]
+    fn repeated_failures_move_endpoint_to_the_back() {
+        let failover = transport("http://a,http://b,http://c");
+        let err = || HttpClientError::SerdeJson {
+            err: serde::de::Error::custom("bad gateway"),
+            text: String::new(),
+        };
+        assert_eq!(failover.attempt_order(), vec![0, 1, 2]);
+        for _ in 0..FAILURE_THRESHOLD {
+            failover.record_failure(0, &err());
+        }
+        failover.record_success(1);
+        assert_eq!(failover.attempt_order(), vec![1, 2, 0]);
+        assert_eq!(failover.failure_counts(), vec![3, 0, 0]);
+
+        // Recovering clears the cooldown.
+        failover.record_success(0);
+        assert_eq!(failover.attempt_order(), vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn dead_endpoint_fails_over_to_the_next() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::post(|| async {
+                axum::Json(serde_json::json!({"jsonrpc": "2.0", "id": 0, "result": "0x10"}))
+            }),
+        );
+        let server = tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        // Nothing listens on port 1, so the first endpoint refuses connections.
+        let failover = transport(&format!("http://127.0.0.1:1,http://{}", addr));
+        let head: ethers_core::types::U64 = failover.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(head.as_u64(), 16);
+        assert_eq!(failover.failure_counts(), vec![1, 0]);
+        // The healthy endpoint is now tried first.
+        assert_eq!(failover.attempt_order(), vec![1, 0]);
+        server.abort();
+    }
+
+    #[test]
+    fn only_transport_and_rate_limit_errors_fail_over() {
+        let rpc = |code| {
+            HttpClientError::JsonRpcError(JsonRpcError {
+                code,
+                message: String::new(),
+                data: None,
+            })
+        };
+        assert!(should_fail_over(&rpc(429)));
+        assert!(should_fail_over(&rpc(-32005)));
+        assert!(!should_fail_over(&rpc(3)));
+        assert!(!should_fail_over(&rpc(-32000)));
+    }
+}
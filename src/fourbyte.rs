@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+/// Common selectors recognised without a `FOURBYTE_FILE`.
+const BUILTIN: &[(&str, &str)] = &[
+    ("0xa9059cbb", "transfer(address,uint256)"),
+    ("0x095ea7b3", "approve(address,uint256)"),
+    ("0x23b872dd", "transferFrom(address,address,uint256)"),
+    ("0xd0e30db0", "deposit()"),
+    ("0x2e1a7d4d", "withdraw(uint256)"),
+    (
+        "0x38ed1739",
+        "swapExactTokensForTokens(uint256,uint256,address[],address,uint256)",
+    ),
+    (
+        "0x7ff36ab5",
+        "swapExactETHForTokens(uint256,address[],address,uint256)",
+    ),
+    (
+        "0x18cbafe5",
+        "swapExactTokensForETH(uint256,uint256,address[],address,uint256)",
+    ),
+    ("0x5ae401dc", "multicall(uint256,bytes[])"),
+    ("0x3593564c", "execute(bytes,bytes[],uint256)"),
+];
+
+/// Maps 4-byte method selectors (`0x` + 8 lowercase hex chars) to function
+/// signatures.
+#[derive(Debug, Clone)]
+pub struct SignatureDb {
+    signatures: HashMap<String, String>,
+}
+
+impl Default for SignatureDb {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl SignatureDb {
+    pub fn builtin() -> Self {
+        Self {
+            signatures: BUILTIN
+                .iter()
+                .map(|(selector, sig)| (selector.to_string(), sig.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Loads a JSON object of `selector -> signature` on top of the built-in
+    /// set; file entries win on conflict.
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed reading 4byte file {:?}", path))?;
+        let entries: HashMap<String, String> =
+            serde_json::from_str(&raw).with_context(|| format!("invalid 4byte file {:?}", path))?;
+
+        let mut db = Self::builtin();
+        for (selector, signature) in entries {
+            db.signatures
+                .insert(selector.trim().to_ascii_lowercase(), signature);
+        }
+        Ok(db)
+    }
+
+    pub fn len(&self) -> usize {
+        self.signatures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty()
+    }
+
+    /// Looks up the signature for the selector at the start of `input_data`.
+    pub fn lookup_input(&self, input_data: &str) -> Option<&str> {
+        let selector = selector_of(input_data)?;
+        self.signatures
+            .get(&selector.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+}
+
+/// Returns the `0x`-prefixed 4-byte selector of hex calldata, if present.
+pub fn selector_of(input_data: &str) -> Option<&str> {
+    if input_data.len() < 10 || !input_data.starts_with("0x") {
+        return None;
+    }
+    input_data.get(..10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_selectors_match_signatures() {
+        for (selector, signature) in BUILTIN {
+            let id = ethers_core::utils::id(signature);
+            assert_eq!(
+                *selector,
+                format!("0x{}", ethers_core::utils::hex::encode(id)),
+                "{}",
+                signature
+            );
+        }
+    }
+
+    #[test]
+    fn lookup_uses_leading_selector() {
+        let db = SignatureDb::builtin();
+        assert_eq!(
+            db.lookup_input("0xA9059CBB000000000000000000000000"),
+            Some("transfer(address,uint256)")
+        );
+        assert_eq!(db.lookup_input("0x"), None);
+        assert_eq!(db.lookup_input("0xdeadbeef"), None);
+    }
+}
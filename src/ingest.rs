@@ -0,0 +1,395 @@
+use std::collections::HashSet;
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use ethers_core::types::H256;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::eth::{log_dry_run, unix_now_secs, EthClient, FetchedBlock, TxFilter};
+use crate::ingest_stats::INGEST_STATS;
+use crate::models::{BlockInfo, NormalizedTx, RowCounts};
+use crate::recent_cache::RecentTxCache;
+use crate::storage::{self, Storage, StorageOptions};
+
+/// Settings shared by every block-ingest entry point (`ingest-once` and
+/// `POST /ingest`).
+#[derive(Debug, Clone, Default)]
+pub struct IngestOptions {
+    pub filter: TxFilter,
+    pub block_sample_rate: Option<f64>,
+    pub storage: StorageOptions,
+    /// Block counts above this need explicit confirmation (`--yes`) on the CLI
+    /// and are refused over HTTP.
+    pub blocks_soft_cap: u64,
+    /// Topic0 hashes whose logs are fetched alongside each block.
+    pub watch_topics: Vec<H256>,
+    /// Fetch each stored transaction's receipt to fill `status` (one extra
+    /// call per transaction).
+    pub with_receipts: bool,
+    /// Fetch and filter as usual but write nothing (no inserts, reorg
+    /// replacement, retention or `INGEST_STATS` updates); what would have been
+    /// stored is logged instead.
+    pub dry_run: bool,
+}
+
+impl IngestOptions {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            filter: TxFilter::from_config(config),
+            block_sample_rate: config.block_sample_rate,
+            storage: StorageOptions::from_config(config),
+            blocks_soft_cap: config.ingest_blocks_soft_cap,
+            watch_topics: config.watch_topics.clone(),
+            with_receipts: false,
+            dry_run: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct IngestSummary {
+    /// Blocks and transactions written (including rows already stored).
+    pub blocks: usize,
+    pub transactions: usize,
+    /// Transactions dropped by `BLOCK_SAMPLE_RATE`.
+    pub sampled_out: usize,
+    /// Row-count growth, i.e. rows that were not stored before this run.
+    pub new_blocks: i64,
+    pub new_transactions: i64,
+    /// Logs matching `WATCH_TOPICS`.
+    pub logs: usize,
+    /// ERC-20 transfers decoded from receipts (`--with-receipts` only).
+    pub token_transfers: usize,
+    /// Blocks the provider returned nothing usable for (see the warn log).
+    pub skipped_blocks: usize,
+    /// Stored blocks whose hash no longer matched the chain and were replaced.
+    pub reorged_blocks: usize,
+    /// Rows removed by the retention limits after the run.
+    pub pruned: RowCounts,
+    /// Highest block fully stored by this run.
+    pub last_block: Option<u64>,
+}
+
+/// Fetches the last `blocks` blocks, applies sampling and the address filter,
+/// and stores the result. Stored transactions are also pushed to `cache`.
+/// `store` may be `None` for a dry run, which then never touches a database.
+pub async fn ingest_recent(
+    eth: &EthClient,
+    store: Option<&dyn Storage>,
+    blocks: u64,
+    options: &IngestOptions,
+    cache: Option<&RecentTxCache>,
+) -> Result<IngestSummary> {
+    let range = eth.recent_block_range(blocks).await?;
+    ingest_range(eth, store, range, options, cache).await
+}
+
+/// Like [`ingest_recent`] for the fixed inclusive range `from..=to`, e.g. a
+/// historical backfill.
+pub async fn ingest_block_range(
+    eth: &EthClient,
+    store: Option<&dyn Storage>,
+    from: u64,
+    to: u64,
+    options: &IngestOptions,
+    cache: Option<&RecentTxCache>,
+) -> Result<IngestSummary> {
+    eth.check_block_range(from, to)?;
+    ingest_range(eth, store, Some((from, to)), options, cache).await
+}
+
+async fn ingest_range(
+    eth: &EthClient,
+    store: Option<&dyn Storage>,
+    range: Option<(u64, u64)>,
+    options: &IngestOptions,
+    cache: Option<&RecentTxCache>,
+) -> Result<IngestSummary> {
+    // A dry run never touches the database, even when one is given.
+    let store = match options.dry_run {
+        true => None,
+        false => Some(store.context("ingest needs a database unless it is a dry run")?),
+    };
+    let before = match store {
+        Some(store) => store.count_rows().await?,
+        None => RowCounts::default(),
+    };
+    let mut summary = IngestSummary::default();
+    if let Some((start, end)) = range {
+        // Each block is stored before the next is fetched, so a failure keeps
+        // everything up to `last_block` and the error says where to resume.
+        for num in start..=end {
+            ingest_block(eth, store, num, options, cache, &mut summary)
+                .await
+                .with_context(|| match summary.last_block {
+                    Some(last) => format!(
+                        "ingest stopped at block {}; blocks {}..={} are stored, resume from block {}",
+                        num, start, last, num
+                    ),
+                    None => format!("ingest stopped at block {}; nothing stored", num),
+                })?;
+        }
+    }
+    if let Some(store) = store {
+        let after = store.count_rows().await?;
+        summary.new_blocks = after.blocks - before.blocks;
+        summary.new_transactions = after.transactions - before.transactions;
+        summary.pruned = storage::apply_retention(store, &options.storage, unix_now_secs()).await?;
+    }
+    Ok(summary)
+}
+
+/// Stored blocks re-fetched on every advance so a short reorg overwrites the
+/// replaced blocks.
+const FOLLOW_REORG_DEPTH: u64 = 2;
+
+/// Polls the chain head every `poll` and ingests blocks past the highest one
+/// stored, so a restart resumes where the database left off. A first run (or
+/// a gap above `blocks_soft_cap`) starts near the head instead of backfilling.
+/// Errors are logged and retried on the next tick; this only returns on a
+/// database error at startup. Stored transactions are also pushed to `cache`.
+pub async fn follow_head(
+    eth: &EthClient,
+    store: &dyn Storage,
+    poll: Duration,
+    options: &IngestOptions,
+    cache: Option<&RecentTxCache>,
+) -> Result<()> {
+    let chain_id = eth.chain_id().await?;
+    let mut last = store
+        .get_latest_block_number(Some(chain_id))
+        .await?
+        .map(|n| n as u64);
+    if let Some(last) = last {
+        tracing::info!("following chain head from stored block {}", last);
+    }
+    let mut ticker = tokio::time::interval(poll);
+    loop {
+        ticker.tick().await;
+        let head = match eth.latest_block_number().await {
+            Ok(head) => head,
+            Err(err) => {
+                tracing::warn!("{:#}; retrying in {:?}", err, poll);
+                continue;
+            }
+        };
+        if last.is_some_and(|last| head <= last) {
+            continue;
+        }
+        let start = follow_start(last, head, options.blocks_soft_cap);
+        let mut summary = IngestSummary::default();
+        for num in start..=head {
+            if let Err(err) =
+                ingest_block(eth, Some(store), num, options, cache, &mut summary).await
+            {
+                tracing::warn!(
+                    "follow: block {} failed: {:#}; retrying next poll",
+                    num,
+                    err
+                );
+                break;
+            }
+            if summary.last_block == Some(num) {
+                tracing::info!("ingested block {}", num);
+                last = last.max(Some(num));
+            }
+        }
+        match storage::apply_retention(store, &options.storage, unix_now_secs()).await {
+            Ok(pruned) if pruned.transactions > 0 || pruned.blocks > 0 => tracing::info!(
+                "retention pruned {} blocks, {} transactions",
+                pruned.blocks,
+                pruned.transactions
+            ),
+            Ok(_) => {}
+            Err(err) => tracing::warn!("retention failed: {:#}", err),
+        }
+    }
+}
+
+/// First block to fetch when the head moved past `last`.
+fn follow_start(last: Option<u64>, head: u64, soft_cap: u64) -> u64 {
+    let earliest = head.saturating_sub(soft_cap.max(1) - 1);
+    match last {
+        Some(last) if last + 1 >= earliest => (last + 1).saturating_sub(FOLLOW_REORG_DEPTH),
+        Some(last) => {
+            tracing::warn!(
+                "stored head {} is more than {} blocks behind {}; skipping ahead",
+                last,
+                soft_cap,
+                head
+            );
+            earliest
+        }
+        None => head,
+    }
+}
+
+/// Fetches (with the client's per-call retries) and stores one block; `store` is `None` only on a
+/// dry run.
+async fn ingest_block(
+    eth: &EthClient,
+    store: Option<&dyn Storage>,
+    num: u64,
+    options: &IngestOptions,
+    cache: Option<&RecentTxCache>,
+    summary: &mut IngestSummary,
+) -> Result<()> {
+    let Some(FetchedBlock {
+        info: block_info,
+        txs,
+        mut metadata,
+    }) = eth.fetch_block(num).await?
+    else {
+        summary.skipped_blocks += 1;
+        return Ok(());
+    };
+
+    let txs = match options.block_sample_rate {
+        Some(rate) => {
+            let (kept, dropped) = sample_txs(txs, rate, options.filter.addresses.as_ref());
+            summary.sampled_out += dropped;
+            kept
+        }
+        None => txs,
+    };
+    let mut filtered = filter_txs(&txs, &options.filter);
+    let transfers = if options.with_receipts {
+        eth.apply_receipts(&mut filtered).await?
+    } else {
+        Vec::new()
+    };
+    let Some(store) = store.filter(|_| !options.dry_run) else {
+        log_dry_run(&format!("block {}", num), &filtered);
+        summary.token_transfers += transfers.len();
+        if !options.watch_topics.is_empty() {
+            let logs = eth.fetch_logs(num, num, &options.watch_topics).await?;
+            summary.logs += logs.len();
+        }
+        summary.transactions += filtered.len();
+        summary.blocks += 1;
+        summary.last_block = Some(num);
+        return Ok(());
+    };
+
+    reconcile_block(store, &block_info, summary).await?;
+    store.upsert_block(&block_info).await?;
+    if !filtered.is_empty() {
+        store
+            .insert_transactions(&filtered, &options.storage)
+            .await?;
+        INGEST_STATS.inc_transactions(filtered.len() as u64);
+        if let Some(cache) = cache {
+            cache.push_many(&filtered);
+        }
+    }
+
+    let stored: HashSet<&str> = filtered.iter().map(|tx| tx.hash.as_str()).collect();
+    metadata.retain(|row| stored.contains(row.hash.as_str()));
+    if !metadata.is_empty() {
+        store.insert_tx_metadata(&metadata).await?;
+    }
+
+    if !transfers.is_empty() {
+        store.insert_token_transfers(&transfers).await?;
+        summary.token_transfers += transfers.len();
+    }
+
+    if !options.watch_topics.is_empty() {
+        let logs = eth.fetch_logs(num, num, &options.watch_topics).await?;
+        store.insert_logs(&logs).await?;
+        summary.logs += logs.len();
+    }
+
+    INGEST_STATS.inc_blocks(1);
+    summary.transactions += filtered.len();
+    summary.blocks += 1;
+    summary.last_block = Some(num);
+    Ok(())
+}
+
+/// Replaces the stored block at `block.number` when its hash differs from the
+/// freshly fetched one, i.e. the chain reorganized since it was stored. An
+/// empty (placeholder) hash on either side is not a reorg; `upsert_block`
+/// fills it in.
+async fn reconcile_block(
+    store: &dyn Storage,
+    block: &BlockInfo,
+    summary: &mut IngestSummary,
+) -> Result<()> {
+    let Some(stored) = store.get_block(block.chain_id, block.number).await? else {
+        return Ok(());
+    };
+    if stored.hash.is_empty() || block.hash.is_empty() || stored.hash == block.hash {
+        return Ok(());
+    }
+    tracing::warn!(
+        "reorg detected at block {}: stored hash {} replaced by {}",
+        block.number,
+        stored.hash,
+        block.hash
+    );
+    let removed = store.replace_block(block).await?;
+    tracing::info!(
+        "dropped {} transactions from orphaned block {}",
+        removed.transactions,
+        stored.hash
+    );
+    summary.reorged_blocks += 1;
+    Ok(())
+}
+
+fn filter_txs(txs: &[NormalizedTx], filter: &TxFilter) -> Vec<NormalizedTx> {
+    txs.iter()
+        .filter(|tx| filter.matches(tx))
+        .cloned()
+        .collect()
+}
+
+/// Keeps roughly `rate` of `txs`, always retaining transactions that match the
+/// address filter. Selection is keyed on the tx hash so reruns keep the same set.
+fn sample_txs(
+    txs: Vec<NormalizedTx>,
+    rate: f64,
+    filters: Option<&HashSet<String>>,
+) -> (Vec<NormalizedTx>, usize) {
+    let before = txs.len();
+    let kept: Vec<NormalizedTx> = txs
+        .into_iter()
+        .filter(|tx| {
+            let watched = filters
+                .map(|filter| {
+                    filter.contains(&tx.from)
+                        || tx.to.as_ref().map(|a| filter.contains(a)).unwrap_or(false)
+                })
+                .unwrap_or(false);
+            watched || hash_sample_point(&tx.hash) < rate
+        })
+        .collect();
+    let dropped = before - kept.len();
+    (kept, dropped)
+}
+
+/// Maps a tx hash onto `[0, 1)` using its trailing 64 bits, which are already
+/// uniformly distributed.
+fn hash_sample_point(hash: &str) -> f64 {
+    let tail = &hash[hash.len().saturating_sub(16)..];
+    match u64::from_str_radix(tail, 16) {
+        Ok(bits) => bits as f64 / (u64::MAX as f64 + 1.0),
+        Err(_) => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follow_start_refetches_recent_blocks_and_skips_large_gaps() {
+        assert_eq!(follow_start(None, 500, 100), 500);
+        assert_eq!(follow_start(Some(499), 500, 100), 498);
+        assert_eq!(follow_start(Some(1), 3, 100), 0);
+        assert_eq!(follow_start(Some(10), 500, 100), 401);
+    }
+}
@@ -1,10 +1,21 @@
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct IngestStats {
     blocks: AtomicU64,
     transactions: AtomicU64,
     pending_transactions: AtomicU64,
+    dropped_due_to_backpressure: AtomicU64,
+    skipped_blocks: AtomicU64,
+    rpc_errors: AtomicU64,
+    insert_errors: AtomicU64,
+    /// Completed runs recorded through `record_run`.
+    runs: AtomicU64,
+    last_run_micros: AtomicU64,
+    /// Totals over every recorded run, for the average throughput.
+    run_micros: AtomicU64,
+    run_transactions: AtomicU64,
 }
 
 impl Default for IngestStats {
@@ -19,9 +30,38 @@ impl IngestStats {
             blocks: AtomicU64::new(0),
             transactions: AtomicU64::new(0),
             pending_transactions: AtomicU64::new(0),
+            dropped_due_to_backpressure: AtomicU64::new(0),
+            skipped_blocks: AtomicU64::new(0),
+            rpc_errors: AtomicU64::new(0),
+            insert_errors: AtomicU64::new(0),
+            runs: AtomicU64::new(0),
+            last_run_micros: AtomicU64::new(0),
+            run_micros: AtomicU64::new(0),
+            run_transactions: AtomicU64::new(0),
         }
     }
 
+    pub fn inc_rpc_errors(&self, n: u64) {
+        self.rpc_errors.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn inc_insert_errors(&self, n: u64) {
+        self.insert_errors.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Records one finished run (`ingest-once`, `mempool-sample` or
+    /// `POST /ingest`) that took `elapsed` and stored `transactions` rows.
+    /// Like every counter here this is per process: CLI runs reach
+    /// Prometheus through the Pushgateway, not `serve`'s `/stats/ingest`.
+    pub fn record_run(&self, elapsed: Duration, transactions: u64) {
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        self.last_run_micros.store(micros, Ordering::Relaxed);
+        self.run_micros.fetch_add(micros, Ordering::Relaxed);
+        self.run_transactions
+            .fetch_add(transactions, Ordering::Relaxed);
+        self.runs.fetch_add(1, Ordering::Relaxed);
+    }
+
     pub fn inc_blocks(&self, n: u64) {
         self.blocks.fetch_add(n, Ordering::Relaxed);
     }
@@ -34,11 +74,30 @@ impl IngestStats {
         self.pending_transactions.fetch_add(n, Ordering::Relaxed);
     }
 
+    pub fn inc_dropped_due_to_backpressure(&self, n: u64) {
+        self.dropped_due_to_backpressure
+            .fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn inc_skipped_blocks(&self, n: u64) {
+        self.skipped_blocks.fetch_add(n, Ordering::Relaxed);
+    }
+
     pub fn snapshot(&self) -> IngestSnapshot {
         IngestSnapshot {
             blocks: self.blocks.load(Ordering::Relaxed),
             transactions: self.transactions.load(Ordering::Relaxed),
             pending_transactions: self.pending_transactions.load(Ordering::Relaxed),
+            dropped_due_to_backpressure: self.dropped_due_to_backpressure.load(Ordering::Relaxed),
+            skipped_blocks: self.skipped_blocks.load(Ordering::Relaxed),
+            rpc_errors: self.rpc_errors.load(Ordering::Relaxed),
+            insert_errors: self.insert_errors.load(Ordering::Relaxed),
+            last_run_secs: (self.runs.load(Ordering::Relaxed) > 0)
+                .then(|| self.last_run_micros.load(Ordering::Relaxed) as f64 / 1e6),
+            avg_tx_per_sec: tx_per_sec(
+                self.run_transactions.load(Ordering::Relaxed),
+                self.run_micros.load(Ordering::Relaxed),
+            ),
         }
     }
 }
@@ -48,6 +107,41 @@ pub struct IngestSnapshot {
     pub blocks: u64,
     pub transactions: u64,
     pub pending_transactions: u64,
+    pub dropped_due_to_backpressure: u64,
+    /// Blocks neither the full-block nor the per-hash path could normalize.
+    pub skipped_blocks: u64,
+    /// Block fetches and pending-transaction lookups that failed after their
+    /// retries.
+    pub rpc_errors: u64,
+    /// Failed writes of pending-sample batches, arrivals or metadata.
+    pub insert_errors: u64,
+    /// Wall-clock duration of the latest recorded run; `None` before the first.
+    pub last_run_secs: Option<f64>,
+    /// Transactions stored per second across all recorded runs.
+    pub avg_tx_per_sec: Option<f64>,
+}
+
+/// `None` until some run time has been recorded.
+fn tx_per_sec(transactions: u64, micros: u64) -> Option<f64> {
+    (micros > 0).then(|| transactions as f64 * 1e6 / micros as f64)
 }
 
 pub static INGEST_STATS: IngestStats = IngestStats::new();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_report_last_duration_and_average_rate() {
+        let stats = IngestStats::new();
+        let snap = stats.snapshot();
+        assert_eq!((snap.last_run_secs, snap.avg_tx_per_sec), (None, None));
+
+        stats.record_run(Duration::from_secs(2), 100);
+        stats.record_run(Duration::from_millis(500), 25);
+        let snap = stats.snapshot();
+        assert_eq!(snap.last_run_secs, Some(0.5));
+        assert_eq!(snap.avg_tx_per_sec, Some(50.0));
+    }
+}
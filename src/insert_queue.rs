@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::Notify;
+
+/// What a full [`InsertQueue`] does with a new item.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    /// Wait for the writer to catch up, slowing the producer down.
+    #[default]
+    Block,
+    /// Discard the oldest queued item to make room, keeping memory bounded
+    /// at the cost of losing data.
+    DropOldest,
+}
+
+impl FromStr for BackpressurePolicy {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "block" => Ok(Self::Block),
+            "drop_oldest" | "drop-oldest" => Ok(Self::DropOldest),
+            other => Err(format!("unknown backpressure policy {:?}", other)),
+        }
+    }
+}
+
+struct State<T> {
+    items: VecDeque<T>,
+    closed: bool,
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    capacity: usize,
+    policy: BackpressurePolicy,
+    not_empty: Notify,
+    not_full: Notify,
+}
+
+/// Bounded single-producer/single-consumer queue between the code that
+/// gathers rows and the task that writes them to SQLite.
+pub struct InsertQueue<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for InsertQueue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> InsertQueue<T> {
+    /// `capacity` is clamped to at least one item.
+    pub fn new(capacity: usize, policy: BackpressurePolicy) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    items: VecDeque::new(),
+                    closed: false,
+                }),
+                capacity: capacity.max(1),
+                policy,
+                not_empty: Notify::new(),
+                not_full: Notify::new(),
+            }),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State<T>> {
+        self.shared.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Enqueues `item`, returning whatever the policy evicted to make room.
+    pub async fn push(&self, item: T) -> Option<T> {
+        let mut item = Some(item);
+        loop {
+            {
+                let mut state = self.lock();
+                if state.items.len() < self.shared.capacity {
+                    state.items.extend(item.take());
+                    self.shared.not_empty.notify_one();
+                    return None;
+                }
+                if self.shared.policy == BackpressurePolicy::DropOldest {
+                    let evicted = state.items.pop_front();
+                    state.items.extend(item.take());
+                    self.shared.not_empty.notify_one();
+                    return evicted;
+                }
+            }
+            self.shared.not_full.notified().await;
+        }
+    }
+
+    /// Waits for the next item; `None` once the queue is closed and drained.
+    pub async fn pop(&self) -> Option<T> {
+        loop {
+            {
+                let mut state = self.lock();
+                if let Some(item) = state.items.pop_front() {
+                    self.shared.not_full.notify_one();
+                    return Some(item);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            self.shared.not_empty.notified().await;
+        }
+    }
+
+    /// Stops accepting work; the consumer drains what is left, then `pop`
+    /// returns `None`.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.shared.not_empty.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn drop_oldest_evicts_front_when_full() {
+        let queue = InsertQueue::new(2, BackpressurePolicy::DropOldest);
+        assert_eq!(queue.push(1).await, None);
+        assert_eq!(queue.push(2).await, None);
+        assert_eq!(queue.push(3).await, Some(1));
+        queue.close();
+        assert_eq!(queue.pop().await, Some(2));
+        assert_eq!(queue.pop().await, Some(3));
+        assert_eq!(queue.pop().await, None);
+    }
+
+    #[tokio::test]
+    async fn block_waits_for_consumer() {
+        let queue = InsertQueue::new(1, BackpressurePolicy::Block);
+        queue.push(1).await;
+        let producer = {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.push(2).await })
+        };
+        tokio::task::yield_now().await;
+        assert!(!producer.is_finished());
+        assert_eq!(queue.pop().await, Some(1));
+        assert_eq!(producer.await.unwrap(), None);
+        assert_eq!(queue.pop().await, Some(2));
+    }
+}
@@ -1,7 +1,22 @@
+pub mod alert;
+pub mod analysis;
 pub mod api;
+pub mod chain;
+pub mod chain_head;
 pub mod cli;
 pub mod config;
 pub mod eth;
+pub mod export;
+pub mod failover;
+pub mod fourbyte;
+pub mod ingest;
 pub mod ingest_stats;
+pub mod insert_queue;
+pub mod metrics;
 pub mod models;
+pub mod rate_limit;
+pub mod recent_cache;
 pub mod storage;
+pub mod tx_feed;
+pub mod tx_metadata;
+pub mod units;
@@ -1,41 +1,41 @@
-mod api;
-mod cli;
-mod config;
-mod eth;
-mod ingest_stats;
-mod models;
-mod storage;
-
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context};
 use clap::Parser;
+use futures_util::StreamExt;
+use serde::Serialize;
 
-use crate::cli::{Cli, Commands};
-use crate::config::Config;
-use crate::eth::EthClient;
-use crate::ingest_stats::INGEST_STATS;
-use crate::models::NormalizedTx;
-use std::collections::HashSet;
-
-fn filter_txs(txs: &[NormalizedTx], filters: Option<&HashSet<String>>) -> Vec<NormalizedTx> {
-    if let Some(filter) = filters {
-        txs.iter()
-            .filter(|tx| {
-                let from_match = filter.contains(&tx.from);
-                let to_match = tx
-                    .to
-                    .as_ref()
-                    .map(|addr| filter.contains(addr))
-                    .unwrap_or(false);
-                from_match || to_match
-            })
-            .cloned()
-            .collect()
-    } else {
-        txs.to_vec()
-    }
-}
+use rust_eth_mempool_lab::alert::{self, Alert};
+use rust_eth_mempool_lab::analysis::MevHeuristic;
+use rust_eth_mempool_lab::cli::{Cli, Commands, OutputFormat};
+use rust_eth_mempool_lab::config::Config;
+use rust_eth_mempool_lab::eth::{unix_now_secs, EthClient, SampleOptions, TxFilter};
+use rust_eth_mempool_lab::export::{self, ProgressWriter};
+use rust_eth_mempool_lab::fourbyte::SignatureDb;
+use rust_eth_mempool_lab::ingest::{self, IngestOptions};
+use rust_eth_mempool_lab::ingest_stats::INGEST_STATS;
+use rust_eth_mempool_lab::{api, chain_head, metrics, storage};
+
+/// Upper bound on concurrent `eth_getBalance` calls issued by `balances`.
+const BALANCE_CONCURRENCY: usize = 8;
+
+/// `export` flushes its output file every this many rows.
+const EXPORT_FLUSH_ROWS: u64 = 1_000;
+
+/// `export` logs progress every this many rows.
+const EXPORT_PROGRESS_ROWS: u64 = 100_000;
+
+/// Matched transactions buffered while `watch-address` prints or delivers
+/// earlier alerts; once it is full the subscription waits for room.
+const ALERT_QUEUE: usize = 1_000;
+
+/// How often `serve` refreshes its cached chain head.
+const CHAIN_HEAD_REFRESH: Duration = Duration::from_secs(5);
+
+/// How often `serve` re-checks an unreachable RPC endpoint, and waits before
+/// restarting a background follow that failed.
+const RPC_RECHECK: Duration = Duration::from_secs(30);
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -43,100 +43,448 @@ async fn main() -> anyhow::Result<()> {
     init_tracing();
 
     let cli = Cli::parse();
-    let config = Config::from_env().context("failed to load configuration")?;
+    let format = cli.format;
+    let dry_run = cli.dry_run;
+    let config = match &cli.config {
+        Some(path) => Config::from_file(path),
+        None => Config::from_env(),
+    }
+    .context("failed to load configuration")?;
+    // Serialized transactions scale `value_eth` by the configured units.
+    config.chain_units.clone().install();
 
     match cli.command {
-        Commands::Serve { addr } => {
+        Commands::Serve { addr, follow_secs } => {
             let bind = addr.unwrap_or_else(|| config.http_bind_addr.clone());
-            let pool = storage::init_pool(&config.database_url).await?;
-            api::run_http_server(&bind, pool).await?;
+            let store = open_storage(&config).await?;
+            let mut state = api::AppState::new(store.clone())
+                .with_mev(MevHeuristic::new(
+                    config.mev_routers.clone(),
+                    config.mev_min_value_wei,
+                ))
+                .with_recent_cache(config.recent_cache_size)
+                .with_units(config.chain_units.clone())
+                .with_recent_order(config.recent_order)
+                .with_ingest(IngestOptions::from_config(&config), config.api_key.clone())
+                .with_api_token(config.api_token.clone());
+            if let Some(rps) = config.http_rate_limit_rps {
+                let burst = config
+                    .http_rate_limit_burst
+                    .unwrap_or_else(|| rps.ceil() as u32);
+                state = state.with_rate_limit(rps, burst);
+            }
+            if let Some(path) = &config.fourbyte_file {
+                let signatures = SignatureDb::load(path)?;
+                tracing::info!("loaded {} method signatures", signatures.len());
+                state = state.with_signatures(signatures);
+            }
+            // Stored data stays servable during provider outages, so RPC problems
+            // only disable the live endpoints instead of aborting startup.
+            match EthClient::from_config(&config) {
+                Ok(eth) => {
+                    tokio::spawn(log_rpc_connectivity(eth.clone()));
+                    tokio::spawn(chain_head::run_refresher(
+                        state.chain_head.clone(),
+                        eth.clone(),
+                        CHAIN_HEAD_REFRESH,
+                    ));
+                    if let Some(poll_secs) = follow_secs {
+                        let eth = eth.clone();
+                        let cache = state.recent_cache.clone();
+                        let options = IngestOptions::from_config(&config);
+                        tokio::spawn(async move {
+                            tracing::info!("following chain head, polling every {}s", poll_secs);
+                            let poll = Duration::from_secs(poll_secs);
+                            loop {
+                                let followed = ingest::follow_head(
+                                    &eth,
+                                    store.as_ref(),
+                                    poll,
+                                    &options,
+                                    cache.as_deref(),
+                                );
+                                if let Err(err) = followed.await {
+                                    tracing::warn!(
+                                        "follow stopped: {:#}; restarting in {:?}",
+                                        err,
+                                        RPC_RECHECK
+                                    );
+                                }
+                                tokio::time::sleep(RPC_RECHECK).await;
+                            }
+                        });
+                    }
+                    state = state.with_eth(eth);
+                }
+                Err(err) => tracing::warn!("RPC client disabled: {:#}", err),
+            }
+            api::run_http_server(&bind, state, &config.api_prefix).await?;
         }
-        Commands::IngestOnce { blocks } => {
-            tracing::info!("starting ingest-once for last {} blocks", blocks);
-
-            let pool = storage::init_pool(&config.database_url).await?;
-            let eth = EthClient::new(&config.eth_rpc_url)?;
-            let blocks_with_txs = eth.fetch_recent_blocks(blocks).await?;
-
-            let mut total_txs = 0usize;
-            let mut total_blocks = 0usize;
-
-            for (block_info, txs) in blocks_with_txs {
-                let filtered = filter_txs(&txs, config.filter_addresses.as_ref());
-                storage::insert_block(&pool, &block_info).await?;
-                if !filtered.is_empty() {
-                    storage::insert_transactions(&pool, &filtered).await?;
-                    INGEST_STATS.inc_transactions(filtered.len() as u64);
+        Commands::IngestOnce {
+            blocks,
+            from_block,
+            to_block,
+            yes,
+            with_receipts,
+        } => {
+            let range = from_block.zip(to_block);
+            let count = match range {
+                Some((from, to)) if from > to => {
+                    return Err(anyhow!("--from-block {} is after --to-block {}", from, to));
                 }
-                total_txs += filtered.len();
-                total_blocks += 1;
+                Some((from, to)) => to - from + 1,
+                None => blocks,
+            };
+            let cap = config.ingest_blocks_soft_cap;
+            if count > cap {
+                if !yes {
+                    return Err(anyhow!(
+                        "{} blocks exceeds the soft cap of {} (INGEST_BLOCKS_SOFT_CAP); \
+                         pass --yes to confirm",
+                        count,
+                        cap
+                    ));
+                }
+                tracing::warn!(
+                    "ingesting {} blocks (above soft cap {}); expect heavy RPC usage",
+                    count,
+                    cap
+                );
             }
-            if total_blocks > 0 {
-                INGEST_STATS.inc_blocks(total_blocks as u64);
+            match range {
+                Some((from, to)) => {
+                    tracing::info!("starting ingest-once for blocks {}..={}", from, to)
+                }
+                None => tracing::info!("starting ingest-once for last {} blocks", blocks),
             }
 
+            // A dry run fetches blocks but never opens the database.
+            let store = if dry_run {
+                None
+            } else {
+                Some(open_storage(&config).await?)
+            };
+            let eth = EthClient::from_config(&config)?.with_dry_run(dry_run);
+            let options = IngestOptions {
+                with_receipts,
+                dry_run,
+                ..IngestOptions::from_config(&config)
+            };
+            let started = Instant::now();
+            let summary = match range {
+                Some((from, to)) => {
+                    ingest::ingest_block_range(&eth, store.as_deref(), from, to, &options, None)
+                        .await?
+                }
+                None => {
+                    ingest::ingest_recent(&eth, store.as_deref(), blocks, &options, None).await?
+                }
+            };
+            if dry_run {
+                tracing::info!(
+                    "dry run complete: would insert {} blocks, {} transactions; nothing was written",
+                    summary.blocks,
+                    summary.transactions
+                );
+                return Ok(());
+            }
+            let elapsed = started.elapsed();
+            INGEST_STATS.record_run(elapsed, summary.transactions as u64);
             tracing::info!(
-                "ingest-once complete: inserted {} blocks, {} transactions",
-                total_blocks,
-                total_txs
+                "ingest-once complete in {:.1?}: inserted {} blocks, {} transactions (net new: {} blocks, {} transactions)",
+                elapsed,
+                summary.blocks,
+                summary.transactions,
+                summary.new_blocks,
+                summary.new_transactions
             );
+            if config.block_sample_rate.is_some() {
+                tracing::info!("sampled out {} transactions", summary.sampled_out);
+            }
+            if summary.skipped_blocks > 0 {
+                tracing::warn!(
+                    "skipped {} blocks the provider could not return",
+                    summary.skipped_blocks
+                );
+            }
+            if summary.reorged_blocks > 0 {
+                tracing::warn!("replaced {} reorged blocks", summary.reorged_blocks);
+            }
+            if summary.pruned.transactions > 0 || summary.pruned.blocks > 0 {
+                tracing::info!(
+                    "retention pruned {} blocks, {} transactions",
+                    summary.pruned.blocks,
+                    summary.pruned.transactions
+                );
+            }
+            if !config.watch_topics.is_empty() {
+                tracing::info!("stored {} watched logs", summary.logs);
+            }
+            if with_receipts {
+                tracing::info!("stored {} token transfers", summary.token_transfers);
+            }
+            push_metrics(&config, "ingest_once").await;
+        }
+        Commands::Follow { poll_secs } => {
+            let store = open_storage(&config).await?;
+            let eth = EthClient::from_config(&config)?;
+            tracing::info!("following chain head, polling every {}s", poll_secs);
+            ingest::follow_head(
+                &eth,
+                store.as_ref(),
+                Duration::from_secs(poll_secs.max(1)),
+                &IngestOptions::from_config(&config),
+                None,
+            )
+            .await?;
         }
-        Commands::MempoolSample { duration_secs, max } => {
+        Commands::MempoolSample {
+            duration_secs,
+            max,
+            dedup_window,
+            flush_size,
+            flush_interval_secs,
+        } => {
             let ws_url = config
                 .eth_ws_url
                 .as_deref()
                 .ok_or_else(|| anyhow!("ETH_WS_URL must be set for mempool sampling"))?;
 
-            let pool = storage::init_pool(&config.database_url).await?;
-            let eth = EthClient::new(&config.eth_rpc_url)?;
+            let store = if dry_run {
+                None
+            } else {
+                Some(open_storage(&config).await?)
+            };
+            let eth = EthClient::from_config(&config)?.with_dry_run(dry_run);
             tracing::info!(
                 "starting mempool sample: duration_secs={}, max={}",
                 duration_secs,
                 max
             );
 
+            let started = Instant::now();
             let stats = eth
                 .sample_pending(
                     ws_url,
-                    Duration::from_secs(duration_secs),
-                    max as usize,
-                    &pool,
-                    config.filter_addresses.clone(),
+                    &SampleOptions {
+                        duration: Duration::from_secs(duration_secs),
+                        max: max as usize,
+                        dedup_window: dedup_window as usize,
+                        flush_size: flush_size as usize,
+                        flush_interval: flush_interval_secs.map(Duration::from_secs),
+                    },
+                    store.clone(),
+                    TxFilter::from_config(&config),
                 )
                 .await?;
 
             tracing::info!(
-                "mempool sample complete: received={}, fetched={}, inserted={}, insert_errors={}",
+                "mempool sample {} complete: received={}, fetched={}, inserted={}, insert_errors={}, duplicates_skipped={}, dropped_due_to_backpressure={}, reconnects={}",
+                stats.sample_id,
                 stats.received,
                 stats.fetched,
                 stats.inserted,
-                stats.insert_errors
+                stats.insert_errors,
+                stats.duplicates_skipped,
+                stats.dropped_due_to_backpressure,
+                stats.reconnects
             );
+            if let Some(store) = store {
+                INGEST_STATS.record_run(started.elapsed(), stats.inserted as u64);
+                // Retention covers pending rows too, so long-running samplers
+                // stay within RETENTION_MAX_ROWS without a separate prune.
+                let pruned = storage::apply_retention(
+                    store.as_ref(),
+                    &storage::StorageOptions::from_config(&config),
+                    unix_now_secs(),
+                )
+                .await?;
+                if pruned.transactions > 0 || pruned.blocks > 0 {
+                    tracing::info!(
+                        "retention pruned {} blocks, {} transactions",
+                        pruned.blocks,
+                        pruned.transactions
+                    );
+                }
+                push_metrics(&config, "mempool_sample").await;
+            } else {
+                tracing::info!("dry run: nothing was written");
+            }
+        }
+        Commands::WatchAddress { addresses, webhook } => {
+            let ws_url = config
+                .eth_ws_url
+                .clone()
+                .ok_or_else(|| anyhow!("ETH_WS_URL must be set for watch-address"))?;
+            let eth = EthClient::from_config(&config)?;
+            let filter = TxFilter {
+                addresses: Some(addresses.into_iter().collect()),
+                min_value_wei: None,
+            };
+            tracing::info!(
+                "watching {} addresses for pending transactions",
+                filter.addresses.as_ref().map_or(0, |a| a.len())
+            );
+
+            // The subscription runs on its own task so a slow webhook does not
+            // stall it until ALERT_QUEUE alerts are waiting; past that, reading
+            // pending transactions pauses until deliveries catch up.
+            let (sender, mut matches) = tokio::sync::mpsc::channel(ALERT_QUEUE);
+            let watcher =
+                tokio::spawn(async move { eth.watch_pending(&ws_url, &filter, sender).await });
+            let client = reqwest::Client::new();
+            while let Some(tx) = matches.recv().await {
+                let alert = Alert::from(&tx);
+                if format == OutputFormat::Json {
+                    println!("{}", serde_json::to_string(&alert)?);
+                } else {
+                    println!("{}", alert);
+                }
+                if let Some(url) = &webhook {
+                    if let Err(err) = alert::post_webhook(&client, url, &alert).await {
+                        tracing::warn!("{:#}", err);
+                    }
+                }
+            }
+            watcher.await.context("watch task failed")??;
         }
         Commands::TopSenders { limit } => {
-            let pool = storage::init_pool(&config.database_url).await?;
-            let rows = storage::get_top_senders(&pool, limit as i64).await?;
-            for row in rows {
-                println!("{} {}", row.address, row.count);
+            let store = open_storage(&config).await?;
+            let rows = store
+                .get_top_senders(limit as i64, chain_filter(&config))
+                .await?;
+            if format == OutputFormat::Json {
+                print_json(&rows)?;
+            } else {
+                for row in rows {
+                    println!("{} {}", row.address, row.count);
+                }
+            }
+        }
+        Commands::Balances { limit } => {
+            let store = open_storage(&config).await?;
+            let eth = EthClient::from_config(&config)?;
+            let chain_id = eth.chain_id().await?;
+            let rows = store.get_top_senders(limit as i64, Some(chain_id)).await?;
+
+            let mut balances = futures_util::stream::iter(rows)
+                .map(|row| {
+                    let eth = &eth;
+                    async move {
+                        let balance = eth.get_balance(&row.address).await;
+                        (row.address, balance)
+                    }
+                })
+                .buffered(BALANCE_CONCURRENCY);
+
+            let mut found = Vec::new();
+            while let Some((address, balance)) = balances.next().await {
+                match balance {
+                    Ok(wei) if format == OutputFormat::Json => found.push(serde_json::json!({
+                        "address": address,
+                        "balance_wei": wei.to_string(),
+                    })),
+                    Ok(wei) => println!("{} {}", address, wei),
+                    Err(err) => tracing::warn!("skipping balance for {}: {:#}", address, err),
+                }
+            }
+            if format == OutputFormat::Json {
+                print_json(&found)?;
             }
         }
         Commands::RecentTxs { limit } => {
-            let pool = storage::init_pool(&config.database_url).await?;
-            let txs = storage::get_recent_transactions(&pool, limit as i64).await?;
-            for tx in txs {
-                println!(
-                    "{} from={} to={:?} value_wei={}",
-                    tx.hash, tx.from, tx.to, tx.value_wei
-                );
+            let store = open_storage(&config).await?;
+            let filter = storage::RecentTxFilter {
+                chain_id: chain_filter(&config),
+                order: config.recent_order,
+                ..Default::default()
+            };
+            let txs = store.get_recent_transactions(limit as i64, &filter).await?;
+            if format == OutputFormat::Json {
+                print_json(&txs)?;
+            } else {
+                for tx in txs {
+                    println!(
+                        "{} from={} to={:?} value_wei={}",
+                        tx.hash, tx.from, tx.to, tx.value_wei
+                    );
+                }
             }
         }
+        Commands::Prune {
+            older_than_days,
+            vacuum,
+        } => {
+            let store = open_storage(&config).await?;
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs();
+            let cutoff = now.saturating_sub(older_than_days.saturating_mul(86_400));
+            let pruned = store
+                .prune_before(i64::try_from(cutoff).unwrap_or(i64::MAX))
+                .await?;
+            tracing::info!(
+                "pruned {} blocks, {} transactions older than {} days",
+                pruned.blocks,
+                pruned.transactions,
+                older_than_days
+            );
+            if vacuum {
+                store.vacuum().await?;
+                tracing::info!("vacuum complete");
+            }
+        }
+        Commands::Export {
+            export_format,
+            out,
+            limit,
+            from_block,
+            to_block,
+        } => {
+            if let (Some(from), Some(to)) = (from_block, to_block) {
+                if from > to {
+                    return Err(anyhow!("--from-block must not exceed --to-block"));
+                }
+            }
+            let pool = open_pool(&config).await?;
+            let file = std::fs::File::create(&out)
+                .with_context(|| format!("creating {}", out.display()))?;
+            let range = storage::BlockRange {
+                from: from_block.map(|n| n as i64),
+                to: to_block.map(|n| n as i64),
+            };
+            let writer = ProgressWriter::new(file, EXPORT_FLUSH_ROWS, EXPORT_PROGRESS_ROWS);
+            let rows = export::export_transactions(&pool, export_format, range, limit, writer)
+                .await
+                .with_context(|| format!("exporting to {}", out.display()))?;
+            tracing::info!("exported {} transactions to {}", rows, out.display());
+        }
         Commands::GasStats { blocks } => {
-            let pool = storage::init_pool(&config.database_url).await?;
-            match storage::get_gas_stats(&pool, blocks as i64).await? {
-                Some(stats) => println!(
-                    "gas_price_wei min={} max={} avg={}",
-                    stats.min, stats.max, stats.avg
-                ),
+            let store = open_storage(&config).await?;
+            let stats = store
+                .get_gas_stats(blocks as i64, chain_filter(&config))
+                .await?;
+            match stats {
+                _ if format == OutputFormat::Json => print_json(&stats)?,
+                Some(stats) => {
+                    println!(
+                        "gas_price_wei min={} max={} avg={} p25={} p50={} p75={} p90={}",
+                        stats.min, stats.max, stats.avg, stats.p25, stats.p50, stats.p75, stats.p90
+                    );
+                    let units = &config.chain_units;
+                    let fmt = |raw: String| units.format_gas_price(&raw).unwrap_or_default();
+                    println!(
+                        "gas_price_{} min={} max={} avg={} p25={} p50={} p75={} p90={}",
+                        units.gas_price_unit,
+                        fmt(stats.min.to_string()),
+                        fmt(stats.max.to_string()),
+                        fmt(format!("{:.0}", stats.avg)),
+                        fmt(stats.p25.to_string()),
+                        fmt(stats.p50.to_string()),
+                        fmt(stats.p75.to_string()),
+                        fmt(stats.p90.to_string())
+                    );
+                }
                 None => println!("no gas stats available"),
             }
         }
@@ -145,8 +493,73 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Writes `value` to stdout as a single JSON document for `--format json`.
+fn print_json<T: Serialize>(value: &T) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Checks the RPC endpoint every [`RPC_RECHECK`] until it answers, so the
+/// logs show both the outage and the recovery.
+async fn log_rpc_connectivity(eth: EthClient) {
+    let mut ticker = tokio::time::interval(RPC_RECHECK);
+    let mut failed = false;
+    loop {
+        ticker.tick().await;
+        match eth.check_connectivity().await {
+            Ok(head) => {
+                let state = if failed { "recovered" } else { "reachable" };
+                tracing::info!("RPC {}, head block {}", state, head);
+                return;
+            }
+            Err(err) => {
+                tracing::warn!("{:#}; serving stored data only until it recovers", err);
+                failed = true;
+            }
+        }
+    }
+}
+
+/// Chain the query commands read: `CHAIN_ID` when set, else every stored chain.
+fn chain_filter(config: &Config) -> Option<i64> {
+    config.chain_id.map(|id| id as i64)
+}
+
+/// SQLite pool for `export`, which streams rows straight off SQLite.
+async fn open_pool(config: &Config) -> anyhow::Result<storage::DbPool> {
+    if storage::SqlDialect::from_url(&config.database_url) != storage::SqlDialect::Sqlite {
+        return Err(anyhow!("export needs a SQLite DATABASE_URL"));
+    }
+    storage::init_pool_with_options(
+        &config.database_url,
+        &storage::StorageOptions::from_config(config),
+    )
+    .await
+}
+
+/// Backend chosen by the `DATABASE_URL` scheme (SQLite or Postgres).
+async fn open_storage(config: &Config) -> anyhow::Result<Arc<dyn storage::Storage>> {
+    storage::connect(
+        &config.database_url,
+        &storage::StorageOptions::from_config(config),
+    )
+    .await
+}
+
+/// Pushes the process counters to the Pushgateway when one is configured.
+/// Failures are logged rather than failing a run whose data is already stored.
+async fn push_metrics(config: &Config, job: &str) {
+    if let Some(url) = &config.pushgateway_url {
+        if let Err(err) = metrics::push(url, job, &INGEST_STATS.snapshot()).await {
+            tracing::warn!("{:#}", err);
+        }
+    }
+}
+
 fn init_tracing() {
+    // Logs go to stderr so `--format json` output on stdout stays parseable.
     tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
         .with_env_filter(
             tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
         )
@@ -0,0 +1,247 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+use crate::ingest_stats::IngestSnapshot;
+
+/// Content type of the Prometheus text exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Upper bounds, in seconds, of the API latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Cumulative counts per entry of [`LATENCY_BUCKETS`].
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+/// Request latency per API route, recorded by the HTTP middleware and
+/// rendered on `/metrics`. Like [`crate::ingest_stats::INGEST_STATS`] it
+/// lives for the whole process, so every series only ever grows.
+#[derive(Debug)]
+pub struct HttpLatency {
+    routes: Mutex<BTreeMap<String, Histogram>>,
+}
+
+impl Default for HttpLatency {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpLatency {
+    pub const fn new() -> Self {
+        Self {
+            routes: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn observe(&self, route: &str, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        let histogram = routes.entry(route.to_string()).or_default();
+        for (bucket, bound) in histogram.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if secs <= bound {
+                *bucket += 1;
+            }
+        }
+        histogram.count += 1;
+        histogram.sum += secs;
+    }
+
+    /// Renders `mempool_http_request_duration_seconds` with one series per
+    /// route seen so far.
+    pub fn render(&self) -> String {
+        const NAME: &str = "mempool_http_request_duration_seconds";
+        let routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out = String::new();
+        let _ = writeln!(out, "# HELP {} API request latency by route", NAME);
+        let _ = writeln!(out, "# TYPE {} histogram", NAME);
+        for (route, histogram) in routes.iter() {
+            let route = route.replace('\\', "\\\\").replace('"', "\\\"");
+            for (count, bound) in histogram.buckets.iter().zip(LATENCY_BUCKETS) {
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{{route=\"{}\",le=\"{}\"}} {}",
+                    NAME, route, bound, count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{}_bucket{{route=\"{}\",le=\"+Inf\"}} {}",
+                NAME, route, histogram.count
+            );
+            let _ = writeln!(out, "{}_sum{{route=\"{}\"}} {}", NAME, route, histogram.sum);
+            let _ = writeln!(
+                out,
+                "{}_count{{route=\"{}\"}} {}",
+                NAME, route, histogram.count
+            );
+        }
+        out
+    }
+}
+
+pub static HTTP_LATENCY: HttpLatency = HttpLatency::new();
+
+/// Renders an ingest snapshot in the Prometheus text exposition format.
+pub fn render(snapshot: &IngestSnapshot) -> String {
+    let counters = [
+        (
+            "mempool_blocks_total",
+            "Blocks ingested by this process",
+            snapshot.blocks,
+        ),
+        (
+            "mempool_transactions_total",
+            "Mined transactions ingested by this process",
+            snapshot.transactions,
+        ),
+        (
+            "mempool_pending_transactions_total",
+            "Pending transactions ingested by this process",
+            snapshot.pending_transactions,
+        ),
+        (
+            "mempool_dropped_due_to_backpressure_total",
+            "Pending transactions dropped because the insert queue was full",
+            snapshot.dropped_due_to_backpressure,
+        ),
+        (
+            "mempool_skipped_blocks_total",
+            "Blocks skipped because no fetch path returned them",
+            snapshot.skipped_blocks,
+        ),
+        (
+            "mempool_rpc_errors_total",
+            "Block fetches and pending transaction lookups that failed",
+            snapshot.rpc_errors,
+        ),
+        (
+            "mempool_insert_errors_total",
+            "Failed writes of pending sample batches",
+            snapshot.insert_errors,
+        ),
+    ];
+
+    let mut out = String::new();
+    for (name, help, value) in counters {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        let _ = writeln!(out, "{} {}", name, value);
+    }
+    // Run gauges are left out until a run has been recorded.
+    let gauges = [
+        (
+            "mempool_last_run_seconds",
+            "Wall-clock duration of the latest ingest run",
+            snapshot.last_run_secs,
+        ),
+        (
+            "mempool_avg_tx_per_second",
+            "Transactions stored per second across all ingest runs",
+            snapshot.avg_tx_per_sec,
+        ),
+    ];
+    for (name, help, value) in gauges {
+        if let Some(value) = value {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} gauge", name);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+    }
+    out
+}
+
+/// Pushes `snapshot` to a Prometheus Pushgateway under `job`, replacing any
+/// metrics previously pushed for that job.
+pub async fn push(gateway_url: &str, job: &str, snapshot: &IngestSnapshot) -> Result<()> {
+    let url = format!("{}/metrics/job/{}", gateway_url.trim_end_matches('/'), job);
+    reqwest::Client::new()
+        .put(&url)
+        .header(reqwest::header::CONTENT_TYPE, CONTENT_TYPE)
+        .body(render(snapshot))
+        .send()
+        .await
+        .with_context(|| format!("failed to push metrics to {}", url))?
+        .error_for_status()
+        .with_context(|| format!("pushgateway rejected metrics at {}", url))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_emits_counter_lines() {
+        let snapshot = IngestSnapshot {
+            blocks: 3,
+            transactions: 42,
+            pending_transactions: 7,
+            dropped_due_to_backpressure: 0,
+            skipped_blocks: 1,
+            rpc_errors: 4,
+            insert_errors: 0,
+            last_run_secs: None,
+            avg_tx_per_sec: None,
+        };
+        let text = render(&snapshot);
+        assert!(text.contains("# TYPE mempool_blocks_total counter\n"));
+        assert!(text.contains("mempool_blocks_total 3\n"));
+        assert!(text.contains("mempool_transactions_total 42\n"));
+        assert!(text.contains("mempool_pending_transactions_total 7\n"));
+        assert!(text.contains("mempool_skipped_blocks_total 1\n"));
+        assert!(text.contains("mempool_rpc_errors_total 4\n"));
+        assert!(!text.contains("mempool_last_run_seconds"));
+    }
+
+    #[test]
+    fn render_emits_run_gauges_once_recorded() {
+        let snapshot = IngestSnapshot {
+            blocks: 3,
+            transactions: 42,
+            pending_transactions: 0,
+            dropped_due_to_backpressure: 0,
+            skipped_blocks: 0,
+            rpc_errors: 0,
+            insert_errors: 0,
+            last_run_secs: Some(1.5),
+            avg_tx_per_sec: Some(28.0),
+        };
+        let text = render(&snapshot);
+        assert!(text.contains("# TYPE mempool_last_run_seconds gauge\n"));
+        assert!(text.contains("mempool_last_run_seconds 1.5\n"));
+        assert!(text.contains("mempool_avg_tx_per_second 28\n"));
+    }
+
+    #[test]
+    fn latency_buckets_are_cumulative() {
+        let latency = HttpLatency::new();
+        latency.observe("/stats/gas", Duration::from_millis(3));
+        latency.observe("/stats/gas", Duration::from_millis(200));
+        latency.observe("/stats/gas", Duration::from_secs(30));
+        let text = latency.render();
+        assert!(text.contains("# TYPE mempool_http_request_duration_seconds histogram\n"));
+        assert!(text.contains(
+            "mempool_http_request_duration_seconds_bucket{route=\"/stats/gas\",le=\"0.005\"} 1\n"
+        ));
+        assert!(text.contains(
+            "mempool_http_request_duration_seconds_bucket{route=\"/stats/gas\",le=\"0.25\"} 2\n"
+        ));
+        assert!(text.contains(
+            "mempool_http_request_duration_seconds_bucket{route=\"/stats/gas\",le=\"+Inf\"} 3\n"
+        ));
+        assert!(
+            text.contains("mempool_http_request_duration_seconds_count{route=\"/stats/gas\"} 3\n")
+        );
+    }
+}
@@ -1,6 +1,14 @@
-use serde::Serialize;
+use std::collections::HashSet;
 
-#[derive(Debug, Clone, Serialize)]
+use serde::{Serialize, Serializer};
+
+use crate::chain::{format_units, ChainUnits};
+use utoipa::ToSchema;
+
+/// Serializes through [`NormalizedTxJson`], which adds `value_eth` (see
+/// [`NormalizedTx::value_eth`]).
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(remote = "Self")]
 pub struct NormalizedTx {
     pub hash: String,
     pub from: String,
@@ -9,17 +17,230 @@ pub struct NormalizedTx {
     pub gas: i64,
     pub gas_price_wei: Option<String>,
     pub max_fee_per_gas_wei: Option<String>,
+    /// EIP-1559 tip cap; `None` for legacy transactions.
+    pub max_priority_fee_per_gas_wei: Option<String>,
     pub nonce: i64,
     pub block_number: Option<i64>,
     pub timestamp: Option<i64>,
     pub status: Option<String>,
+    /// Calldata as `0x`-prefixed hex; `None` for rows stored before it was captured.
+    pub input_data: Option<String>,
+    /// Position within the block; `None` for pending rows and rows stored
+    /// before it was recorded.
+    pub tx_index: Option<i64>,
+    /// EIP-155 chain id of the network the transaction was observed on.
+    pub chain_id: i64,
+    /// EIP-2718 envelope type (0 legacy, 1 access list, 2 EIP-1559, ...);
+    /// `None` for rows stored before it was recorded.
+    pub tx_type: Option<i64>,
+    /// Calldata length in bytes; `None` for rows stored before it was recorded.
+    pub input_size: Option<i64>,
 }
 
+impl Serialize for NormalizedTx {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        NormalizedTxJson {
+            tx: self,
+            value_eth: self.value_eth(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// JSON form of a [`NormalizedTx`]: the stored fields plus `value_eth`.
+// Registered in the OpenAPI spec as `NormalizedTx`, which `TxView` refers to.
+#[derive(Serialize, ToSchema)]
+#[schema(as = NormalizedTx)]
+pub struct NormalizedTxJson<'a> {
+    #[serde(flatten, with = "NormalizedTx")]
+    #[schema(inline)]
+    tx: &'a NormalizedTx,
+    /// `value_wei` in whole native tokens with every decimal kept.
+    value_eth: Option<String>,
+}
+
+impl NormalizedTx {
+    /// `value_wei` in whole native tokens with every decimal kept, scaled by
+    /// the chain's native decimals (18 on Ethereum, or `NATIVE_DECIMALS` on
+    /// the configured chain); `None` if `value_wei` is not a decimal integer.
+    pub fn value_eth(&self) -> Option<String> {
+        let decimals = ChainUnits::native_decimals_for(self.chain_id);
+        format_units(&self.value_wei, decimals, false)
+    }
+
+    /// Lowercase 4-byte method selector of the calldata; `None` for plain
+    /// transfers, contract creations without a selector-sized prefix, and rows
+    /// without stored calldata.
+    pub fn input_selector(&self) -> Option<String> {
+        self.input_data
+            .as_deref()
+            .and_then(crate::fourbyte::selector_of)
+            .map(str::to_ascii_lowercase)
+    }
+}
+
+/// A transaction read back from storage together with the columns derived
+/// from it when it was written, so responses match what the filters on those
+/// columns see.
 #[derive(Debug, Clone)]
+pub struct StoredTx {
+    pub tx: NormalizedTx,
+    pub flags: TxFlags,
+    /// Lowercase 4-byte method selector from the `input_selector` column.
+    pub input_selector: Option<String>,
+}
+
+impl StoredTx {
+    /// Derives the columns the way inserts do, for transactions that were not
+    /// read back from storage (such as those in the recent cache).
+    pub fn derive(tx: NormalizedTx, routers: &HashSet<String>) -> Self {
+        Self {
+            flags: TxFlags::from_tx(&tx, routers),
+            input_selector: tx.input_selector(),
+            tx,
+        }
+    }
+}
+
+/// Derived boolean classifications packed into the `transactions.flags`
+/// column, so new ones need no schema change and filters stay a cheap
+/// `flags & mask != 0`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TxFlags(pub i64);
+
+impl TxFlags {
+    /// No `to` address.
+    pub const CONTRACT_CREATION: i64 = 1 << 0;
+    /// Sender and receiver are the same address.
+    pub const SELF_TRANSFER: i64 = 1 << 1;
+    /// Sent to one of the built-in DEX routers.
+    pub const DEX_SWAP: i64 = 1 << 2;
+    /// Carries non-empty calldata.
+    pub const HAS_INPUT: i64 = 1 << 3;
+
+    /// Names used in API responses and query parameters, in bit order.
+    pub const NAMES: &'static [(&'static str, i64)] = &[
+        ("contract_creation", Self::CONTRACT_CREATION),
+        ("self_transfer", Self::SELF_TRANSFER),
+        ("dex_swap", Self::DEX_SWAP),
+        ("has_input", Self::HAS_INPUT),
+    ];
+
+    /// Classifies `tx`; `routers` (`MEV_ROUTERS`, or the built-in list) decides
+    /// [`TxFlags::DEX_SWAP`].
+    pub fn from_tx(tx: &NormalizedTx, routers: &HashSet<String>) -> Self {
+        let mut bits = 0;
+        match tx.to.as_deref() {
+            None => bits |= Self::CONTRACT_CREATION,
+            Some(to) => {
+                if to == tx.from {
+                    bits |= Self::SELF_TRANSFER;
+                }
+                if routers.contains(to) {
+                    bits |= Self::DEX_SWAP;
+                }
+            }
+        }
+        if matches!(tx.input_data.as_deref(), Some(input) if !input.is_empty() && input != "0x") {
+            bits |= Self::HAS_INPUT;
+        }
+        Self(bits)
+    }
+
+    /// Looks up a single flag bit by its API name.
+    pub fn bit_named(name: &str) -> Option<i64> {
+        Self::NAMES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, bit)| *bit)
+    }
+
+    pub fn contains(self, bit: i64) -> bool {
+        self.0 & bit != 0
+    }
+
+    pub fn is_contract_creation(self) -> bool {
+        self.contains(Self::CONTRACT_CREATION)
+    }
+
+    pub fn is_self_transfer(self) -> bool {
+        self.contains(Self::SELF_TRANSFER)
+    }
+
+    pub fn is_dex_swap(self) -> bool {
+        self.contains(Self::DEX_SWAP)
+    }
+
+    pub fn has_input(self) -> bool {
+        self.contains(Self::HAS_INPUT)
+    }
+
+    /// Names of the set flags, for API responses.
+    pub fn names(self) -> Vec<&'static str> {
+        Self::NAMES
+            .iter()
+            .filter(|(_, bit)| self.contains(*bit))
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+/// An event log captured because its first topic is in `WATCH_TOPICS`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogEntry {
+    pub chain_id: i64,
+    pub block_number: i64,
+    pub tx_hash: String,
+    pub log_index: i64,
+    /// Emitting contract, lowercase hex.
+    pub address: String,
+    /// Indexed topics as `0x` hex, topic0 (the event signature hash) first.
+    pub topics: Vec<String>,
+    pub data: String,
+}
+
+/// An ERC-20 `Transfer(address,address,uint256)` event decoded from a receipt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TokenTransfer {
+    pub chain_id: i64,
+    pub tx_hash: String,
+    pub log_index: i64,
+    pub block_number: Option<i64>,
+    /// Token contract, lowercase hex.
+    pub token: String,
+    pub from: String,
+    pub to: String,
+    /// Raw token units as a decimal string (not scaled by the token's decimals).
+    pub amount: String,
+}
+
+/// One derived key/value pair emitted by a normalization hook.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TxMetadata {
+    pub chain_id: i64,
+    pub hash: String,
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct BlockInfo {
     pub number: i64,
     pub hash: String,
     pub timestamp: i64,
+    /// EIP-1559 base fee as decimal wei; `None` before London.
+    pub base_fee_wei: Option<String>,
+    /// EIP-155 chain id; block numbers are only unique within a chain.
+    pub chain_id: i64,
+}
+
+/// A stored block with the number of its transactions kept in the database
+/// (after address filtering and sampling, so not necessarily the full block).
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockSummary {
+    #[serde(flatten)]
+    pub block: BlockInfo,
+    pub tx_count: i64,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -27,10 +248,195 @@ pub struct GasStats {
     pub min: i64,
     pub max: i64,
     pub avg: f64,
+    /// Nearest-rank percentiles of the same gas prices.
+    pub p25: i64,
+    pub p50: i64,
+    pub p75: i64,
+    pub p90: i64,
 }
 
+/// Base fee spread over recent blocks; pre-London blocks are not counted.
 #[derive(Debug, Clone, Serialize)]
+pub struct BaseFeeStats {
+    pub min: i64,
+    pub max: i64,
+    pub avg: f64,
+    /// Blocks that contributed a base fee.
+    pub blocks: i64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct TopSender {
     pub address: String,
     pub count: i64,
 }
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct RowCounts {
+    pub blocks: i64,
+    pub transactions: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulationResult {
+    pub success: bool,
+    pub revert_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Sent,
+    Received,
+    /// The address is both sender and receiver.
+    #[serde(rename = "self")]
+    SelfTransfer,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineEntry {
+    pub direction: Direction,
+    #[serde(flatten)]
+    pub tx: NormalizedTx,
+}
+
+/// Transactions priced within `bucket_gwei..bucket_gwei + width` gwei.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GasHistogramBucket {
+    /// Lower bound of the bucket in gwei.
+    pub bucket_gwei: i64,
+    pub count: i64,
+}
+
+/// Mined transactions whose timestamp falls in
+/// `bucket_start..bucket_start + bucket_secs`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ThroughputBucket {
+    /// Unix seconds, a multiple of the bucket width.
+    pub bucket_start: i64,
+    pub tx_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArrivalBucket {
+    /// Seconds since the sample started at which this bucket begins.
+    pub offset_secs: i64,
+    pub tx_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArrivalRate {
+    pub sample_id: i64,
+    pub started_at: i64,
+    pub bucket_secs: i64,
+    pub buckets: Vec<ArrivalBucket>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlowSummary {
+    pub from: String,
+    pub to: String,
+    pub tx_count: i64,
+    /// Decimal wei total; summed as a 256-bit integer so it cannot overflow i64.
+    pub total_value_wei: String,
+}
+
+/// Activity of one address across every stored transaction.
+#[derive(Debug, Clone, Serialize)]
+pub struct AddressSummary {
+    pub address: String,
+    pub sent_count: i64,
+    pub received_count: i64,
+    /// Decimal wei total of the sent transactions, summed as a 256-bit integer.
+    pub total_sent_wei: String,
+    /// Earliest and latest timestamps of transactions from or to the address;
+    /// `None` when none are stored (or none carry a timestamp).
+    pub first_seen: Option<i64>,
+    pub last_seen: Option<i64>,
+}
+
+/// Missing nonces `start..=end` between two mined nonces of one sender.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct NonceGap {
+    pub start: i64,
+    pub end: i64,
+}
+
+/// Mined nonces of one sender and the holes between them.
+#[derive(Debug, Clone, Serialize)]
+pub struct NonceGaps {
+    pub address: String,
+    /// Distinct mined nonces, ascending.
+    pub nonces: Vec<i64>,
+    /// Empty when the stored nonces are contiguous.
+    pub gaps: Vec<NonceGap>,
+}
+
+/// Aggregates over the mined transactions of an inclusive block range.
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowSummary {
+    pub from_block: i64,
+    pub to_block: i64,
+    pub tx_count: i64,
+    /// Mean of numeric `gas_price_wei` values; `None` when the window has none.
+    pub avg_gas_price_wei: Option<f64>,
+    /// Decimal wei mean (integer division); `None` for an empty window.
+    pub avg_value_wei: Option<String>,
+    /// Distinct senders and receivers combined.
+    pub unique_addresses: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tx_flags_classify_and_name() {
+        let tx = NormalizedTx {
+            hash: "0x1".to_string(),
+            from: "0xabc".to_string(),
+            to: None,
+            value_wei: "0".to_string(),
+            gas: 100_000,
+            gas_price_wei: None,
+            max_fee_per_gas_wei: None,
+            max_priority_fee_per_gas_wei: None,
+            nonce: 0,
+            block_number: None,
+            timestamp: None,
+            status: None,
+            input_data: Some("0x6080".to_string()),
+            tx_index: None,
+            chain_id: 1,
+            tx_type: None,
+            input_size: None,
+        };
+        let routers = crate::analysis::default_routers();
+        let flags = TxFlags::from_tx(&tx, &routers);
+        assert!(flags.is_contract_creation() && flags.has_input());
+        assert!(!flags.is_self_transfer() && !flags.is_dex_swap());
+        assert_eq!(flags.names(), vec!["contract_creation", "has_input"]);
+
+        let self_send = NormalizedTx {
+            to: Some("0xabc".to_string()),
+            input_data: Some("0x".to_string()),
+            tx_index: None,
+            ..tx
+        };
+        assert_eq!(
+            TxFlags::from_tx(&self_send, &routers).names(),
+            vec!["self_transfer"]
+        );
+        assert_eq!(TxFlags::bit_named("dex_swap"), Some(TxFlags::DEX_SWAP));
+
+        // Configured routers replace the built-in list.
+        let swap = NormalizedTx {
+            to: Some(crate::analysis::DEFAULT_ROUTERS[0].to_string()),
+            input_data: None,
+            ..self_send
+        };
+        assert!(TxFlags::from_tx(&swap, &routers).is_dex_swap());
+        let custom = HashSet::from(["0xfeed".to_string()]);
+        assert!(!TxFlags::from_tx(&swap, &custom).is_dex_swap());
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Most clients tracked at once; a new client past this evicts the one
+/// tracked longest, so a stream of one-off addresses cannot grow the map
+/// without bound.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Per-IP token bucket: each client may burst `burst` requests, refilled at
+/// `rate` per second.
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    max_clients: usize,
+    clients: Mutex<Clients>,
+}
+
+#[derive(Debug, Default)]
+struct Clients {
+    buckets: HashMap<IpAddr, Bucket>,
+    /// Tracked addresses, oldest first; each appears exactly once.
+    order: VecDeque<IpAddr>,
+}
+
+impl RateLimiter {
+    /// `rate` must be positive; a zero `burst` is raised to one request.
+    pub fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            rate,
+            burst: f64::from(burst.max(1)),
+            max_clients: MAX_TRACKED_CLIENTS,
+            clients: Mutex::new(Clients::default()),
+        }
+    }
+
+    /// Takes a token for `ip`, or returns how long until one is available.
+    pub fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        self.check_at(ip, Instant::now())
+    }
+
+    fn check_at(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        let Clients { buckets, order } = &mut *clients;
+        if !buckets.contains_key(&ip) {
+            while buckets.len() >= self.max_clients {
+                match order.pop_front() {
+                    Some(oldest) => buckets.remove(&oldest),
+                    None => break,
+                };
+            }
+            order.push_back(ip);
+        }
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        bucket.tokens = refilled(bucket, self.rate, self.burst, now);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+}
+
+fn refilled(bucket: &Bucket, rate: f64, burst: f64, now: Instant) -> f64 {
+    let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+    (bucket.tokens + elapsed * rate).min(burst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bursts_then_refills_per_client() {
+        let limiter = RateLimiter::new(2.0, 3);
+        let a: IpAddr = "10.0.0.1".parse().unwrap();
+        let b: IpAddr = "10.0.0.2".parse().unwrap();
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at(a, start).is_ok());
+        }
+        let wait = limiter.check_at(a, start).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(500));
+        // Other clients have their own bucket.
+        assert!(limiter.check_at(b, start).is_ok());
+
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.check_at(a, later).is_ok());
+        assert!(limiter.check_at(a, later).is_err());
+    }
+
+    #[test]
+    fn new_clients_past_the_cap_evict_the_oldest() {
+        let limiter = RateLimiter {
+            max_clients: 2,
+            ..RateLimiter::new(1.0, 1)
+        };
+        let ip = |n: u8| IpAddr::from([10, 0, 0, n]);
+        let now = Instant::now();
+        for n in 1..=3 {
+            assert!(limiter.check_at(ip(n), now).is_ok());
+        }
+        let clients = limiter.clients.lock().unwrap();
+        assert_eq!(clients.buckets.len(), 2);
+        assert!(!clients.buckets.contains_key(&ip(1)));
+        assert_eq!(clients.order, [ip(2), ip(3)]);
+    }
+}
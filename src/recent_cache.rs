@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use crate::models::NormalizedTx;
+
+/// Fixed-capacity ring of the most recently ingested transactions, letting
+/// `/tx/recent` skip SQLite for small unfiltered reads.
+#[derive(Debug)]
+pub struct RecentTxCache {
+    capacity: usize,
+    txs: Mutex<VecDeque<NormalizedTx>>,
+}
+
+impl RecentTxCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            txs: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Appends freshly stored transactions, evicting the oldest entries.
+    pub fn push_many(&self, txs: &[NormalizedTx]) {
+        let mut ring = self.txs.lock().unwrap_or_else(|e| e.into_inner());
+        for tx in txs {
+            if ring.len() == self.capacity {
+                ring.pop_front();
+            }
+            ring.push_back(tx.clone());
+        }
+    }
+
+    /// Returns the newest `limit` transactions in the same order as
+    /// `storage::get_recent_transactions`, or `None` when the cache holds
+    /// fewer than `limit` rows and the caller should query the database.
+    pub fn recent(&self, limit: usize) -> Option<Vec<NormalizedTx>> {
+        let ring = self.txs.lock().unwrap_or_else(|e| e.into_inner());
+        if limit == 0 || ring.len() < limit {
+            return None;
+        }
+        let mut txs: Vec<NormalizedTx> = ring.iter().cloned().collect();
+        txs.sort_by(|a, b| {
+            (b.timestamp.unwrap_or(0), &b.hash).cmp(&(a.timestamp.unwrap_or(0), &a.hash))
+        });
+        txs.truncate(limit);
+        Some(txs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tx(hash: &str, timestamp: i64) -> NormalizedTx {
+        NormalizedTx {
+            hash: hash.to_string(),
+            from: "0xa".to_string(),
+            to: None,
+            value_wei: "0".to_string(),
+            gas: 21_000,
+            gas_price_wei: None,
+            max_fee_per_gas_wei: None,
+            max_priority_fee_per_gas_wei: None,
+            nonce: 0,
+            block_number: Some(1),
+            timestamp: Some(timestamp),
+            status: None,
+            input_data: None,
+            tx_index: None,
+            chain_id: 1,
+            tx_type: None,
+            input_size: None,
+        }
+    }
+
+    #[test]
+    fn evicts_oldest_and_requires_warm_cache() {
+        let cache = RecentTxCache::new(2);
+        cache.push_many(&[tx("0x1", 10)]);
+        assert!(cache.recent(2).is_none());
+
+        cache.push_many(&[tx("0x2", 20), tx("0x3", 30)]);
+        let hashes: Vec<String> = cache
+            .recent(2)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.hash)
+            .collect();
+        assert_eq!(hashes, vec!["0x3", "0x2"]);
+        assert!(cache.recent(3).is_none());
+    }
+}
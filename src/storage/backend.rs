@@ -0,0 +1,674 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::stream::BoxStream;
+
+use super::{postgres::PostgresStorage, DbPool, RecentTxFilter, StorageOptions};
+use crate::models::{
+    AddressSummary, ArrivalRate, BaseFeeStats, BlockInfo, BlockSummary, FlowSummary,
+    GasHistogramBucket, GasStats, LogEntry, NonceGaps, NormalizedTx, RowCounts, StoredTx,
+    ThroughputBucket, TimelineEntry, TokenTransfer, TopSender, TxMetadata, WindowSummary,
+};
+
+/// SQL that differs between the supported databases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlDialect {
+    Sqlite,
+    Postgres,
+}
+
+impl SqlDialect {
+    /// Picks the backend from the `DATABASE_URL` scheme; anything that is not
+    /// `postgres://`/`postgresql://` is treated as SQLite.
+    pub fn from_url(database_url: &str) -> Self {
+        if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
+            Self::Postgres
+        } else {
+            Self::Sqlite
+        }
+    }
+
+    /// Cast of a decimal text column to the widest native integer.
+    pub fn integer_cast(self, column: &str) -> String {
+        match self {
+            Self::Sqlite => format!("CAST({} AS INTEGER)", column),
+            Self::Postgres => format!("CAST({} AS BIGINT)", column),
+        }
+    }
+
+    /// Keeps non-numeric text away from [`Self::integer_cast`]. SQLite's cast
+    /// only needs a leading digit; Postgres rejects anything but digits.
+    pub fn numeric_guard(self, column: &str) -> String {
+        match self {
+            Self::Sqlite => format!("{} GLOB '[0-9]*'", column),
+            Self::Postgres => format!("{} ~ '^[0-9]+$'", column),
+        }
+    }
+
+    /// Average as a double; Postgres would otherwise return NUMERIC.
+    fn average(self, expr: &str) -> String {
+        match self {
+            Self::Sqlite => format!("AVG({})", expr),
+            Self::Postgres => format!("AVG({})::DOUBLE PRECISION", expr),
+        }
+    }
+
+    /// Smaller of two integer expressions (a scalar, not the aggregate).
+    fn least(self, a: &str, b: &str) -> String {
+        match self {
+            Self::Sqlite => format!("MIN({}, {})", a, b),
+            Self::Postgres => format!("LEAST({}, {})", a, b),
+        }
+    }
+
+    /// `column` holds a decimal amount short enough (18 digits) to cast
+    /// without overflow.
+    fn castable_wei(self, column: &str) -> String {
+        format!(
+            "{column} IS NOT NULL AND {guard} AND LENGTH({column}) <= 18",
+            column = column,
+            guard = self.numeric_guard(column),
+        )
+    }
+
+    /// `priced` CTE body: each transaction's `chain_id`, `block_number`,
+    /// `timestamp` and `price`. Fee strings are limited to 18 digits before casting to avoid
+    /// overflow, and non-numeric ones leave `price` NULL. EIP-1559
+    /// transactions are priced at their effective gas price,
+    /// `min(maxFee, baseFee + priorityFee)`, when their block's base fee is
+    /// stored, else at their `gasPrice`, so 1559 and legacy transactions are
+    /// comparable (see [`crate::analysis::effective_gas_price_at`]).
+    fn priced_transactions(self) -> String {
+        let cast = |column: &str| self.integer_cast(column);
+        format!(
+            r#"
+    priced AS (
+        SELECT t.chain_id, t.block_number, t.timestamp,
+               CASE
+                   WHEN {max_fee_ok} AND {priority_ok} AND {base_fee_ok}
+                       THEN {effective}
+                   WHEN {gas_price_ok}
+                       THEN {gas_price}
+               END AS price
+        FROM transactions t
+        LEFT JOIN blocks b ON b.chain_id = t.chain_id AND b.block_number = t.block_number
+    )"#,
+            max_fee_ok = self.castable_wei("t.max_fee_per_gas_wei"),
+            priority_ok = self.castable_wei("t.max_priority_fee_per_gas_wei"),
+            base_fee_ok = self.castable_wei("b.base_fee_wei"),
+            effective = self.least(
+                &cast("t.max_fee_per_gas_wei"),
+                &format!(
+                    "{} + {}",
+                    cast("b.base_fee_wei"),
+                    cast("t.max_priority_fee_per_gas_wei")
+                ),
+            ),
+            gas_price_ok = self.castable_wei("t.gas_price_wei"),
+            gas_price = cast("t.gas_price_wei"),
+        )
+    }
+
+    /// Gas-price aggregate over the rows of [`Self::priced_transactions`]
+    /// with a price. `filter` holds the caller's extra `AND ...` conditions
+    /// on the priced rows' `chain_id`, `block_number` and `timestamp`. Percentiles use
+    /// the nearest-rank method over the same rows.
+    pub fn gas_stats_select(self, filter: &str) -> String {
+        format!(
+            r#"
+    WITH {priced},
+    prices AS (
+        SELECT price
+        FROM priced
+        WHERE price IS NOT NULL
+          {filter}
+    ),
+    ranked AS (
+        SELECT price,
+               ROW_NUMBER() OVER (ORDER BY price) AS rn,
+               COUNT(*) OVER () AS n
+        FROM prices
+    )
+    SELECT
+        MIN(price) as min_gas_price,
+        MAX(price) as max_gas_price,
+        {avg} as avg_gas_price,
+        MIN(CASE WHEN rn * 100 >= n * 25 THEN price END) as p25_gas_price,
+        MIN(CASE WHEN rn * 100 >= n * 50 THEN price END) as p50_gas_price,
+        MIN(CASE WHEN rn * 100 >= n * 75 THEN price END) as p75_gas_price,
+        MIN(CASE WHEN rn * 100 >= n * 90 THEN price END) as p90_gas_price
+    FROM ranked
+"#,
+            priced = self.priced_transactions(),
+            filter = filter,
+            avg = self.average("price"),
+        )
+    }
+
+    /// Counts of the same prices as [`Self::gas_stats_select`] per
+    /// `bucket_wei`-wide range, as `(bucket, tx_count)` rows ordered by
+    /// bucket index. `bucket_wei` is a placeholder or literal.
+    pub fn gas_histogram_select(self, filter: &str, bucket_wei: &str) -> String {
+        format!(
+            r#"
+    WITH {priced}
+    SELECT price / {bucket_wei} AS bucket, COUNT(*) AS tx_count
+    FROM priced
+    WHERE price IS NOT NULL
+      {filter}
+    GROUP BY bucket
+    ORDER BY bucket
+"#,
+            priced = self.priced_transactions(),
+            bucket_wei = bucket_wei,
+            filter = filter,
+        )
+    }
+}
+
+/// The storage operations shared by the SQLite and Postgres backends: block
+/// ingestion, mempool sampling, the HTTP API and the CLI reports. Only
+/// `export` still streams from SQLite through the free functions directly.
+/// Each read documented on the SQLite function of the same name behaves the
+/// same on both backends.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Fails when the database is unreachable.
+    async fn ping(&self) -> Result<()>;
+    /// Waits for checked-out connections and closes the pool.
+    async fn close(&self);
+    async fn insert_block(&self, block: &BlockInfo) -> Result<()>;
+    async fn upsert_block(&self, block: &BlockInfo) -> Result<()>;
+    async fn get_block(&self, chain_id: i64, number: i64) -> Result<Option<BlockInfo>>;
+    /// Replaces a reorged-out block and everything stored under it; see
+    /// [`super::replace_block`].
+    async fn replace_block(&self, block: &BlockInfo) -> Result<RowCounts>;
+    async fn insert_transactions(
+        &self,
+        txs: &[NormalizedTx],
+        options: &StorageOptions,
+    ) -> Result<()>;
+    async fn insert_tx_metadata(&self, rows: &[TxMetadata]) -> Result<()>;
+    async fn insert_logs(&self, logs: &[LogEntry]) -> Result<()>;
+    async fn insert_token_transfers(&self, transfers: &[TokenTransfer]) -> Result<()>;
+    /// Registers a mempool sampling run and returns its id.
+    async fn create_sample(&self, started_at: i64) -> Result<i64>;
+    async fn insert_arrivals(
+        &self,
+        sample_id: i64,
+        chain_id: i64,
+        arrivals: &[(String, i64)],
+    ) -> Result<()>;
+
+    async fn get_recent_stored_transactions(
+        &self,
+        limit: i64,
+        filter: &RecentTxFilter,
+    ) -> Result<Vec<StoredTx>>;
+    async fn get_recent_transactions(
+        &self,
+        limit: i64,
+        filter: &RecentTxFilter,
+    ) -> Result<Vec<NormalizedTx>> {
+        let rows = self.get_recent_stored_transactions(limit, filter).await?;
+        Ok(rows.into_iter().map(|stored| stored.tx).collect())
+    }
+    /// Position of the newest stored transaction in insertion order (0 when
+    /// there is none); the starting point for [`Self::get_transactions_after_seq`].
+    async fn max_transaction_seq(&self) -> Result<i64>;
+    /// Up to `limit` transactions stored after position `seq`, oldest first,
+    /// each with its own position. Rows written by other processes show up too.
+    async fn get_transactions_after_seq(
+        &self,
+        seq: i64,
+        limit: i64,
+    ) -> Result<Vec<(i64, StoredTx)>>;
+    async fn get_tx_metadata(&self, chain_id: i64, hash: &str) -> Result<Vec<TxMetadata>>;
+    async fn get_arrival_rate(
+        &self,
+        sample_id: Option<i64>,
+        bucket_secs: i64,
+    ) -> Result<Option<ArrivalRate>>;
+
+    /// `chain_id` narrows the reads below to one chain; `None` spans all.
+    async fn get_transactions_by_address(
+        &self,
+        address: &str,
+        limit: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<StoredTx>>;
+    async fn get_nonce_attempts(
+        &self,
+        from: &str,
+        nonce: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<NormalizedTx>>;
+    async fn get_address_timeline(
+        &self,
+        address: &str,
+        limit: i64,
+        before: Option<i64>,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<TimelineEntry>>;
+    async fn get_address_summary(
+        &self,
+        address: &str,
+        chain_id: Option<i64>,
+    ) -> Result<AddressSummary>;
+    async fn get_flow_between(
+        &self,
+        from: &str,
+        to: &str,
+        chain_id: Option<i64>,
+    ) -> Result<FlowSummary>;
+    async fn get_window_summary(
+        &self,
+        from_block: i64,
+        to_block: i64,
+        chain_id: Option<i64>,
+    ) -> Result<WindowSummary>;
+    async fn get_throughput(
+        &self,
+        bucket_secs: i64,
+        window_secs: i64,
+        now: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<ThroughputBucket>>;
+    async fn get_transaction_by_hash(
+        &self,
+        hash: &str,
+        chain_id: Option<i64>,
+    ) -> Result<Option<StoredTx>>;
+    async fn get_transactions_by_hashes(
+        &self,
+        hashes: &[String],
+        chain_id: Option<i64>,
+    ) -> Result<Vec<StoredTx>>;
+    fn stream_block_transactions(
+        &self,
+        last_n_blocks: i64,
+        chain_id: Option<i64>,
+    ) -> BoxStream<'_, Result<NormalizedTx>>;
+    async fn get_nonce_gaps(&self, address: &str, chain_id: Option<i64>) -> Result<NonceGaps>;
+    async fn get_top_senders(&self, limit: i64, chain_id: Option<i64>) -> Result<Vec<TopSender>>;
+    async fn get_top_receivers(&self, limit: i64, chain_id: Option<i64>) -> Result<Vec<TopSender>>;
+    async fn get_gas_stats(
+        &self,
+        last_n_blocks: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Option<GasStats>>;
+    async fn get_gas_histogram(
+        &self,
+        last_n_blocks: i64,
+        bucket_gwei: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<GasHistogramBucket>>;
+    async fn get_gas_stats_at(
+        &self,
+        timestamp: i64,
+        window_secs: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Option<GasStats>>;
+    async fn get_base_fee_stats(
+        &self,
+        last_n_blocks: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Option<BaseFeeStats>>;
+    async fn get_max_value_transaction(
+        &self,
+        last_n_blocks: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Option<NormalizedTx>>;
+    async fn get_recent_blocks(
+        &self,
+        limit: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<BlockSummary>>;
+    async fn get_recent_logs(
+        &self,
+        limit: i64,
+        address: Option<&str>,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<LogEntry>>;
+    async fn get_token_transfers(
+        &self,
+        limit: i64,
+        token: Option<&str>,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<TokenTransfer>>;
+    async fn get_latest_block_number(&self, chain_id: Option<i64>) -> Result<Option<i64>>;
+    async fn count_rows(&self) -> Result<RowCounts>;
+    async fn prune_before(&self, cutoff: i64) -> Result<RowCounts>;
+    async fn prune_to_count(&self, max: u64) -> Result<RowCounts>;
+    /// Reclaims space left by deletes (`VACUUM`).
+    async fn vacuum(&self) -> Result<()>;
+}
+
+/// Opens the backend named by `database_url` and applies its schema.
+pub async fn connect(database_url: &str, options: &StorageOptions) -> Result<Arc<dyn Storage>> {
+    Ok(match SqlDialect::from_url(database_url) {
+        SqlDialect::Sqlite => Arc::new(SqliteStorage::new(
+            super::init_pool_with_options(database_url, options).await?,
+        )),
+        SqlDialect::Postgres => Arc::new(PostgresStorage::connect(database_url, options).await?),
+    })
+}
+
+/// [`Storage`] over the SQLite free functions.
+#[derive(Clone)]
+pub struct SqliteStorage {
+    pool: DbPool,
+}
+
+impl SqliteStorage {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn ping(&self) -> Result<()> {
+        super::ping(&self.pool).await
+    }
+
+    async fn close(&self) {
+        self.pool.close().await
+    }
+
+    async fn insert_block(&self, block: &BlockInfo) -> Result<()> {
+        super::insert_block(&self.pool, block).await
+    }
+
+    async fn upsert_block(&self, block: &BlockInfo) -> Result<()> {
+        super::upsert_block(&self.pool, block).await
+    }
+
+    async fn get_block(&self, chain_id: i64, number: i64) -> Result<Option<BlockInfo>> {
+        super::get_block(&self.pool, chain_id, number).await
+    }
+
+    async fn replace_block(&self, block: &BlockInfo) -> Result<RowCounts> {
+        super::replace_block(&self.pool, block).await
+    }
+
+    async fn insert_transactions(
+        &self,
+        txs: &[NormalizedTx],
+        options: &StorageOptions,
+    ) -> Result<()> {
+        super::insert_transactions_with_options(&self.pool, txs, options).await
+    }
+
+    async fn insert_tx_metadata(&self, rows: &[TxMetadata]) -> Result<()> {
+        super::insert_tx_metadata(&self.pool, rows).await
+    }
+
+    async fn insert_logs(&self, logs: &[LogEntry]) -> Result<()> {
+        super::insert_logs(&self.pool, logs).await
+    }
+
+    async fn insert_token_transfers(&self, transfers: &[TokenTransfer]) -> Result<()> {
+        super::insert_token_transfers(&self.pool, transfers).await
+    }
+
+    async fn create_sample(&self, started_at: i64) -> Result<i64> {
+        super::create_sample(&self.pool, started_at).await
+    }
+
+    async fn insert_arrivals(
+        &self,
+        sample_id: i64,
+        chain_id: i64,
+        arrivals: &[(String, i64)],
+    ) -> Result<()> {
+        super::insert_arrivals(&self.pool, sample_id, chain_id, arrivals).await
+    }
+
+    async fn get_recent_stored_transactions(
+        &self,
+        limit: i64,
+        filter: &RecentTxFilter,
+    ) -> Result<Vec<StoredTx>> {
+        super::get_recent_stored_transactions(&self.pool, limit, filter).await
+    }
+
+    async fn max_transaction_seq(&self) -> Result<i64> {
+        super::max_transaction_rowid(&self.pool).await
+    }
+
+    async fn get_transactions_after_seq(
+        &self,
+        seq: i64,
+        limit: i64,
+    ) -> Result<Vec<(i64, StoredTx)>> {
+        super::get_transactions_after_rowid(&self.pool, seq, limit).await
+    }
+
+    async fn get_transactions_by_address(
+        &self,
+        address: &str,
+        limit: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<StoredTx>> {
+        super::get_transactions_by_address(&self.pool, address, limit, chain_id).await
+    }
+
+    async fn get_nonce_attempts(
+        &self,
+        from: &str,
+        nonce: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<NormalizedTx>> {
+        super::get_nonce_attempts(&self.pool, from, nonce, chain_id).await
+    }
+
+    async fn get_address_timeline(
+        &self,
+        address: &str,
+        limit: i64,
+        before: Option<i64>,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<TimelineEntry>> {
+        super::get_address_timeline(&self.pool, address, limit, before, chain_id).await
+    }
+
+    async fn get_address_summary(
+        &self,
+        address: &str,
+        chain_id: Option<i64>,
+    ) -> Result<AddressSummary> {
+        super::get_address_summary(&self.pool, address, chain_id).await
+    }
+
+    async fn get_flow_between(
+        &self,
+        from: &str,
+        to: &str,
+        chain_id: Option<i64>,
+    ) -> Result<FlowSummary> {
+        super::get_flow_between(&self.pool, from, to, chain_id).await
+    }
+
+    async fn get_window_summary(
+        &self,
+        from_block: i64,
+        to_block: i64,
+        chain_id: Option<i64>,
+    ) -> Result<WindowSummary> {
+        super::get_window_summary(&self.pool, from_block, to_block, chain_id).await
+    }
+
+    async fn get_tx_metadata(&self, chain_id: i64, hash: &str) -> Result<Vec<TxMetadata>> {
+        super::get_tx_metadata(&self.pool, chain_id, hash).await
+    }
+
+    async fn get_arrival_rate(
+        &self,
+        sample_id: Option<i64>,
+        bucket_secs: i64,
+    ) -> Result<Option<ArrivalRate>> {
+        super::get_arrival_rate(&self.pool, sample_id, bucket_secs).await
+    }
+
+    async fn get_throughput(
+        &self,
+        bucket_secs: i64,
+        window_secs: i64,
+        now: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<ThroughputBucket>> {
+        super::get_throughput(&self.pool, bucket_secs, window_secs, now, chain_id).await
+    }
+
+    async fn get_transaction_by_hash(
+        &self,
+        hash: &str,
+        chain_id: Option<i64>,
+    ) -> Result<Option<StoredTx>> {
+        super::get_transaction_by_hash(&self.pool, hash, chain_id).await
+    }
+
+    async fn get_transactions_by_hashes(
+        &self,
+        hashes: &[String],
+        chain_id: Option<i64>,
+    ) -> Result<Vec<StoredTx>> {
+        super::get_transactions_by_hashes(&self.pool, hashes, chain_id).await
+    }
+
+    fn stream_block_transactions(
+        &self,
+        last_n_blocks: i64,
+        chain_id: Option<i64>,
+    ) -> BoxStream<'_, Result<NormalizedTx>> {
+        super::stream_block_transactions(&self.pool, last_n_blocks, chain_id)
+    }
+
+    async fn get_nonce_gaps(&self, address: &str, chain_id: Option<i64>) -> Result<NonceGaps> {
+        super::get_nonce_gaps(&self.pool, address, chain_id).await
+    }
+
+    async fn get_top_senders(&self, limit: i64, chain_id: Option<i64>) -> Result<Vec<TopSender>> {
+        super::get_top_senders(&self.pool, limit, chain_id).await
+    }
+
+    async fn get_top_receivers(&self, limit: i64, chain_id: Option<i64>) -> Result<Vec<TopSender>> {
+        super::get_top_receivers(&self.pool, limit, chain_id).await
+    }
+
+    async fn get_gas_stats(
+        &self,
+        last_n_blocks: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Option<GasStats>> {
+        super::get_gas_stats(&self.pool, last_n_blocks, chain_id).await
+    }
+
+    async fn get_gas_histogram(
+        &self,
+        last_n_blocks: i64,
+        bucket_gwei: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<GasHistogramBucket>> {
+        super::get_gas_histogram(&self.pool, last_n_blocks, bucket_gwei, chain_id).await
+    }
+
+    async fn get_gas_stats_at(
+        &self,
+        timestamp: i64,
+        window_secs: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Option<GasStats>> {
+        super::get_gas_stats_at(&self.pool, timestamp, window_secs, chain_id).await
+    }
+
+    async fn get_base_fee_stats(
+        &self,
+        last_n_blocks: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Option<BaseFeeStats>> {
+        super::get_base_fee_stats(&self.pool, last_n_blocks, chain_id).await
+    }
+
+    async fn get_max_value_transaction(
+        &self,
+        last_n_blocks: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Option<NormalizedTx>> {
+        super::get_max_value_transaction(&self.pool, last_n_blocks, chain_id).await
+    }
+
+    async fn get_recent_blocks(
+        &self,
+        limit: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<BlockSummary>> {
+        super::get_recent_blocks(&self.pool, limit, chain_id).await
+    }
+
+    async fn get_recent_logs(
+        &self,
+        limit: i64,
+        address: Option<&str>,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<LogEntry>> {
+        super::get_recent_logs(&self.pool, limit, address, chain_id).await
+    }
+
+    async fn get_token_transfers(
+        &self,
+        limit: i64,
+        token: Option<&str>,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<TokenTransfer>> {
+        super::get_token_transfers(&self.pool, limit, token, chain_id).await
+    }
+
+    async fn get_latest_block_number(&self, chain_id: Option<i64>) -> Result<Option<i64>> {
+        super::get_latest_block_number(&self.pool, chain_id).await
+    }
+
+    async fn count_rows(&self) -> Result<RowCounts> {
+        super::count_rows(&self.pool).await
+    }
+
+    async fn prune_before(&self, cutoff: i64) -> Result<RowCounts> {
+        super::prune_before(&self.pool, cutoff).await
+    }
+
+    async fn prune_to_count(&self, max: u64) -> Result<RowCounts> {
+        super::prune_to_count(&self.pool, max).await
+    }
+
+    async fn vacuum(&self) -> Result<()> {
+        super::vacuum(&self.pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dialect_follows_url_scheme_and_casts() {
+        assert_eq!(
+            SqlDialect::from_url("postgres://u@localhost/db"),
+            SqlDialect::Postgres
+        );
+        assert_eq!(
+            SqlDialect::from_url("postgresql://localhost/db"),
+            SqlDialect::Postgres
+        );
+        assert_eq!(SqlDialect::from_url("sqlite://data.db"), SqlDialect::Sqlite);
+
+        let pg = SqlDialect::Postgres.gas_stats_select("");
+        assert!(pg.contains("CAST(t.gas_price_wei AS BIGINT)"));
+        assert!(pg.contains("LEAST(CAST(t.max_fee_per_gas_wei AS BIGINT)"));
+        assert!(pg.contains("~ '^[0-9]+$'"));
+        assert!(SqlDialect::Sqlite
+            .gas_stats_select("")
+            .contains("GLOB '[0-9]*'"));
+    }
+}
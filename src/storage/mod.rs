@@ -1,26 +1,177 @@
+use std::collections::HashSet;
 use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
 
-use anyhow::{Context, Result};
-use sqlx::{sqlite::SqlitePoolOptions, FromRow, Row, SqlitePool};
-use tracing::warn;
+use anyhow::{anyhow, Context, Result};
+use ethers_core::types::U256;
+use ethers_core::utils::hex;
+use futures_util::stream::BoxStream;
+use futures_util::{StreamExt, TryStreamExt};
+use sqlx::pool::PoolOptions;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteSynchronous};
+use sqlx::{Connection, FromRow, QueryBuilder, Row, Sqlite, SqliteConnection, SqlitePool};
+use tracing::{info, warn};
 
-use crate::models::{BlockInfo, GasStats, NormalizedTx, TopSender};
+use crate::analysis;
+use crate::config::Config;
+use crate::insert_queue::BackpressurePolicy;
+use crate::models::{
+    AddressSummary, ArrivalBucket, ArrivalRate, BaseFeeStats, BlockInfo, BlockSummary, Direction,
+    FlowSummary, GasHistogramBucket, GasStats, LogEntry, NonceGaps, NormalizedTx, RowCounts,
+    StoredTx, ThroughputBucket, TimelineEntry, TokenTransfer, TopSender, TxFlags, TxMetadata,
+    WindowSummary,
+};
+
+mod backend;
+mod postgres;
+
+pub use backend::{connect, SqlDialect, SqliteStorage, Storage};
+pub use postgres::PostgresStorage;
 
 pub type DbPool = SqlitePool;
 
+/// Batches the pending-sample writer may fall behind by before backpressure.
+pub const DEFAULT_INSERT_QUEUE_CAPACITY: usize = 16;
+
+/// Pooled connections per process unless `DB_MAX_CONNECTIONS` says otherwise.
+pub const DEFAULT_DB_MAX_CONNECTIONS: u32 = 5;
+
+/// How long a SQLite connection waits on a lock before `database is locked`.
+pub const DEFAULT_SQLITE_BUSY_TIMEOUT_MS: u64 = 5_000;
+
+/// Tunables applied when opening the database and writing rows.
+#[derive(Debug, Clone)]
+pub struct StorageOptions {
+    /// Store calldata zstd-compressed when that is smaller.
+    pub compress_input: bool,
+    /// Bound on queued insert batches awaiting the writer task.
+    pub insert_queue_capacity: usize,
+    /// What to do when the insert queue is full.
+    pub backpressure: BackpressurePolicy,
+    /// Drop mined rows older than this many seconds after each ingest run.
+    pub retention_max_age_secs: Option<u64>,
+    /// Keep at most this many transactions after each ingest run.
+    pub retention_max_rows: Option<u64>,
+    /// SQLite `busy_timeout` applied to every pooled connection.
+    pub busy_timeout_ms: u64,
+    /// Chain id given to rows stored before chain ids were recorded.
+    pub legacy_chain_id: i64,
+    /// Most connections the pool opens.
+    pub max_connections: u32,
+    /// Connections kept open while idle.
+    pub min_connections: u32,
+    /// Wait for a free connection before failing; `None` keeps sqlx's default.
+    pub acquire_timeout: Option<Duration>,
+    /// Routers whose transactions get the `DEX_SWAP` flag (`MEV_ROUTERS`).
+    pub dex_routers: HashSet<String>,
+}
+
+impl Default for StorageOptions {
+    fn default() -> Self {
+        Self {
+            compress_input: false,
+            insert_queue_capacity: DEFAULT_INSERT_QUEUE_CAPACITY,
+            backpressure: BackpressurePolicy::default(),
+            retention_max_age_secs: None,
+            retention_max_rows: None,
+            busy_timeout_ms: DEFAULT_SQLITE_BUSY_TIMEOUT_MS,
+            legacy_chain_id: 1,
+            max_connections: DEFAULT_DB_MAX_CONNECTIONS,
+            min_connections: 0,
+            acquire_timeout: None,
+            dex_routers: analysis::default_routers(),
+        }
+    }
+}
+
+impl StorageOptions {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            compress_input: config.compress_input_data,
+            insert_queue_capacity: config.insert_queue_capacity,
+            backpressure: config.insert_queue_policy,
+            retention_max_age_secs: config.retention_max_age_secs,
+            retention_max_rows: config.retention_max_rows,
+            busy_timeout_ms: config.sqlite_busy_timeout_ms,
+            legacy_chain_id: config.chain_id.map_or(1, |id| id as i64),
+            max_connections: config.db_max_connections,
+            min_connections: config.db_min_connections,
+            acquire_timeout: config.db_acquire_timeout_secs.map(Duration::from_secs),
+            dex_routers: config
+                .mev_routers
+                .clone()
+                .unwrap_or_else(analysis::default_routers),
+        }
+    }
+
+    /// Pool settings shared by the SQLite and Postgres backends; logs them so
+    /// operators can see what is in effect.
+    pub fn pool_options<DB: sqlx::Database>(&self) -> PoolOptions<DB> {
+        let mut pool = PoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections);
+        if let Some(timeout) = self.acquire_timeout {
+            pool = pool.acquire_timeout(timeout);
+        }
+        info!(
+            "database pool: max_connections={} min_connections={} acquire_timeout={:?}",
+            self.max_connections,
+            self.min_connections,
+            pool.get_acquire_timeout()
+        );
+        pool
+    }
+}
+
 pub async fn init_pool(database_url: &str) -> Result<DbPool> {
+    init_pool_with_options(database_url, &StorageOptions::default()).await
+}
+
+pub async fn init_pool_with_options(
+    database_url: &str,
+    options: &StorageOptions,
+) -> Result<DbPool> {
     ensure_dir_exists(database_url)?;
 
-    let pool = SqlitePoolOptions::new()
-        .max_connections(5)
-        .connect(database_url)
+    let pool = options
+        .pool_options::<Sqlite>()
+        .connect_with(connect_options(database_url, options)?)
         .await
         .context("failed to connect to SQLite")?;
 
-    apply_schema(&pool).await?;
+    apply_schema(&pool, options).await?;
     Ok(pool)
 }
 
+/// Runs `SELECT 1`; fails when the pool is closed or the database is unreachable.
+pub async fn ping(pool: &DbPool) -> Result<()> {
+    sqlx::query("SELECT 1").execute(pool).await?;
+    Ok(())
+}
+
+/// Per-connection settings: WAL with `synchronous=NORMAL` lets API reads run
+/// alongside an ingest writer, and `busy_timeout` makes a blocked writer wait
+/// instead of failing. In-memory databases have no file to journal to, so
+/// they keep SQLite's default journal.
+fn connect_options(database_url: &str, options: &StorageOptions) -> Result<SqliteConnectOptions> {
+    let connect = SqliteConnectOptions::from_str(database_url)
+        .with_context(|| format!("invalid SQLite DATABASE_URL {:?}", database_url))?
+        .busy_timeout(Duration::from_millis(options.busy_timeout_ms));
+    Ok(if is_memory_url(database_url) {
+        connect
+    } else {
+        connect
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+    })
+}
+
+fn is_memory_url(database_url: &str) -> bool {
+    database_url.contains(":memory:") || database_url.contains("mode=memory")
+}
+
 fn ensure_dir_exists(database_url: &str) -> Result<()> {
     if let Some(path) = database_url.strip_prefix("sqlite://") {
         if path != ":memory:" {
@@ -33,107 +184,713 @@ fn ensure_dir_exists(database_url: &str) -> Result<()> {
     Ok(())
 }
 
-async fn apply_schema(pool: &SqlitePool) -> Result<()> {
-    let mut tx = pool.begin().await?;
-    sqlx::query(
+/// `CREATE TABLE` for `blocks` under `name`. Block numbers are only unique
+/// within a chain, so the chain id is part of the key.
+fn blocks_table_sql(name: &str) -> String {
+    format!(
         r#"
-        CREATE TABLE IF NOT EXISTS blocks (
-            block_number INTEGER PRIMARY KEY,
+        CREATE TABLE IF NOT EXISTS {} (
+            chain_id INTEGER NOT NULL,
+            block_number INTEGER NOT NULL,
             block_hash TEXT NOT NULL,
-            timestamp INTEGER NOT NULL
+            timestamp INTEGER NOT NULL,
+            base_fee_wei TEXT,
+            PRIMARY KEY(chain_id, block_number)
         );
         "#,
+        name
     )
-    .execute(&mut *tx)
-    .await?;
+}
 
-    sqlx::query(
+/// `CREATE TABLE` for `transactions` under `name`, keyed like
+/// [`blocks_table_sql`].
+fn transactions_table_sql(name: &str) -> String {
+    format!(
         r#"
-        CREATE TABLE IF NOT EXISTS transactions (
-            hash TEXT PRIMARY KEY,
+        CREATE TABLE IF NOT EXISTS {} (
+            chain_id INTEGER NOT NULL,
+            hash TEXT NOT NULL,
             from_addr TEXT NOT NULL,
             to_addr TEXT,
             value_wei TEXT NOT NULL,
             gas INTEGER NOT NULL,
             gas_price_wei TEXT,
             max_fee_per_gas_wei TEXT,
+            max_priority_fee_per_gas_wei TEXT,
             nonce INTEGER NOT NULL,
             block_number INTEGER,
             timestamp INTEGER,
             status TEXT,
-            FOREIGN KEY(block_number) REFERENCES blocks(block_number)
+            input_data TEXT,
+            input_compressed INTEGER NOT NULL DEFAULT 0,
+            flags INTEGER NOT NULL DEFAULT 0,
+            tx_index INTEGER,
+            input_selector TEXT,
+            tx_type INTEGER,
+            input_size INTEGER,
+            PRIMARY KEY(chain_id, hash),
+            FOREIGN KEY(chain_id, block_number) REFERENCES blocks(chain_id, block_number)
         );
         "#,
+        name
     )
-    .execute(&mut *tx)
-    .await?;
+}
 
-    sqlx::query(
+/// `CREATE TABLE` for `sample_arrivals` under `name`. Like the other tables
+/// below it holds rows of a transaction, so it carries the chain id of the
+/// transactions it refers to.
+fn sample_arrivals_table_sql(name: &str) -> String {
+    format!(
         r#"
-        CREATE INDEX IF NOT EXISTS idx_transactions_from_addr ON transactions(from_addr);
+        CREATE TABLE IF NOT EXISTS {} (
+            sample_id INTEGER NOT NULL,
+            chain_id INTEGER NOT NULL,
+            hash TEXT NOT NULL,
+            offset_ms INTEGER NOT NULL,
+            PRIMARY KEY(sample_id, chain_id, hash),
+            FOREIGN KEY(sample_id) REFERENCES mempool_samples(id)
+        );
         "#,
+        name
     )
-    .execute(&mut *tx)
-    .await?;
+}
 
-    sqlx::query(
+/// `CREATE TABLE` for `logs` under `name`.
+fn logs_table_sql(name: &str) -> String {
+    format!(
         r#"
-        CREATE INDEX IF NOT EXISTS idx_transactions_block_number ON transactions(block_number);
+        CREATE TABLE IF NOT EXISTS {} (
+            chain_id INTEGER NOT NULL,
+            tx_hash TEXT NOT NULL,
+            log_index INTEGER NOT NULL,
+            block_number INTEGER NOT NULL,
+            address TEXT NOT NULL,
+            topics TEXT NOT NULL,
+            data TEXT NOT NULL,
+            PRIMARY KEY(chain_id, tx_hash, log_index)
+        );
         "#,
+        name
     )
-    .execute(&mut *tx)
-    .await?;
+}
+
+/// `CREATE TABLE` for `token_transfers` under `name`.
+fn token_transfers_table_sql(name: &str) -> String {
+    format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {} (
+            chain_id INTEGER NOT NULL,
+            tx_hash TEXT NOT NULL,
+            log_index INTEGER NOT NULL,
+            block_number INTEGER,
+            token TEXT NOT NULL,
+            from_addr TEXT NOT NULL,
+            to_addr TEXT NOT NULL,
+            amount TEXT NOT NULL,
+            PRIMARY KEY(chain_id, tx_hash, log_index)
+        );
+        "#,
+        name
+    )
+}
+
+/// `CREATE TABLE` for `tx_metadata` under `name`.
+fn tx_metadata_table_sql(name: &str) -> String {
+    format!(
+        r#"
+        CREATE TABLE IF NOT EXISTS {} (
+            chain_id INTEGER NOT NULL,
+            hash TEXT NOT NULL,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            PRIMARY KEY(chain_id, hash, key)
+        );
+        "#,
+        name
+    )
+}
+
+/// Builds a table's `CREATE TABLE` statement under the given name.
+type TableSql = fn(&str) -> String;
+
+/// Tables holding rows of a transaction: `(table, hash column, CREATE TABLE)`.
+/// Pruning a transaction deletes its rows here in the same database
+/// transaction.
+const TX_DEPENDENT_TABLES: &[(&str, &str, TableSql)] = &[
+    ("tx_metadata", "hash", tx_metadata_table_sql),
+    ("logs", "tx_hash", logs_table_sql),
+    ("token_transfers", "tx_hash", token_transfers_table_sql),
+    ("sample_arrivals", "hash", sample_arrivals_table_sql),
+];
+
+/// Indexes, created once every table has its current columns (older
+/// databases gain them first).
+const CORE_INDEXES: &[&str] = &[
+    // Hash lookups across chains; the primary key leads with the chain id.
+    "CREATE INDEX IF NOT EXISTS idx_transactions_hash ON transactions(hash);",
+    "CREATE INDEX IF NOT EXISTS idx_transactions_from_addr ON transactions(from_addr);",
+    "CREATE INDEX IF NOT EXISTS idx_transactions_to_addr ON transactions(to_addr);",
+    "CREATE INDEX IF NOT EXISTS idx_transactions_block_number ON transactions(block_number);",
+    "CREATE INDEX IF NOT EXISTS idx_transactions_timestamp ON transactions(timestamp);",
+    // Covers the `/tx/recent` keyset, ties on timestamp broken by hash. It
+    // replaces the timestamp-only index older databases were given.
+    "DROP INDEX IF EXISTS idx_transactions_ts_coalesce;",
+    "CREATE INDEX IF NOT EXISTS idx_transactions_ts_hash \
+     ON transactions(COALESCE(timestamp, 0), hash);",
+    // Chain-order reads and the selector filter.
+    "CREATE INDEX IF NOT EXISTS idx_transactions_chain_order \
+     ON transactions(block_number, tx_index);",
+    "CREATE INDEX IF NOT EXISTS idx_transactions_input_selector ON transactions(input_selector);",
+    // Lets range prunes on block age seek instead of scanning.
+    "CREATE INDEX IF NOT EXISTS idx_blocks_timestamp ON blocks(timestamp);",
+    // "Latest N blocks" across every chain; the primary key covers one chain.
+    "CREATE INDEX IF NOT EXISTS idx_blocks_number ON blocks(block_number);",
+    "CREATE INDEX IF NOT EXISTS idx_logs_block_number ON logs(block_number);",
+    "CREATE INDEX IF NOT EXISTS idx_token_transfers_token \
+     ON token_transfers(token, block_number);",
+];
 
+/// One step of the SQLite schema history. Every step is idempotent, so a
+/// database from before versioning (version 0) can replay all of them.
+enum Migration {
+    /// Tables and columns as a fresh database gets them.
+    BaseTables,
+    /// `ALTER TABLE table ADD COLUMN column decl`, skipped when the column
+    /// exists. The declaration must be nullable or carry a default.
+    AddColumn(&'static str, &'static str, &'static str),
+    /// Keys `blocks` and `transactions` by chain id; see [`migrate_chain_id`].
+    ChainId,
+    /// Rebuilds `transactions` when a wei column is not TEXT; see
+    /// [`migrate_wei_columns`].
+    WeiColumnsText,
+    /// Keys the [`TX_DEPENDENT_TABLES`] by chain id; see
+    /// [`migrate_dependent_chain_id`].
+    DependentChainId,
+}
+
+/// The schema history in order: a database at version `n` has had the first
+/// `n` steps applied. Append new steps; never reorder or remove them.
+const MIGRATIONS: &[Migration] = &[
+    Migration::BaseTables,
+    Migration::AddColumn("transactions", "status", "TEXT"),
+    Migration::AddColumn("transactions", "input_data", "TEXT"),
+    Migration::AddColumn(
+        "transactions",
+        "input_compressed",
+        "INTEGER NOT NULL DEFAULT 0",
+    ),
+    Migration::AddColumn("transactions", "flags", "INTEGER NOT NULL DEFAULT 0"),
+    Migration::AddColumn("transactions", "tx_index", "INTEGER"),
+    Migration::AddColumn("blocks", "base_fee_wei", "TEXT"),
+    Migration::AddColumn("transactions", "max_priority_fee_per_gas_wei", "TEXT"),
+    Migration::AddColumn("transactions", "input_selector", "TEXT"),
+    Migration::AddColumn("transactions", "tx_type", "INTEGER"),
+    Migration::AddColumn("transactions", "input_size", "INTEGER"),
+    Migration::ChainId,
+    Migration::WeiColumnsText,
+    Migration::DependentChainId,
+];
+
+/// Schema version this build migrates SQLite databases to.
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+impl Migration {
+    async fn apply(&self, pool: &SqlitePool, options: &StorageOptions) -> Result<()> {
+        match self {
+            Migration::BaseTables => create_base_tables(pool).await,
+            Migration::AddColumn(table, column, decl) => {
+                add_column_if_missing(pool, table, column, decl).await
+            }
+            Migration::ChainId => {
+                if has_column(pool, "blocks", "chain_id").await?
+                    && has_column(pool, "transactions", "chain_id").await?
+                {
+                    return Ok(());
+                }
+                migrate_chain_id(pool, options.legacy_chain_id).await
+            }
+            Migration::WeiColumnsText => migrate_wei_columns(pool).await,
+            Migration::DependentChainId => {
+                migrate_dependent_chain_id(pool, options.legacy_chain_id).await
+            }
+        }
+    }
+}
+
+/// Brings the database up to [`SCHEMA_VERSION`], recording each applied step
+/// in `schema_version`, then makes sure the indexes exist.
+async fn apply_schema(pool: &SqlitePool, options: &StorageOptions) -> Result<()> {
     sqlx::query(
         r#"
-        CREATE INDEX IF NOT EXISTS idx_transactions_timestamp ON transactions(timestamp);
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            applied_at INTEGER NOT NULL
+        );
         "#,
     )
-    .execute(&mut *tx)
+    .execute(pool)
     .await?;
 
+    let before = schema_version(pool).await?;
+    if before > SCHEMA_VERSION {
+        warn!(
+            "database schema version {} is newer than this build's {}; continuing",
+            before, SCHEMA_VERSION
+        );
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(before as usize) {
+        let version = index as i64 + 1;
+        migration
+            .apply(pool, options)
+            .await
+            .with_context(|| format!("schema migration to version {} failed", version))?;
+        // Another process opening the same new database may record the
+        // step first; every step is idempotent, so that is not an error.
+        sqlx::query(
+            "INSERT OR IGNORE INTO schema_version (version, applied_at) \
+             VALUES (?1, CAST(strftime('%s', 'now') AS INTEGER));",
+        )
+        .bind(version)
+        .execute(pool)
+        .await?;
+    }
+    for sql in CORE_INDEXES {
+        sqlx::query(sql).execute(pool).await?;
+    }
+
+    let after = schema_version(pool).await?;
+    if after != before {
+        info!(
+            "database schema migrated from version {} to {}",
+            before, after
+        );
+    } else {
+        info!("database schema at version {}", after);
+    }
+    Ok(())
+}
+
+/// Latest recorded schema version; 0 for a new database or one that
+/// predates versioning.
+pub async fn schema_version(pool: &SqlitePool) -> Result<i64> {
+    let version: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM schema_version;")
+        .fetch_one(pool)
+        .await?;
+    Ok(version.unwrap_or(0))
+}
+
+async fn create_base_tables(pool: &SqlitePool) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(&blocks_table_sql("blocks"))
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(&transactions_table_sql("transactions"))
+        .execute(&mut *tx)
+        .await?;
+
     sqlx::query(
         r#"
-        CREATE INDEX IF NOT EXISTS idx_transactions_ts_coalesce
-        ON transactions(COALESCE(timestamp, 0));
+        CREATE TABLE IF NOT EXISTS mempool_samples (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            started_at INTEGER NOT NULL
+        );
         "#,
     )
     .execute(&mut *tx)
     .await?;
 
+    for sql in [
+        sample_arrivals_table_sql("sample_arrivals"),
+        logs_table_sql("logs"),
+        token_transfers_table_sql("token_transfers"),
+        tx_metadata_table_sql("tx_metadata"),
+    ] {
+        sqlx::query(&sql).execute(&mut *tx).await?;
+    }
+
     tx.commit().await?;
-    verify_value_wei_column(pool).await?;
+    Ok(())
+}
+
+/// `(name, declared type)` of each column of `table`, in table order.
+async fn table_columns<'e, E>(executor: E, table: &str) -> Result<Vec<(String, String)>>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    sqlx::query(&format!("PRAGMA table_info({});", table))
+        .fetch_all(executor)
+        .await?
+        .into_iter()
+        .map(|row| Ok((row.try_get("name")?, row.try_get("type")?)))
+        .collect()
+}
+
+async fn has_column(pool: &SqlitePool, table: &str, column: &str) -> Result<bool> {
+    Ok(table_columns(pool, table)
+        .await?
+        .iter()
+        .any(|(name, _)| name == column))
+}
+
+/// Adds `column` to `table` when an older database predates it. New columns
+/// must be nullable (or carry a default) for `ALTER TABLE ADD COLUMN` to work.
+async fn add_column_if_missing(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+    decl: &str,
+) -> Result<()> {
+    if has_column(pool, table, column).await? {
+        return Ok(());
+    }
+
+    sqlx::query(&format!(
+        "ALTER TABLE {} ADD COLUMN {} {};",
+        table, column, decl
+    ))
+    .execute(pool)
+    .await
+    .with_context(|| format!("failed adding column {}.{}", table, column))?;
+    warn!(
+        "added missing column {}.{} to existing database",
+        table, column
+    );
+    Ok(())
+}
+
+/// Rebuilds `blocks` and `transactions` from a database that predates chain
+/// ids, tagging every existing row with `chain_id`. SQLite cannot change a
+/// primary key in place, so both tables are copied into the current layout
+/// and swapped in within one transaction. Foreign keys are switched off for
+/// the swap on a connection that is closed afterwards, never returned to
+/// the pool.
+async fn migrate_chain_id(pool: &SqlitePool, chain_id: i64) -> Result<()> {
+    let mut conn = pool.acquire().await?.detach();
+    sqlx::query("PRAGMA foreign_keys = OFF;")
+        .execute(&mut conn)
+        .await?;
+    let mut txn = conn.begin().await?;
+    sqlx::query(&blocks_table_sql("blocks_new"))
+        .execute(&mut *txn)
+        .await?;
+    sqlx::query(&transactions_table_sql("transactions_new"))
+        .execute(&mut *txn)
+        .await?;
+    copy_shared_columns(&mut txn, "blocks", "blocks_new", Some(chain_id)).await?;
+    copy_shared_columns(&mut txn, "transactions", "transactions_new", Some(chain_id)).await?;
+    for sql in [
+        "DROP TABLE transactions;",
+        "DROP TABLE blocks;",
+        "ALTER TABLE blocks_new RENAME TO blocks;",
+        "ALTER TABLE transactions_new RENAME TO transactions;",
+    ] {
+        sqlx::query(sql).execute(&mut *txn).await?;
+    }
+    txn.commit().await?;
+    conn.close().await?;
+    warn!(
+        "keyed existing blocks and transactions by chain id {}",
+        chain_id
+    );
+    Ok(())
+}
+
+/// Rebuilds `transactions` when a [`WEI_TEXT_COLUMNS`] column was declared
+/// with another type, so wei amounts above i64 are kept as exact text from
+/// then on. Values already stored are converted by the TEXT column affinity;
+/// amounts an INTEGER column had already rounded to REAL stay rounded.
+async fn migrate_wei_columns(pool: &SqlitePool) -> Result<()> {
+    let columns = table_columns(pool, "transactions").await?;
+    let retyped: Vec<&str> = WEI_TEXT_COLUMNS
+        .iter()
+        .copied()
+        .filter(|wei| {
+            columns
+                .iter()
+                .any(|(name, ty)| name == wei && !ty.eq_ignore_ascii_case("TEXT"))
+        })
+        .collect();
+    if retyped.is_empty() {
+        return Ok(());
+    }
+
+    // Same connection handling as `migrate_chain_id`.
+    let mut conn = pool.acquire().await?.detach();
+    sqlx::query("PRAGMA foreign_keys = OFF;")
+        .execute(&mut conn)
+        .await?;
+    let mut txn = conn.begin().await?;
+    sqlx::query(&transactions_table_sql("transactions_new"))
+        .execute(&mut *txn)
+        .await?;
+    copy_shared_columns(&mut txn, "transactions", "transactions_new", None).await?;
+    for sql in [
+        "DROP TABLE transactions;",
+        "ALTER TABLE transactions_new RENAME TO transactions;",
+    ] {
+        sqlx::query(sql).execute(&mut *txn).await?;
+    }
+    txn.commit().await?;
+    conn.close().await?;
+    warn!(
+        "rebuilt transactions to store {} as TEXT",
+        retyped.join(", ")
+    );
+    Ok(())
+}
+
+/// Rebuilds each of the [`TX_DEPENDENT_TABLES`] that predates chain ids.
+/// Existing rows take the chain id of the stored transaction they refer to,
+/// or `fallback` when it is gone (e.g. already pruned). Connections are
+/// handled as in [`migrate_chain_id`].
+async fn migrate_dependent_chain_id(pool: &SqlitePool, fallback: i64) -> Result<()> {
+    let mut legacy = Vec::new();
+    for (table, hash_column, create) in TX_DEPENDENT_TABLES {
+        if !has_column(pool, table, "chain_id").await? {
+            legacy.push((*table, *hash_column, create));
+        }
+    }
+    if legacy.is_empty() {
+        return Ok(());
+    }
+
+    let mut conn = pool.acquire().await?.detach();
+    sqlx::query("PRAGMA foreign_keys = OFF;")
+        .execute(&mut conn)
+        .await?;
+    let mut txn = conn.begin().await?;
+    for (table, hash_column, create) in &legacy {
+        let rebuilt = format!("{}_new", table);
+        sqlx::query(&create(&rebuilt)).execute(&mut *txn).await?;
+        let cols = shared_columns(&mut txn, table, &rebuilt, true).await?;
+        let sql = format!(
+            "INSERT INTO {to} (chain_id, {cols}) \
+             SELECT COALESCE((SELECT MIN(t.chain_id) FROM transactions t \
+             WHERE t.hash = {from}.{hash}), ?1), {cols} FROM {from};",
+            to = rebuilt,
+            cols = cols,
+            from = table,
+            hash = hash_column,
+        );
+        sqlx::query(&sql).bind(fallback).execute(&mut *txn).await?;
+        sqlx::query(&format!("DROP TABLE {};", table))
+            .execute(&mut *txn)
+            .await?;
+        sqlx::query(&format!("ALTER TABLE {} RENAME TO {};", rebuilt, table))
+            .execute(&mut *txn)
+            .await?;
+    }
+    txn.commit().await?;
+    conn.close().await?;
+    let tables: Vec<&str> = legacy.iter().map(|(table, _, _)| *table).collect();
+    warn!("keyed existing {} by chain id", tables.join(", "));
+    Ok(())
+}
+
+/// Comma-separated columns `from` and `to` have in common, leaving out
+/// `chain_id` when `skip_chain_id` is set.
+async fn shared_columns(
+    conn: &mut SqliteConnection,
+    from: &str,
+    to: &str,
+    skip_chain_id: bool,
+) -> Result<String> {
+    let target = table_columns(&mut *conn, to).await?;
+    Ok(table_columns(&mut *conn, from)
+        .await?
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| !skip_chain_id || name != "chain_id")
+        .filter(|name| target.iter().any(|(col, _)| col == name))
+        .collect::<Vec<_>>()
+        .join(", "))
+}
+
+/// Copies every row of `from` into `to` over the columns both tables have,
+/// tagging them with `chain_id` when given (and `from` has none).
+async fn copy_shared_columns(
+    conn: &mut SqliteConnection,
+    from: &str,
+    to: &str,
+    chain_id: Option<i64>,
+) -> Result<()> {
+    let cols = shared_columns(&mut *conn, from, to, chain_id.is_some()).await?;
+    let sql = match chain_id {
+        Some(_) => format!(
+            "INSERT INTO {to} (chain_id, {cols}) SELECT ?1, {cols} FROM {from};",
+            to = to,
+            cols = cols,
+            from = from
+        ),
+        None => format!(
+            "INSERT INTO {to} ({cols}) SELECT {cols} FROM {from};",
+            to = to,
+            cols = cols,
+            from = from
+        ),
+    };
+    sqlx::query(&sql).bind(chain_id).execute(&mut *conn).await?;
     Ok(())
 }
 
 pub async fn insert_block(pool: &SqlitePool, block: &BlockInfo) -> Result<()> {
     sqlx::query(
         r#"
-        INSERT INTO blocks (block_number, block_hash, timestamp)
-        VALUES (?1, ?2, ?3)
-        ON CONFLICT(block_number) DO NOTHING;
+        INSERT INTO blocks (block_number, block_hash, timestamp, base_fee_wei, chain_id)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ON CONFLICT(chain_id, block_number) DO NOTHING;
+        "#,
+    )
+    .bind(block.number)
+    .bind(&block.hash)
+    .bind(block.timestamp)
+    .bind(&block.base_fee_wei)
+    .bind(block.chain_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Inserts `block`, or fills in the stored row's hash/timestamp/base fee when
+/// they are placeholders (empty hash, zero timestamp, NULL fee). Real stored
+/// values are never replaced, and placeholder values in `block` never
+/// overwrite real ones.
+pub async fn upsert_block(pool: &SqlitePool, block: &BlockInfo) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO blocks (block_number, block_hash, timestamp, base_fee_wei, chain_id)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ON CONFLICT(chain_id, block_number) DO UPDATE SET
+            block_hash = CASE
+                WHEN blocks.block_hash = '' THEN excluded.block_hash
+                ELSE blocks.block_hash
+            END,
+            timestamp = CASE
+                WHEN blocks.timestamp = 0 THEN excluded.timestamp
+                ELSE blocks.timestamp
+            END,
+            base_fee_wei = COALESCE(blocks.base_fee_wei, excluded.base_fee_wei);
         "#,
     )
     .bind(block.number)
     .bind(&block.hash)
     .bind(block.timestamp)
+    .bind(&block.base_fee_wei)
+    .bind(block.chain_id)
     .execute(pool)
     .await?;
     Ok(())
 }
 
+pub async fn get_block(pool: &SqlitePool, chain_id: i64, number: i64) -> Result<Option<BlockInfo>> {
+    let row = sqlx::query(
+        r#"
+        SELECT block_number, block_hash, timestamp, base_fee_wei
+        FROM blocks
+        WHERE chain_id = ?1 AND block_number = ?2;
+        "#,
+    )
+    .bind(chain_id)
+    .bind(number)
+    .fetch_optional(pool)
+    .await?;
+
+    row.map(|row| {
+        Ok(BlockInfo {
+            number: row.try_get("block_number")?,
+            hash: row.try_get("block_hash")?,
+            timestamp: row.try_get("timestamp")?,
+            base_fee_wei: row.try_get("base_fee_wei")?,
+            chain_id,
+        })
+    })
+    .transpose()
+}
+
+/// Swaps the stored block at `block.number` on `block.chain_id` for `block`
+/// after a reorg. The old block, its transactions (with their metadata), logs
+/// and token transfers are deleted and `block` inserted in one database
+/// transaction, so readers never see the height empty. Rows of other chains
+/// at the same height are kept. Returns the rows removed.
+pub async fn replace_block(pool: &SqlitePool, block: &BlockInfo) -> Result<RowCounts> {
+    let mut txn = pool.begin().await?;
+    for sql in [
+        "DELETE FROM tx_metadata WHERE chain_id = ?2 AND hash IN \
+         (SELECT hash FROM transactions WHERE chain_id = ?2 AND block_number = ?1);",
+        "DELETE FROM logs WHERE chain_id = ?2 AND block_number = ?1;",
+        "DELETE FROM token_transfers WHERE chain_id = ?2 AND block_number = ?1;",
+    ] {
+        sqlx::query(sql)
+            .bind(block.number)
+            .bind(block.chain_id)
+            .execute(&mut *txn)
+            .await?;
+    }
+    let transactions =
+        sqlx::query("DELETE FROM transactions WHERE chain_id = ?2 AND block_number = ?1;")
+            .bind(block.number)
+            .bind(block.chain_id)
+            .execute(&mut *txn)
+            .await?
+            .rows_affected();
+    let blocks = sqlx::query("DELETE FROM blocks WHERE chain_id = ?2 AND block_number = ?1;")
+        .bind(block.number)
+        .bind(block.chain_id)
+        .execute(&mut *txn)
+        .await?
+        .rows_affected();
+    sqlx::query(
+        r#"
+        INSERT INTO blocks (block_number, block_hash, timestamp, base_fee_wei, chain_id)
+        VALUES (?1, ?2, ?3, ?4, ?5);
+        "#,
+    )
+    .bind(block.number)
+    .bind(&block.hash)
+    .bind(block.timestamp)
+    .bind(&block.base_fee_wei)
+    .bind(block.chain_id)
+    .execute(&mut *txn)
+    .await?;
+    txn.commit().await?;
+    Ok(RowCounts {
+        blocks: blocks as i64,
+        transactions: transactions as i64,
+    })
+}
+
 pub async fn insert_transactions(pool: &SqlitePool, txs: &[NormalizedTx]) -> Result<()> {
+    insert_transactions_with_options(pool, txs, &StorageOptions::default()).await
+}
+
+pub async fn insert_transactions_with_options(
+    pool: &SqlitePool,
+    txs: &[NormalizedTx],
+    options: &StorageOptions,
+) -> Result<()> {
     let mut txn = pool.begin().await?;
 
     for tx in txs {
+        // Compressed calldata goes in as a BLOB in place of the hex text.
+        let compressed = if options.compress_input {
+            tx.input_data.as_deref().and_then(compress_input)
+        } else {
+            None
+        };
         sqlx::query(
             r#"
             INSERT INTO transactions (
                 hash, from_addr, to_addr, value_wei, gas, gas_price_wei,
-                max_fee_per_gas_wei, nonce, block_number, timestamp, status
+                max_fee_per_gas_wei, nonce, block_number, timestamp, status,
+                input_data, input_compressed, flags, tx_index, max_priority_fee_per_gas_wei,
+                input_selector, chain_id, tx_type, input_size
             )
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
-            ON CONFLICT(hash) DO NOTHING;
+            VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, COALESCE(?13, ?12), ?14, ?15, ?16, ?17,
+                ?18, ?19, ?20, ?21
+            )
+            ON CONFLICT(chain_id, hash) DO UPDATE
+                SET status = COALESCE(excluded.status, transactions.status);
             "#,
         )
         .bind(&tx.hash)
@@ -147,6 +904,16 @@ pub async fn insert_transactions(pool: &SqlitePool, txs: &[NormalizedTx]) -> Res
         .bind(tx.block_number)
         .bind(tx.timestamp)
         .bind(&tx.status)
+        .bind(&tx.input_data)
+        .bind(compressed.as_deref())
+        .bind(compressed.is_some())
+        .bind(TxFlags::from_tx(tx, &options.dex_routers).0)
+        .bind(tx.tx_index)
+        .bind(&tx.max_priority_fee_per_gas_wei)
+        .bind(tx.input_selector())
+        .bind(tx.chain_id)
+        .bind(tx.tx_type)
+        .bind(tx.input_size)
         .execute(&mut *txn)
         .await?;
     }
@@ -155,38 +922,70 @@ pub async fn insert_transactions(pool: &SqlitePool, txs: &[NormalizedTx]) -> Res
     Ok(())
 }
 
-pub async fn get_recent_transactions(pool: &SqlitePool, limit: i64) -> Result<Vec<NormalizedTx>> {
-    #[derive(FromRow)]
-    struct TxRow {
-        hash: String,
-        from_addr: String,
-        to_addr: Option<String>,
-        value_wei: String,
-        gas: i64,
-        gas_price_wei: Option<String>,
-        max_fee_per_gas_wei: Option<String>,
-        nonce: i64,
-        block_number: Option<i64>,
-        timestamp: Option<i64>,
-        status: Option<String>,
+/// zstd-compresses `0x`-prefixed hex calldata as raw bytes. Returns `None` when
+/// the input is not hex or compression would not save space (short payloads).
+fn compress_input(input_data: &str) -> Option<Vec<u8>> {
+    let raw = hex::decode(input_data.strip_prefix("0x")?).ok()?;
+    let compressed = zstd::bulk::compress(&raw, 3).ok()?;
+    (compressed.len() < input_data.len()).then_some(compressed)
+}
+
+fn decompress_input(blob: &[u8]) -> Option<String> {
+    // Calldata is bounded by the block gas limit, far below this cap.
+    const MAX_INPUT_BYTES: usize = 16 * 1024 * 1024;
+    match zstd::bulk::decompress(blob, MAX_INPUT_BYTES) {
+        Ok(raw) => Some(format!("0x{}", hex::encode(raw))),
+        Err(err) => {
+            warn!("failed to decompress stored calldata: {}", err);
+            None
+        }
     }
+}
 
-    let rows = sqlx::query_as::<_, TxRow>(
-        r#"
-        SELECT hash, from_addr, to_addr, value_wei, gas, gas_price_wei,
-               max_fee_per_gas_wei, nonce, block_number, timestamp, status
-        FROM transactions
-        ORDER BY COALESCE(timestamp, 0) DESC
-        LIMIT ?1;
-        "#,
-    )
-    .bind(limit)
-    .fetch_all(pool)
-    .await?;
+#[derive(FromRow)]
+struct TxRow {
+    hash: String,
+    from_addr: String,
+    to_addr: Option<String>,
+    value_wei: String,
+    gas: i64,
+    gas_price_wei: Option<String>,
+    max_fee_per_gas_wei: Option<String>,
+    max_priority_fee_per_gas_wei: Option<String>,
+    nonce: i64,
+    block_number: Option<i64>,
+    timestamp: Option<i64>,
+    status: Option<String>,
+    /// Hex text, or zstd bytes when `input_compressed` is set.
+    input_data: Option<Vec<u8>>,
+    input_compressed: bool,
+    tx_index: Option<i64>,
+    chain_id: i64,
+    tx_type: Option<i64>,
+    input_size: Option<i64>,
+    flags: i64,
+    input_selector: Option<String>,
+}
 
-    Ok(rows
-        .into_iter()
-        .map(|row| NormalizedTx {
+/// Column list matching [`TxRow`], shared by every query that loads transactions.
+const TX_COLUMNS: &str = "hash, from_addr, to_addr, value_wei, gas, gas_price_wei, \
+     max_fee_per_gas_wei, nonce, block_number, timestamp, status, input_data, \
+     input_compressed, tx_index, max_priority_fee_per_gas_wei, chain_id, tx_type, input_size, \
+     flags, input_selector";
+
+impl From<TxRow> for StoredTx {
+    fn from(mut row: TxRow) -> Self {
+        StoredTx {
+            flags: TxFlags(row.flags),
+            input_selector: row.input_selector.take(),
+            tx: row.into(),
+        }
+    }
+}
+
+impl From<TxRow> for NormalizedTx {
+    fn from(row: TxRow) -> Self {
+        NormalizedTx {
             hash: row.hash,
             from: row.from_addr,
             to: row.to_addr,
@@ -194,114 +993,2235 @@ pub async fn get_recent_transactions(pool: &SqlitePool, limit: i64) -> Result<Ve
             gas: row.gas,
             gas_price_wei: row.gas_price_wei,
             max_fee_per_gas_wei: row.max_fee_per_gas_wei,
+            max_priority_fee_per_gas_wei: row.max_priority_fee_per_gas_wei,
             nonce: row.nonce,
             block_number: row.block_number,
             timestamp: row.timestamp,
             status: row.status,
-        })
-        .collect())
+            input_data: row.input_data.and_then(|data| {
+                if row.input_compressed {
+                    decompress_input(&data)
+                } else {
+                    String::from_utf8(data).ok()
+                }
+            }),
+            tx_index: row.tx_index,
+            chain_id: row.chain_id,
+            tx_type: row.tx_type,
+            input_size: row.input_size,
+        }
+    }
 }
 
-pub async fn get_top_senders(pool: &SqlitePool, limit: i64) -> Result<Vec<TopSender>> {
-    #[derive(FromRow)]
-    struct Row {
-        address: String,
-        count: i64,
+/// How [`get_recent_transactions`] ranks rows, newest first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RecentOrder {
+    /// By block timestamp, then hash so ties page deterministically.
+    #[default]
+    Timestamp,
+    /// Strict chain order: block number, then position in the block, then
+    /// hash for pending rows and rows stored without a position.
+    Block,
+}
+
+impl FromStr for RecentOrder {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "timestamp" => Ok(Self::Timestamp),
+            "block" => Ok(Self::Block),
+            other => Err(format!("unknown recent order {:?}", other)),
+        }
     }
+}
 
-    let rows = sqlx::query_as::<_, Row>(
-        r#"
-        SELECT from_addr as address, COUNT(*) as count
-        FROM transactions
-        GROUP BY from_addr
-        ORDER BY count DESC
-        LIMIT ?1;
-        "#,
-    )
-    .bind(limit)
-    .fetch_all(pool)
-    .await?;
+/// Keyset position in [`RecentOrder::Timestamp`] order: rows strictly after
+/// `(COALESCE(timestamp, 0), hash)` descending. Clients see it as an opaque
+/// `"<timestamp>:<hash>"` token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentCursor {
+    pub timestamp: i64,
+    pub hash: String,
+}
 
-    Ok(rows
-        .into_iter()
-        .map(|row| TopSender {
-            address: row.address,
-            count: row.count,
+impl RecentCursor {
+    /// Cursor continuing after `tx`, the last row of a page.
+    pub fn after(tx: &NormalizedTx) -> Self {
+        Self {
+            timestamp: tx.timestamp.unwrap_or(0),
+            hash: tx.hash.clone(),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        format!("{}:{}", self.timestamp, self.hash)
+    }
+}
+
+impl FromStr for RecentCursor {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid cursor {:?}", raw);
+        let (timestamp, hash) = raw.split_once(':').ok_or_else(invalid)?;
+        if hash.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            timestamp: timestamp.parse().map_err(|_| invalid())?,
+            hash: hash.to_string(),
         })
-        .collect())
+    }
 }
 
-pub async fn get_gas_stats(pool: &SqlitePool, last_n_blocks: i64) -> Result<Option<GasStats>> {
-    #[derive(FromRow)]
-    struct Row {
-        min_gas_price: Option<i64>,
-        max_gas_price: Option<i64>,
-        avg_gas_price: Option<f64>,
+/// Optional filters for [`get_recent_transactions`]; the default matches every row.
+#[derive(Debug, Clone, Default)]
+pub struct RecentTxFilter {
+    /// Lowercased address matched against either `from_addr` or `to_addr`.
+    pub address: Option<String>,
+    /// Only rows not yet mined (`block_number IS NULL`).
+    pub pending_only: bool,
+    /// Lowercased addresses; `to_addr` must be one of them.
+    pub to_any: Option<Vec<String>>,
+    /// `Some(true)` keeps contract calls, `Some(false)` plain transfers. Rows
+    /// stored before calldata was recorded match neither.
+    pub has_input: Option<bool>,
+    /// Bitmask of [`TxFlags`]; rows with any of these bits set match.
+    pub flags_any: Option<i64>,
+    /// Lowercase `0x` + 8 hex method selector. Matched against the column
+    /// filled at insert time, so it also finds compressed calldata; rows
+    /// stored before selectors were recorded never match.
+    pub selector: Option<String>,
+    /// Only rows stored for this chain id.
+    pub chain_id: Option<i64>,
+    /// EIP-2718 transaction type; rows stored before it was recorded never match.
+    pub tx_type: Option<i64>,
+    /// Canonical decimal wei (no leading zeros); smaller values are skipped.
+    pub min_value_wei: Option<String>,
+    pub order: RecentOrder,
+    /// Resume after this cursor; only meaningful with [`RecentOrder::Timestamp`].
+    pub before: Option<RecentCursor>,
+}
+
+impl RecentTxFilter {
+    /// True when no filter is set, i.e. the query is a plain "latest N".
+    pub fn is_unfiltered(&self) -> bool {
+        self.address.is_none()
+            && !self.pending_only
+            && self.to_any.is_none()
+            && self.has_input.is_none()
+            && self.flags_any.is_none()
+            && self.selector.is_none()
+            && self.chain_id.is_none()
+            && self.tx_type.is_none()
+            && self.min_value_wei.is_none()
+            && self.order == RecentOrder::Timestamp
+            && self.before.is_none()
     }
+}
 
-    let row = sqlx::query_as::<_, Row>(
-        // Filter to numeric strings of reasonable length before casting to avoid overflow.
+pub async fn get_recent_transactions(
+    pool: &SqlitePool,
+    limit: i64,
+    filter: &RecentTxFilter,
+) -> Result<Vec<NormalizedTx>> {
+    let rows = get_recent_stored_transactions(pool, limit, filter).await?;
+    Ok(rows.into_iter().map(|stored| stored.tx).collect())
+}
+
+/// [`get_recent_transactions`] with the stored flags and selector of each row.
+pub async fn get_recent_stored_transactions(
+    pool: &SqlitePool,
+    limit: i64,
+    filter: &RecentTxFilter,
+) -> Result<Vec<StoredTx>> {
+    let mut qb = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT {} FROM transactions WHERE 1 = 1",
+        TX_COLUMNS
+    ));
+    if let Some(address) = &filter.address {
+        qb.push(" AND (from_addr = ")
+            .push_bind(address)
+            .push(" OR to_addr = ")
+            .push_bind(address)
+            .push(")");
+    }
+    if filter.pending_only {
+        qb.push(" AND block_number IS NULL");
+    }
+    if let Some(to_any) = &filter.to_any {
+        if to_any.is_empty() {
+            return Ok(Vec::new());
+        }
+        qb.push(" AND to_addr IN (");
+        let mut list = qb.separated(", ");
+        for addr in to_any {
+            list.push_bind(addr);
+        }
+        qb.push(")");
+    }
+    match filter.has_input {
+        Some(true) => {
+            qb.push(" AND input_data IS NOT NULL AND input_data NOT IN ('', '0x')");
+        }
+        Some(false) => {
+            qb.push(" AND input_data IN ('', '0x')");
+        }
+        None => {}
+    }
+    if let Some(mask) = filter.flags_any {
+        qb.push(" AND flags & ").push_bind(mask).push(" != 0");
+    }
+    if let Some(selector) = &filter.selector {
+        qb.push(" AND input_selector = ").push_bind(selector);
+    }
+    if let Some(chain_id) = filter.chain_id {
+        qb.push(" AND chain_id = ").push_bind(chain_id);
+    }
+    if let Some(tx_type) = filter.tx_type {
+        qb.push(" AND tx_type = ").push_bind(tx_type);
+    }
+    if let Some(min) = &filter.min_value_wei {
+        // Canonical decimal strings compare numerically by (length, text).
+        qb.push(" AND value_wei NOT GLOB '*[^0-9]*' AND (LENGTH(value_wei) > ")
+            .push_bind(min.len() as i64)
+            .push(" OR (LENGTH(value_wei) = ")
+            .push_bind(min.len() as i64)
+            .push(" AND value_wei >= ")
+            .push_bind(min)
+            .push("))");
+    }
+    if let Some(cursor) = &filter.before {
+        qb.push(" AND (COALESCE(timestamp, 0), hash) < (")
+            .push_bind(cursor.timestamp)
+            .push(", ")
+            .push_bind(&cursor.hash)
+            .push(")");
+    }
+    // Pending rows sort last either way.
+    qb.push(match filter.order {
+        RecentOrder::Timestamp => " ORDER BY COALESCE(timestamp, 0) DESC, hash DESC",
+        RecentOrder::Block => {
+            " ORDER BY COALESCE(block_number, -1) DESC, COALESCE(tx_index, -1) DESC, hash DESC"
+        }
+    });
+    qb.push(" LIMIT ").push_bind(limit);
+
+    let rows = qb.build_query_as::<TxRow>().fetch_all(pool).await?;
+    Ok(rows.into_iter().map(StoredTx::from).collect())
+}
+
+/// Canonical form of a decimal wei amount (digits only, no leading zeros),
+/// as compared by [`RecentTxFilter::min_value_wei`]. `None` if not decimal.
+pub fn canonical_wei(raw: &str) -> Option<String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() || !trimmed.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let canonical = trimmed.trim_start_matches('0');
+    Some(if canonical.is_empty() { "0" } else { canonical }.to_string())
+}
+
+/// Newest transactions worth at least `min_wei`. Values are compared as
+/// decimal strings by `(length, text)`, so amounts beyond `u128` still order
+/// correctly; rows with a non-numeric `value_wei` never match.
+pub async fn get_transactions_min_value(
+    pool: &SqlitePool,
+    min_wei: &str,
+    limit: i64,
+) -> Result<Vec<NormalizedTx>> {
+    let min_value_wei =
+        canonical_wei(min_wei).ok_or_else(|| anyhow!("invalid wei amount: {:?}", min_wei))?;
+    let filter = RecentTxFilter {
+        min_value_wei: Some(min_value_wei),
+        ..Default::default()
+    };
+    get_recent_transactions(pool, limit, &filter).await
+}
+
+/// Inclusive block-number bounds; `None` leaves that end open.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockRange {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+/// Yields stored transactions in chain order (block, then position, with
+/// pending rows last) straight off the SQLite cursor, so exports of any size
+/// run in constant memory. A bounded `range` leaves out pending rows.
+pub fn stream_transactions(
+    pool: &SqlitePool,
+    range: BlockRange,
+    limit: Option<u64>,
+) -> BoxStream<'_, Result<NormalizedTx>> {
+    static SQL: OnceLock<String> = OnceLock::new();
+    let sql = SQL.get_or_init(|| {
+        format!(
+            r#"
+            SELECT {}
+            FROM transactions
+            WHERE (?1 IS NULL OR block_number >= ?1)
+              AND (?2 IS NULL OR block_number <= ?2)
+            ORDER BY block_number IS NULL, block_number, tx_index, hash
+            LIMIT ?3;
+            "#,
+            TX_COLUMNS
+        )
+    });
+    // A negative LIMIT means no limit in SQLite.
+    let limit = limit.map_or(-1, |n| i64::try_from(n).unwrap_or(i64::MAX));
+    sqlx::query_as::<_, TxRow>(sql)
+        .bind(range.from)
+        .bind(range.to)
+        .bind(limit)
+        .fetch(pool)
+        .map_ok(NormalizedTx::from)
+        .map_err(anyhow::Error::from)
+        .boxed()
+}
+
+/// Highest `rowid` in `transactions`, or 0 when it is empty; the starting
+/// point for [`get_transactions_after_rowid`].
+pub async fn max_transaction_rowid(pool: &SqlitePool) -> Result<i64> {
+    let max: Option<i64> = sqlx::query_scalar("SELECT MAX(rowid) FROM transactions;")
+        .fetch_one(pool)
+        .await?;
+    Ok(max.unwrap_or(0))
+}
+
+#[derive(FromRow)]
+struct FeedRow {
+    rowid: i64,
+    #[sqlx(flatten)]
+    tx: TxRow,
+}
+
+/// Up to `limit` transactions stored after `rowid`, oldest first, each with
+/// its own rowid. Whichever process wrote them, new rows show up here.
+pub async fn get_transactions_after_rowid(
+    pool: &SqlitePool,
+    rowid: i64,
+    limit: i64,
+) -> Result<Vec<(i64, StoredTx)>> {
+    let sql = format!(
+        "SELECT rowid, {} FROM transactions WHERE rowid > ?1 ORDER BY rowid LIMIT ?2;",
+        TX_COLUMNS
+    );
+    let rows = sqlx::query_as::<_, FeedRow>(&sql)
+        .bind(rowid)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.rowid, StoredTx::from(row.tx)))
+        .collect())
+}
+
+/// The transaction stored under `hash` on `chain_id`. Across all chains
+/// (`None`), a hash stored on several of them resolves to the lowest chain id.
+pub async fn get_transaction_by_hash(
+    pool: &SqlitePool,
+    hash: &str,
+    chain_id: Option<i64>,
+) -> Result<Option<StoredTx>> {
+    let sql = format!(
+        "SELECT {} FROM transactions WHERE hash = ?1 AND (?2 IS NULL OR chain_id = ?2) \
+         ORDER BY chain_id LIMIT 1;",
+        TX_COLUMNS
+    );
+    let row = sqlx::query_as::<_, TxRow>(&sql)
+        .bind(hash)
+        .bind(chain_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(StoredTx::from))
+}
+
+/// Newest transactions sent or received by `address` (lowercased) on
+/// `chain_id`, or on every chain when `None`. The two halves of the union
+/// each seek their own index, unlike an `OR` filter.
+pub async fn get_transactions_by_address(
+    pool: &SqlitePool,
+    address: &str,
+    limit: i64,
+    chain_id: Option<i64>,
+) -> Result<Vec<StoredTx>> {
+    let sql = format!(
         r#"
-        SELECT
-            MIN(CAST(gas_price_wei AS INTEGER)) as min_gas_price,
-            MAX(CAST(gas_price_wei AS INTEGER)) as max_gas_price,
-            AVG(CAST(gas_price_wei AS INTEGER)) as avg_gas_price
+        SELECT * FROM (
+            SELECT {cols} FROM transactions
+            WHERE from_addr = ?1 AND (?3 IS NULL OR chain_id = ?3)
+            UNION
+            SELECT {cols} FROM transactions
+            WHERE to_addr = ?1 AND (?3 IS NULL OR chain_id = ?3)
+        )
+        ORDER BY timestamp IS NULL, timestamp DESC, hash DESC
+        LIMIT ?2;
+        "#,
+        cols = TX_COLUMNS
+    );
+    let rows = sqlx::query_as::<_, TxRow>(&sql)
+        .bind(address)
+        .bind(limit)
+        .bind(chain_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().map(StoredTx::from).collect())
+}
+
+/// Hashes per `IN (...)` query, well under SQLite's bound-parameter limit.
+const HASH_LOOKUP_CHUNK: usize = 500;
+
+/// Loads the stored rows among `hashes` (already normalized) on `chain_id`,
+/// or on every chain when it is `None`; unknown hashes are simply absent.
+/// Rows come back by chain id, in no particular order within a chain.
+pub async fn get_transactions_by_hashes(
+    pool: &SqlitePool,
+    hashes: &[String],
+    chain_id: Option<i64>,
+) -> Result<Vec<StoredTx>> {
+    let mut found = Vec::with_capacity(hashes.len());
+    for chunk in hashes.chunks(HASH_LOOKUP_CHUNK) {
+        let mut qb =
+            QueryBuilder::<Sqlite>::new(format!("SELECT {} FROM transactions WHERE (", TX_COLUMNS));
+        qb.push_bind(chain_id)
+            .push(" IS NULL OR chain_id = ")
+            .push_bind(chain_id)
+            .push(") AND hash IN (");
+        let mut list = qb.separated(", ");
+        for hash in chunk {
+            list.push_bind(hash);
+        }
+        qb.push(") ORDER BY chain_id");
+        let rows = qb.build_query_as::<TxRow>().fetch_all(pool).await?;
+        found.extend(rows.into_iter().map(StoredTx::from));
+    }
+    Ok(found)
+}
+
+/// Every stored transaction from `from` with `nonce` on `chain_id` (on every
+/// chain when `None`), in the order they were first stored (replacements
+/// seen by the sampler follow the original).
+pub async fn get_nonce_attempts(
+    pool: &SqlitePool,
+    from: &str,
+    nonce: i64,
+    chain_id: Option<i64>,
+) -> Result<Vec<NormalizedTx>> {
+    let sql = format!(
+        r#"
+        SELECT {} FROM transactions
+        WHERE from_addr = ?1 AND nonce = ?2 AND (?3 IS NULL OR chain_id = ?3)
+        ORDER BY rowid;
+        "#,
+        TX_COLUMNS
+    );
+    let rows = sqlx::query_as::<_, TxRow>(&sql)
+        .bind(from)
+        .bind(nonce)
+        .bind(chain_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().map(NormalizedTx::from).collect())
+}
+
+/// Returns up to `limit` transactions sent or received by `address` in
+/// chronological order, ending just before the `before` timestamp when given.
+/// Pending rows (no timestamp) sort last. `chain_id` keeps to one chain.
+pub async fn get_address_timeline(
+    pool: &SqlitePool,
+    address: &str,
+    limit: i64,
+    before: Option<i64>,
+    chain_id: Option<i64>,
+) -> Result<Vec<TimelineEntry>> {
+    // Take the newest `limit` rows, then flip them so the page reads oldest-first.
+    let sql = format!(
+        r#"
+        SELECT {}
+        FROM transactions
+        WHERE (from_addr = ?1 OR to_addr = ?1)
+          AND (?2 IS NULL OR timestamp < ?2)
+          AND (?4 IS NULL OR chain_id = ?4)
+        ORDER BY COALESCE(timestamp, 9223372036854775807) DESC, nonce DESC
+        LIMIT ?3;
+        "#,
+        TX_COLUMNS
+    );
+    let mut rows = sqlx::query_as::<_, TxRow>(&sql)
+        .bind(address)
+        .bind(before)
+        .bind(limit)
+        .bind(chain_id)
+        .fetch_all(pool)
+        .await?;
+    rows.reverse();
+
+    Ok(rows
+        .into_iter()
+        .map(|row| timeline_entry(address, row.into()))
+        .collect())
+}
+
+/// `tx` as seen from `address`: sent, received, or both.
+fn timeline_entry(address: &str, tx: NormalizedTx) -> TimelineEntry {
+    let sent = tx.from == address;
+    let received = tx.to.as_deref() == Some(address);
+    let direction = match (sent, received) {
+        (true, true) => Direction::SelfTransfer,
+        (true, false) => Direction::Sent,
+        _ => Direction::Received,
+    };
+    TimelineEntry { direction, tx }
+}
+
+/// Yields the transactions of the last `last_n_blocks` stored blocks of
+/// `chain_id` (of every chain when `None`) one row at a time, so
+/// aggregations over wide windows run in constant memory.
+pub fn stream_block_transactions(
+    pool: &SqlitePool,
+    last_n_blocks: i64,
+    chain_id: Option<i64>,
+) -> BoxStream<'_, Result<NormalizedTx>> {
+    static SQL: OnceLock<String> = OnceLock::new();
+    let sql = SQL.get_or_init(|| {
+        format!(
+            r#"
+            SELECT {}
+            FROM transactions
+            WHERE (chain_id, block_number) IN (
+                SELECT chain_id, block_number
+                FROM blocks
+                WHERE ?2 IS NULL OR chain_id = ?2
+                ORDER BY block_number DESC
+                LIMIT ?1
+            );
+            "#,
+            TX_COLUMNS
+        )
+    });
+    sqlx::query_as::<_, TxRow>(sql)
+        .bind(last_n_blocks)
+        .bind(chain_id)
+        .fetch(pool)
+        .map_ok(NormalizedTx::from)
+        .map_err(anyhow::Error::from)
+        .boxed()
+}
+
+/// Senders with the most stored transactions, on one chain or across all.
+pub async fn get_top_senders(
+    pool: &SqlitePool,
+    limit: i64,
+    chain_id: Option<i64>,
+) -> Result<Vec<TopSender>> {
+    #[derive(FromRow)]
+    struct Row {
+        address: String,
+        count: i64,
+    }
+
+    let rows = sqlx::query_as::<_, Row>(
+        r#"
+        SELECT from_addr as address, COUNT(*) as count
+        FROM transactions
+        WHERE ?2 IS NULL OR chain_id = ?2
+        GROUP BY from_addr
+        ORDER BY count DESC
+        LIMIT ?1;
+        "#,
+    )
+    .bind(limit)
+    .bind(chain_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| TopSender {
+            address: row.address,
+            count: row.count,
+        })
+        .collect())
+}
+
+/// Receivers with the most stored transactions, on one chain or across all.
+/// Contract creations (no `to_addr`) are left out.
+pub async fn get_top_receivers(
+    pool: &SqlitePool,
+    limit: i64,
+    chain_id: Option<i64>,
+) -> Result<Vec<TopSender>> {
+    let rows = sqlx::query_as::<_, (String, i64)>(
+        r#"
+        SELECT to_addr, COUNT(*) as count
         FROM transactions
-        WHERE gas_price_wei IS NOT NULL
-          AND gas_price_wei GLOB '[0-9]*'
-          AND LENGTH(gas_price_wei) <= 18
-          AND block_number IN (
-              SELECT block_number
+        WHERE to_addr IS NOT NULL AND (?2 IS NULL OR chain_id = ?2)
+        GROUP BY to_addr
+        ORDER BY count DESC
+        LIMIT ?1;
+        "#,
+    )
+    .bind(limit)
+    .bind(chain_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(address, count)| TopSender { address, count })
+        .collect())
+}
+
+#[derive(FromRow)]
+struct GasStatsRow {
+    min_gas_price: Option<i64>,
+    max_gas_price: Option<i64>,
+    avg_gas_price: Option<f64>,
+    p25_gas_price: Option<i64>,
+    p50_gas_price: Option<i64>,
+    p75_gas_price: Option<i64>,
+    p90_gas_price: Option<i64>,
+}
+
+impl GasStatsRow {
+    fn into_stats(self) -> Option<GasStats> {
+        match (self.min_gas_price, self.max_gas_price, self.avg_gas_price) {
+            (Some(min), Some(max), Some(avg)) => Some(GasStats {
+                min,
+                max,
+                avg,
+                // Present whenever min/max are: each rank threshold is <= n.
+                p25: self.p25_gas_price.unwrap_or(min),
+                p50: self.p50_gas_price.unwrap_or(min),
+                p75: self.p75_gas_price.unwrap_or(max),
+                p90: self.p90_gas_price.unwrap_or(max),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Gas-price stats over the last `last_n_blocks` stored blocks of `chain_id`,
+/// or of every chain when it is `None`.
+pub async fn get_gas_stats(
+    pool: &SqlitePool,
+    last_n_blocks: i64,
+    chain_id: Option<i64>,
+) -> Result<Option<GasStats>> {
+    let sql = format!(
+        "{};",
+        SqlDialect::Sqlite.gas_stats_select(
+            r#"AND (chain_id, block_number) IN (
+              SELECT chain_id, block_number
+              FROM blocks
+              WHERE ?2 IS NULL OR chain_id = ?2
+              ORDER BY block_number DESC
+              LIMIT ?1
+          )"#
+        )
+    );
+    let row = sqlx::query_as::<_, GasStatsRow>(&sql)
+        .bind(last_n_blocks)
+        .bind(chain_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(row.into_stats())
+}
+
+/// Transaction counts per `bucket_gwei`-wide gas-price range over the last
+/// `last_n_blocks` blocks of `chain_id` (or of every chain), cheapest bucket
+/// first and empty buckets omitted. Prices follow [`get_gas_stats`], so
+/// unparsable and oversized ones are skipped the same way.
+pub async fn get_gas_histogram(
+    pool: &SqlitePool,
+    last_n_blocks: i64,
+    bucket_gwei: i64,
+    chain_id: Option<i64>,
+) -> Result<Vec<GasHistogramBucket>> {
+    let bucket_gwei = bucket_gwei.max(1);
+    let sql = format!(
+        "{};",
+        SqlDialect::Sqlite.gas_histogram_select(
+            r#"AND (chain_id, block_number) IN (
+              SELECT chain_id, block_number
               FROM blocks
+              WHERE ?3 IS NULL OR chain_id = ?3
               ORDER BY block_number DESC
               LIMIT ?1
-          );
+          )"#,
+            "?2"
+        )
+    );
+    let rows: Vec<(i64, i64)> = sqlx::query_as(&sql)
+        .bind(last_n_blocks)
+        .bind(bucket_gwei.saturating_mul(1_000_000_000))
+        .bind(chain_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(bucket, count)| GasHistogramBucket {
+            bucket_gwei: bucket * bucket_gwei,
+            count,
+        })
+        .collect())
+}
+
+/// Gas stats for mined transactions whose block timestamp falls within
+/// `window_secs` centered on `timestamp`, on one chain or across all.
+pub async fn get_gas_stats_at(
+    pool: &SqlitePool,
+    timestamp: i64,
+    window_secs: i64,
+    chain_id: Option<i64>,
+) -> Result<Option<GasStats>> {
+    let half = window_secs.max(0) / 2;
+    let sql = format!(
+        "{};",
+        SqlDialect::Sqlite
+            .gas_stats_select("AND timestamp BETWEEN ?1 AND ?2 AND (?3 IS NULL OR chain_id = ?3)")
+    );
+    let row = sqlx::query_as::<_, GasStatsRow>(&sql)
+        .bind(timestamp.saturating_sub(half))
+        .bind(timestamp.saturating_add(half))
+        .bind(chain_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(row.into_stats())
+}
+
+/// Base fee min/max/avg over the last `last_n_blocks` stored blocks of
+/// `chain_id` (or of every chain). Blocks without a base fee (pre-London, or
+/// stored before it was tracked) are skipped; `None` when none of them has one.
+pub async fn get_base_fee_stats(
+    pool: &SqlitePool,
+    last_n_blocks: i64,
+    chain_id: Option<i64>,
+) -> Result<Option<BaseFeeStats>> {
+    let row: (Option<i64>, Option<i64>, Option<f64>, i64) = sqlx::query_as(
+        r#"
+        SELECT
+            MIN(CAST(base_fee_wei AS INTEGER)),
+            MAX(CAST(base_fee_wei AS INTEGER)),
+            AVG(CAST(base_fee_wei AS INTEGER)),
+            COUNT(*)
+        FROM (
+            SELECT base_fee_wei
+            FROM blocks
+            WHERE ?2 IS NULL OR chain_id = ?2
+            ORDER BY block_number DESC
+            LIMIT ?1
+        )
+        WHERE base_fee_wei IS NOT NULL
+          AND base_fee_wei GLOB '[0-9]*'
+          AND LENGTH(base_fee_wei) <= 18;
         "#,
     )
     .bind(last_n_blocks)
+    .bind(chain_id)
     .fetch_one(pool)
     .await?;
 
-    match (row.min_gas_price, row.max_gas_price, row.avg_gas_price) {
-        (Some(min), Some(max), Some(avg)) => Ok(Some(GasStats { min, max, avg })),
-        _ => Ok(None),
+    Ok(match row {
+        (Some(min), Some(max), Some(avg), blocks) => Some(BaseFeeStats {
+            min,
+            max,
+            avg,
+            blocks,
+        }),
+        _ => None,
+    })
+}
+
+/// Newest stored blocks first, each with its stored transaction count, on
+/// one chain or across all.
+pub async fn get_recent_blocks(
+    pool: &SqlitePool,
+    limit: i64,
+    chain_id: Option<i64>,
+) -> Result<Vec<BlockSummary>> {
+    let rows: Vec<(i64, String, i64, Option<String>, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT b.block_number, b.block_hash, b.timestamp, b.base_fee_wei, b.chain_id,
+               COUNT(t.hash)
+        FROM (
+            SELECT chain_id, block_number, block_hash, timestamp, base_fee_wei
+            FROM blocks
+            WHERE ?2 IS NULL OR chain_id = ?2
+            ORDER BY block_number DESC
+            LIMIT ?1
+        ) b
+        LEFT JOIN transactions t
+            ON t.chain_id = b.chain_id AND t.block_number = b.block_number
+        GROUP BY b.chain_id, b.block_number, b.block_hash, b.timestamp, b.base_fee_wei
+        ORDER BY b.block_number DESC, b.chain_id;
+        "#,
+    )
+    .bind(limit)
+    .bind(chain_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(number, hash, timestamp, base_fee_wei, chain_id, tx_count)| BlockSummary {
+                block: BlockInfo {
+                    number,
+                    hash,
+                    timestamp,
+                    base_fee_wei,
+                    chain_id,
+                },
+                tx_count,
+            },
+        )
+        .collect())
+}
+
+/// Highest stored block number of `chain_id` (of any chain when `None`), if
+/// any block has been ingested.
+pub async fn get_latest_block_number(
+    pool: &SqlitePool,
+    chain_id: Option<i64>,
+) -> Result<Option<i64>> {
+    let latest: Option<i64> = sqlx::query_scalar(
+        "SELECT MAX(block_number) FROM blocks WHERE ?1 IS NULL OR chain_id = ?1;",
+    )
+    .bind(chain_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(latest)
+}
+
+pub async fn count_rows(pool: &SqlitePool) -> Result<RowCounts> {
+    let row = sqlx::query(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM blocks) as blocks,
+            (SELECT COUNT(*) FROM transactions) as transactions;
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(RowCounts {
+        blocks: row.try_get("blocks")?,
+        transactions: row.try_get("transactions")?,
+    })
+}
+
+/// Applies both retention limits from `options`; each deletes the oldest rows,
+/// so running them in turn leaves whichever bound is tighter in effect.
+pub async fn apply_retention(
+    store: &dyn Storage,
+    options: &StorageOptions,
+    now: i64,
+) -> Result<RowCounts> {
+    let mut pruned = RowCounts::default();
+    if let Some(max_age) = options.retention_max_age_secs {
+        let by_age = store
+            .prune_before(now.saturating_sub(max_age as i64))
+            .await?;
+        pruned.blocks += by_age.blocks;
+        pruned.transactions += by_age.transactions;
+    }
+    if let Some(max_rows) = options.retention_max_rows {
+        let by_count = store.prune_to_count(max_rows).await?;
+        pruned.blocks += by_count.blocks;
+        pruned.transactions += by_count.transactions;
     }
+    Ok(pruned)
 }
 
-async fn verify_value_wei_column(pool: &SqlitePool) -> Result<()> {
-    let rows = sqlx::query("PRAGMA table_info(transactions);")
-        .fetch_all(pool)
+/// Deletes the dependent rows of the transactions `doomed` (a subquery
+/// selecting `chain_id, hash`, with at most one `?1` parameter bound to
+/// `param`).
+async fn delete_tx_dependents(
+    conn: &mut SqliteConnection,
+    doomed: &str,
+    param: Option<i64>,
+) -> Result<()> {
+    for (table, column, _) in TX_DEPENDENT_TABLES {
+        let sql = format!(
+            "DELETE FROM {} WHERE (chain_id, {}) IN ({});",
+            table, column, doomed
+        );
+        let mut query = sqlx::query(&sql);
+        if let Some(param) = param {
+            query = query.bind(param);
+        }
+        query.execute(&mut *conn).await?;
+    }
+    Ok(())
+}
+
+/// Deletes all but the newest `max` transactions (with their metadata, logs,
+/// token transfers and sample arrivals), ranking pending rows newest and
+/// mined rows by timestamp then chain position, plus blocks older than the
+/// oldest block still referenced.
+pub async fn prune_to_count(pool: &SqlitePool, max: u64) -> Result<RowCounts> {
+    let mut txn = pool.begin().await?;
+    // The ranking sorts the whole table, so it runs once into a scratch table
+    // that the dependent deletes then share.
+    sqlx::query(
+        "CREATE TEMP TABLE pruned_txs \
+         (id INTEGER PRIMARY KEY, chain_id INTEGER NOT NULL, hash TEXT NOT NULL);",
+    )
+    .execute(&mut *txn)
+    .await?;
+    sqlx::query(
+        r#"
+        INSERT INTO pruned_txs (id, chain_id, hash)
+        SELECT rowid, chain_id, hash
+        FROM transactions
+        ORDER BY timestamp IS NULL DESC, timestamp DESC, block_number DESC,
+            tx_index DESC, rowid DESC
+        LIMIT -1 OFFSET ?1;
+        "#,
+    )
+    .bind(i64::try_from(max).unwrap_or(i64::MAX))
+    .execute(&mut *txn)
+    .await?;
+    delete_tx_dependents(&mut txn, "SELECT chain_id, hash FROM pruned_txs", None).await?;
+    let transactions =
+        sqlx::query("DELETE FROM transactions WHERE rowid IN (SELECT id FROM pruned_txs);")
+            .execute(&mut *txn)
+            .await?
+            .rows_affected();
+    sqlx::query("DROP TABLE pruned_txs;")
+        .execute(&mut *txn)
+        .await?;
+    let blocks = if transactions > 0 {
+        sqlx::query(
+            r#"
+            DELETE FROM blocks
+            WHERE block_number < (
+                SELECT MIN(t.block_number) FROM transactions t
+                WHERE t.chain_id = blocks.chain_id AND t.block_number IS NOT NULL
+            );
+            "#,
+        )
+        .execute(&mut *txn)
+        .await?
+        .rows_affected()
+    } else {
+        0
+    };
+    txn.commit().await?;
+    Ok(RowCounts {
+        blocks: blocks as i64,
+        transactions: transactions as i64,
+    })
+}
+
+/// Range deletes used by [`prune_before`]; both must stay index-driven (see
+/// the plan test below). Blocks still referenced by a transaction are kept so
+/// the foreign key never dangles.
+const PRUNE_TRANSACTIONS_SQL: &str = "DELETE FROM transactions WHERE timestamp < ?1;";
+const PRUNE_BLOCKS_SQL: &str = "DELETE FROM blocks WHERE timestamp < ?1 \
+     AND NOT EXISTS (SELECT 1 FROM transactions t \
+     WHERE t.chain_id = blocks.chain_id AND t.block_number = blocks.block_number);";
+
+/// Deletes mined transactions older than `cutoff` (unix seconds) with their
+/// dependent rows, then the blocks older than it that no longer have
+/// transactions, in one database transaction, and returns how many blocks
+/// and transactions went. Pending rows carry no timestamp and are left alone.
+pub async fn prune_before(pool: &SqlitePool, cutoff: i64) -> Result<RowCounts> {
+    let mut txn = pool.begin().await?;
+    delete_tx_dependents(
+        &mut txn,
+        "SELECT chain_id, hash FROM transactions WHERE timestamp < ?1",
+        Some(cutoff),
+    )
+    .await?;
+    let transactions = sqlx::query(PRUNE_TRANSACTIONS_SQL)
+        .bind(cutoff)
+        .execute(&mut *txn)
+        .await?
+        .rows_affected();
+    let blocks = sqlx::query(PRUNE_BLOCKS_SQL)
+        .bind(cutoff)
+        .execute(&mut *txn)
+        .await?
+        .rows_affected();
+    txn.commit().await?;
+    Ok(RowCounts {
+        blocks: blocks as i64,
+        transactions: transactions as i64,
+    })
+}
+
+/// Rebuilds the database file so space freed by deletes is returned to the
+/// filesystem. Cannot run inside a transaction.
+pub async fn vacuum(pool: &SqlitePool) -> Result<()> {
+    sqlx::query("VACUUM;").execute(pool).await?;
+    Ok(())
+}
+
+/// Highest-value transaction in the last `last_n_blocks` stored blocks of
+/// `chain_id`, or of every chain when it is `None`.
+pub async fn get_max_value_transaction(
+    pool: &SqlitePool,
+    last_n_blocks: i64,
+    chain_id: Option<i64>,
+) -> Result<Option<NormalizedTx>> {
+    // value_wei holds canonical decimal strings (no sign, no leading zeros), so
+    // ordering by (length, text) is an exact numeric comparison at any width.
+    let sql = format!(
+        r#"
+        SELECT {}
+        FROM transactions
+        WHERE value_wei GLOB '[0-9]*'
+          AND value_wei NOT GLOB '*[^0-9]*'
+          AND (chain_id, block_number) IN (
+              SELECT chain_id, block_number
+              FROM blocks
+              WHERE ?2 IS NULL OR chain_id = ?2
+              ORDER BY block_number DESC
+              LIMIT ?1
+          )
+        ORDER BY LENGTH(value_wei) DESC, value_wei DESC
+        LIMIT 1;
+        "#,
+        TX_COLUMNS
+    );
+    let row = sqlx::query_as::<_, TxRow>(&sql)
+        .bind(last_n_blocks)
+        .bind(chain_id)
+        .fetch_optional(pool)
         .await?;
+    Ok(row.map(NormalizedTx::from))
+}
 
-    let mut value_is_text = false;
-    let mut gas_price_is_text = false;
-    let mut max_fee_is_text = false;
+/// Counts `value_wei` strings and totals them exactly; values can exceed any
+/// SQL integer, so the sum is accumulated here. Unparsable ones are counted
+/// but left out of the total, with a warning naming `what` was being summed.
+async fn sum_wei<S>(mut values: S, what: &str) -> Result<(i64, U256)>
+where
+    S: futures_util::Stream<Item = sqlx::Result<String>> + Unpin,
+{
+    let mut count = 0i64;
+    let mut total = U256::zero();
+    while let Some(value) = values.try_next().await? {
+        count += 1;
+        match U256::from_dec_str(&value) {
+            Ok(v) => total = total.saturating_add(v),
+            Err(_) => warn!("skipping unparsable value_wei {:?} in {}", value, what),
+        }
+    }
+    Ok((count, total))
+}
 
+/// Counts and sums the value of every stored transaction from `from` to `to`
+/// on `chain_id`, or on every chain when `None`.
+pub async fn get_flow_between(
+    pool: &SqlitePool,
+    from: &str,
+    to: &str,
+    chain_id: Option<i64>,
+) -> Result<FlowSummary> {
+    let rows = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT value_wei
+        FROM transactions
+        WHERE from_addr = ?1 AND to_addr = ?2 AND (?3 IS NULL OR chain_id = ?3);
+        "#,
+    )
+    .bind(from)
+    .bind(to)
+    .bind(chain_id)
+    .fetch(pool);
+    let (tx_count, total) = sum_wei(rows, "flow sum").await?;
+
+    Ok(FlowSummary {
+        from: from.to_string(),
+        to: to.to_string(),
+        tx_count,
+        total_value_wei: total.to_string(),
+    })
+}
+
+/// Sent/received counts, first and last sighting, and the exact wei total
+/// sent by `address` (matched as stored, so pass it lowercased), on `chain_id`
+/// or on every chain when `None`.
+pub async fn get_address_summary(
+    pool: &SqlitePool,
+    address: &str,
+    chain_id: Option<i64>,
+) -> Result<AddressSummary> {
+    let row = sqlx::query(
+        r#"
+        SELECT
+            COALESCE(SUM(CASE WHEN from_addr = ?1 THEN 1 ELSE 0 END), 0) AS sent_count,
+            COALESCE(SUM(CASE WHEN to_addr = ?1 THEN 1 ELSE 0 END), 0) AS received_count,
+            MIN(timestamp) AS first_seen,
+            MAX(timestamp) AS last_seen
+        FROM transactions
+        WHERE (from_addr = ?1 OR to_addr = ?1) AND (?2 IS NULL OR chain_id = ?2);
+        "#,
+    )
+    .bind(address)
+    .bind(chain_id)
+    .fetch_one(pool)
+    .await?;
+
+    let values = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT value_wei
+        FROM transactions
+        WHERE from_addr = ?1 AND (?2 IS NULL OR chain_id = ?2);
+        "#,
+    )
+    .bind(address)
+    .bind(chain_id)
+    .fetch(pool);
+    let (_, total) = sum_wei(values, "address summary").await?;
+
+    Ok(AddressSummary {
+        address: address.to_string(),
+        sent_count: row.try_get("sent_count")?,
+        received_count: row.try_get("received_count")?,
+        total_sent_wei: total.to_string(),
+        first_seen: row.try_get("first_seen")?,
+        last_seen: row.try_get("last_seen")?,
+    })
+}
+
+/// Distinct mined nonces sent by `address` (lowercased), ascending, with the
+/// gaps between them. Nonces only count per chain, so pass `chain_id` when the
+/// database holds several.
+pub async fn get_nonce_gaps(
+    pool: &SqlitePool,
+    address: &str,
+    chain_id: Option<i64>,
+) -> Result<NonceGaps> {
+    let nonces = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT DISTINCT nonce
+        FROM transactions
+        WHERE from_addr = ?1
+          AND block_number IS NOT NULL
+          AND (?2 IS NULL OR chain_id = ?2)
+        ORDER BY nonce;
+        "#,
+    )
+    .bind(address)
+    .bind(chain_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(NonceGaps {
+        address: address.to_string(),
+        gaps: crate::analysis::nonce_gaps(&nonces),
+        nonces,
+    })
+}
+
+/// Summarizes the transactions mined in blocks `from_block..=to_block` of
+/// `chain_id`, or of every chain when `None`.
+pub async fn get_window_summary(
+    pool: &SqlitePool,
+    from_block: i64,
+    to_block: i64,
+    chain_id: Option<i64>,
+) -> Result<WindowSummary> {
+    let gas_sql = format!(
+        "{};",
+        SqlDialect::Sqlite.gas_stats_select(
+            "AND block_number BETWEEN ?1 AND ?2 AND (?3 IS NULL OR chain_id = ?3)"
+        )
+    );
+    let gas = sqlx::query_as::<_, GasStatsRow>(&gas_sql)
+        .bind(from_block)
+        .bind(to_block)
+        .bind(chain_id)
+        .fetch_one(pool)
+        .await?
+        .into_stats();
+
+    let unique_addresses: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*) FROM (
+            SELECT from_addr FROM transactions
+            WHERE block_number BETWEEN ?1 AND ?2 AND (?3 IS NULL OR chain_id = ?3)
+            UNION
+            SELECT to_addr FROM transactions
+            WHERE block_number BETWEEN ?1 AND ?2 AND (?3 IS NULL OR chain_id = ?3)
+              AND to_addr IS NOT NULL
+        );
+        "#,
+    )
+    .bind(from_block)
+    .bind(to_block)
+    .bind(chain_id)
+    .fetch_one(pool)
+    .await?;
+
+    let rows = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT value_wei FROM transactions
+        WHERE block_number BETWEEN ?1 AND ?2 AND (?3 IS NULL OR chain_id = ?3);
+        "#,
+    )
+    .bind(from_block)
+    .bind(to_block)
+    .bind(chain_id)
+    .fetch(pool);
+    let (tx_count, total) = sum_wei(rows, "window sum").await?;
+
+    Ok(WindowSummary {
+        from_block,
+        to_block,
+        tx_count,
+        avg_gas_price_wei: gas.map(|g| g.avg),
+        avg_value_wei: (tx_count > 0).then(|| (total / U256::from(tx_count)).to_string()),
+        unique_addresses,
+    })
+}
+
+/// Registers a new mempool sampling run and returns its id.
+pub async fn create_sample(pool: &SqlitePool, started_at: i64) -> Result<i64> {
+    let result = sqlx::query("INSERT INTO mempool_samples (started_at) VALUES (?1);")
+        .bind(started_at)
+        .execute(pool)
+        .await?;
+    Ok(result.last_insert_rowid())
+}
+
+/// Records when each `(hash, offset_ms)` of `chain_id` arrived during a sample.
+pub async fn insert_arrivals(
+    pool: &SqlitePool,
+    sample_id: i64,
+    chain_id: i64,
+    arrivals: &[(String, i64)],
+) -> Result<()> {
+    let mut txn = pool.begin().await?;
+    for (hash, offset_ms) in arrivals {
+        sqlx::query(
+            r#"
+            INSERT INTO sample_arrivals (sample_id, chain_id, hash, offset_ms)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(sample_id, chain_id, hash) DO NOTHING;
+            "#,
+        )
+        .bind(sample_id)
+        .bind(chain_id)
+        .bind(hash)
+        .bind(offset_ms)
+        .execute(&mut *txn)
+        .await?;
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Stores captured logs; topics are kept as a JSON array. Re-ingesting a
+/// block leaves existing rows untouched.
+pub async fn insert_logs(pool: &SqlitePool, logs: &[LogEntry]) -> Result<()> {
+    let mut txn = pool.begin().await?;
+    for log in logs {
+        sqlx::query(
+            r#"
+            INSERT INTO logs (tx_hash, log_index, block_number, address, topics, data, chain_id)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            ON CONFLICT(chain_id, tx_hash, log_index) DO NOTHING;
+            "#,
+        )
+        .bind(&log.tx_hash)
+        .bind(log.log_index)
+        .bind(log.block_number)
+        .bind(&log.address)
+        .bind(serde_json::to_string(&log.topics)?)
+        .bind(&log.data)
+        .bind(log.chain_id)
+        .execute(&mut *txn)
+        .await?;
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+pub async fn insert_token_transfers(pool: &SqlitePool, transfers: &[TokenTransfer]) -> Result<()> {
+    let mut txn = pool.begin().await?;
+    for transfer in transfers {
+        sqlx::query(
+            r#"
+            INSERT INTO token_transfers (
+                tx_hash, log_index, block_number, token, from_addr, to_addr, amount, chain_id
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            ON CONFLICT(chain_id, tx_hash, log_index) DO NOTHING;
+            "#,
+        )
+        .bind(&transfer.tx_hash)
+        .bind(transfer.log_index)
+        .bind(transfer.block_number)
+        .bind(&transfer.token)
+        .bind(&transfer.from)
+        .bind(&transfer.to)
+        .bind(&transfer.amount)
+        .bind(transfer.chain_id)
+        .execute(&mut *txn)
+        .await?;
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Newest token transfers first, optionally limited to one token contract
+/// and to one chain.
+pub async fn get_token_transfers(
+    pool: &SqlitePool,
+    limit: i64,
+    token: Option<&str>,
+    chain_id: Option<i64>,
+) -> Result<Vec<TokenTransfer>> {
+    #[derive(FromRow)]
+    struct Row {
+        chain_id: i64,
+        tx_hash: String,
+        log_index: i64,
+        block_number: Option<i64>,
+        token: String,
+        from_addr: String,
+        to_addr: String,
+        amount: String,
+    }
+
+    let rows = sqlx::query_as::<_, Row>(
+        r#"
+        SELECT chain_id, tx_hash, log_index, block_number, token, from_addr, to_addr, amount
+        FROM token_transfers
+        WHERE (?1 IS NULL OR token = ?1)
+          AND (?3 IS NULL OR chain_id = ?3)
+        ORDER BY block_number DESC, log_index DESC
+        LIMIT ?2;
+        "#,
+    )
+    .bind(token)
+    .bind(limit)
+    .bind(chain_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| TokenTransfer {
+            chain_id: row.chain_id,
+            tx_hash: row.tx_hash,
+            log_index: row.log_index,
+            block_number: row.block_number,
+            token: row.token,
+            from: row.from_addr,
+            to: row.to_addr,
+            amount: row.amount,
+        })
+        .collect())
+}
+
+/// Newest logs first, optionally limited to one emitting contract and to
+/// one chain.
+pub async fn get_recent_logs(
+    pool: &SqlitePool,
+    limit: i64,
+    address: Option<&str>,
+    chain_id: Option<i64>,
+) -> Result<Vec<LogEntry>> {
+    #[derive(FromRow)]
+    struct Row {
+        chain_id: i64,
+        tx_hash: String,
+        log_index: i64,
+        block_number: i64,
+        address: String,
+        topics: String,
+        data: String,
+    }
+
+    let rows = sqlx::query_as::<_, Row>(
+        r#"
+        SELECT chain_id, tx_hash, log_index, block_number, address, topics, data
+        FROM logs
+        WHERE (?1 IS NULL OR address = ?1)
+          AND (?3 IS NULL OR chain_id = ?3)
+        ORDER BY block_number DESC, log_index DESC
+        LIMIT ?2;
+        "#,
+    )
+    .bind(address)
+    .bind(limit)
+    .bind(chain_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(LogEntry {
+                topics: serde_json::from_str(&row.topics)?,
+                chain_id: row.chain_id,
+                tx_hash: row.tx_hash,
+                log_index: row.log_index,
+                block_number: row.block_number,
+                address: row.address,
+                data: row.data,
+            })
+        })
+        .collect()
+}
+
+/// Stores hook output; a later value for the same `(chain_id, hash, key)`
+/// replaces the earlier one.
+pub async fn insert_tx_metadata(pool: &SqlitePool, rows: &[TxMetadata]) -> Result<()> {
+    let mut txn = pool.begin().await?;
     for row in rows {
-        let name: String = row.try_get("name")?;
-        let col_type: Option<String> = row.try_get("type")?;
-        match name.as_str() {
-            "value_wei" => {
-                value_is_text = col_type.as_deref() == Some("TEXT");
-            }
-            "gas_price_wei" => {
-                gas_price_is_text = col_type.as_deref() == Some("TEXT");
-            }
-            "max_fee_per_gas_wei" => {
-                max_fee_is_text = col_type.as_deref() == Some("TEXT");
+        sqlx::query(
+            r#"
+            INSERT INTO tx_metadata (hash, key, value, chain_id)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(chain_id, hash, key) DO UPDATE SET value = excluded.value;
+            "#,
+        )
+        .bind(&row.hash)
+        .bind(&row.key)
+        .bind(&row.value)
+        .bind(row.chain_id)
+        .execute(&mut *txn)
+        .await?;
+    }
+    txn.commit().await?;
+    Ok(())
+}
+
+pub async fn get_tx_metadata(
+    pool: &SqlitePool,
+    chain_id: i64,
+    hash: &str,
+) -> Result<Vec<TxMetadata>> {
+    let rows = sqlx::query_as::<_, (String, String, String)>(
+        "SELECT hash, key, value FROM tx_metadata WHERE chain_id = ?1 AND hash = ?2 ORDER BY key;",
+    )
+    .bind(chain_id)
+    .bind(hash)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(hash, key, value)| TxMetadata {
+            chain_id,
+            hash,
+            key,
+            value,
+        })
+        .collect())
+}
+
+/// Counts arrivals per `bucket_secs` bucket for one sample (the latest when
+/// `sample_id` is `None`). Returns `None` when no such sample exists.
+pub async fn get_arrival_rate(
+    pool: &SqlitePool,
+    sample_id: Option<i64>,
+    bucket_secs: i64,
+) -> Result<Option<ArrivalRate>> {
+    let sample = sqlx::query(
+        r#"
+        SELECT id, started_at
+        FROM mempool_samples
+        WHERE ?1 IS NULL OR id = ?1
+        ORDER BY id DESC
+        LIMIT 1;
+        "#,
+    )
+    .bind(sample_id)
+    .fetch_optional(pool)
+    .await?;
+    let Some(sample) = sample else {
+        return Ok(None);
+    };
+    let sample_id: i64 = sample.try_get("id")?;
+    let started_at: i64 = sample.try_get("started_at")?;
+
+    #[derive(FromRow)]
+    struct Row {
+        bucket: i64,
+        tx_count: i64,
+    }
+
+    let bucket_ms = bucket_secs.max(1) * 1000;
+    let rows = sqlx::query_as::<_, Row>(
+        r#"
+        SELECT offset_ms / ?2 as bucket, COUNT(*) as tx_count
+        FROM sample_arrivals
+        WHERE sample_id = ?1
+        GROUP BY bucket
+        ORDER BY bucket;
+        "#,
+    )
+    .bind(sample_id)
+    .bind(bucket_ms)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Some(ArrivalRate {
+        sample_id,
+        started_at,
+        bucket_secs: bucket_ms / 1000,
+        buckets: rows
+            .into_iter()
+            .map(|row| ArrivalBucket {
+                offset_secs: row.bucket * bucket_ms / 1000,
+                tx_count: row.tx_count,
+            })
+            .collect(),
+    }))
+}
+
+/// Range scan behind [`get_throughput`]; must stay on `idx_transactions_timestamp`.
+const THROUGHPUT_SQL: &str = r#"
+    SELECT (timestamp / ?1) * ?1 AS bucket_start, COUNT(*) AS tx_count
+    FROM transactions
+    WHERE timestamp >= ?2 AND timestamp <= ?3 AND (?4 IS NULL OR chain_id = ?4)
+    GROUP BY bucket_start
+    ORDER BY bucket_start;
+"#;
+
+/// Mined transactions per `bucket_secs` over the `window_secs` ending at
+/// `now`, oldest bucket first. Buckets are aligned to multiples of
+/// `bucket_secs` and empty ones are omitted; pending rows have no timestamp
+/// and never count. `chain_id` counts one chain only.
+pub async fn get_throughput(
+    pool: &SqlitePool,
+    bucket_secs: i64,
+    window_secs: i64,
+    now: i64,
+    chain_id: Option<i64>,
+) -> Result<Vec<ThroughputBucket>> {
+    let rows: Vec<(i64, i64)> = sqlx::query_as(THROUGHPUT_SQL)
+        .bind(bucket_secs.max(1))
+        .bind(now.saturating_sub(window_secs))
+        .bind(now)
+        .bind(chain_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(bucket_start, tx_count)| ThroughputBucket {
+            bucket_start,
+            tx_count,
+        })
+        .collect())
+}
+
+/// Columns that must be TEXT so wei amounts above i64 survive unchanged.
+const WEI_TEXT_COLUMNS: &[&str] = &[
+    "value_wei",
+    "gas_price_wei",
+    "max_fee_per_gas_wei",
+    "max_priority_fee_per_gas_wei",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn memory_pool() -> SqlitePool {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        apply_schema(&pool, &StorageOptions::default())
+            .await
+            .unwrap();
+        pool
+    }
+
+    async fn query_plan(pool: &SqlitePool, sql: &str) -> String {
+        let rows: Vec<(i64, i64, i64, String)> =
+            sqlx::query_as(&format!("EXPLAIN QUERY PLAN {}", sql))
+                .bind(0i64)
+                .fetch_all(pool)
+                .await
+                .unwrap();
+        rows.into_iter()
+            .map(|(_, _, _, detail)| detail)
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    #[tokio::test]
+    async fn file_databases_use_wal_and_busy_timeout() {
+        let path = std::env::temp_dir().join(format!("storage_wal_{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+        let options = StorageOptions {
+            busy_timeout_ms: 1_234,
+            max_connections: 2,
+            acquire_timeout: Some(Duration::from_secs(3)),
+            ..StorageOptions::default()
+        };
+        let pool = init_pool_with_options(&url, &options).await.unwrap();
+        assert_eq!(pool.options().get_max_connections(), 2);
+        assert_eq!(pool.options().get_acquire_timeout(), Duration::from_secs(3));
+        let mode: String = sqlx::query_scalar("PRAGMA journal_mode;")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(mode, "wal");
+        let timeout: i64 = sqlx::query_scalar("PRAGMA busy_timeout;")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(timeout, 1_234);
+        pool.close().await;
+        let _ = std::fs::remove_file(&path);
+
+        let memory = init_pool("sqlite::memory:").await.unwrap();
+        let mode: String = sqlx::query_scalar("PRAGMA journal_mode;")
+            .fetch_one(&memory)
+            .await
+            .unwrap();
+        assert_eq!(mode, "memory");
+    }
+
+    #[tokio::test]
+    async fn legacy_rows_get_chain_id_and_chains_share_heights() {
+        let path =
+            std::env::temp_dir().join(format!("storage_chain_{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+        let legacy = SqlitePool::connect(&url).await.unwrap();
+        for sql in [
+            "CREATE TABLE blocks (block_number INTEGER PRIMARY KEY, block_hash TEXT NOT NULL, \
+             timestamp INTEGER NOT NULL);",
+            "CREATE TABLE transactions (hash TEXT PRIMARY KEY, from_addr TEXT NOT NULL, \
+             to_addr TEXT, value_wei TEXT NOT NULL, gas INTEGER NOT NULL, gas_price_wei TEXT, \
+             max_fee_per_gas_wei TEXT, nonce INTEGER NOT NULL, block_number INTEGER, \
+             timestamp INTEGER, status TEXT, \
+             FOREIGN KEY(block_number) REFERENCES blocks(block_number));",
+            "INSERT INTO blocks VALUES (7, '0xb7', 1000);",
+            "INSERT INTO transactions (hash, from_addr, value_wei, gas, nonce, block_number, \
+             timestamp) VALUES ('0x01', '0xa', '5', 21000, 0, 7, 1000);",
+        ] {
+            sqlx::query(sql).execute(&legacy).await.unwrap();
+        }
+        legacy.close().await;
+
+        let options = StorageOptions {
+            legacy_chain_id: 10,
+            ..StorageOptions::default()
+        };
+        let pool = init_pool_with_options(&url, &options).await.unwrap();
+        let migrated = get_block(&pool, 10, 7).await.unwrap().unwrap();
+        assert_eq!((migrated.chain_id, migrated.hash.as_str()), (10, "0xb7"));
+        let tx = get_transaction_by_hash(&pool, "0x01", None)
+            .await
+            .unwrap()
+            .unwrap()
+            .tx;
+        assert_eq!(tx.chain_id, 10);
+
+        // The same height and hash on another chain are separate rows.
+        let block = BlockInfo {
+            number: 7,
+            hash: "0xa7".to_string(),
+            timestamp: 2000,
+            base_fee_wei: None,
+            chain_id: 1,
+        };
+        insert_block(&pool, &block).await.unwrap();
+        insert_transactions(&pool, &[NormalizedTx { chain_id: 1, ..tx }])
+            .await
+            .unwrap();
+        assert_eq!(count_rows(&pool).await.unwrap().transactions, 2);
+        let by_chain = |chain_id| get_transaction_by_hash(&pool, "0x01", chain_id);
+        assert_eq!(by_chain(None).await.unwrap().unwrap().tx.chain_id, 1);
+        assert_eq!(by_chain(Some(10)).await.unwrap().unwrap().tx.chain_id, 10);
+        assert!(by_chain(Some(5)).await.unwrap().is_none());
+        let both = get_transactions_by_hashes(&pool, &["0x01".to_string()], None)
+            .await
+            .unwrap();
+        let chains: Vec<i64> = both.iter().map(|stored| stored.tx.chain_id).collect();
+        assert_eq!(chains, vec![1, 10]);
+        assert_eq!(get_block(&pool, 1, 7).await.unwrap().unwrap().hash, "0xa7");
+        let senders = get_top_senders(&pool, 10, Some(1)).await.unwrap();
+        assert_eq!(senders[0].count, 1);
+        assert_eq!(get_recent_blocks(&pool, 10, None).await.unwrap().len(), 2);
+
+        replace_block(
+            &pool,
+            &BlockInfo {
+                hash: "0xc7".to_string(),
+                ..block
+            },
+        )
+        .await
+        .unwrap();
+        let remaining = get_recent_transactions(&pool, 10, &RecentTxFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].chain_id, 10);
+        pool.close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn legacy_dependent_rows_take_their_transactions_chain_id() {
+        let path = std::env::temp_dir().join(format!("storage_deps_{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+        let pool = init_pool(&url).await.unwrap();
+        // Roll tx_metadata back to its layout before chain ids.
+        let mut conn = pool.acquire().await.unwrap();
+        for sql in [
+            "INSERT INTO transactions (chain_id, hash, from_addr, value_wei, gas, nonce) \
+             VALUES (8453, '0x01', '0xa', '0', 21000, 0);",
+            "DROP TABLE tx_metadata;",
+            "CREATE TABLE tx_metadata (hash TEXT NOT NULL, key TEXT NOT NULL, \
+             value TEXT NOT NULL, PRIMARY KEY(hash, key));",
+            "INSERT INTO tx_metadata VALUES ('0x01', 'k', 'kept'), ('0xgone', 'k', 'orphan');",
+        ] {
+            sqlx::query(sql).execute(&mut *conn).await.unwrap();
+        }
+        drop(conn);
+        sqlx::query("DELETE FROM schema_version WHERE version = ?1;")
+            .bind(SCHEMA_VERSION)
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+
+        let options = StorageOptions {
+            legacy_chain_id: 5,
+            ..StorageOptions::default()
+        };
+        let pool = init_pool_with_options(&url, &options).await.unwrap();
+        assert_eq!(
+            get_tx_metadata(&pool, 8453, "0x01").await.unwrap()[0].value,
+            "kept"
+        );
+        assert_eq!(
+            get_tx_metadata(&pool, 5, "0xgone").await.unwrap()[0].value,
+            "orphan"
+        );
+        assert!(has_column(&pool, "logs", "chain_id").await.unwrap());
+        pool.close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn migrations_upgrade_legacy_types_once_and_record_version() {
+        let path =
+            std::env::temp_dir().join(format!("storage_migrate_{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+        let legacy = SqlitePool::connect(&url).await.unwrap();
+        for sql in [
+            "CREATE TABLE blocks (block_number INTEGER PRIMARY KEY, block_hash TEXT NOT NULL, \
+             timestamp INTEGER NOT NULL);",
+            "CREATE TABLE transactions (hash TEXT PRIMARY KEY, from_addr TEXT NOT NULL, \
+             to_addr TEXT, value_wei INTEGER NOT NULL, gas INTEGER NOT NULL, \
+             gas_price_wei INTEGER, max_fee_per_gas_wei TEXT, nonce INTEGER NOT NULL, \
+             block_number INTEGER, timestamp INTEGER);",
+            "INSERT INTO blocks VALUES (7, '0xb7', 1000);",
+            "INSERT INTO transactions (hash, from_addr, value_wei, gas, gas_price_wei, nonce, \
+             block_number, timestamp) VALUES ('0x01', '0xa', 5000, 21000, 7, 0, 7, 1000);",
+        ] {
+            sqlx::query(sql).execute(&legacy).await.unwrap();
+        }
+        legacy.close().await;
+
+        let pool = init_pool(&url).await.unwrap();
+        assert_eq!(schema_version(&pool).await.unwrap(), SCHEMA_VERSION);
+        let columns = table_columns(&pool, "transactions").await.unwrap();
+        for wei in WEI_TEXT_COLUMNS {
+            assert!(columns.contains(&(wei.to_string(), "TEXT".to_string())));
+        }
+        let tx = get_transaction_by_hash(&pool, "0x01", None)
+            .await
+            .unwrap()
+            .unwrap()
+            .tx;
+        assert_eq!(tx.value_wei, "5000");
+        assert_eq!(tx.gas_price_wei.as_deref(), Some("7"));
+        assert_eq!((tx.chain_id, tx.status, tx.tx_type), (1, None, None));
+        pool.close().await;
+
+        // Reopening an up-to-date database applies nothing again.
+        let pool = init_pool(&url).await.unwrap();
+        let applied: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM schema_version;")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(applied, SCHEMA_VERSION);
+        assert_eq!(count_rows(&pool).await.unwrap().transactions, 1);
+        pool.close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn wei_columns_become_text_once_databases_have_chain_ids() {
+        let path =
+            std::env::temp_dir().join(format!("storage_wei_text_{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+        let legacy = SqlitePool::connect(&url).await.unwrap();
+        for sql in [
+            "CREATE TABLE blocks (chain_id INTEGER NOT NULL, block_number INTEGER NOT NULL, \
+             block_hash TEXT NOT NULL, timestamp INTEGER NOT NULL, \
+             PRIMARY KEY(chain_id, block_number));",
+            "CREATE TABLE transactions (chain_id INTEGER NOT NULL, hash TEXT NOT NULL, \
+             from_addr TEXT NOT NULL, to_addr TEXT, value_wei INTEGER NOT NULL, \
+             gas INTEGER NOT NULL, gas_price_wei TEXT, max_fee_per_gas_wei TEXT, \
+             nonce INTEGER NOT NULL, block_number INTEGER, timestamp INTEGER, \
+             PRIMARY KEY(chain_id, hash));",
+            "INSERT INTO blocks VALUES (5, 7, '0xb7', 1000);",
+            "INSERT INTO transactions (chain_id, hash, from_addr, value_wei, gas, nonce, \
+             block_number) VALUES (5, '0x01', '0xa', 5000, 21000, 0, 7);",
+        ] {
+            sqlx::query(sql).execute(&legacy).await.unwrap();
+        }
+        legacy.close().await;
+
+        let pool = init_pool(&url).await.unwrap();
+        let columns = table_columns(&pool, "transactions").await.unwrap();
+        assert!(columns.contains(&("value_wei".to_string(), "TEXT".to_string())));
+        let value: String = sqlx::query_scalar("SELECT value_wei FROM transactions;")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(value, "5000");
+        assert_eq!(
+            get_recent_transactions(&pool, 10, &RecentTxFilter::default())
+                .await
+                .unwrap()[0]
+                .chain_id,
+            5
+        );
+        pool.close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn concurrent_opens_of_a_new_database_both_succeed() {
+        let path = std::env::temp_dir().join(format!("storage_race_{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+        let (a, b) = tokio::join!(init_pool(&url), init_pool(&url));
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert_eq!(schema_version(&a).await.unwrap(), SCHEMA_VERSION);
+        a.close().await;
+        b.close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn throughput_buckets_mined_rows_in_window() {
+        let pool = memory_pool().await;
+        let rows = [
+            ("0x1", Some(1_000)),
+            ("0x2", Some(1_059)),
+            ("0x3", Some(1_060)),
+            ("0x4", Some(900)),
+            ("0x5", None),
+        ];
+        for (hash, timestamp) in rows {
+            sqlx::query(
+                "INSERT INTO transactions (chain_id, hash, from_addr, value_wei, gas, nonce, timestamp) \
+                 VALUES (1, ?1, '0xa', '0', 21000, 0, ?2);",
+            )
+            .bind(hash)
+            .bind(timestamp)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let buckets = get_throughput(&pool, 60, 100, 1_070, None).await.unwrap();
+        let counts: Vec<(i64, i64)> = buckets
+            .into_iter()
+            .map(|b| (b.bucket_start, b.tx_count))
+            .collect();
+        assert_eq!(counts, [(960, 1), (1_020, 2)]);
+
+        let plan = query_plan(&pool, THROUGHPUT_SQL).await;
+        assert!(
+            plan.contains("INDEX idx_transactions_timestamp"),
+            "{}",
+            plan
+        );
+    }
+
+    #[tokio::test]
+    async fn min_value_compares_amounts_beyond_u128() {
+        let pool = memory_pool().await;
+        // 2^128 does not fit in a u128; the 24-digit value does but not in u64.
+        let values = [
+            ("0xhuge", "340282366920938463463374607431768211456"),
+            ("0xnines", "999999999999999999999999"),
+            ("0xten_eth", "10000000000000000000"),
+            ("0xone_eth", "1000000000000000000"),
+            ("0xdust", "5"),
+            ("0xbad", "1e30"),
+        ];
+        for (i, (hash, value)) in values.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO transactions (chain_id, hash, from_addr, value_wei, gas, nonce, timestamp) \
+                 VALUES (1, ?1, '0xa', ?2, 21000, 0, ?3);",
+            )
+            .bind(hash)
+            .bind(value)
+            .bind(1_700_000_000 - i as i64)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        let hashes =
+            |txs: Vec<NormalizedTx>| -> Vec<String> { txs.into_iter().map(|tx| tx.hash).collect() };
+
+        let over_ten_eth = get_transactions_min_value(&pool, "10000000000000000000", 10)
+            .await
+            .unwrap();
+        assert_eq!(hashes(over_ten_eth), ["0xhuge", "0xnines", "0xten_eth"]);
+        let over_nines = get_transactions_min_value(&pool, "0001000000000000000000000000", 10)
+            .await
+            .unwrap();
+        assert_eq!(hashes(over_nines), ["0xhuge"]);
+        let above_huge =
+            get_transactions_min_value(&pool, "340282366920938463463374607431768211457", 10)
+                .await
+                .unwrap();
+        assert!(above_huge.is_empty());
+        let everything = get_transactions_min_value(&pool, "0", 10).await.unwrap();
+        assert_eq!(everything.len(), 5);
+        assert!(get_transactions_min_value(&pool, "-1", 10).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn block_stream_visits_only_the_last_n_blocks() {
+        let pool = memory_pool().await;
+        for n in 1..=3i64 {
+            sqlx::query(
+                "INSERT INTO blocks (chain_id, block_number, block_hash, timestamp) VALUES (1, ?1, ?2, ?3);",
+            )
+            .bind(n)
+            .bind(format!("0x{:x}", n))
+            .bind(1_700_000_000 + n)
+            .execute(&pool)
+            .await
+            .unwrap();
+            for i in 0..n {
+                sqlx::query(
+                    "INSERT INTO transactions (chain_id, hash, from_addr, value_wei, gas, nonce, block_number) \
+                     VALUES (1, ?1, '0xa', '0', ?2, ?3, ?4);",
+                )
+                .bind(format!("0x{}_{}", n, i))
+                .bind(21_000 * (i + 1))
+                .bind(i)
+                .bind(n)
+                .execute(&pool)
+                .await
+                .unwrap();
             }
-            _ => {}
         }
+        sqlx::query(
+            "INSERT INTO transactions (chain_id, hash, from_addr, value_wei, gas, nonce) \
+             VALUES (1, '0xpending', '0xa', '0', 21000, 9);",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        // Blocks 2 and 3 hold five transactions; block 1 and the pending row are skipped.
+        let txs: Vec<NormalizedTx> = stream_block_transactions(&pool, 2, None)
+            .try_collect()
+            .await
+            .unwrap();
+        let gas: i64 = txs.iter().map(|tx| tx.gas).sum();
+        assert_eq!((txs.len(), gas), (5, 21_000 * (1 + 2 + 1 + 2 + 3)));
+
+        let none = stream_block_transactions(&pool, 0, None).count().await;
+        assert_eq!(none, 0);
+        let other_chain = stream_block_transactions(&pool, 2, Some(10)).count().await;
+        assert_eq!(other_chain, 0);
     }
 
-    if !value_is_text {
-        warn!("transactions.value_wei is not TEXT; delete/recreate DB to pick up new schema");
+    #[tokio::test]
+    async fn pending_rows_order_by_hash_under_either_order() {
+        let pool = memory_pool().await;
+        for hash in ["0xb", "0xc", "0xa"] {
+            sqlx::query(
+                "INSERT INTO transactions (chain_id, hash, from_addr, value_wei, gas, nonce) \
+                 VALUES (1, ?1, '0xa', '0', 21000, 0);",
+            )
+            .bind(hash)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        for order in [RecentOrder::Timestamp, RecentOrder::Block] {
+            let filter = RecentTxFilter {
+                pending_only: true,
+                order,
+                ..Default::default()
+            };
+            let hashes: Vec<String> = get_recent_transactions(&pool, 2, &filter)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|tx| tx.hash)
+                .collect();
+            assert_eq!(hashes, ["0xc", "0xb"], "{:?}", order);
+        }
     }
-    if !gas_price_is_text {
-        warn!("transactions.gas_price_wei is not TEXT; delete/recreate DB to pick up new schema");
+
+    #[tokio::test]
+    async fn reinserting_fills_in_status_but_never_clears_it() {
+        let pool = memory_pool().await;
+        let tx = |status: Option<&str>| NormalizedTx {
+            hash: "0xst".to_string(),
+            from: "0xa".to_string(),
+            to: None,
+            value_wei: "0".to_string(),
+            gas: 21_000,
+            gas_price_wei: None,
+            max_fee_per_gas_wei: None,
+            max_priority_fee_per_gas_wei: None,
+            nonce: 0,
+            block_number: None,
+            timestamp: None,
+            status: status.map(str::to_string),
+            input_data: None,
+            tx_index: None,
+            chain_id: 1,
+            tx_type: None,
+            input_size: None,
+        };
+        let status = |pool: &SqlitePool| {
+            let pool = pool.clone();
+            async move {
+                get_transaction_by_hash(&pool, "0xst", None)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .tx
+                    .status
+            }
+        };
+        insert_transactions(&pool, &[tx(None)]).await.unwrap();
+        assert_eq!(status(&pool).await, None);
+        insert_transactions(&pool, &[tx(Some("success"))])
+            .await
+            .unwrap();
+        assert_eq!(status(&pool).await.as_deref(), Some("success"));
+        insert_transactions(&pool, &[tx(None)]).await.unwrap();
+        assert_eq!(status(&pool).await.as_deref(), Some("success"));
     }
-    if !max_fee_is_text {
-        warn!("transactions.max_fee_per_gas_wei is not TEXT; delete/recreate DB to pick up new schema");
+
+    #[tokio::test]
+    async fn reads_return_the_flags_and_selector_stored_with_the_row() {
+        let pool = memory_pool().await;
+        let tx = NormalizedTx {
+            hash: "0xsw".to_string(),
+            from: "0xa".to_string(),
+            to: Some("0xrouter".to_string()),
+            value_wei: "0".to_string(),
+            gas: 21_000,
+            gas_price_wei: None,
+            max_fee_per_gas_wei: None,
+            max_priority_fee_per_gas_wei: None,
+            nonce: 0,
+            block_number: None,
+            timestamp: None,
+            status: None,
+            input_data: Some("0x7FF36AB5".to_string()),
+            tx_index: None,
+            chain_id: 1,
+            tx_type: None,
+            input_size: None,
+        };
+        let options = StorageOptions {
+            dex_routers: HashSet::from(["0xrouter".to_string()]),
+            ..StorageOptions::default()
+        };
+        insert_transactions_with_options(&pool, &[tx], &options)
+            .await
+            .unwrap();
+        let stored = get_transaction_by_hash(&pool, "0xsw", None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.flags.names(), vec!["dex_swap", "has_input"]);
+        assert_eq!(stored.input_selector.as_deref(), Some("0x7ff36ab5"));
+    }
+
+    #[tokio::test]
+    async fn prune_to_count_keeps_newest_rows_and_pending() {
+        let pool = memory_pool().await;
+        for n in 1..=3i64 {
+            sqlx::query(
+                "INSERT INTO blocks (chain_id, block_number, block_hash, timestamp) VALUES (1, ?1, ?2, ?3);",
+            )
+            .bind(n)
+            .bind(format!("0x{:x}", n))
+            .bind(1_700_000_000 + n)
+            .execute(&pool)
+            .await
+            .unwrap();
+            sqlx::query(
+                "INSERT INTO transactions (chain_id, hash, from_addr, value_wei, gas, nonce, block_number, timestamp) \
+                 VALUES (1, ?1, '0xa', '0', 21000, ?2, ?2, ?3);",
+            )
+            .bind(format!("0xmined{}", n))
+            .bind(n)
+            .bind(1_700_000_000 + n)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        sqlx::query(
+            "INSERT INTO transactions (chain_id, hash, from_addr, value_wei, gas, nonce) \
+             VALUES (1, '0xpending', '0xa', '0', 21000, 9);",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let pruned = prune_to_count(&pool, 2).await.unwrap();
+        assert_eq!((pruned.transactions, pruned.blocks), (2, 2));
+        let left: Vec<String> = sqlx::query_scalar("SELECT hash FROM transactions ORDER BY hash;")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(left, ["0xmined3", "0xpending"]);
+
+        let pruned = prune_to_count(&pool, 2).await.unwrap();
+        assert_eq!((pruned.transactions, pruned.blocks), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn pruning_deletes_dependent_rows_of_pruned_transactions() {
+        let pool = memory_pool().await;
+        sqlx::query("INSERT INTO mempool_samples (id, started_at) VALUES (1, 0);")
+            .execute(&pool)
+            .await
+            .unwrap();
+        for n in 1..=3i64 {
+            let hash = format!("0xmined{}", n);
+            sqlx::query(
+                "INSERT INTO transactions (chain_id, hash, from_addr, value_wei, gas, nonce, timestamp) \
+                 VALUES (1, ?1, '0xa', '0', 21000, ?2, ?3);",
+            )
+            .bind(&hash)
+            .bind(n)
+            .bind(1_700_000_000 + n)
+            .execute(&pool)
+            .await
+            .unwrap();
+            for sql in [
+                "INSERT INTO tx_metadata (chain_id, hash, key, value) VALUES (1, ?1, 'k', 'v');",
+                "INSERT INTO logs (chain_id, tx_hash, log_index, block_number, address, topics, data) \
+                 VALUES (1, ?1, 0, 1, '0xc', '[]', '0x');",
+                "INSERT INTO token_transfers \
+                 (chain_id, tx_hash, log_index, token, from_addr, to_addr, amount) \
+                 VALUES (1, ?1, 0, '0xc', '0xa', '0xb', '1');",
+                "INSERT INTO sample_arrivals (sample_id, chain_id, hash, offset_ms) \
+                 VALUES (1, 1, ?1, 0);",
+            ] {
+                sqlx::query(sql).bind(&hash).execute(&pool).await.unwrap();
+            }
+        }
+        // The same hash on another chain belongs to a transaction never pruned.
+        sqlx::query(
+            "INSERT INTO tx_metadata (chain_id, hash, key, value) VALUES (10, '0xmined1', 'k', 'v');",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let dependents = |pool: SqlitePool| async move {
+            let mut hashes = Vec::new();
+            for (table, column, _) in TX_DEPENDENT_TABLES {
+                let sql = format!(
+                    "SELECT DISTINCT {} FROM {} WHERE chain_id = 1 ORDER BY 1;",
+                    column, table
+                );
+                let rows: Vec<String> = sqlx::query_scalar(&sql).fetch_all(&pool).await.unwrap();
+                hashes.push(rows);
+            }
+            hashes
+        };
+
+        prune_before(&pool, 1_700_000_002).await.unwrap();
+        assert_eq!(
+            dependents(pool.clone()).await,
+            vec![vec!["0xmined2", "0xmined3"]; 4]
+        );
+        prune_to_count(&pool, 1).await.unwrap();
+        assert_eq!(dependents(pool.clone()).await, vec![vec!["0xmined3"]; 4]);
+        let other_chain: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM tx_metadata WHERE chain_id = 10;")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(other_chain, 1);
+    }
+
+    #[tokio::test]
+    async fn prune_deletes_use_timestamp_indexes() {
+        let pool = memory_pool().await;
+        let mut txn = pool.begin().await.unwrap();
+        for n in 0..50i64 {
+            sqlx::query(
+                "INSERT INTO blocks (chain_id, block_number, block_hash, timestamp) VALUES (1, ?1, ?2, ?3);",
+            )
+            .bind(n)
+            .bind(format!("0x{:x}", n))
+            .bind(1_700_000_000 + n * 100)
+            .execute(&mut *txn)
+            .await
+            .unwrap();
+        }
+        for i in 0..5_000i64 {
+            sqlx::query(
+                "INSERT INTO transactions (chain_id, hash, from_addr, value_wei, gas, nonce, block_number, timestamp) \
+                 VALUES (1, ?1, '0xa', '0', 21000, ?2, ?3, ?4);",
+            )
+            .bind(format!("0x{:064x}", i))
+            .bind(i)
+            .bind(i / 100)
+            .bind(1_700_000_000 + i)
+            .execute(&mut *txn)
+            .await
+            .unwrap();
+        }
+        txn.commit().await.unwrap();
+        sqlx::query("ANALYZE;").execute(&pool).await.unwrap();
+
+        let plan = query_plan(&pool, PRUNE_TRANSACTIONS_SQL).await;
+        assert!(
+            plan.starts_with("SEARCH") && plan.contains("INDEX idx_transactions_timestamp"),
+            "{}",
+            plan
+        );
+        let plan = query_plan(&pool, PRUNE_BLOCKS_SQL).await;
+        assert!(
+            plan.starts_with("SEARCH") && plan.contains("INDEX idx_blocks_timestamp"),
+            "{}",
+            plan
+        );
+
+        let pruned = prune_before(&pool, 1_700_001_000).await.unwrap();
+        assert_eq!(pruned.transactions, 1_000);
+        assert_eq!(pruned.blocks, 10);
+        assert_eq!(count_rows(&pool).await.unwrap().transactions, 4_000);
+
+        // Block 10 is older than this cutoff but one of its transactions is not.
+        let pruned = prune_before(&pool, 1_700_001_050).await.unwrap();
+        assert_eq!(pruned.transactions, 50);
+        assert_eq!(pruned.blocks, 0);
+        assert!(get_block(&pool, 1, 10).await.unwrap().is_some());
     }
-    Ok(())
 }
@@ -0,0 +1,1666 @@
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use ethers_core::types::U256;
+use futures_util::stream::BoxStream;
+use futures_util::{StreamExt, TryStreamExt};
+use sqlx::{FromRow, PgConnection, PgPool, Postgres, QueryBuilder, Row};
+
+use super::backend::{SqlDialect, Storage};
+use super::{
+    compress_input, sum_wei, timeline_entry, FeedRow, GasStatsRow, RecentOrder, RecentTxFilter,
+    StorageOptions, TxRow,
+};
+use crate::models::{
+    AddressSummary, ArrivalBucket, ArrivalRate, BaseFeeStats, BlockInfo, BlockSummary, FlowSummary,
+    GasHistogramBucket, GasStats, LogEntry, NonceGaps, NormalizedTx, RowCounts, StoredTx,
+    ThroughputBucket, TimelineEntry, TokenTransfer, TopSender, TxFlags, TxMetadata, WindowSummary,
+};
+
+/// Same shape as `TX_COLUMNS`. Hex calldata is stored as text and compressed
+/// calldata in `input_data_zstd`, so whichever is set is read back as bytes
+/// for the shared [`TxRow`] decoding.
+const PG_TX_COLUMNS: &str = "hash, from_addr, to_addr, value_wei, gas, gas_price_wei, \
+     max_fee_per_gas_wei, nonce, block_number, timestamp, status, \
+     COALESCE(input_data_zstd, convert_to(input_data, 'UTF8')) AS input_data, input_compressed, \
+     tx_index, max_priority_fee_per_gas_wei, chain_id, tx_type, input_size, flags, input_selector";
+
+/// Tables whose rows belong to a transaction, deleted with it when pruning:
+/// `(table, hash column, primary key)`.
+const PG_TX_DEPENDENT_TABLES: &[(&str, &str, &str)] = &[
+    ("tx_metadata", "hash", "chain_id, hash, key"),
+    ("logs", "tx_hash", "chain_id, tx_hash, log_index"),
+    ("token_transfers", "tx_hash", "chain_id, tx_hash, log_index"),
+    ("sample_arrivals", "hash", "sample_id, chain_id, hash"),
+];
+
+const PG_SCHEMA: &[&str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS blocks (
+        chain_id BIGINT NOT NULL,
+        block_number BIGINT NOT NULL,
+        block_hash TEXT NOT NULL,
+        timestamp BIGINT NOT NULL,
+        base_fee_wei TEXT,
+        PRIMARY KEY(chain_id, block_number)
+    );
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS transactions (
+        chain_id BIGINT NOT NULL,
+        hash TEXT NOT NULL,
+        from_addr TEXT NOT NULL,
+        to_addr TEXT,
+        value_wei TEXT NOT NULL,
+        gas BIGINT NOT NULL,
+        gas_price_wei TEXT,
+        max_fee_per_gas_wei TEXT,
+        max_priority_fee_per_gas_wei TEXT,
+        nonce BIGINT NOT NULL,
+        block_number BIGINT,
+        timestamp BIGINT,
+        status TEXT,
+        input_data TEXT,
+        input_data_zstd BYTEA,
+        input_compressed BOOLEAN NOT NULL DEFAULT FALSE,
+        flags BIGINT NOT NULL DEFAULT 0,
+        tx_index BIGINT,
+        input_selector TEXT,
+        tx_type BIGINT,
+        input_size BIGINT,
+        seq BIGSERIAL,
+        PRIMARY KEY(chain_id, hash),
+        FOREIGN KEY(chain_id, block_number) REFERENCES blocks(chain_id, block_number)
+    );
+    "#,
+    // Columns added after the tables above first shipped.
+    "ALTER TABLE blocks ADD COLUMN IF NOT EXISTS base_fee_wei TEXT;",
+    "ALTER TABLE transactions ADD COLUMN IF NOT EXISTS max_priority_fee_per_gas_wei TEXT;",
+    "ALTER TABLE transactions ADD COLUMN IF NOT EXISTS input_selector TEXT;",
+    "ALTER TABLE transactions ADD COLUMN IF NOT EXISTS tx_type BIGINT;",
+    "ALTER TABLE transactions ADD COLUMN IF NOT EXISTS input_size BIGINT;",
+    "ALTER TABLE transactions ADD COLUMN IF NOT EXISTS input_data_zstd BYTEA;",
+    "ALTER TABLE transactions ADD COLUMN IF NOT EXISTS seq BIGSERIAL;",
+    "CREATE INDEX IF NOT EXISTS idx_transactions_hash ON transactions(hash);",
+    "CREATE INDEX IF NOT EXISTS idx_transactions_from_addr ON transactions(from_addr);",
+    "CREATE INDEX IF NOT EXISTS idx_transactions_to_addr ON transactions(to_addr);",
+    "CREATE INDEX IF NOT EXISTS idx_transactions_block_number ON transactions(block_number);",
+    "CREATE INDEX IF NOT EXISTS idx_transactions_timestamp ON transactions(timestamp);",
+    "CREATE INDEX IF NOT EXISTS idx_transactions_ts_hash \
+     ON transactions((COALESCE(timestamp, 0)), hash);",
+    "CREATE INDEX IF NOT EXISTS idx_transactions_chain_order ON transactions(block_number, tx_index);",
+    "CREATE INDEX IF NOT EXISTS idx_transactions_input_selector ON transactions(input_selector);",
+    "CREATE INDEX IF NOT EXISTS idx_transactions_seq ON transactions(seq);",
+    "CREATE INDEX IF NOT EXISTS idx_blocks_timestamp ON blocks(timestamp);",
+    "CREATE INDEX IF NOT EXISTS idx_blocks_number ON blocks(block_number);",
+    r#"
+    CREATE TABLE IF NOT EXISTS tx_metadata (
+        chain_id BIGINT NOT NULL,
+        hash TEXT NOT NULL,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY(chain_id, hash, key)
+    );
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS logs (
+        chain_id BIGINT NOT NULL,
+        tx_hash TEXT NOT NULL,
+        log_index BIGINT NOT NULL,
+        block_number BIGINT NOT NULL,
+        address TEXT NOT NULL,
+        topics TEXT NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY(chain_id, tx_hash, log_index)
+    );
+    "#,
+    "CREATE INDEX IF NOT EXISTS idx_logs_block_number ON logs(block_number);",
+    r#"
+    CREATE TABLE IF NOT EXISTS token_transfers (
+        chain_id BIGINT NOT NULL,
+        tx_hash TEXT NOT NULL,
+        log_index BIGINT NOT NULL,
+        block_number BIGINT,
+        token TEXT NOT NULL,
+        from_addr TEXT NOT NULL,
+        to_addr TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        PRIMARY KEY(chain_id, tx_hash, log_index)
+    );
+    "#,
+    "CREATE INDEX IF NOT EXISTS idx_token_transfers_token ON token_transfers(token, block_number);",
+    r#"
+    CREATE TABLE IF NOT EXISTS mempool_samples (
+        id BIGSERIAL PRIMARY KEY,
+        started_at BIGINT NOT NULL
+    );
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS sample_arrivals (
+        sample_id BIGINT NOT NULL REFERENCES mempool_samples(id),
+        chain_id BIGINT NOT NULL,
+        hash TEXT NOT NULL,
+        offset_ms BIGINT NOT NULL,
+        PRIMARY KEY(sample_id, chain_id, hash)
+    );
+    "#,
+];
+
+/// Re-keys `blocks` and `transactions` created before chain ids were
+/// recorded; `{chain_id}` is the id given to their existing rows. Skipped
+/// once `blocks.chain_id` exists.
+const PG_CHAIN_ID_MIGRATION: &[&str] = &[
+    "ALTER TABLE blocks ADD COLUMN chain_id BIGINT NOT NULL DEFAULT {chain_id};",
+    "ALTER TABLE transactions ADD COLUMN chain_id BIGINT NOT NULL DEFAULT {chain_id};",
+    "ALTER TABLE blocks ALTER COLUMN chain_id DROP DEFAULT;",
+    "ALTER TABLE transactions ALTER COLUMN chain_id DROP DEFAULT;",
+    "ALTER TABLE transactions DROP CONSTRAINT IF EXISTS transactions_block_number_fkey;",
+    "ALTER TABLE transactions DROP CONSTRAINT transactions_pkey;",
+    "ALTER TABLE blocks DROP CONSTRAINT blocks_pkey;",
+    "ALTER TABLE blocks ADD PRIMARY KEY (chain_id, block_number);",
+    "ALTER TABLE transactions ADD PRIMARY KEY (chain_id, hash);",
+    "ALTER TABLE transactions ADD FOREIGN KEY (chain_id, block_number) \
+     REFERENCES blocks(chain_id, block_number);",
+];
+
+/// Re-keys one of the [`PG_TX_DEPENDENT_TABLES`] created before it had a
+/// chain id: `{table}` and `{hash}` name it and its hash column, and existing
+/// rows take the chain of their stored transaction, else `{chain_id}`.
+/// Skipped once the table has `chain_id`.
+const PG_DEPENDENT_CHAIN_ID_MIGRATION: &[&str] = &[
+    "ALTER TABLE {table} ADD COLUMN chain_id BIGINT;",
+    "UPDATE {table} SET chain_id = COALESCE((SELECT MIN(t.chain_id) FROM transactions t \
+     WHERE t.hash = {table}.{hash}), {chain_id});",
+    "ALTER TABLE {table} ALTER COLUMN chain_id SET NOT NULL;",
+    "ALTER TABLE {table} DROP CONSTRAINT {table}_pkey;",
+];
+
+/// [`Storage`] on PostgreSQL, for deployments with several writers. With
+/// `COMPRESS_INPUT_DATA`, calldata goes into `input_data_zstd` (zstd, as on
+/// SQLite) instead of the hex `input_data` column. `seq` numbers rows in
+/// insertion order for the transaction feed, standing in for SQLite's rowid.
+#[derive(Clone)]
+pub struct PostgresStorage {
+    pool: PgPool,
+}
+
+impl PostgresStorage {
+    pub async fn connect(database_url: &str, options: &StorageOptions) -> Result<Self> {
+        let pool = options
+            .pool_options::<Postgres>()
+            .connect(database_url)
+            .await
+            .context("failed to connect to Postgres")?;
+        let mut tx = pool.begin().await?;
+        let legacy_chain_id = options.legacy_chain_id.to_string();
+        if lacks_chain_id(&mut tx, "blocks").await? {
+            for statement in PG_CHAIN_ID_MIGRATION {
+                let statement = statement.replace("{chain_id}", &legacy_chain_id);
+                sqlx::query(&statement).execute(&mut *tx).await?;
+            }
+        }
+        for (table, hash, key) in PG_TX_DEPENDENT_TABLES {
+            if !lacks_chain_id(&mut tx, table).await? {
+                continue;
+            }
+            for statement in PG_DEPENDENT_CHAIN_ID_MIGRATION {
+                let statement = statement
+                    .replace("{table}", table)
+                    .replace("{hash}", hash)
+                    .replace("{chain_id}", &legacy_chain_id);
+                sqlx::query(&statement).execute(&mut *tx).await?;
+            }
+            sqlx::query(&format!("ALTER TABLE {} ADD PRIMARY KEY ({});", table, key))
+                .execute(&mut *tx)
+                .await?;
+        }
+        for statement in PG_SCHEMA {
+            sqlx::query(statement).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(Self { pool })
+    }
+}
+
+/// Whether `table` exists but predates its `chain_id` column.
+async fn lacks_chain_id(conn: &mut PgConnection, table: &str) -> Result<bool> {
+    let exists: bool = sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL;")
+        .bind(table)
+        .fetch_one(&mut *conn)
+        .await?;
+    let has_chain_id: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM information_schema.columns
+            WHERE table_schema = current_schema()
+              AND table_name = $1 AND column_name = 'chain_id'
+        );
+        "#,
+    )
+    .bind(table)
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(exists && !has_chain_id)
+}
+
+#[async_trait]
+impl Storage for PostgresStorage {
+    async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn close(&self) {
+        self.pool.close().await
+    }
+
+    async fn insert_block(&self, block: &BlockInfo) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO blocks (block_number, block_hash, timestamp, base_fee_wei, chain_id)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT(chain_id, block_number) DO NOTHING;
+            "#,
+        )
+        .bind(block.number)
+        .bind(&block.hash)
+        .bind(block.timestamp)
+        .bind(&block.base_fee_wei)
+        .bind(block.chain_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn upsert_block(&self, block: &BlockInfo) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO blocks (block_number, block_hash, timestamp, base_fee_wei, chain_id)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT(chain_id, block_number) DO UPDATE SET
+                block_hash = CASE
+                    WHEN blocks.block_hash = '' THEN excluded.block_hash
+                    ELSE blocks.block_hash
+                END,
+                timestamp = CASE
+                    WHEN blocks.timestamp = 0 THEN excluded.timestamp
+                    ELSE blocks.timestamp
+                END,
+                base_fee_wei = COALESCE(blocks.base_fee_wei, excluded.base_fee_wei);
+            "#,
+        )
+        .bind(block.number)
+        .bind(&block.hash)
+        .bind(block.timestamp)
+        .bind(&block.base_fee_wei)
+        .bind(block.chain_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_block(&self, chain_id: i64, number: i64) -> Result<Option<BlockInfo>> {
+        let row = sqlx::query(
+            r#"
+            SELECT block_number, block_hash, timestamp, base_fee_wei
+            FROM blocks
+            WHERE chain_id = $1 AND block_number = $2;
+            "#,
+        )
+        .bind(chain_id)
+        .bind(number)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.map(|row| {
+            Ok(BlockInfo {
+                number: row.try_get("block_number")?,
+                hash: row.try_get("block_hash")?,
+                timestamp: row.try_get("timestamp")?,
+                base_fee_wei: row.try_get("base_fee_wei")?,
+                chain_id,
+            })
+        })
+        .transpose()
+    }
+
+    async fn replace_block(&self, block: &BlockInfo) -> Result<RowCounts> {
+        let mut txn = self.pool.begin().await?;
+        for sql in [
+            "DELETE FROM tx_metadata WHERE chain_id = $2 AND hash IN \
+             (SELECT hash FROM transactions WHERE chain_id = $2 AND block_number = $1);",
+            "DELETE FROM logs WHERE chain_id = $2 AND block_number = $1;",
+            "DELETE FROM token_transfers WHERE chain_id = $2 AND block_number = $1;",
+        ] {
+            sqlx::query(sql)
+                .bind(block.number)
+                .bind(block.chain_id)
+                .execute(&mut *txn)
+                .await?;
+        }
+        let transactions =
+            sqlx::query("DELETE FROM transactions WHERE chain_id = $2 AND block_number = $1;")
+                .bind(block.number)
+                .bind(block.chain_id)
+                .execute(&mut *txn)
+                .await?
+                .rows_affected();
+        let blocks = sqlx::query("DELETE FROM blocks WHERE chain_id = $2 AND block_number = $1;")
+            .bind(block.number)
+            .bind(block.chain_id)
+            .execute(&mut *txn)
+            .await?
+            .rows_affected();
+        sqlx::query(
+            r#"
+            INSERT INTO blocks (block_number, block_hash, timestamp, base_fee_wei, chain_id)
+            VALUES ($1, $2, $3, $4, $5);
+            "#,
+        )
+        .bind(block.number)
+        .bind(&block.hash)
+        .bind(block.timestamp)
+        .bind(&block.base_fee_wei)
+        .bind(block.chain_id)
+        .execute(&mut *txn)
+        .await?;
+        txn.commit().await?;
+        Ok(RowCounts {
+            blocks: blocks as i64,
+            transactions: transactions as i64,
+        })
+    }
+
+    async fn insert_transactions(
+        &self,
+        txs: &[NormalizedTx],
+        options: &StorageOptions,
+    ) -> Result<()> {
+        let mut txn = self.pool.begin().await?;
+        for tx in txs {
+            let compressed = if options.compress_input {
+                tx.input_data.as_deref().and_then(compress_input)
+            } else {
+                None
+            };
+            let input_data = match compressed {
+                Some(_) => None,
+                None => tx.input_data.as_deref(),
+            };
+            sqlx::query(
+                r#"
+                INSERT INTO transactions (
+                    hash, from_addr, to_addr, value_wei, gas, gas_price_wei,
+                    max_fee_per_gas_wei, nonce, block_number, timestamp, status,
+                    input_data, input_data_zstd, input_compressed, flags, tx_index,
+                    max_priority_fee_per_gas_wei, input_selector, chain_id, tx_type, input_size
+                )
+                VALUES (
+                    $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+                    $18, $19, $20, $21
+                )
+                ON CONFLICT(chain_id, hash) DO UPDATE
+                    SET status = COALESCE(excluded.status, transactions.status);
+                "#,
+            )
+            .bind(&tx.hash)
+            .bind(&tx.from)
+            .bind(&tx.to)
+            .bind(&tx.value_wei)
+            .bind(tx.gas)
+            .bind(&tx.gas_price_wei)
+            .bind(&tx.max_fee_per_gas_wei)
+            .bind(tx.nonce)
+            .bind(tx.block_number)
+            .bind(tx.timestamp)
+            .bind(&tx.status)
+            .bind(input_data)
+            .bind(compressed.as_deref())
+            .bind(compressed.is_some())
+            .bind(TxFlags::from_tx(tx, &options.dex_routers).0)
+            .bind(tx.tx_index)
+            .bind(&tx.max_priority_fee_per_gas_wei)
+            .bind(tx.input_selector())
+            .bind(tx.chain_id)
+            .bind(tx.tx_type)
+            .bind(tx.input_size)
+            .execute(&mut *txn)
+            .await?;
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
+    async fn insert_tx_metadata(&self, rows: &[TxMetadata]) -> Result<()> {
+        let mut txn = self.pool.begin().await?;
+        for row in rows {
+            sqlx::query(
+                r#"
+                INSERT INTO tx_metadata (hash, key, value, chain_id)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT(chain_id, hash, key) DO UPDATE SET value = excluded.value;
+                "#,
+            )
+            .bind(&row.hash)
+            .bind(&row.key)
+            .bind(&row.value)
+            .bind(row.chain_id)
+            .execute(&mut *txn)
+            .await?;
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
+    async fn insert_logs(&self, logs: &[LogEntry]) -> Result<()> {
+        let mut txn = self.pool.begin().await?;
+        for log in logs {
+            sqlx::query(
+                r#"
+                INSERT INTO logs (tx_hash, log_index, block_number, address, topics, data, chain_id)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT(chain_id, tx_hash, log_index) DO NOTHING;
+                "#,
+            )
+            .bind(&log.tx_hash)
+            .bind(log.log_index)
+            .bind(log.block_number)
+            .bind(&log.address)
+            .bind(serde_json::to_string(&log.topics)?)
+            .bind(&log.data)
+            .bind(log.chain_id)
+            .execute(&mut *txn)
+            .await?;
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
+    async fn insert_token_transfers(&self, transfers: &[TokenTransfer]) -> Result<()> {
+        let mut txn = self.pool.begin().await?;
+        for transfer in transfers {
+            sqlx::query(
+                r#"
+                INSERT INTO token_transfers (
+                    tx_hash, log_index, block_number, token, from_addr, to_addr, amount,
+                    chain_id
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT(chain_id, tx_hash, log_index) DO NOTHING;
+                "#,
+            )
+            .bind(&transfer.tx_hash)
+            .bind(transfer.log_index)
+            .bind(transfer.block_number)
+            .bind(&transfer.token)
+            .bind(&transfer.from)
+            .bind(&transfer.to)
+            .bind(&transfer.amount)
+            .bind(transfer.chain_id)
+            .execute(&mut *txn)
+            .await?;
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
+    async fn create_sample(&self, started_at: i64) -> Result<i64> {
+        let id = sqlx::query_scalar(
+            "INSERT INTO mempool_samples (started_at) VALUES ($1) RETURNING id;",
+        )
+        .bind(started_at)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn insert_arrivals(
+        &self,
+        sample_id: i64,
+        chain_id: i64,
+        arrivals: &[(String, i64)],
+    ) -> Result<()> {
+        let mut txn = self.pool.begin().await?;
+        for (hash, offset_ms) in arrivals {
+            sqlx::query(
+                r#"
+                INSERT INTO sample_arrivals (sample_id, chain_id, hash, offset_ms)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT(sample_id, chain_id, hash) DO NOTHING;
+                "#,
+            )
+            .bind(sample_id)
+            .bind(chain_id)
+            .bind(hash)
+            .bind(offset_ms)
+            .execute(&mut *txn)
+            .await?;
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
+    async fn get_recent_stored_transactions(
+        &self,
+        limit: i64,
+        filter: &RecentTxFilter,
+    ) -> Result<Vec<StoredTx>> {
+        let mut qb = QueryBuilder::<Postgres>::new(format!(
+            "SELECT {} FROM transactions WHERE 1 = 1",
+            PG_TX_COLUMNS
+        ));
+        if let Some(address) = &filter.address {
+            qb.push(" AND (from_addr = ")
+                .push_bind(address)
+                .push(" OR to_addr = ")
+                .push_bind(address)
+                .push(")");
+        }
+        if filter.pending_only {
+            qb.push(" AND block_number IS NULL");
+        }
+        if let Some(to_any) = &filter.to_any {
+            if to_any.is_empty() {
+                return Ok(Vec::new());
+            }
+            qb.push(" AND to_addr = ANY(").push_bind(to_any).push(")");
+        }
+        match filter.has_input {
+            Some(true) => {
+                qb.push(
+                    " AND (input_compressed \
+                     OR (input_data IS NOT NULL AND input_data NOT IN ('', '0x')))",
+                );
+            }
+            Some(false) => {
+                qb.push(" AND input_data IN ('', '0x')");
+            }
+            None => {}
+        }
+        if let Some(mask) = filter.flags_any {
+            qb.push(" AND flags & ").push_bind(mask).push(" != 0");
+        }
+        if let Some(selector) = &filter.selector {
+            qb.push(" AND input_selector = ").push_bind(selector);
+        }
+        if let Some(chain_id) = filter.chain_id {
+            qb.push(" AND chain_id = ").push_bind(chain_id);
+        }
+        if let Some(tx_type) = filter.tx_type {
+            qb.push(" AND tx_type = ").push_bind(tx_type);
+        }
+        if let Some(min) = &filter.min_value_wei {
+            qb.push(" AND value_wei ~ '^[0-9]+$' AND (LENGTH(value_wei) > ")
+                .push_bind(min.len() as i32)
+                .push(" OR (LENGTH(value_wei) = ")
+                .push_bind(min.len() as i32)
+                .push(" AND value_wei >= ")
+                .push_bind(min)
+                .push("))");
+        }
+        if let Some(cursor) = &filter.before {
+            qb.push(" AND (COALESCE(timestamp, 0), hash) < (")
+                .push_bind(cursor.timestamp)
+                .push(", ")
+                .push_bind(&cursor.hash)
+                .push(")");
+        }
+        qb.push(match filter.order {
+            RecentOrder::Timestamp => " ORDER BY COALESCE(timestamp, 0) DESC, hash DESC",
+            RecentOrder::Block => {
+                " ORDER BY COALESCE(block_number, -1) DESC, COALESCE(tx_index, -1) DESC, hash DESC"
+            }
+        });
+        qb.push(" LIMIT ").push_bind(limit);
+
+        let rows = qb.build_query_as::<TxRow>().fetch_all(&self.pool).await?;
+        Ok(rows.into_iter().map(StoredTx::from).collect())
+    }
+
+    async fn max_transaction_seq(&self) -> Result<i64> {
+        let max: Option<i64> = sqlx::query_scalar("SELECT MAX(seq) FROM transactions;")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(max.unwrap_or(0))
+    }
+
+    /// `seq` is drawn when a row is inserted, so with concurrent writers a
+    /// row can commit after a higher one was already read and be skipped.
+    async fn get_transactions_after_seq(
+        &self,
+        seq: i64,
+        limit: i64,
+    ) -> Result<Vec<(i64, StoredTx)>> {
+        let sql = format!(
+            "SELECT seq AS rowid, {} FROM transactions WHERE seq > $1 ORDER BY seq LIMIT $2;",
+            PG_TX_COLUMNS
+        );
+        let rows = sqlx::query_as::<_, FeedRow>(&sql)
+            .bind(seq)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.rowid, StoredTx::from(row.tx)))
+            .collect())
+    }
+
+    async fn get_transactions_by_address(
+        &self,
+        address: &str,
+        limit: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<StoredTx>> {
+        let sql = format!(
+            r#"
+            SELECT * FROM (
+                SELECT {cols} FROM transactions
+                WHERE from_addr = $1 AND ($3::BIGINT IS NULL OR chain_id = $3)
+                UNION
+                SELECT {cols} FROM transactions
+                WHERE to_addr = $1 AND ($3::BIGINT IS NULL OR chain_id = $3)
+            ) AS t
+            ORDER BY timestamp IS NULL, timestamp DESC, hash DESC
+            LIMIT $2;
+            "#,
+            cols = PG_TX_COLUMNS
+        );
+        let rows = sqlx::query_as::<_, TxRow>(&sql)
+            .bind(address)
+            .bind(limit)
+            .bind(chain_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(StoredTx::from).collect())
+    }
+
+    async fn get_nonce_attempts(
+        &self,
+        from: &str,
+        nonce: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<NormalizedTx>> {
+        let sql = format!(
+            r#"
+            SELECT {} FROM transactions
+            WHERE from_addr = $1 AND nonce = $2 AND ($3::BIGINT IS NULL OR chain_id = $3)
+            ORDER BY seq;
+            "#,
+            PG_TX_COLUMNS
+        );
+        let rows = sqlx::query_as::<_, TxRow>(&sql)
+            .bind(from)
+            .bind(nonce)
+            .bind(chain_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(NormalizedTx::from).collect())
+    }
+
+    async fn get_address_timeline(
+        &self,
+        address: &str,
+        limit: i64,
+        before: Option<i64>,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<TimelineEntry>> {
+        let sql = format!(
+            r#"
+            SELECT {}
+            FROM transactions
+            WHERE (from_addr = $1 OR to_addr = $1)
+              AND ($2::BIGINT IS NULL OR timestamp < $2)
+              AND ($4::BIGINT IS NULL OR chain_id = $4)
+            ORDER BY COALESCE(timestamp, 9223372036854775807) DESC, nonce DESC
+            LIMIT $3;
+            "#,
+            PG_TX_COLUMNS
+        );
+        let mut rows = sqlx::query_as::<_, TxRow>(&sql)
+            .bind(address)
+            .bind(before)
+            .bind(limit)
+            .bind(chain_id)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.reverse();
+        Ok(rows
+            .into_iter()
+            .map(|row| timeline_entry(address, row.into()))
+            .collect())
+    }
+
+    async fn get_address_summary(
+        &self,
+        address: &str,
+        chain_id: Option<i64>,
+    ) -> Result<AddressSummary> {
+        let row = sqlx::query(
+            r#"
+            SELECT
+                COALESCE(SUM(CASE WHEN from_addr = $1 THEN 1 ELSE 0 END), 0)::BIGINT
+                    AS sent_count,
+                COALESCE(SUM(CASE WHEN to_addr = $1 THEN 1 ELSE 0 END), 0)::BIGINT
+                    AS received_count,
+                MIN(timestamp) AS first_seen,
+                MAX(timestamp) AS last_seen
+            FROM transactions
+            WHERE (from_addr = $1 OR to_addr = $1) AND ($2::BIGINT IS NULL OR chain_id = $2);
+            "#,
+        )
+        .bind(address)
+        .bind(chain_id)
+        .fetch_one(&self.pool)
+        .await?;
+        let values = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT value_wei FROM transactions
+            WHERE from_addr = $1 AND ($2::BIGINT IS NULL OR chain_id = $2);
+            "#,
+        )
+        .bind(address)
+        .bind(chain_id)
+        .fetch(&self.pool);
+        let (_, total) = sum_wei(values, "address summary").await?;
+        Ok(AddressSummary {
+            address: address.to_string(),
+            sent_count: row.try_get("sent_count")?,
+            received_count: row.try_get("received_count")?,
+            total_sent_wei: total.to_string(),
+            first_seen: row.try_get("first_seen")?,
+            last_seen: row.try_get("last_seen")?,
+        })
+    }
+
+    async fn get_flow_between(
+        &self,
+        from: &str,
+        to: &str,
+        chain_id: Option<i64>,
+    ) -> Result<FlowSummary> {
+        let rows = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT value_wei FROM transactions
+            WHERE from_addr = $1 AND to_addr = $2 AND ($3::BIGINT IS NULL OR chain_id = $3);
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .bind(chain_id)
+        .fetch(&self.pool);
+        let (tx_count, total) = sum_wei(rows, "flow sum").await?;
+        Ok(FlowSummary {
+            from: from.to_string(),
+            to: to.to_string(),
+            tx_count,
+            total_value_wei: total.to_string(),
+        })
+    }
+
+    async fn get_window_summary(
+        &self,
+        from_block: i64,
+        to_block: i64,
+        chain_id: Option<i64>,
+    ) -> Result<WindowSummary> {
+        let gas_sql = format!(
+            "{};",
+            SqlDialect::Postgres.gas_stats_select(
+                "AND block_number BETWEEN $1 AND $2 AND ($3::BIGINT IS NULL OR chain_id = $3)"
+            )
+        );
+        let gas = sqlx::query_as::<_, GasStatsRow>(&gas_sql)
+            .bind(from_block)
+            .bind(to_block)
+            .bind(chain_id)
+            .fetch_one(&self.pool)
+            .await?
+            .into_stats();
+        let unique_addresses: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM (
+                SELECT from_addr FROM transactions
+                WHERE block_number BETWEEN $1 AND $2 AND ($3::BIGINT IS NULL OR chain_id = $3)
+                UNION
+                SELECT to_addr FROM transactions
+                WHERE block_number BETWEEN $1 AND $2 AND ($3::BIGINT IS NULL OR chain_id = $3)
+                  AND to_addr IS NOT NULL
+            ) AS addresses;
+            "#,
+        )
+        .bind(from_block)
+        .bind(to_block)
+        .bind(chain_id)
+        .fetch_one(&self.pool)
+        .await?;
+        let rows = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT value_wei FROM transactions
+            WHERE block_number BETWEEN $1 AND $2 AND ($3::BIGINT IS NULL OR chain_id = $3);
+            "#,
+        )
+        .bind(from_block)
+        .bind(to_block)
+        .bind(chain_id)
+        .fetch(&self.pool);
+        let (tx_count, total) = sum_wei(rows, "window sum").await?;
+        Ok(WindowSummary {
+            from_block,
+            to_block,
+            tx_count,
+            avg_gas_price_wei: gas.map(|g| g.avg),
+            avg_value_wei: (tx_count > 0).then(|| (total / U256::from(tx_count)).to_string()),
+            unique_addresses,
+        })
+    }
+
+    async fn get_tx_metadata(&self, chain_id: i64, hash: &str) -> Result<Vec<TxMetadata>> {
+        let rows = sqlx::query_as::<_, (String, String, String)>(
+            "SELECT hash, key, value FROM tx_metadata WHERE chain_id = $1 AND hash = $2 ORDER BY key;",
+        )
+        .bind(chain_id)
+        .bind(hash)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(hash, key, value)| TxMetadata {
+                chain_id,
+                hash,
+                key,
+                value,
+            })
+            .collect())
+    }
+
+    async fn get_arrival_rate(
+        &self,
+        sample_id: Option<i64>,
+        bucket_secs: i64,
+    ) -> Result<Option<ArrivalRate>> {
+        let sample: Option<(i64, i64)> = sqlx::query_as(
+            r#"
+            SELECT id, started_at
+            FROM mempool_samples
+            WHERE $1::BIGINT IS NULL OR id = $1
+            ORDER BY id DESC
+            LIMIT 1;
+            "#,
+        )
+        .bind(sample_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some((sample_id, started_at)) = sample else {
+            return Ok(None);
+        };
+        let bucket_ms = bucket_secs.max(1) * 1000;
+        let rows: Vec<(i64, i64)> = sqlx::query_as(
+            r#"
+            SELECT offset_ms / $2 AS bucket, COUNT(*)
+            FROM sample_arrivals
+            WHERE sample_id = $1
+            GROUP BY bucket
+            ORDER BY bucket;
+            "#,
+        )
+        .bind(sample_id)
+        .bind(bucket_ms)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(Some(ArrivalRate {
+            sample_id,
+            started_at,
+            bucket_secs: bucket_ms / 1000,
+            buckets: rows
+                .into_iter()
+                .map(|(bucket, tx_count)| ArrivalBucket {
+                    offset_secs: bucket * bucket_ms / 1000,
+                    tx_count,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn get_throughput(
+        &self,
+        bucket_secs: i64,
+        window_secs: i64,
+        now: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<ThroughputBucket>> {
+        let rows: Vec<(i64, i64)> = sqlx::query_as(
+            r#"
+            SELECT (timestamp / $1) * $1 AS bucket_start, COUNT(*)
+            FROM transactions
+            WHERE timestamp >= $2 AND timestamp <= $3 AND ($4::BIGINT IS NULL OR chain_id = $4)
+            GROUP BY bucket_start
+            ORDER BY bucket_start;
+            "#,
+        )
+        .bind(bucket_secs.max(1))
+        .bind(now.saturating_sub(window_secs))
+        .bind(now)
+        .bind(chain_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(bucket_start, tx_count)| ThroughputBucket {
+                bucket_start,
+                tx_count,
+            })
+            .collect())
+    }
+
+    async fn get_transaction_by_hash(
+        &self,
+        hash: &str,
+        chain_id: Option<i64>,
+    ) -> Result<Option<StoredTx>> {
+        let sql = format!(
+            "SELECT {} FROM transactions WHERE hash = $1 AND ($2::BIGINT IS NULL OR chain_id = $2) \
+             ORDER BY chain_id LIMIT 1;",
+            PG_TX_COLUMNS
+        );
+        let row = sqlx::query_as::<_, TxRow>(&sql)
+            .bind(hash)
+            .bind(chain_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(StoredTx::from))
+    }
+
+    async fn get_transactions_by_hashes(
+        &self,
+        hashes: &[String],
+        chain_id: Option<i64>,
+    ) -> Result<Vec<StoredTx>> {
+        let sql = format!(
+            "SELECT {} FROM transactions \
+             WHERE hash = ANY($1) AND ($2::BIGINT IS NULL OR chain_id = $2) ORDER BY chain_id;",
+            PG_TX_COLUMNS
+        );
+        let rows = sqlx::query_as::<_, TxRow>(&sql)
+            .bind(hashes)
+            .bind(chain_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(StoredTx::from).collect())
+    }
+
+    fn stream_block_transactions(
+        &self,
+        last_n_blocks: i64,
+        chain_id: Option<i64>,
+    ) -> BoxStream<'_, Result<NormalizedTx>> {
+        static SQL: OnceLock<String> = OnceLock::new();
+        let sql = SQL.get_or_init(|| {
+            format!(
+                r#"
+                SELECT {}
+                FROM transactions
+                WHERE (chain_id, block_number) IN (
+                    SELECT chain_id, block_number
+                    FROM blocks
+                    WHERE $2::BIGINT IS NULL OR chain_id = $2
+                    ORDER BY block_number DESC
+                    LIMIT $1
+                );
+                "#,
+                PG_TX_COLUMNS
+            )
+        });
+        sqlx::query_as::<_, TxRow>(sql)
+            .bind(last_n_blocks)
+            .bind(chain_id)
+            .fetch(&self.pool)
+            .map_ok(NormalizedTx::from)
+            .map_err(anyhow::Error::from)
+            .boxed()
+    }
+
+    async fn get_nonce_gaps(&self, address: &str, chain_id: Option<i64>) -> Result<NonceGaps> {
+        let nonces = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT DISTINCT nonce
+            FROM transactions
+            WHERE from_addr = $1
+              AND block_number IS NOT NULL
+              AND ($2::BIGINT IS NULL OR chain_id = $2)
+            ORDER BY nonce;
+            "#,
+        )
+        .bind(address)
+        .bind(chain_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(NonceGaps {
+            address: address.to_string(),
+            gaps: crate::analysis::nonce_gaps(&nonces),
+            nonces,
+        })
+    }
+
+    async fn get_top_senders(&self, limit: i64, chain_id: Option<i64>) -> Result<Vec<TopSender>> {
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            r#"
+            SELECT from_addr, COUNT(*)
+            FROM transactions
+            WHERE $2::BIGINT IS NULL OR chain_id = $2
+            GROUP BY from_addr
+            ORDER BY COUNT(*) DESC
+            LIMIT $1;
+            "#,
+        )
+        .bind(limit)
+        .bind(chain_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(address, count)| TopSender { address, count })
+            .collect())
+    }
+
+    async fn get_gas_stats(
+        &self,
+        last_n_blocks: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Option<GasStats>> {
+        let sql = format!(
+            "{};",
+            SqlDialect::Postgres.gas_stats_select(
+                r#"AND (chain_id, block_number) IN (
+                  SELECT chain_id, block_number
+                  FROM blocks
+                  WHERE $2::BIGINT IS NULL OR chain_id = $2
+                  ORDER BY block_number DESC
+                  LIMIT $1
+              )"#
+            )
+        );
+        let row = sqlx::query_as::<_, GasStatsRow>(&sql)
+            .bind(last_n_blocks)
+            .bind(chain_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(row.into_stats())
+    }
+
+    async fn get_top_receivers(&self, limit: i64, chain_id: Option<i64>) -> Result<Vec<TopSender>> {
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            r#"
+            SELECT to_addr, COUNT(*)
+            FROM transactions
+            WHERE to_addr IS NOT NULL AND ($2::BIGINT IS NULL OR chain_id = $2)
+            GROUP BY to_addr
+            ORDER BY COUNT(*) DESC
+            LIMIT $1;
+            "#,
+        )
+        .bind(limit)
+        .bind(chain_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(address, count)| TopSender { address, count })
+            .collect())
+    }
+
+    async fn get_gas_histogram(
+        &self,
+        last_n_blocks: i64,
+        bucket_gwei: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<GasHistogramBucket>> {
+        let bucket_gwei = bucket_gwei.max(1);
+        let sql = format!(
+            "{};",
+            SqlDialect::Postgres.gas_histogram_select(
+                r#"AND (chain_id, block_number) IN (
+                  SELECT chain_id, block_number
+                  FROM blocks
+                  WHERE $3::BIGINT IS NULL OR chain_id = $3
+                  ORDER BY block_number DESC
+                  LIMIT $1
+              )"#,
+                "$2::BIGINT"
+            )
+        );
+        let rows: Vec<(i64, i64)> = sqlx::query_as(&sql)
+            .bind(last_n_blocks)
+            .bind(bucket_gwei.saturating_mul(1_000_000_000))
+            .bind(chain_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(bucket, count)| GasHistogramBucket {
+                bucket_gwei: bucket * bucket_gwei,
+                count,
+            })
+            .collect())
+    }
+
+    async fn get_gas_stats_at(
+        &self,
+        timestamp: i64,
+        window_secs: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Option<GasStats>> {
+        let half = window_secs.max(0) / 2;
+        let sql = format!(
+            "{};",
+            SqlDialect::Postgres.gas_stats_select(
+                "AND timestamp BETWEEN $1 AND $2 AND ($3::BIGINT IS NULL OR chain_id = $3)"
+            )
+        );
+        let row = sqlx::query_as::<_, GasStatsRow>(&sql)
+            .bind(timestamp.saturating_sub(half))
+            .bind(timestamp.saturating_add(half))
+            .bind(chain_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(row.into_stats())
+    }
+
+    async fn get_base_fee_stats(
+        &self,
+        last_n_blocks: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Option<BaseFeeStats>> {
+        let row: (Option<i64>, Option<i64>, Option<f64>, i64) = sqlx::query_as(
+            r#"
+            SELECT
+                MIN(CAST(base_fee_wei AS BIGINT)),
+                MAX(CAST(base_fee_wei AS BIGINT)),
+                AVG(CAST(base_fee_wei AS BIGINT))::DOUBLE PRECISION,
+                COUNT(*)
+            FROM (
+                SELECT base_fee_wei
+                FROM blocks
+                WHERE $2::BIGINT IS NULL OR chain_id = $2
+                ORDER BY block_number DESC
+                LIMIT $1
+            ) AS recent
+            WHERE base_fee_wei IS NOT NULL
+              AND base_fee_wei ~ '^[0-9]+$'
+              AND LENGTH(base_fee_wei) <= 18;
+            "#,
+        )
+        .bind(last_n_blocks)
+        .bind(chain_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(match row {
+            (Some(min), Some(max), Some(avg), blocks) => Some(BaseFeeStats {
+                min,
+                max,
+                avg,
+                blocks,
+            }),
+            _ => None,
+        })
+    }
+
+    async fn get_max_value_transaction(
+        &self,
+        last_n_blocks: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Option<NormalizedTx>> {
+        let sql = format!(
+            r#"
+            SELECT {}
+            FROM transactions
+            WHERE value_wei ~ '^[0-9]+$'
+              AND (chain_id, block_number) IN (
+                  SELECT chain_id, block_number
+                  FROM blocks
+                  WHERE $2::BIGINT IS NULL OR chain_id = $2
+                  ORDER BY block_number DESC
+                  LIMIT $1
+              )
+            ORDER BY LENGTH(value_wei) DESC, value_wei DESC
+            LIMIT 1;
+            "#,
+            PG_TX_COLUMNS
+        );
+        let row = sqlx::query_as::<_, TxRow>(&sql)
+            .bind(last_n_blocks)
+            .bind(chain_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(NormalizedTx::from))
+    }
+
+    async fn get_recent_blocks(
+        &self,
+        limit: i64,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<BlockSummary>> {
+        let rows = sqlx::query_as::<_, (i64, String, i64, Option<String>, i64, i64)>(
+            r#"
+            SELECT b.block_number, b.block_hash, b.timestamp, b.base_fee_wei, b.chain_id,
+                   COUNT(t.hash)
+            FROM (
+                SELECT chain_id, block_number, block_hash, timestamp, base_fee_wei
+                FROM blocks
+                WHERE $2::BIGINT IS NULL OR chain_id = $2
+                ORDER BY block_number DESC
+                LIMIT $1
+            ) b
+            LEFT JOIN transactions t
+                ON t.chain_id = b.chain_id AND t.block_number = b.block_number
+            GROUP BY b.chain_id, b.block_number, b.block_hash, b.timestamp, b.base_fee_wei
+            ORDER BY b.block_number DESC, b.chain_id;
+            "#,
+        )
+        .bind(limit)
+        .bind(chain_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(number, hash, timestamp, base_fee_wei, chain_id, tx_count)| BlockSummary {
+                    block: BlockInfo {
+                        number,
+                        hash,
+                        timestamp,
+                        base_fee_wei,
+                        chain_id,
+                    },
+                    tx_count,
+                },
+            )
+            .collect())
+    }
+
+    async fn get_recent_logs(
+        &self,
+        limit: i64,
+        address: Option<&str>,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<LogEntry>> {
+        #[derive(FromRow)]
+        struct Row {
+            chain_id: i64,
+            tx_hash: String,
+            log_index: i64,
+            block_number: i64,
+            address: String,
+            topics: String,
+            data: String,
+        }
+
+        let rows = sqlx::query_as::<_, Row>(
+            r#"
+            SELECT chain_id, tx_hash, log_index, block_number, address, topics, data
+            FROM logs
+            WHERE ($1::TEXT IS NULL OR address = $1)
+              AND ($3::BIGINT IS NULL OR chain_id = $3)
+            ORDER BY block_number DESC, log_index DESC
+            LIMIT $2;
+            "#,
+        )
+        .bind(address)
+        .bind(limit)
+        .bind(chain_id)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                Ok(LogEntry {
+                    topics: serde_json::from_str(&row.topics)?,
+                    chain_id: row.chain_id,
+                    tx_hash: row.tx_hash,
+                    log_index: row.log_index,
+                    block_number: row.block_number,
+                    address: row.address,
+                    data: row.data,
+                })
+            })
+            .collect()
+    }
+
+    async fn get_token_transfers(
+        &self,
+        limit: i64,
+        token: Option<&str>,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<TokenTransfer>> {
+        #[derive(FromRow)]
+        struct Row {
+            chain_id: i64,
+            tx_hash: String,
+            log_index: i64,
+            block_number: Option<i64>,
+            token: String,
+            from_addr: String,
+            to_addr: String,
+            amount: String,
+        }
+
+        // Postgres sorts NULLs first in descending order; SQLite sorts them last.
+        let rows = sqlx::query_as::<_, Row>(
+            r#"
+            SELECT chain_id, tx_hash, log_index, block_number, token, from_addr, to_addr, amount
+            FROM token_transfers
+            WHERE ($1::TEXT IS NULL OR token = $1)
+              AND ($3::BIGINT IS NULL OR chain_id = $3)
+            ORDER BY block_number DESC NULLS LAST, log_index DESC
+            LIMIT $2;
+            "#,
+        )
+        .bind(token)
+        .bind(limit)
+        .bind(chain_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| TokenTransfer {
+                chain_id: row.chain_id,
+                tx_hash: row.tx_hash,
+                log_index: row.log_index,
+                block_number: row.block_number,
+                token: row.token,
+                from: row.from_addr,
+                to: row.to_addr,
+                amount: row.amount,
+            })
+            .collect())
+    }
+
+    async fn get_latest_block_number(&self, chain_id: Option<i64>) -> Result<Option<i64>> {
+        let latest: Option<i64> = sqlx::query_scalar(
+            "SELECT MAX(block_number) FROM blocks WHERE $1::BIGINT IS NULL OR chain_id = $1;",
+        )
+        .bind(chain_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(latest)
+    }
+
+    async fn count_rows(&self) -> Result<RowCounts> {
+        let row = sqlx::query(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM blocks) as blocks,
+                (SELECT COUNT(*) FROM transactions) as transactions;
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(RowCounts {
+            blocks: row.try_get("blocks")?,
+            transactions: row.try_get("transactions")?,
+        })
+    }
+
+    async fn prune_before(&self, cutoff: i64) -> Result<RowCounts> {
+        let mut txn = self.pool.begin().await?;
+        for (table, column, _) in PG_TX_DEPENDENT_TABLES {
+            let sql = format!(
+                "DELETE FROM {} WHERE (chain_id, {}) IN \
+                 (SELECT chain_id, hash FROM transactions WHERE timestamp < $1);",
+                table, column
+            );
+            sqlx::query(&sql).bind(cutoff).execute(&mut *txn).await?;
+        }
+        let transactions = sqlx::query("DELETE FROM transactions WHERE timestamp < $1;")
+            .bind(cutoff)
+            .execute(&mut *txn)
+            .await?
+            .rows_affected();
+        let blocks = sqlx::query(
+            r#"
+            DELETE FROM blocks
+            WHERE timestamp < $1
+              AND NOT EXISTS (
+                  SELECT 1 FROM transactions t
+                  WHERE t.chain_id = blocks.chain_id AND t.block_number = blocks.block_number
+              );
+            "#,
+        )
+        .bind(cutoff)
+        .execute(&mut *txn)
+        .await?
+        .rows_affected();
+        txn.commit().await?;
+        Ok(RowCounts {
+            blocks: blocks as i64,
+            transactions: transactions as i64,
+        })
+    }
+
+    async fn prune_to_count(&self, max: u64) -> Result<RowCounts> {
+        let mut txn = self.pool.begin().await?;
+        // Ranked once into a scratch table the dependent deletes share.
+        sqlx::query("CREATE TEMP TABLE pruned_txs (chain_id BIGINT, hash TEXT) ON COMMIT DROP;")
+            .execute(&mut *txn)
+            .await?;
+        // Postgres sorts NULLs first in descending order; SQLite sorts them
+        // last, so spell that out to rank rows the same way.
+        sqlx::query(
+            r#"
+            INSERT INTO pruned_txs (chain_id, hash)
+            SELECT chain_id, hash
+            FROM transactions
+            ORDER BY timestamp IS NULL DESC, timestamp DESC, block_number DESC NULLS LAST,
+                tx_index DESC NULLS LAST, hash DESC
+            OFFSET $1;
+            "#,
+        )
+        .bind(i64::try_from(max).unwrap_or(i64::MAX))
+        .execute(&mut *txn)
+        .await?;
+        for (table, column, _) in PG_TX_DEPENDENT_TABLES {
+            let sql = format!(
+                "DELETE FROM {} WHERE (chain_id, {}) IN (SELECT chain_id, hash FROM pruned_txs);",
+                table, column
+            );
+            sqlx::query(&sql).execute(&mut *txn).await?;
+        }
+        let transactions = sqlx::query(
+            "DELETE FROM transactions WHERE (chain_id, hash) IN (SELECT chain_id, hash FROM pruned_txs);",
+        )
+        .execute(&mut *txn)
+        .await?
+        .rows_affected();
+        let blocks = if transactions > 0 {
+            sqlx::query(
+                r#"
+                DELETE FROM blocks
+                WHERE block_number < (
+                    SELECT MIN(t.block_number) FROM transactions t
+                    WHERE t.chain_id = blocks.chain_id AND t.block_number IS NOT NULL
+                );
+                "#,
+            )
+            .execute(&mut *txn)
+            .await?
+            .rows_affected()
+        } else {
+            0
+        };
+        txn.commit().await?;
+        Ok(RowCounts {
+            blocks: blocks as i64,
+            transactions: transactions as i64,
+        })
+    }
+
+    async fn vacuum(&self) -> Result<()> {
+        sqlx::query("VACUUM;").execute(&self.pool).await?;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "postgres-tests"))]
+mod tests {
+    use super::*;
+
+    /// Connects to the scratch database in `TEST_DATABASE_URL` after dropping
+    /// whatever an earlier run left there.
+    async fn fresh_storage(options: &StorageOptions) -> PostgresStorage {
+        let url = std::env::var("TEST_DATABASE_URL")
+            .expect("TEST_DATABASE_URL must name a scratch Postgres database");
+        let pool = PgPool::connect(&url).await.unwrap();
+        sqlx::query(
+            "DROP TABLE IF EXISTS sample_arrivals, mempool_samples, tx_metadata, logs, \
+             token_transfers, transactions, blocks CASCADE;",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool.close().await;
+        PostgresStorage::connect(&url, options).await.unwrap()
+    }
+
+    fn tx(hash: &str, nonce: i64, block_number: Option<i64>) -> NormalizedTx {
+        NormalizedTx {
+            hash: hash.to_string(),
+            from: "0xaaa".to_string(),
+            to: Some("0xbbb".to_string()),
+            value_wei: "5".to_string(),
+            gas: 21_000,
+            gas_price_wei: Some("1000".to_string()),
+            max_fee_per_gas_wei: None,
+            max_priority_fee_per_gas_wei: None,
+            nonce,
+            block_number,
+            timestamp: block_number.map(|_| 1_700_000_000),
+            status: None,
+            input_data: Some("0x".to_string()),
+            tx_index: block_number.map(|_| nonce),
+            chain_id: 1,
+            tx_type: None,
+            input_size: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn round_trips_compressed_calldata_feed_samples_and_reads() {
+        let options = StorageOptions {
+            compress_input: true,
+            ..Default::default()
+        };
+        let store = fresh_storage(&options).await;
+        store
+            .insert_block(&BlockInfo {
+                number: 1,
+                hash: "0xb1".to_string(),
+                timestamp: 1_700_000_000,
+                base_fee_wei: Some("100".to_string()),
+                chain_id: 1,
+            })
+            .await
+            .unwrap();
+        let calldata = format!("0xa9059cbb{}", "00".repeat(200));
+        let mined = NormalizedTx {
+            input_data: Some(calldata.clone()),
+            ..tx("0xa", 0, Some(1))
+        };
+        store
+            .insert_transactions(&[mined, tx("0xb", 1, None)], &options)
+            .await
+            .unwrap();
+
+        let (compressed, hex_is_null): (bool, bool) = sqlx::query_as(
+            "SELECT input_compressed, input_data IS NULL FROM transactions WHERE hash = '0xa';",
+        )
+        .fetch_one(&store.pool)
+        .await
+        .unwrap();
+        assert!(compressed && hex_is_null);
+        let read = store.get_transaction_by_hash("0xa", None).await.unwrap();
+        assert_eq!(
+            read.unwrap().tx.input_data.as_deref(),
+            Some(calldata.as_str())
+        );
+        let calls = store
+            .get_recent_stored_transactions(
+                10,
+                &RecentTxFilter {
+                    has_input: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].input_selector.as_deref(), Some("0xa9059cbb"));
+
+        // The feed reads rows back in insertion order, then only newer ones.
+        let feed = store.get_transactions_after_seq(0, 10).await.unwrap();
+        let hashes: Vec<_> = feed.iter().map(|(_, row)| row.tx.hash.as_str()).collect();
+        assert_eq!(hashes, ["0xa", "0xb"]);
+        let after = store.max_transaction_seq().await.unwrap();
+        store
+            .insert_transactions(&[tx("0xc", 2, None)], &options)
+            .await
+            .unwrap();
+        let newer = store.get_transactions_after_seq(after, 10).await.unwrap();
+        assert_eq!(newer.len(), 1);
+        assert_eq!(newer[0].1.tx.hash, "0xc");
+
+        let sample_id = store.create_sample(1_700_000_100).await.unwrap();
+        store
+            .insert_arrivals(sample_id, 1, &[("0xb".to_string(), 1_500)])
+            .await
+            .unwrap();
+        let rate = store.get_arrival_rate(None, 1).await.unwrap().unwrap();
+        assert_eq!(rate.sample_id, sample_id);
+        assert_eq!(rate.buckets.len(), 1);
+        assert_eq!(rate.buckets[0].offset_secs, 1);
+
+        store
+            .insert_tx_metadata(&[TxMetadata {
+                chain_id: 1,
+                hash: "0xb".to_string(),
+                key: "source".to_string(),
+                value: "ws".to_string(),
+            }])
+            .await
+            .unwrap();
+        assert_eq!(store.get_tx_metadata(1, "0xb").await.unwrap().len(), 1);
+
+        let stats = store.get_gas_stats(10, Some(1)).await.unwrap().unwrap();
+        assert_eq!((stats.min, stats.max), (1000, 1000));
+        let base_fee = store.get_base_fee_stats(10, None).await.unwrap().unwrap();
+        assert_eq!(base_fee.max, 100);
+        let summary = store.get_address_summary("0xaaa", None).await.unwrap();
+        assert_eq!(summary.sent_count, 3);
+        assert_eq!(summary.total_sent_wei, "15");
+        let elsewhere = store.get_address_summary("0xaaa", Some(10)).await.unwrap();
+        assert_eq!(elsewhere.sent_count, 0);
+        let timeline = store
+            .get_address_timeline("0xbbb", 10, None, None)
+            .await
+            .unwrap();
+        assert_eq!(timeline.len(), 3);
+        let by_hash = store
+            .get_transactions_by_hashes(&["0xa".to_string(), "0xz".to_string()], None)
+            .await
+            .unwrap();
+        assert_eq!(by_hash.len(), 1);
+        let included: Vec<NormalizedTx> = store
+            .stream_block_transactions(10, None)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(included.len(), 1);
+        let blocks = store.get_recent_blocks(10, None).await.unwrap();
+        assert_eq!(blocks[0].tx_count, 1);
+
+        // Pruning to the newest row (pending rows rank newest) takes the
+        // arrivals and metadata of the pruned ones along.
+        store.prune_to_count(1).await.unwrap();
+        assert!(store.get_tx_metadata(1, "0xb").await.unwrap().is_empty());
+        let rate = store.get_arrival_rate(None, 1).await.unwrap().unwrap();
+        assert!(rate.buckets.is_empty());
+    }
+}
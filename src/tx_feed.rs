@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::sync::{broadcast, Mutex};
+use tracing::warn;
+
+use crate::models::StoredTx;
+use crate::storage::Storage;
+
+/// Transactions buffered per subscriber before it is considered too slow.
+pub const TX_FEED_CAPACITY: usize = 1024;
+
+/// How often the feed looks for rows stored since its last read.
+pub const TX_FEED_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Rows read per poll; a larger backlog drains over the following polls.
+const TX_FEED_BATCH: i64 = 500;
+
+/// Broadcast of newly stored transactions behind `/ws/txs`. New rows are
+/// read back from the database in insertion order (see
+/// [`Storage::get_transactions_after_seq`]), so transactions written by other
+/// processes (`follow`, `mempool-sample`) reach subscribers as well as those
+/// stored by `serve` itself. The poller only runs while someone is
+/// subscribed. Publishing never waits: a subscriber that falls
+/// `TX_FEED_CAPACITY` messages behind sees `RecvError::Lagged`.
+#[derive(Clone)]
+pub struct TxFeed {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    store: Arc<dyn Storage>,
+    sender: broadcast::Sender<StoredTx>,
+    /// Whether a poller task is running; held while starting or stopping it
+    /// so a new subscriber never sees a poller that is about to exit.
+    polling: Mutex<bool>,
+}
+
+impl TxFeed {
+    pub fn new(store: Arc<dyn Storage>) -> Self {
+        let (sender, _) = broadcast::channel(TX_FEED_CAPACITY);
+        Self {
+            inner: Arc::new(Inner {
+                store,
+                sender,
+                polling: Mutex::new(false),
+            }),
+        }
+    }
+
+    /// Subscribes to rows stored from now on, starting the poller if it is
+    /// not already running.
+    pub async fn subscribe(&self) -> Result<broadcast::Receiver<StoredTx>> {
+        let mut polling = self.inner.polling.lock().await;
+        let receiver = self.inner.sender.subscribe();
+        if !*polling {
+            let after = self.inner.store.max_transaction_seq().await?;
+            tokio::spawn(poll(self.inner.clone(), after));
+            *polling = true;
+        }
+        Ok(receiver)
+    }
+}
+
+async fn poll(inner: Arc<Inner>, mut after: i64) {
+    let mut ticker = tokio::time::interval(TX_FEED_POLL_INTERVAL);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        {
+            let mut polling = inner.polling.lock().await;
+            if inner.sender.receiver_count() == 0 {
+                *polling = false;
+                return;
+            }
+        }
+        match inner
+            .store
+            .get_transactions_after_seq(after, TX_FEED_BATCH)
+            .await
+        {
+            Ok(rows) => {
+                for (seq, tx) in rows {
+                    after = seq;
+                    // An error only means every receiver has gone away.
+                    let _ = inner.sender.send(tx);
+                }
+            }
+            Err(err) => warn!("tx feed poll failed: {}", err),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use ethers_core::types::Transaction;
+
+use crate::models::TxMetadata;
+
+/// Derives extra `(key, value)` pairs from a raw transaction; results land in
+/// the `tx_metadata` table keyed by hash.
+pub type MetadataFn = dyn Fn(&Transaction) -> Vec<(String, String)> + Send + Sync;
+
+/// Registered normalization hooks, run on every transaction ingested through
+/// an `EthClient` that carries them.
+#[derive(Clone, Default)]
+pub struct MetadataHooks {
+    hooks: Vec<Arc<MetadataFn>>,
+}
+
+impl MetadataHooks {
+    pub fn register<F>(&mut self, hook: F)
+    where
+        F: Fn(&Transaction) -> Vec<(String, String)> + Send + Sync + 'static,
+    {
+        self.hooks.push(Arc::new(hook));
+    }
+
+    /// Registers a built-in hook by name, returning `false` for unknown names.
+    pub fn register_builtin(&mut self, name: &str) -> bool {
+        match name {
+            "envelope" => self.register(envelope_hook),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Runs every hook against `tx`, tagging the output with its chain and hash.
+    pub fn collect(&self, chain_id: i64, hash: &str, tx: &Transaction) -> Vec<TxMetadata> {
+        self.hooks
+            .iter()
+            .flat_map(|hook| hook(tx))
+            .map(|(key, value)| TxMetadata {
+                chain_id,
+                hash: hash.to_string(),
+                key,
+                value,
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for MetadataHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetadataHooks")
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+/// Built-in example: records the EIP-2718 envelope type and access-list size,
+/// which `NormalizedTx` does not carry.
+pub fn envelope_hook(tx: &Transaction) -> Vec<(String, String)> {
+    let mut out = Vec::new();
+    if let Some(kind) = tx.transaction_type {
+        out.push(("tx_type".to_string(), kind.to_string()));
+    }
+    if let Some(list) = &tx.access_list {
+        out.push(("access_list_entries".to_string(), list.0.len().to_string()));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers_core::types::U64;
+
+    #[test]
+    fn hooks_emit_tagged_pairs() {
+        let mut hooks = MetadataHooks::default();
+        assert!(hooks.register_builtin("envelope"));
+        assert!(!hooks.register_builtin("nope"));
+        hooks.register(|tx| {
+            vec![(
+                "nonce_parity".to_string(),
+                (tx.nonce.low_u64() % 2).to_string(),
+            )]
+        });
+
+        let tx = Transaction {
+            transaction_type: Some(U64::from(2)),
+            ..Default::default()
+        };
+        let rows = hooks.collect(1, "0xabc", &tx);
+        let pairs: Vec<(&str, &str)> = rows
+            .iter()
+            .map(|row| (row.key.as_str(), row.value.as_str()))
+            .collect();
+        assert_eq!(pairs, vec![("tx_type", "2"), ("nonce_parity", "0")]);
+        assert!(rows
+            .iter()
+            .all(|row| (row.chain_id, row.hash.as_str()) == (1, "0xabc")));
+    }
+}
@@ -0,0 +1,47 @@
+use crate::chain::format_units;
+
+/// Wei per ether, as a power of ten.
+pub const ETH_DECIMALS: u32 = 18;
+
+/// Formats a decimal wei amount as ether with all 18 decimals kept, e.g.
+/// `"1500000000000000000"` becomes `"1.500000000000000000"`. Works digit by
+/// digit on the string, so amounts of any width stay exact; `None` for
+/// anything that is not a non-negative decimal integer.
+pub fn wei_to_eth_string(wei: &str) -> Option<String> {
+    format_units(wei, ETH_DECIMALS, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wei_to_eth_keeps_eighteen_decimals() {
+        assert_eq!(
+            wei_to_eth_string("1000000000000000000").as_deref(),
+            Some("1.000000000000000000")
+        );
+        assert_eq!(
+            wei_to_eth_string("1").as_deref(),
+            Some("0.000000000000000001")
+        );
+        assert_eq!(
+            wei_to_eth_string("0").as_deref(),
+            Some("0.000000000000000000")
+        );
+        assert_eq!(
+            wei_to_eth_string("000999999999999999999").as_deref(),
+            Some("0.999999999999999999")
+        );
+        assert_eq!(
+            wei_to_eth_string(
+                "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+            )
+            .as_deref(),
+            Some("115792089237316195423570985008687907853269984665640564039457.584007913129639935")
+        );
+        assert_eq!(wei_to_eth_string(""), None);
+        assert_eq!(wei_to_eth_string("-1"), None);
+        assert_eq!(wei_to_eth_string("0.5"), None);
+    }
+}
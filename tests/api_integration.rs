@@ -1,12 +1,15 @@
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 use reqwest::Client;
 use tokio::task::JoinHandle;
 
-use rust_eth_mempool_lab::api::{app_router, AppState};
+use rust_eth_mempool_lab::api::{app_router, app_router_with_prefix, AppState};
+use rust_eth_mempool_lab::ingest::IngestOptions;
 use rust_eth_mempool_lab::ingest_stats::INGEST_STATS;
-use rust_eth_mempool_lab::models::{BlockInfo, NormalizedTx};
-use rust_eth_mempool_lab::storage::{self, DbPool};
+use rust_eth_mempool_lab::models::{BlockInfo, LogEntry, NormalizedTx, TokenTransfer, TxMetadata};
+use rust_eth_mempool_lab::storage::{self, DbPool, SqliteStorage};
 
 #[tokio::test]
 async fn health_endpoint_works() {
@@ -23,90 +26,2282 @@ async fn health_endpoint_works() {
     handle.abort();
 }
 
+#[tokio::test]
+async fn health_reports_degraded_when_database_is_unreachable() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    pool.close().await;
+
+    let res = Client::new()
+        .get(format!("{}/health", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body, serde_json::json!({"status": "degraded"}));
+    handle.abort();
+}
+
+#[tokio::test]
+async fn api_prefix_nests_routes_and_keeps_root_health() {
+    let pool = storage::init_pool(&temp_db_url()).await.unwrap();
+    seed_data(&pool).await.unwrap();
+    let (base_url, handle) = serve_router(app_router_with_prefix(
+        AppState::new(Arc::new(SqliteStorage::new(pool))),
+        "/mempool",
+    ))
+    .await;
+    let client = Client::new();
+    let status = |path: &'static str| {
+        let client = client.clone();
+        let url = format!("{}{}", base_url, path);
+        async move { client.get(url).send().await.unwrap().status().as_u16() }
+    };
+    assert_eq!(status("/mempool/stats/gas").await, 200);
+    assert_eq!(status("/mempool/health").await, 200);
+    assert_eq!(status("/health").await, 200);
+    assert_eq!(status("/stats/gas").await, 404);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn pretty_param_indents_json() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    let client = Client::new();
+    let compact = client
+        .get(format!("{}/stats/top-senders?limit=2", base_url))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let pretty = client
+        .get(format!(
+            "{}/stats/top-senders?limit=2&pretty=true",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(!compact.contains('\n'));
+    assert!(pretty.contains('\n'));
+    let a: serde_json::Value = serde_json::from_str(&compact).unwrap();
+    let b: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+    assert_eq!(a, b);
+    handle.abort();
+}
+
 #[tokio::test]
 async fn top_senders_returns_data() {
     let (base_url, handle) = spawn_app_with_data().await;
     let client = Client::new();
     let res = client
-        .get(format!("{}/stats/top-senders?limit=2", base_url))
+        .get(format!("{}/stats/top-senders?limit=2", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let body: serde_json::Value = res.json().await.unwrap();
+    let arr = body
+        .get("top_senders")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    assert!(!arr.is_empty());
+    handle.abort();
+}
+
+#[tokio::test]
+async fn top_receivers_skip_contract_creations() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    let router = "0x00000000000000000000000000000000000000c7";
+    let pending = |hash: &str, to: Option<&str>| NormalizedTx {
+        from: "0x00000000000000000000000000000000000000e6".to_string(),
+        to: to.map(str::to_string),
+        gas_price_wei: Some("1000".to_string()),
+        ..tx(hash)
+    };
+    storage::insert_transactions(
+        &pool,
+        &[
+            pending("0xr1", Some(router)),
+            pending("0xr2", Some(router)),
+            pending("0xr3", Some("0x00000000000000000000000000000000000000c8")),
+            // Three creations would outrank the router if NULL were grouped.
+            pending("0xc1", None),
+            pending("0xc2", None),
+            pending("0xc3", None),
+        ],
+    )
+    .await
+    .unwrap();
+
+    let body: serde_json::Value = Client::new()
+        .get(format!("{}/stats/top-receivers?limit=10", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    // The seeded rows each add one more receiver below the router.
+    let receivers = body["top_receivers"].as_array().unwrap();
+    assert!(receivers.len() > 1);
+    assert_eq!(
+        receivers[0],
+        serde_json::json!({"address": router, "count": 2})
+    );
+    assert!(receivers.iter().all(|r| !r["address"].is_null()));
+    handle.abort();
+}
+
+#[tokio::test]
+async fn gas_stats_returns_numbers() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    let client = Client::new();
+    let res = client
+        .get(format!("{}/stats/gas?blocks=10", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert!(body.get("min").is_some());
+    assert!(body.get("max").is_some());
+    assert!(body.get("avg").is_some());
+    assert_eq!(body["unit"], "gwei");
+    assert_eq!(body["min_formatted"], "0.000001");
+    // Seeded prices are 1000, 1500 and 2000 wei.
+    assert_eq!(body["p25"], 1000);
+    assert_eq!(body["p50"], 1500);
+    assert_eq!(body["p75"], 2000);
+    assert_eq!(body["p90"], 2000);
+    assert_eq!(body["p50_formatted"], "0.0000015");
+    handle.abort();
+}
+
+#[tokio::test]
+async fn metrics_expose_ingest_counters_and_route_latency() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    let client = Client::new();
+    client
+        .get(format!("{}/stats/gas", base_url))
+        .send()
+        .await
+        .unwrap();
+
+    let res = client
+        .get(format!("{}/metrics", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert!(res.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/plain"));
+    let text = res.text().await.unwrap();
+    assert!(text.contains("# TYPE mempool_blocks_total counter\n"));
+    assert!(text.contains("mempool_pending_transactions_total "));
+    assert!(text.contains("mempool_http_request_duration_seconds_count{route=\"/stats/gas\"} "));
+    handle.abort();
+}
+
+#[tokio::test]
+async fn gas_stats_price_dynamic_fee_txs_at_effective_price() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    storage::insert_block(
+        &pool,
+        &BlockInfo {
+            number: 2,
+            hash: "0xdef".to_string(),
+            timestamp: 1_700_000_012,
+            base_fee_wei: Some("1200".to_string()),
+            chain_id: 1,
+        },
+    )
+    .await
+    .unwrap();
+    let dynamic = |hash: &str, max_fee: &str| NormalizedTx {
+        max_fee_per_gas_wei: Some(max_fee.to_string()),
+        max_priority_fee_per_gas_wei: Some("100".to_string()),
+        block_number: Some(2),
+        timestamp: Some(1_700_000_012),
+        ..tx(hash)
+    };
+    storage::insert_transactions(&pool, &[dynamic("0xd1", "5000"), dynamic("0xd2", "1250")])
+        .await
+        .unwrap();
+
+    let body: serde_json::Value = Client::new()
+        .get(format!("{}/stats/gas?blocks=1", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    // base fee + tip = 1300, and the second tx is capped by its 1250 max fee.
+    assert_eq!(body["min"], 1250);
+    assert_eq!(body["max"], 1300);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn gas_at_uses_window_around_timestamp() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    let client = Client::new();
+    let fetch = |query: &str| {
+        let client = client.clone();
+        let url = format!("{}/stats/gas-at?{}", base_url, query);
+        async move { client.get(url).send().await.unwrap() }
+    };
+
+    // Seeded txs sit at ..._990, ..._000 and ..._005; a 4s window around
+    // 1_700_000_001 only reaches tx1.
+    let body: serde_json::Value = fetch("timestamp=1700000001&window_secs=4")
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["min"], 1000);
+    assert_eq!(body["max"], 1000);
+
+    let body: serde_json::Value = fetch("timestamp=1600000000").await.json().await.unwrap();
+    assert!(body["min"].is_null());
+
+    let res = fetch("window_secs=600").await;
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn largest_tx_compares_values_numerically() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    let client = Client::new();
+    let res = client
+        .get(format!("{}/stats/largest-tx?blocks=10", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let body: serde_json::Value = res.json().await.unwrap();
+    let tx = body.get("transaction").unwrap();
+    assert_eq!(
+        tx.get("value_wei").and_then(|v| v.as_str()),
+        Some("2000000000000000000")
+    );
+    handle.abort();
+}
+
+#[tokio::test]
+async fn replace_block_drops_orphaned_rows() {
+    let pool = storage::init_pool(&temp_db_url()).await.unwrap();
+    seed_data(&pool).await.unwrap();
+    storage::insert_tx_metadata(
+        &pool,
+        &[TxMetadata {
+            chain_id: 1,
+            hash: "0xtx1".to_string(),
+            key: "note".to_string(),
+            value: "orphaned".to_string(),
+        }],
+    )
+    .await
+    .unwrap();
+    let canonical = BlockInfo {
+        number: 1,
+        hash: "0xcanonical".to_string(),
+        timestamp: 1_700_000_001,
+        base_fee_wei: None,
+        chain_id: 1,
+    };
+
+    let removed = storage::replace_block(&pool, &canonical).await.unwrap();
+    assert_eq!(removed.blocks, 1);
+    assert_eq!(removed.transactions, 3);
+    let stored = storage::get_block(&pool, 1, 1).await.unwrap().unwrap();
+    assert_eq!(stored.hash, "0xcanonical");
+    assert_eq!(stored.timestamp, 1_700_000_001);
+    let counts = storage::count_rows(&pool).await.unwrap();
+    assert_eq!((counts.blocks, counts.transactions), (1, 0));
+    assert!(storage::get_tx_metadata(&pool, 1, "0xtx1")
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn arrival_rate_buckets_by_offset() {
+    let db_url = temp_db_url();
+    let pool = storage::init_pool(&db_url).await.unwrap();
+    let sample_id = storage::create_sample(&pool, 1_700_000_000).await.unwrap();
+    let arrivals = vec![
+        ("0xa".to_string(), 100),
+        ("0xb".to_string(), 900),
+        ("0xc".to_string(), 2_500),
+    ];
+    storage::insert_arrivals(&pool, sample_id, 1, &arrivals)
+        .await
+        .unwrap();
+
+    let rate = storage::get_arrival_rate(&pool, Some(sample_id), 1)
+        .await
+        .unwrap()
+        .unwrap();
+    let counts: Vec<(i64, i64)> = rate
+        .buckets
+        .iter()
+        .map(|b| (b.offset_secs, b.tx_count))
+        .collect();
+    assert_eq!(counts, vec![(0, 2), (2, 1)]);
+}
+
+#[tokio::test]
+async fn ingest_endpoint_requires_api_key() {
+    let client = Client::new();
+    let (base_url, handle) = spawn_app_with_data().await;
+    let res = client
+        .post(format!("{}/ingest?blocks=1", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+    handle.abort();
+
+    let (base_url, handle) = spawn_app_with_state(|state| {
+        let options = IngestOptions {
+            blocks_soft_cap: 10,
+            ..Default::default()
+        };
+        state.with_ingest(options, Some("secret".to_string()))
+    })
+    .await;
+    let post = |query: &str, key: Option<&str>| {
+        let mut req = client.post(format!("{}/ingest?{}", base_url, query));
+        if let Some(key) = key {
+            req = req.header("x-api-key", key);
+        }
+        async move { req.send().await.unwrap().status() }
+    };
+    assert_eq!(
+        post("blocks=1", None).await,
+        reqwest::StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        post("blocks=1", Some("wrong")).await,
+        reqwest::StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        post("blocks=11", Some("secret")).await,
+        reqwest::StatusCode::BAD_REQUEST
+    );
+    // Authorized, but this state has no RPC client.
+    assert_eq!(
+        post("blocks=1", Some("secret")).await,
+        reqwest::StatusCode::SERVICE_UNAVAILABLE
+    );
+    handle.abort();
+}
+
+#[tokio::test]
+async fn upsert_block_fills_placeholders_only() {
+    let pool = storage::init_pool(&temp_db_url()).await.unwrap();
+    let placeholder = BlockInfo {
+        number: 7,
+        hash: String::new(),
+        timestamp: 0,
+        base_fee_wei: None,
+        chain_id: 1,
+    };
+    let real = BlockInfo {
+        number: 7,
+        hash: "0xreal".to_string(),
+        timestamp: 1_700_000_100,
+        base_fee_wei: None,
+        chain_id: 1,
+    };
+
+    storage::upsert_block(&pool, &placeholder).await.unwrap();
+    storage::upsert_block(&pool, &real).await.unwrap();
+    let stored = storage::get_block(&pool, 1, 7).await.unwrap().unwrap();
+    assert_eq!(stored.hash, "0xreal");
+    assert_eq!(stored.timestamp, 1_700_000_100);
+
+    // A later placeholder (or a different hash) must not clobber real metadata.
+    storage::upsert_block(&pool, &placeholder).await.unwrap();
+    let other = BlockInfo {
+        hash: "0xother".to_string(),
+        ..real.clone()
+    };
+    storage::upsert_block(&pool, &other).await.unwrap();
+    let stored = storage::get_block(&pool, 1, 7).await.unwrap().unwrap();
+    assert_eq!(stored.hash, "0xreal");
+    assert_eq!(stored.timestamp, 1_700_000_100);
+}
+
+#[tokio::test]
+async fn compressed_input_round_trips() {
+    let pool = storage::init_pool(&temp_db_url()).await.unwrap();
+    // swapExactTokensForTokens(amountIn, amountOutMin, [a, b], to, deadline)
+    let swap = format!(
+        "0x38ed1739{:0>64}{:0>64}{:0>64}{:0>64}{:0>64}{:0>64}{:0>64}{:0>64}",
+        "de0b6b3a7640000",
+        "0",
+        "a0",
+        "aaaa",
+        "65f0a1b2",
+        "2",
+        "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+        "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"
+    );
+    let call = |hash: &str, input: &str| NormalizedTx {
+        from: "0xaaa".to_string(),
+        input_data: Some(input.to_string()),
+        ..tx(hash)
+    };
+    let options = storage::StorageOptions {
+        compress_input: true,
+        ..Default::default()
+    };
+    storage::insert_transactions_with_options(
+        &pool,
+        &[call("0xswap", &swap), call("0xshort", "0xa9059cbb")],
+        &options,
+    )
+    .await
+    .unwrap();
+
+    let stored: Vec<(String, i64, bool)> = sqlx::query_as(
+        "SELECT hash, LENGTH(CAST(input_data AS BLOB)), input_compressed FROM transactions ORDER BY hash",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(stored[0], ("0xshort".to_string(), 10, false));
+    assert_eq!(stored[1].0, "0xswap");
+    assert!(stored[1].2);
+    assert!(stored[1].1 * 4 < swap.len() as i64, "{:?}", stored[1]);
+
+    for (hash, input) in [("0xswap", swap.as_str()), ("0xshort", "0xa9059cbb")] {
+        let read = storage::get_transaction_by_hash(&pool, hash, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(read.tx.input_data.as_deref(), Some(input));
+    }
+}
+
+#[tokio::test]
+async fn tx_metadata_round_trips_and_overwrites() {
+    let pool = storage::init_pool(&temp_db_url()).await.unwrap();
+    let row = |chain_id: i64, key: &str, value: &str| TxMetadata {
+        chain_id,
+        hash: "0xtx1".to_string(),
+        key: key.to_string(),
+        value: value.to_string(),
+    };
+    storage::insert_tx_metadata(&pool, &[row(1, "tx_type", "0"), row(1, "label", "a")])
+        .await
+        .unwrap();
+    storage::insert_tx_metadata(&pool, &[row(1, "tx_type", "2"), row(10, "tx_type", "0")])
+        .await
+        .unwrap();
+
+    let stored = storage::get_tx_metadata(&pool, 1, "0xtx1").await.unwrap();
+    assert_eq!(stored, vec![row(1, "label", "a"), row(1, "tx_type", "2")]);
+    let stored = storage::get_tx_metadata(&pool, 10, "0xtx1").await.unwrap();
+    assert_eq!(stored, vec![row(10, "tx_type", "0")]);
+}
+
+#[tokio::test]
+async fn recent_logs_lists_newest_first_and_filters_by_address() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    let transfer_topic = format!("0xddf252ad{}", "0".repeat(56));
+    let log = |block: i64, index: i64, address: &str| LogEntry {
+        chain_id: if block > 10 { 10 } else { 1 },
+        block_number: block,
+        tx_hash: format!("0xlog{}", block),
+        log_index: index,
+        address: address.to_string(),
+        topics: vec![transfer_topic.clone()],
+        data: "0x01".to_string(),
+    };
+    let token = format!("0x{}", "1".repeat(40));
+    let other = format!("0x{}", "2".repeat(40));
+    storage::insert_logs(&pool, &[log(10, 0, &token), log(11, 3, &other)])
+        .await
+        .unwrap();
+    // Re-ingesting the same block is a no-op.
+    storage::insert_logs(&pool, &[log(10, 0, &token)])
+        .await
+        .unwrap();
+
+    let client = Client::new();
+    let body: serde_json::Value = client
+        .get(format!("{}/logs/recent", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let logs = body["logs"].as_array().unwrap();
+    assert_eq!(logs.len(), 2);
+    assert_eq!(logs[0]["block_number"], 11);
+    assert_eq!(logs[1]["topics"][0], transfer_topic.as_str());
+
+    let body: serde_json::Value = client
+        .get(format!(
+            "{}/logs/recent?address={}",
+            base_url,
+            token.to_uppercase().replace("0X", "0x")
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let logs = body["logs"].as_array().unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0]["address"], token.as_str());
+
+    let body: serde_json::Value = client
+        .get(format!("{}/logs/recent?chain_id=10", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let logs = body["logs"].as_array().unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(
+        (&logs[0]["chain_id"], &logs[0]["block_number"]),
+        (&10.into(), &11.into())
+    );
+    handle.abort();
+}
+
+#[tokio::test]
+async fn recent_blocks_include_transaction_counts() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    storage::insert_block(
+        &pool,
+        &BlockInfo {
+            number: 2,
+            hash: "0xdef".to_string(),
+            timestamp: 1_700_000_012,
+            base_fee_wei: None,
+            chain_id: 1,
+        },
+    )
+    .await
+    .unwrap();
+
+    let client = Client::new();
+    let body: serde_json::Value = client
+        .get(format!("{}/blocks/recent", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let blocks = body["blocks"].as_array().unwrap();
+    assert_eq!(blocks.len(), 2);
+    assert_eq!(blocks[0]["number"], 2);
+    assert_eq!(blocks[0]["hash"], "0xdef");
+    assert_eq!(blocks[0]["tx_count"], 0);
+    assert_eq!(blocks[1]["number"], 1);
+    assert_eq!(blocks[1]["tx_count"], 3);
+
+    let body: serde_json::Value = client
+        .get(format!("{}/blocks/recent?limit=1", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["blocks"].as_array().unwrap().len(), 1);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn chain_id_param_scopes_reads_to_one_chain() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    // Block 1 again, on Base, with one transaction from a seeded sender.
+    storage::insert_block(
+        &pool,
+        &BlockInfo {
+            number: 1,
+            hash: "0xbase1".to_string(),
+            timestamp: 1_700_000_001,
+            base_fee_wei: None,
+            chain_id: 8453,
+        },
+    )
+    .await
+    .unwrap();
+    storage::insert_transactions(
+        &pool,
+        &[NormalizedTx {
+            from: "0xccc".to_string(),
+            value_wei: "1".to_string(),
+            gas_price_wei: Some("9000".to_string()),
+            block_number: Some(1),
+            timestamp: Some(1_700_000_001),
+            tx_index: Some(0),
+            chain_id: 8453,
+            ..tx("0xbasetx")
+        }],
+    )
+    .await
+    .unwrap();
+
+    let client = Client::new();
+    let get = |path: &str| {
+        let request = client.get(format!("{}{}", base_url, path));
+        async move {
+            request
+                .send()
+                .await
+                .unwrap()
+                .json::<serde_json::Value>()
+                .await
+                .unwrap()
+        }
+    };
+    let body = get("/tx/recent?chain_id=8453").await;
+    let txs = body["transactions"].as_array().unwrap();
+    assert_eq!(txs.len(), 1);
+    assert_eq!(txs[0]["hash"], "0xbasetx");
+    assert_eq!(txs[0]["chain_id"], 8453);
+    assert_eq!(
+        get("/tx/recent?chain_id=1").await["transactions"]
+            .as_array()
+            .unwrap()
+            .len(),
+        3
+    );
+
+    let blocks = get("/blocks/recent?chain_id=8453").await;
+    let blocks = blocks["blocks"].as_array().unwrap();
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0]["hash"], "0xbase1");
+    assert_eq!(blocks[0]["tx_count"], 1);
+
+    let body = get("/stats/top-senders?chain_id=8453").await;
+    assert_eq!(body["top_senders"].as_array().unwrap().len(), 1);
+    let body = get("/stats/gas?chain_id=1").await;
+    assert_eq!(body["max"], 2000);
+    let body = get("/stats/gas?chain_id=8453").await;
+    assert_eq!(body["max"], 9000);
+    let body = get("/stats/gas-at?timestamp=1700000001&chain_id=8453").await;
+    assert_eq!((&body["min"], &body["max"]), (&9000.into(), &9000.into()));
+    let body = get("/stats/gas-histogram?chain_id=8453").await;
+    assert_eq!(body["buckets"][0]["count"], 1);
+    let body = get("/stats/largest-tx?chain_id=8453").await;
+    assert_eq!(body["transaction"]["hash"], "0xbasetx");
+    let body = get("/stats/largest-tx?chain_id=1").await;
+    assert_ne!(body["transaction"]["hash"], "0xbasetx");
+    handle.abort();
+}
+
+#[tokio::test]
+async fn chain_id_param_scopes_address_reads() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let a1 = "0x00000000000000000000000000000000000000a1";
+    let b2 = "0x00000000000000000000000000000000000000b2";
+    let router = "0x7a250d5630b4cf539739df2c5dacb4c659f2488d";
+    // The seeded a1 -> b2 transfer lives on chain 1; mirror it on Optimism
+    // and give each chain one pending high-value swap from a1.
+    storage::insert_block(
+        &pool,
+        &BlockInfo {
+            number: 1,
+            hash: "0xop".to_string(),
+            timestamp: now - 10,
+            base_fee_wei: None,
+            chain_id: 10,
+        },
+    )
+    .await
+    .unwrap();
+    storage::insert_transactions(
+        &pool,
+        &[
+            NormalizedTx {
+                from: a1.to_string(),
+                to: Some(b2.to_string()),
+                value_wei: "7".to_string(),
+                gas_price_wei: Some("1000".to_string()),
+                block_number: Some(1),
+                timestamp: Some(now - 10),
+                chain_id: 10,
+                ..tx("0xop1")
+            },
+            NormalizedTx {
+                from: a1.to_string(),
+                to: Some(router.to_string()),
+                value_wei: "2000000000000000000".to_string(),
+                nonce: 1,
+                chain_id: 10,
+                ..tx("0xopswap")
+            },
+            NormalizedTx {
+                from: a1.to_string(),
+                to: Some(router.to_string()),
+                value_wei: "2000000000000000000".to_string(),
+                nonce: 1,
+                ..tx("0xl1swap")
+            },
+        ],
+    )
+    .await
+    .unwrap();
+
+    let client = Client::new();
+    let get = |path: String| {
+        let request = client.get(format!("{}{}", base_url, path));
+        async move {
+            request
+                .send()
+                .await
+                .unwrap()
+                .json::<serde_json::Value>()
+                .await
+                .unwrap()
+        }
+    };
+    let hashes = |list: &serde_json::Value| {
+        let mut hashes: Vec<String> = list
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tx| tx["hash"].as_str().unwrap().to_string())
+            .collect();
+        hashes.sort();
+        hashes
+    };
+
+    let body = get(format!("/tx/by-address?address={}&chain_id=10", a1)).await;
+    assert_eq!(hashes(&body["transactions"]), ["0xop1", "0xopswap"]);
+    let body = get(format!("/tx/by-address?address={}&chain_id=1", a1)).await;
+    assert_eq!(hashes(&body["transactions"]), ["0xl1swap", "0xtx3"]);
+
+    let body = get(format!("/analysis/timeline?address={}&chain_id=10", a1)).await;
+    assert_eq!(hashes(&body["timeline"]), ["0xop1", "0xopswap"]);
+    let body = get(format!("/analysis/timeline?address={}&chain_id=1", a1)).await;
+    assert_eq!(hashes(&body["timeline"]), ["0xl1swap", "0xtx3"]);
+
+    let body = get(format!("/address/{}/summary?chain_id=10", a1)).await;
+    assert_eq!(body["sent_count"], 2);
+    assert_eq!(body["total_sent_wei"], "2000000000000000007");
+    let body = get(format!("/address/{}/summary?chain_id=1", a1)).await;
+    assert_eq!(body["sent_count"], 2);
+    assert_eq!(body["total_sent_wei"], "2000000000000000005");
+
+    let body = get(format!("/analysis/flow?from={}&to={}&chain_id=10", a1, b2)).await;
+    assert_eq!(
+        (&body["tx_count"], &body["total_value_wei"]),
+        (&1.into(), &"7".into())
+    );
+    let body = get(format!("/analysis/flow?from={}&to={}&chain_id=1", a1, b2)).await;
+    assert_eq!(
+        (&body["tx_count"], &body["total_value_wei"]),
+        (&1.into(), &"5".into())
+    );
+
+    let body = get("/stats/throughput?bucket=60&window=3600&chain_id=10".to_string()).await;
+    assert_eq!(body["buckets"].as_array().unwrap().len(), 1);
+    assert_eq!(body["buckets"][0]["tx_count"], 1);
+    let body = get("/stats/throughput?bucket=60&window=3600&chain_id=1".to_string()).await;
+    assert!(body["buckets"].as_array().unwrap().is_empty());
+
+    let body = get("/analysis/mev-targets?chain_id=10".to_string()).await;
+    assert_eq!(hashes(&body["targets"]), ["0xopswap"]);
+    let body = get("/analysis/mev-targets?chain_id=1".to_string()).await;
+    assert_eq!(hashes(&body["targets"]), ["0xl1swap"]);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn rbf_bumps_never_compare_across_chains() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    let a1 = "0x00000000000000000000000000000000000000a1";
+    // One sender reusing nonce 5 on two chains: two transactions, no bump.
+    let attempt = |hash: &str, chain_id: i64, price: &str| NormalizedTx {
+        from: a1.to_string(),
+        gas_price_wei: Some(price.to_string()),
+        nonce: 5,
+        chain_id,
+        ..tx(hash)
+    };
+    storage::insert_transactions(
+        &pool,
+        &[attempt("0xl1", 1, "1000"), attempt("0xop", 10, "2000")],
+    )
+    .await
+    .unwrap();
+
+    let client = Client::new();
+    let get = |query: &str| {
+        let request = client.get(format!(
+            "{}/analysis/rbf-bumps?address={}&nonce=5{}",
+            base_url, a1, query
+        ));
+        async move {
+            request
+                .send()
+                .await
+                .unwrap()
+                .json::<serde_json::Value>()
+                .await
+                .unwrap()
+        }
+    };
+    let body = get("&chain_id=1").await;
+    let attempts = body["attempts"].as_array().unwrap();
+    assert_eq!(attempts.len(), 1);
+    assert_eq!(attempts[0]["hash"], "0xl1");
+    assert!(attempts[0]["bump_pct"].is_null());
+
+    let body = get("").await;
+    let attempts = body["attempts"].as_array().unwrap();
+    assert_eq!(attempts.len(), 2);
+    assert!(attempts.iter().all(|a| a["bump_pct"].is_null()));
+    handle.abort();
+}
+
+#[tokio::test]
+async fn base_fee_stats_skip_pre_london_blocks() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    for (number, fee) in [(2, "1000"), (3, "3000")] {
+        storage::insert_block(
+            &pool,
+            &BlockInfo {
+                number,
+                hash: format!("0xblock{}", number),
+                timestamp: 1_700_000_000 + number * 12,
+                base_fee_wei: Some(fee.to_string()),
+                chain_id: 1,
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    let client = Client::new();
+    // Seeded block 1 has no base fee and is left out of the aggregate.
+    let body: serde_json::Value = client
+        .get(format!("{}/stats/base-fee?blocks=3", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["min"], 1000);
+    assert_eq!(body["max"], 3000);
+    assert_eq!(body["avg"], 2000.0);
+    assert_eq!(body["blocks"], 2);
+
+    let body: serde_json::Value = client
+        .get(format!("{}/stats/base-fee?blocks=1", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["min"], 3000);
+    assert_eq!(body["blocks"], 1);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn token_transfers_filter_by_token() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    let usdc = format!("0x{}", "c".repeat(40));
+    let dai = format!("0x{}", "d".repeat(40));
+    let transfer = |block: i64, token: &str, amount: &str| TokenTransfer {
+        chain_id: 1,
+        tx_hash: format!("0xtransfer{}", block),
+        log_index: 0,
+        block_number: Some(block),
+        token: token.to_string(),
+        from: "0xaaa".to_string(),
+        to: "0xbbb".to_string(),
+        amount: amount.to_string(),
+    };
+    storage::insert_token_transfers(
+        &pool,
+        &[transfer(20, &usdc, "1000000"), transfer(21, &dai, "5")],
+    )
+    .await
+    .unwrap();
+
+    let client = Client::new();
+    let body: serde_json::Value = client
+        .get(format!("{}/token-transfers", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let transfers = body["transfers"].as_array().unwrap();
+    assert_eq!(transfers.len(), 2);
+    assert_eq!(transfers[0]["token"], dai.as_str());
+
+    let body: serde_json::Value = client
+        .get(format!("{}/token-transfers?token={}", base_url, usdc))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let transfers = body["transfers"].as_array().unwrap();
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(transfers[0]["amount"], "1000000");
+    assert_eq!(transfers[0]["to"], "0xbbb");
+
+    let res = client
+        .get(format!("{}/token-transfers?token=0x12", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn stats_lag_compares_cached_head_with_stored_blocks() {
+    let mut head = None;
+    let (base_url, handle) = spawn_app_with_state(|state| {
+        head = Some(state.chain_head.clone());
+        state
+    })
+    .await;
+    let client = Client::new();
+    let url = format!("{}/stats/lag", base_url);
+
+    let body: serde_json::Value = client.get(&url).send().await.unwrap().json().await.unwrap();
+    assert_eq!(body["head_block"], serde_json::Value::Null);
+    assert_eq!(body["latest_stored_block"], 1);
+    assert_eq!(body["lag_blocks"], serde_json::Value::Null);
+
+    head.unwrap().set(4);
+    let body: serde_json::Value = client.get(&url).send().await.unwrap().json().await.unwrap();
+    assert_eq!(body["head_block"], 4);
+    assert_eq!(body["lag_blocks"], 3);
+    assert!(body["head_age_secs"].as_u64().unwrap() <= 1);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn ingest_stats_returns_counters() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    INGEST_STATS.inc_blocks(1);
+    INGEST_STATS.inc_transactions(2);
+    let client = Client::new();
+    let res = client
+        .get(format!("{}/stats/ingest", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert!(body.get("blocks").is_some());
+    assert!(body.get("transactions").is_some());
+    assert!(body.get("pending_transactions").is_some());
+    assert!(body.get("last_run_secs").is_some());
+    assert!(body.get("avg_tx_per_sec").is_some());
+    assert!(body["rpc_errors"].is_u64());
+    assert!(body["insert_errors"].is_u64());
+    handle.abort();
+}
+
+#[tokio::test]
+async fn ingest_stats_stream_emits_snapshot_events() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    let mut res = Client::new()
+        .get(format!("{}/stats/ingest/stream?interval_secs=0", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.headers()["content-type"], "text/event-stream");
+
+    // The zero interval is clamped to one second, so two events arrive quickly.
+    let mut text = String::new();
+    while text.matches("event: ingest").count() < 2 {
+        let chunk = tokio::time::timeout(std::time::Duration::from_secs(5), res.chunk())
+            .await
+            .expect("no event pushed")
+            .unwrap()
+            .expect("stream ended");
+        text.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+    let data = text
+        .lines()
+        .find_map(|line| line.strip_prefix("data: "))
+        .unwrap();
+    let snapshot: serde_json::Value = serde_json::from_str(data).unwrap();
+    assert!(snapshot["blocks"].is_u64());
+    assert!(snapshot["skipped_blocks"].is_u64());
+    handle.abort();
+}
+
+#[tokio::test]
+async fn recent_txs_returns_rows() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    let client = Client::new();
+    let res = client
+        .get(format!("{}/tx/recent?limit=5", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let body: serde_json::Value = res.json().await.unwrap();
+    let arr = body
+        .get("transactions")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    assert!(!arr.is_empty());
+    handle.abort();
+}
+
+#[tokio::test]
+async fn recent_txs_filters_on_calldata() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    let client = Client::new();
+    let hashes = |has_input: bool| {
+        let client = client.clone();
+        let url = format!("{}/tx/recent?has_input={}", base_url, has_input);
+        async move {
+            let body: serde_json::Value =
+                client.get(url).send().await.unwrap().json().await.unwrap();
+            body["transactions"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|tx| tx["hash"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+    // tx1 predates calldata capture (NULL) and matches neither side.
+    assert_eq!(hashes(true).await, vec!["0xtx3"]);
+    assert_eq!(hashes(false).await, vec!["0xtx2"]);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn recent_txs_filter_by_method_selector() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    let client = Client::new();
+    let body: serde_json::Value = client
+        .get(format!("{}/tx/recent?selector=0xA9059CBB", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let txs = body["transactions"].as_array().unwrap();
+    assert_eq!(txs.len(), 1);
+    assert_eq!(txs[0]["hash"], "0xtx3");
+    assert_eq!(txs[0]["input_selector"], "0xa9059cbb");
+
+    let body: serde_json::Value = client
+        .get(format!("{}/tx/recent?selector=0x095ea7b3", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(body["transactions"].as_array().unwrap().is_empty());
+
+    let res = client
+        .get(format!("{}/tx/recent?selector=0xa9059c", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn recent_txs_filter_by_tx_type() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    let pending = |hash: &str, tx_type: Option<i64>| NormalizedTx {
+        from: "0x00000000000000000000000000000000000000e5".to_string(),
+        max_fee_per_gas_wei: Some("3000".to_string()),
+        input_data: Some("0x".to_string()),
+        tx_type,
+        input_size: Some(0),
+        ..tx(hash)
+    };
+    storage::insert_transactions(
+        &pool,
+        &[
+            pending("0xlegacy", Some(0)),
+            pending("0xdynamic", Some(2)),
+            // Stored before the type was recorded; matches no tx_type.
+            pending("0xuntyped", None),
+        ],
+    )
+    .await
+    .unwrap();
+
+    let body: serde_json::Value = Client::new()
+        .get(format!("{}/tx/recent?tx_type=2", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let txs = body["transactions"].as_array().unwrap();
+    assert_eq!(txs.len(), 1);
+    assert_eq!(txs[0]["hash"], "0xdynamic");
+    assert_eq!(txs[0]["tx_type"], 2);
+    assert_eq!(txs[0]["input_size"], 0);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn compare_reports_windows_and_deltas() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    let client = Client::new();
+    let res = client
+        .get(format!(
+            "{}/analysis/compare?window_a=0-0&window_b=1-1",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["window_a"]["tx_count"], 0);
+    assert!(body["window_a"]["avg_value_wei"].is_null());
+    assert_eq!(body["window_b"]["tx_count"], 3);
+    assert_eq!(body["window_b"]["unique_addresses"], 6);
+    assert_eq!(body["window_b"]["avg_gas_price_wei"], 1500.0);
+    assert_eq!(body["window_b"]["avg_value_wei"], "1000000000000000001");
+    assert_eq!(body["delta"]["tx_count"], 3);
+    assert_eq!(body["delta"]["unique_addresses"], 6);
+
+    // Block 1 on Optimism only counts when no chain is picked.
+    storage::insert_block(
+        &pool,
+        &BlockInfo {
+            number: 1,
+            hash: "0xop1".to_string(),
+            timestamp: 1_700_000_000,
+            base_fee_wei: None,
+            chain_id: 10,
+        },
+    )
+    .await
+    .unwrap();
+    storage::insert_transactions(
+        &pool,
+        &[NormalizedTx {
+            to: Some("0xfff".to_string()),
+            gas_price_wei: Some("9000".to_string()),
+            block_number: Some(1),
+            timestamp: Some(1_700_000_000),
+            chain_id: 10,
+            ..tx("0xoptx")
+        }],
+    )
+    .await
+    .unwrap();
+    let compare = |query: &str| {
+        let request = client.get(format!(
+            "{}/analysis/compare?window_a=0-0&window_b=1-1{}",
+            base_url, query
+        ));
+        async move {
+            request
+                .send()
+                .await
+                .unwrap()
+                .json::<serde_json::Value>()
+                .await
+                .unwrap()
+        }
+    };
+    let body = compare("&chain_id=1").await;
+    assert_eq!(body["window_b"]["tx_count"], 3);
+    assert_eq!(body["window_b"]["unique_addresses"], 6);
+    assert_eq!(body["window_b"]["avg_gas_price_wei"], 1500.0);
+    let body = compare("&chain_id=10").await;
+    assert_eq!(body["window_b"]["tx_count"], 1);
+    assert_eq!(body["window_b"]["unique_addresses"], 2);
+    assert_eq!(body["window_b"]["avg_gas_price_wei"], 9000.0);
+    assert_eq!(compare("").await["window_b"]["tx_count"], 4);
+
+    let res = client
+        .get(format!(
+            "{}/analysis/compare?window_a=5-1&window_b=1-1",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn ws_txs_pushes_inserted_transactions_for_address() {
+    use futures_util::StreamExt;
+
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    let watched = "0x00000000000000000000000000000000000000ee";
+    let ws_url = format!(
+        "{}/ws/txs?address={}",
+        base_url.replacen("http://", "ws://", 1),
+        watched.to_uppercase().replacen("0X", "0x", 1)
+    );
+    let (mut socket, _) = tokio_tungstenite::connect_async(ws_url).await.unwrap();
+
+    let pending = |hash: &str, to: &str| NormalizedTx {
+        from: "0xfff".to_string(),
+        to: Some(to.to_string()),
+        value_wei: "7".to_string(),
+        gas_price_wei: Some("1000".to_string()),
+        ..tx(hash)
+    };
+    storage::insert_transactions(
+        &pool,
+        &[
+            pending("0xws_other", "0xbbb"),
+            pending("0xws_watched", watched),
+        ],
+    )
+    .await
+    .unwrap();
+
+    let frame = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+        .await
+        .expect("no frame pushed")
+        .unwrap()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_str(frame.to_text().unwrap()).unwrap();
+    assert_eq!(body["hash"], "0xws_watched");
+    assert_eq!(body["value_wei"], "7");
+    assert_eq!(body["value_eth"], "0.000000000000000007");
+
+    let res = Client::new()
+        .get(format!("{}/ws/txs?address=nope", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 400);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn inclusion_estimate_labels_pending_against_median() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    // Mined seed prices are 1000/1500/2000 wei, so the median is 1500.
+    let pending = |hash: &str, gas_price: &str| NormalizedTx {
+        from: "0xfff".to_string(),
+        gas_price_wei: Some(gas_price.to_string()),
+        ..tx(hash)
+    };
+    storage::insert_transactions(&pool, &[pending("0xhigh", "1700"), pending("0xlow", "900")])
+        .await
+        .unwrap();
+
+    let body: serde_json::Value = Client::new()
+        .get(format!(
+            "{}/analysis/inclusion-estimate?blocks=10",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["median_gas_price_wei"], "1500");
+    let mut labels: Vec<(String, String)> = body["estimates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| {
+            (
+                e["hash"].as_str().unwrap().to_string(),
+                e["label"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    labels.sort();
+    assert_eq!(
+        labels,
+        vec![
+            ("0xhigh".to_string(), "likely".to_string()),
+            ("0xlow".to_string(), "unlikely".to_string())
+        ]
+    );
+    handle.abort();
+}
+
+#[tokio::test]
+async fn recent_txs_filters_and_reports_flags() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    let client = Client::new();
+    let body: serde_json::Value = client
+        .get(format!("{}/tx/recent?flags=has_input,dex_swap", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let txs = body["transactions"].as_array().unwrap();
+    assert_eq!(txs.len(), 1);
+    assert_eq!(txs[0]["hash"], "0xtx3");
+    assert_eq!(txs[0]["flags"], serde_json::json!(["has_input"]));
+
+    let res = client
+        .get(format!("{}/tx/recent?flags=bogus", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn recent_txs_normalizes_address_param() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    let client = Client::new();
+    let res = client
+        .get(format!(
+            "{}/tx/recent?address=%2000000000000000000000000000000000000000B2%20",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let body: serde_json::Value = res.json().await.unwrap();
+    let arr = body
+        .get("transactions")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    assert_eq!(arr.len(), 1);
+    assert_eq!(arr[0].get("hash").and_then(|h| h.as_str()), Some("0xtx3"));
+    assert_eq!(
+        arr[0].get("method_signature").and_then(|h| h.as_str()),
+        Some("transfer(address,uint256)")
+    );
+
+    let res = client
+        .get(format!("{}/tx/recent?address=0xnothex", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn recent_txs_include_confirmations() {
+    let (base_url, handle) = spawn_app_with_state(|state| {
+        state.chain_head.set(11);
+        state
+    })
+    .await;
+    let client = Client::new();
+    let body: serde_json::Value = client
+        .get(format!("{}/tx/recent?limit=1", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let tx = &body["transactions"][0];
+    assert_eq!(tx.get("block_number").and_then(|v| v.as_i64()), Some(1));
+    assert_eq!(tx.get("confirmations").and_then(|v| v.as_u64()), Some(10));
+    handle.abort();
+}
+
+#[tokio::test]
+async fn recent_txs_min_value_hides_dust() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    let client = Client::new();
+    let fetch = |min: &'static str| {
+        let client = client.clone();
+        let url = format!("{}/tx/recent?min_value_wei={}", base_url, min);
+        async move { client.get(url).send().await.unwrap() }
+    };
+    let hashes = |body: serde_json::Value| -> Vec<String> {
+        body["transactions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tx| tx["hash"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    // Seed values are 1e18, 2e18 and 5 wei. A plain text comparison would
+    // rank "5" above "9000..."; the leading zero must be ignored too.
+    let body = fetch("0900000000000000000").await.json().await.unwrap();
+    assert_eq!(hashes(body), ["0xtx2", "0xtx1"]);
+    let body = fetch("2000000000000000000").await.json().await.unwrap();
+    assert_eq!(hashes(body), ["0xtx2"]);
+    let body = fetch("0").await.json().await.unwrap();
+    assert_eq!(hashes(body).len(), 3);
+
+    assert_eq!(fetch("-1").await.status(), reqwest::StatusCode::BAD_REQUEST);
+    assert_eq!(
+        fetch("1e18").await.status(),
+        reqwest::StatusCode::BAD_REQUEST
+    );
+    handle.abort();
+}
+
+#[tokio::test]
+async fn recent_txs_cursor_pages_through_timestamp_ties() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    // Two more rows sharing tx1's timestamp, so a tie straddles a page edge.
+    let tied = |hash: &str| NormalizedTx {
+        from: "0xaaa".to_string(),
+        value_wei: "1".to_string(),
+        gas_price_wei: Some("1000".to_string()),
+        block_number: Some(1),
+        timestamp: Some(1_700_000_000),
+        ..tx(hash)
+    };
+    storage::insert_transactions(&pool, &[tied("0xtx1a"), tied("0xtx1b")])
+        .await
+        .unwrap();
+
+    let client = Client::new();
+    let mut pages = Vec::new();
+    let mut before = String::new();
+    loop {
+        let mut url = format!("{}/tx/recent?limit=2", base_url);
+        if !before.is_empty() {
+            url.push_str(&format!("&before={}", before));
+        }
+        let body: serde_json::Value = client.get(url).send().await.unwrap().json().await.unwrap();
+        let hashes: Vec<String> = body["transactions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tx| tx["hash"].as_str().unwrap().to_string())
+            .collect();
+        pages.push(hashes);
+        before = body["next_cursor"].as_str().unwrap().to_string();
+        if before.is_empty() {
+            break;
+        }
+    }
+    assert_eq!(
+        pages,
+        vec![
+            vec!["0xtx2", "0xtx1b"],
+            vec!["0xtx1a", "0xtx1"],
+            vec!["0xtx3"],
+        ]
+    );
+
+    for bad in ["nonsense", "abc:0xtx1", "1700000000:"] {
+        let res = client
+            .get(format!("{}/tx/recent?before={}", base_url, bad))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST, "{}", bad);
+    }
+    let res = client
+        .get(format!(
+            "{}/tx/recent?order=block&before=1700000000:0xtx1",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn recent_txs_block_order_follows_position_in_block() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    storage::insert_block(
+        &pool,
+        &BlockInfo {
+            number: 2,
+            hash: "0xdef".to_string(),
+            timestamp: 1_700_000_012,
+            base_fee_wei: None,
+            chain_id: 1,
+        },
+    )
+    .await
+    .unwrap();
+    let mined = |hash: &str, tx_index: i64| NormalizedTx {
+        value_wei: "1".to_string(),
+        nonce: tx_index,
+        block_number: Some(2),
+        timestamp: Some(1_700_000_012),
+        tx_index: Some(tx_index),
+        ..tx(hash)
+    };
+    // Stored out of chain order on purpose.
+    storage::insert_transactions(&pool, &[mined("0xsecond", 1), mined("0xfirst", 0)])
+        .await
+        .unwrap();
+
+    let client = Client::new();
+    let body: serde_json::Value = client
+        .get(format!("{}/tx/recent?order=block&limit=3", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let hashes: Vec<&str> = body["transactions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tx| tx["hash"].as_str().unwrap())
+        .collect();
+    assert_eq!(hashes[..2], ["0xsecond", "0xfirst"]);
+    assert_eq!(body["transactions"][0]["tx_index"], 1);
+    // A full page in block order has no cursor to hand out.
+    assert!(body.get("next_cursor").is_none());
+
+    let res = client
+        .get(format!("{}/tx/recent?order=nonce", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn tx_by_address_matches_sender_or_receiver() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    let sender = format!("0x{}{}", "0".repeat(38), "a1");
+    let receiver = format!("0x{}{}", "0".repeat(38), "B2");
+    let client = Client::new();
+    let fetch = |query: String| {
+        let client = client.clone();
+        let url = format!("{}/tx/by-address?{}", base_url, query);
+        async move { client.get(url).send().await.unwrap() }
+    };
+
+    for address in [&sender, &receiver] {
+        let body: serde_json::Value = fetch(format!("address={}", address))
+            .await
+            .json()
+            .await
+            .unwrap();
+        let txs = body["transactions"].as_array().unwrap();
+        assert_eq!(txs.len(), 1, "{}", address);
+        assert_eq!(txs[0]["hash"], "0xtx3");
+    }
+
+    let res = fetch("limit=5".to_string()).await;
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    let res = fetch("address=0x1234".to_string()).await;
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn tx_lookup_by_hash_validates_and_404s() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    let stored = format!("0x{}", "ab".repeat(32));
+    storage::insert_transactions(
+        &pool,
+        &[NormalizedTx {
+            value_wei: "7".to_string(),
+            nonce: 3,
+            ..tx(&stored)
+        }],
+    )
+    .await
+    .unwrap();
+
+    let client = Client::new();
+    let res = client
+        .get(format!("{}/tx/0x{}", base_url, "AB".repeat(32)))
         .send()
         .await
         .unwrap();
-    assert!(res.status().is_success());
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
     let body: serde_json::Value = res.json().await.unwrap();
-    let arr = body
-        .get("top_senders")
-        .and_then(|v| v.as_array())
-        .cloned()
-        .unwrap_or_default();
-    assert!(!arr.is_empty());
+    assert_eq!(body["hash"], stored);
+    assert_eq!(body["value_wei"], "7");
+    assert_eq!(body["nonce"], 3);
+
+    let res = client
+        .get(format!("{}/tx/0x{}", base_url, "cd".repeat(32)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+
+    for bad in ["0x1234", "abab", "0xtx1"] {
+        let res = client
+            .get(format!("{}/tx/{}", base_url, bad))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST, "{}", bad);
+    }
     handle.abort();
 }
 
 #[tokio::test]
-async fn gas_stats_returns_numbers() {
-    let (base_url, handle) = spawn_app_with_data().await;
+async fn rate_limit_returns_429_with_retry_after_but_spares_health() {
+    let (base_url, handle) = spawn_app_with_state(|state| state.with_rate_limit(0.1, 2)).await;
     let client = Client::new();
+    let url = format!("{}/stats/gas", base_url);
+    for _ in 0..2 {
+        let res = client.get(&url).send().await.unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+    }
+    let res = client.get(&url).send().await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(res.headers()["retry-after"], "10");
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "too_many_requests");
+
     let res = client
-        .get(format!("{}/stats/gas?blocks=10", base_url))
+        .get(format!("{}/health", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn throughput_counts_recent_mined_txs_per_bucket() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let recent = |hash: &str, timestamp: Option<i64>| NormalizedTx {
+        from: "0xfff".to_string(),
+        gas_price_wei: Some("1000".to_string()),
+        timestamp,
+        ..tx(hash)
+    };
+    storage::insert_transactions(
+        &pool,
+        &[
+            recent("0xnew1", Some(now - 10)),
+            recent("0xnew2", Some(now - 10)),
+            recent("0xpending", None),
+        ],
+    )
+    .await
+    .unwrap();
+    let client = Client::new();
+
+    let body: serde_json::Value = client
+        .get(format!(
+            "{}/stats/throughput?bucket=60&window=3600",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["bucket_secs"], 60);
+    // The seeded rows are from 2023 and fall outside the window.
+    let buckets = body["buckets"].as_array().unwrap();
+    assert_eq!(buckets.len(), 1);
+    assert_eq!(buckets[0]["tx_count"], 2);
+    assert_eq!(buckets[0]["bucket_start"], (now - 10) / 60 * 60);
+
+    let body: serde_json::Value = client
+        .get(format!(
+            "{}/stats/throughput?bucket={}&window={}",
+            base_url,
+            i64::MAX,
+            i64::MAX
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["window_secs"], 30 * 24 * 3600);
+    assert_eq!(body["bucket_secs"], 30 * 24 * 3600);
+
+    let res = client
+        .get(format!("{}/stats/throughput?bucket=0", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn gas_histogram_buckets_prices_in_gwei() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    let priced = |hash: &str, gas_price: &str| NormalizedTx {
+        gas_price_wei: Some(gas_price.to_string()),
+        block_number: Some(1),
+        timestamp: Some(1_700_000_000),
+        ..tx(hash)
+    };
+    storage::insert_transactions(
+        &pool,
+        &[
+            priced("0xg7", "7000000000"),
+            priced("0xg9", "9999999999"),
+            priced("0xg12", "12000000000"),
+            priced("0xbad", "n/a"),
+        ],
+    )
+    .await
+    .unwrap();
+    let client = Client::new();
+
+    let body: serde_json::Value = client
+        .get(format!(
+            "{}/stats/gas-histogram?blocks=1&bucket=5",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["bucket_width_gwei"], 5);
+    // The seeded 1000-2000 wei prices land in the first bucket.
+    assert_eq!(
+        body["buckets"],
+        serde_json::json!([
+            {"bucket_gwei": 0, "count": 3},
+            {"bucket_gwei": 5, "count": 2},
+            {"bucket_gwei": 10, "count": 1},
+        ])
+    );
+
+    let res = client
+        .get(format!("{}/stats/gas-histogram?bucket=0", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn api_token_guards_every_route_but_health() {
+    let (base_url, handle) =
+        spawn_app_with_state(|state| state.with_api_token(Some("s3cret".to_string()))).await;
+    let client = Client::new();
+    let get = |path: &str, auth: Option<&str>| {
+        let mut req = client.get(format!("{}{}", base_url, path));
+        if let Some(auth) = auth {
+            req = req.header("authorization", auth);
+        }
+        async move { req.send().await.unwrap() }
+    };
+
+    let res = get("/stats/ingest", None).await;
+    assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+    assert_eq!(res.headers()["www-authenticate"], "Bearer");
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "unauthorized");
+    for auth in ["Bearer wrong", "Bearer s3cre", "s3cret", "Basic s3cret"] {
+        assert_eq!(
+            get("/tx/recent", Some(auth)).await.status(),
+            reqwest::StatusCode::UNAUTHORIZED,
+            "{}",
+            auth
+        );
+    }
+
+    assert!(get("/tx/recent", Some("Bearer s3cret"))
+        .await
+        .status()
+        .is_success());
+    assert!(get("/health", None).await.status().is_success());
+    handle.abort();
+
+    // Without a token the API stays open.
+    let (base_url, handle) = spawn_app_with_data().await;
+    let res = client
+        .get(format!("{}/stats/ingest", base_url))
         .send()
         .await
         .unwrap();
     assert!(res.status().is_success());
+    handle.abort();
+}
+
+#[tokio::test]
+async fn json_responses_are_gzipped_only_when_accepted_and_large() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    let client = Client::new();
+    let get = |path: &str, gzip: bool| {
+        let mut req = client.get(format!("{}{}", base_url, path));
+        if gzip {
+            req = req.header("accept-encoding", "gzip");
+        }
+        async move { req.send().await.unwrap() }
+    };
+
+    let res = get("/tx/recent?limit=100", true).await;
+    assert!(res.status().is_success());
+    assert_eq!(res.headers()["content-encoding"], "gzip");
+    let bytes = res.bytes().await.unwrap();
+    assert!(serde_json::from_slice::<serde_json::Value>(&bytes).is_err());
+
+    let res = get("/tx/recent?limit=100", false).await;
+    assert!(res.headers().get("content-encoding").is_none());
     let body: serde_json::Value = res.json().await.unwrap();
-    assert!(body.get("min").is_some());
-    assert!(body.get("max").is_some());
-    assert!(body.get("avg").is_some());
+    assert_eq!(body["transactions"].as_array().unwrap().len(), 3);
+
+    // Bodies under the size threshold are not worth encoding.
+    let res = get("/health", true).await;
+    assert!(res.headers().get("content-encoding").is_none());
     handle.abort();
 }
 
 #[tokio::test]
-async fn ingest_stats_returns_counters() {
+async fn openapi_spec_lists_core_routes() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    let res = Client::new()
+        .get(format!("{}/openapi.json", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let spec: serde_json::Value = res.json().await.unwrap();
+    assert!(spec["openapi"].as_str().unwrap().starts_with("3.0"));
+    for path in [
+        "/health",
+        "/stats/top-senders",
+        "/stats/gas",
+        "/stats/ingest",
+        "/tx/recent",
+    ] {
+        assert!(spec["paths"].get(path).is_some(), "missing {}", path);
+    }
+    let params = spec["paths"]["/stats/gas"]["get"]["parameters"]
+        .as_array()
+        .unwrap();
+    assert_eq!(params[0]["name"], "blocks");
+    assert_eq!(params[0]["schema"]["default"], 50);
+    let tx = &spec["components"]["schemas"]["NormalizedTx"]["allOf"];
+    assert!(tx[0]["properties"].get("value_wei").is_some());
+    assert!(tx[1]["properties"].get("value_eth").is_some());
+    handle.abort();
+}
+
+#[tokio::test]
+async fn errors_are_structured_json() {
     let (base_url, handle) = spawn_app_with_data().await;
-    INGEST_STATS.inc_blocks(1);
-    INGEST_STATS.inc_transactions(2);
     let client = Client::new();
+
     let res = client
-        .get(format!("{}/stats/ingest", base_url))
+        .get(format!("{}/tx/by-address?address=nope", base_url))
         .send()
         .await
         .unwrap();
-    assert!(res.status().is_success());
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
     let body: serde_json::Value = res.json().await.unwrap();
-    assert!(body.get("blocks").is_some());
-    assert!(body.get("transactions").is_some());
-    assert!(body.get("pending_transactions").is_some());
+    assert_eq!(body["error"]["code"], "bad_request");
+    assert_eq!(body["error"]["message"], "invalid address: \"nope\"");
+
+    let res = client
+        .get(format!("{}/tx/0x{}", base_url, "cd".repeat(32)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "not_found");
+
+    // Extractor rejections use the same shape as handler errors.
+    let res = client
+        .get(format!("{}/stats/gas?blocks=many", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "bad_request");
+    assert!(body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("query string"));
+
+    let res = client
+        .post(format!("{}/tx/batch", base_url))
+        .header("content-type", "application/json")
+        .body(r#"{"hashes": 1}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "unprocessable_entity");
     handle.abort();
 }
 
 #[tokio::test]
-async fn recent_txs_returns_rows() {
+async fn tx_batch_returns_found_in_order_and_lists_missing() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    let stored = format!("0x{}", "ab".repeat(32));
+    let unknown = format!("0x{}", "cd".repeat(32));
+    storage::insert_transactions(
+        &pool,
+        &[NormalizedTx {
+            value_wei: "7".to_string(),
+            ..tx(&stored)
+        }],
+    )
+    .await
+    .unwrap();
+
+    let client = Client::new();
+    let url = format!("{}/tx/batch", base_url);
+    let body: serde_json::Value = client
+        .post(&url)
+        .json(&[unknown.clone(), stored.to_uppercase().replace("0X", "0x")])
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["transactions"].as_array().unwrap().len(), 1);
+    assert_eq!(body["transactions"][0]["hash"], stored.as_str());
+    assert_eq!(body["transactions"][0]["value_wei"], "7");
+    assert_eq!(body["missing"], serde_json::json!([unknown]));
+
+    let res = client.post(&url).json(&["0x12"]).send().await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    let res = client
+        .post(&url)
+        .json(&vec![stored.clone(); 1001])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn recent_txs_served_from_warm_cache() {
+    let (base_url, handle) = spawn_app_with_state(|state| {
+        let state = state.with_recent_cache(4);
+        let cached = NormalizedTx {
+            value_wei: "1".to_string(),
+            block_number: Some(2),
+            timestamp: Some(1_700_000_010),
+            ..tx("0xcached")
+        };
+        state.recent_cache.as_ref().unwrap().push_many(&[cached]);
+        state
+    })
+    .await;
+    let client = Client::new();
+    let fetch = |limit: u32| {
+        let client = client.clone();
+        let url = format!("{}/tx/recent?limit={}", base_url, limit);
+        async move {
+            let body: serde_json::Value =
+                client.get(url).send().await.unwrap().json().await.unwrap();
+            body["transactions"][0]["hash"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        }
+    };
+    // The cached row only exists in memory; a larger limit falls back to SQLite.
+    assert_eq!(fetch(1).await, "0xcached");
+    assert_eq!(fetch(2).await, "0xtx2");
+    handle.abort();
+}
+
+#[tokio::test]
+async fn timeline_tags_direction() {
     let (base_url, handle) = spawn_app_with_data().await;
     let client = Client::new();
     let res = client
-        .get(format!("{}/tx/recent?limit=5", base_url))
+        .get(format!(
+            "{}/analysis/timeline?address=0x00000000000000000000000000000000000000a1",
+            base_url
+        ))
         .send()
         .await
         .unwrap();
     assert!(res.status().is_success());
     let body: serde_json::Value = res.json().await.unwrap();
     let arr = body
-        .get("transactions")
+        .get("timeline")
         .and_then(|v| v.as_array())
         .cloned()
         .unwrap_or_default();
-    assert!(!arr.is_empty());
+    assert_eq!(arr.len(), 1);
+    assert_eq!(
+        arr[0].get("direction").and_then(|d| d.as_str()),
+        Some("sent")
+    );
+    assert_eq!(arr[0].get("hash").and_then(|h| h.as_str()), Some("0xtx3"));
+
+    let res = client
+        .get(format!("{}/analysis/timeline", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn flow_sums_value_between_addresses() {
+    let (base_url, handle) = spawn_app_with_data().await;
+    let client = Client::new();
+    let body: serde_json::Value = client
+        .get(format!(
+            "{}/analysis/flow?from=0x00000000000000000000000000000000000000A1&to=0x00000000000000000000000000000000000000b2",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body.get("tx_count").and_then(|v| v.as_i64()), Some(1));
+    assert_eq!(
+        body.get("total_value_wei").and_then(|v| v.as_str()),
+        Some("5")
+    );
+    handle.abort();
+}
+
+#[tokio::test]
+async fn address_summary_counts_and_sums_activity() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    // Beyond i64, so the total only works as a big-integer sum.
+    let big = NormalizedTx {
+        from: "0x00000000000000000000000000000000000000a1".to_string(),
+        to: Some("0x00000000000000000000000000000000000000c3".to_string()),
+        value_wei: "100000000000000000000000000000".to_string(),
+        gas_price_wei: Some("1500".to_string()),
+        nonce: 1,
+        block_number: Some(1),
+        timestamp: Some(1_700_000_020),
+        ..tx("0xtx4")
+    };
+    storage::insert_transactions(&pool, &[big]).await.unwrap();
+    let client = Client::new();
+
+    let body: serde_json::Value = client
+        .get(format!(
+            "{}/address/0x00000000000000000000000000000000000000A1/summary",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        body["address"],
+        "0x00000000000000000000000000000000000000a1"
+    );
+    assert_eq!(body["sent_count"], 2);
+    assert_eq!(body["received_count"], 0);
+    assert_eq!(body["total_sent_wei"], "100000000000000000000000000005");
+    assert_eq!(body["first_seen"], 1_699_999_990);
+    assert_eq!(body["last_seen"], 1_700_000_020);
+
+    let body: serde_json::Value = client
+        .get(format!(
+            "{}/address/0x00000000000000000000000000000000000000b2/summary",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["sent_count"], 0);
+    assert_eq!(body["received_count"], 1);
+    assert_eq!(body["total_sent_wei"], "0");
+
+    let res = client
+        .get(format!("{}/address/0x123/summary", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn nonce_gaps_lists_missing_mined_nonces() {
+    let (base_url, handle, pool) = spawn_app_with_pool().await;
+    let sender = "0x00000000000000000000000000000000000000d4";
+    let sent = |hash: &str, nonce: i64, block: Option<i64>, chain_id: i64| NormalizedTx {
+        from: sender.to_string(),
+        gas_price_wei: Some("1000".to_string()),
+        nonce,
+        block_number: block,
+        timestamp: Some(1_700_000_000),
+        chain_id,
+        ..tx(hash)
+    };
+    for (chain_id, number) in [(1, 2), (1, 3), (5, 9)] {
+        let block = BlockInfo {
+            number,
+            hash: format!("0xb{}", number),
+            timestamp: 1_700_000_000,
+            base_fee_wei: None,
+            chain_id,
+        };
+        storage::upsert_block(&pool, &block).await.unwrap();
+    }
+    storage::insert_transactions(
+        &pool,
+        &[
+            sent("0xn0", 0, Some(1), 1),
+            sent("0xn1", 1, Some(1), 1),
+            sent("0xn4", 4, Some(2), 1),
+            sent("0xn7", 7, Some(3), 1),
+            // Pending and other-chain nonces do not fill the holes.
+            sent("0xn2", 2, None, 1),
+            sent("0xn3", 3, Some(9), 5),
+        ],
+    )
+    .await
+    .unwrap();
+    let get = |query: &str| {
+        let url = format!("{}/address/{}/nonce-gaps{}", base_url, sender, query);
+        async move {
+            let res = Client::new().get(url).send().await.unwrap();
+            assert!(res.status().is_success());
+            res.json::<serde_json::Value>().await.unwrap()
+        }
+    };
+
+    let body = get("?chain_id=1").await;
+    assert_eq!(body["nonces"], serde_json::json!([0, 1, 4, 7]));
+    assert_eq!(
+        body["gaps"],
+        serde_json::json!([{"start": 2, "end": 3}, {"start": 5, "end": 6}])
+    );
+    let body = get("").await;
+    assert_eq!(body["nonces"], serde_json::json!([0, 1, 3, 4, 7]));
+
+    let body: serde_json::Value = Client::new()
+        .get(format!(
+            "{}/address/0x00000000000000000000000000000000000000A1/nonce-gaps",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["gaps"], serde_json::json!([]));
     handle.abort();
 }
 
 async fn spawn_app_with_data() -> (String, JoinHandle<()>) {
-    let db_url = temp_db_url();
-    let pool = storage::init_pool(&db_url).await.unwrap();
+    spawn_app_with_state(|state| state).await
+}
+
+async fn spawn_app_with_state(
+    configure: impl FnOnce(AppState) -> AppState,
+) -> (String, JoinHandle<()>) {
+    let (base_url, handle, _) = spawn_seeded_app(configure).await;
+    (base_url, handle)
+}
+
+/// [`spawn_app_with_data`], also handing back the pool so a test can store
+/// rows of its own.
+async fn spawn_app_with_pool() -> (String, JoinHandle<()>, DbPool) {
+    spawn_seeded_app(|state| state).await
+}
+
+async fn spawn_seeded_app(
+    configure: impl FnOnce(AppState) -> AppState,
+) -> (String, JoinHandle<()>, DbPool) {
+    let pool = storage::init_pool(&temp_db_url()).await.unwrap();
     seed_data(&pool).await.unwrap();
 
-    let state = AppState { pool: pool.clone() };
-    let app = app_router(state);
+    let state = configure(AppState::new(Arc::new(SqliteStorage::new(pool.clone()))));
+    let (base_url, handle) = serve_router(app_router(state)).await;
+    (base_url, handle, pool)
+}
+
+/// A pending zero-value transfer on chain 1 from `0xeee`; tests override the
+/// fields they care about with struct-update syntax.
+fn tx(hash: &str) -> NormalizedTx {
+    NormalizedTx {
+        hash: hash.to_string(),
+        from: "0xeee".to_string(),
+        to: None,
+        value_wei: "0".to_string(),
+        gas: 21_000,
+        gas_price_wei: None,
+        max_fee_per_gas_wei: None,
+        max_priority_fee_per_gas_wei: None,
+        nonce: 0,
+        block_number: None,
+        timestamp: None,
+        status: None,
+        input_data: None,
+        tx_index: None,
+        chain_id: 1,
+        tx_type: None,
+        input_size: None,
+    }
+}
 
+async fn serve_router(app: axum::Router) -> (String, JoinHandle<()>) {
     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr: SocketAddr = listener.local_addr().unwrap();
     let base_url = format!("http://{}", addr);
@@ -119,14 +2314,17 @@ async fn spawn_app_with_data() -> (String, JoinHandle<()>) {
 }
 
 fn temp_db_url() -> String {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
     let dir = std::env::temp_dir();
     let _ = std::fs::create_dir_all(&dir);
     let file = format!(
-        "rust_eth_mempool_lab_test_{}.sqlite",
+        "rust_eth_mempool_lab_test_{}_{}_{}.sqlite",
+        std::process::id(),
         std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
-            .as_millis()
+            .as_millis(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
     );
     let path = dir.join(file);
     let _ = std::fs::File::create(&path);
@@ -138,35 +2336,43 @@ async fn seed_data(pool: &DbPool) -> anyhow::Result<()> {
         number: 1,
         hash: "0xabc".to_string(),
         timestamp: 1_700_000_000,
+        base_fee_wei: None,
+        chain_id: 1,
     };
     storage::insert_block(pool, &block).await?;
 
     let txs = vec![
         NormalizedTx {
-            hash: "0xtx1".to_string(),
             from: "0xaaa".to_string(),
             to: Some("0xbbb".to_string()),
             value_wei: "1000000000000000000".to_string(),
-            gas: 21_000,
             gas_price_wei: Some("1000".to_string()),
-            max_fee_per_gas_wei: None,
             nonce: 1,
             block_number: Some(1),
             timestamp: Some(1_700_000_000),
-            status: None,
+            ..tx("0xtx1")
+        },
+        NormalizedTx {
+            from: "0x00000000000000000000000000000000000000a1".to_string(),
+            to: Some("0x00000000000000000000000000000000000000b2".to_string()),
+            value_wei: "5".to_string(),
+            gas_price_wei: Some("1500".to_string()),
+            block_number: Some(1),
+            timestamp: Some(1_699_999_990),
+            input_data: Some("0xa9059cbb0000".to_string()),
+            ..tx("0xtx3")
         },
         NormalizedTx {
-            hash: "0xtx2".to_string(),
             from: "0xccc".to_string(),
             to: Some("0xddd".to_string()),
             value_wei: "2000000000000000000".to_string(),
             gas: 30_000,
             gas_price_wei: Some("2000".to_string()),
-            max_fee_per_gas_wei: None,
             nonce: 2,
             block_number: Some(1),
             timestamp: Some(1_700_000_005),
-            status: None,
+            input_data: Some("0x".to_string()),
+            ..tx("0xtx2")
         },
     ];
 